@@ -0,0 +1,13 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate data_chain;
+
+use data_chain::Data;
+
+fuzz_target!(|bytes: &[u8]| {
+    if let Ok(data) = Data::decode_untrusted(bytes) {
+        let _ = data.identifier().local_name();
+        let _ = data.payload_size();
+    }
+});
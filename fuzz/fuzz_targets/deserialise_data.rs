@@ -0,0 +1,15 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate data_chain;
+
+use data_chain::Data;
+use data_chain::serialisation::deserialise;
+
+fuzz_target!(|bytes: &[u8]| {
+    if let Ok(data) = deserialise::<Data>(bytes) {
+        let _ = data.validate();
+        let _ = data.identifier().local_name();
+        let _ = data.payload_size();
+    }
+});
@@ -0,0 +1,13 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate data_chain;
+
+use data_chain::DataIdentifier;
+use data_chain::serialisation::deserialise;
+
+fuzz_target!(|bytes: &[u8]| {
+    if let Ok(identifier) = deserialise::<DataIdentifier>(bytes) {
+        let _ = identifier.local_name();
+    }
+});
@@ -0,0 +1,12 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate data_chain;
+
+use data_chain::data::ImmutableDataRef;
+
+fuzz_target!(|bytes: &[u8]| {
+    if let Ok(data_ref) = ImmutableDataRef::from_serialised_data(bytes) {
+        let _ = data_ref.decompressed_value();
+    }
+});
@@ -0,0 +1,141 @@
+/*
+ * Copyright 2016 MaidSafe.net limited.
+ *
+ * This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+ * version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+ * licence you accepted on initial access to the Software (the "Licences").
+ *
+ * By contributing code to the SAFE Network Software, or to this project generally, you agree to
+ * be bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+ * Licenses can be found in the root directory of this project at LICENSE, COPYING and
+ * CONTRIBUTOR.
+ *
+ * Unless required by applicable law or agreed to in writing, the SAFE Network Software
+ * distributed under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+ * CONDITIONS OF ANY KIND, either express or implied.
+ *
+ * Please review the Licences for the specific language governing permissions and limitations
+ * relating to use of the SAFE Network Software.
+ */
+
+/*
+ * C interface to data_chain, built with the `ffi` feature. The crate only builds as a Rust
+ * library, so the C library type is asked for on the command line:
+ *
+ *     cargo rustc --release --features ffi --crate-type cdylib
+ *
+ * builds the shared library target/release/libdata_chain.so (.dylib on macOS, data_chain.dll on
+ * Windows), and
+ *
+ *     cargo rustc --release --features ffi --crate-type staticlib
+ *
+ * the static library target/release/libdata_chain.a (data_chain.lib on Windows). Include this
+ * header and link against either one; the static library also needs the system libraries listed
+ * by adding `-- --print native-static-libs` to the second command.
+ *
+ * Each function returns 0 on success, a positive error code on failure, DATA_CHAIN_INVALID_ARGUMENT
+ * if an argument is null or of the wrong type and DATA_CHAIN_PANIC if the call panicked. Outputs
+ * are only written on success.
+ */
+
+#ifndef DATA_CHAIN_H
+#define DATA_CHAIN_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Returned if an argument is null, of the wrong length or a handle of the wrong type of data. */
+#define DATA_CHAIN_INVALID_ARGUMENT (-1)
+/* Returned if the call panicked. */
+#define DATA_CHAIN_PANIC (-2)
+
+/* Error codes returned on failure. These are frozen and never reused. */
+#define DATA_CHAIN_ERROR_SERIALISATION 1
+#define DATA_CHAIN_ERROR_IO 2
+#define DATA_CHAIN_ERROR_CRYPTO 3
+#define DATA_CHAIN_ERROR_VALIDATION 4
+#define DATA_CHAIN_ERROR_SIGNATURE 5
+#define DATA_CHAIN_ERROR_MAJORITY 6
+#define DATA_CHAIN_ERROR_NO_LINK 7
+#define DATA_CHAIN_ERROR_NO_SPACE 8
+#define DATA_CHAIN_ERROR_NO_FILE 9
+#define DATA_CHAIN_ERROR_BAD_IDENTIFIER 10
+#define DATA_CHAIN_ERROR_DATA_DELETED 11
+#define DATA_CHAIN_ERROR_DATA_TOO_LARGE 12
+#define DATA_CHAIN_ERROR_ENCODING 13
+#define DATA_CHAIN_ERROR_ACCESS_DENIED 14
+#define DATA_CHAIN_ERROR_ENTRY_EXISTS 15
+#define DATA_CHAIN_ERROR_NO_SUCH_ENTRY 16
+#define DATA_CHAIN_ERROR_INVALID_VERSION 17
+#define DATA_CHAIN_ERROR_EXPIRED 18
+#define DATA_CHAIN_ERROR_INVALID_RANGE 19
+#define DATA_CHAIN_ERROR_NO_OWNERS 20
+#define DATA_CHAIN_ERROR_TOO_MANY_OWNERS 21
+
+/* Sizes of names and of the default ed25519 keys. Builds with the `dilithium` feature use that
+ * scheme's larger keys instead. */
+#define DATA_CHAIN_NAME_BYTES 32
+#define DATA_CHAIN_PUBLIC_KEY_BYTES 32
+#define DATA_CHAIN_SECRET_KEY_BYTES 64
+
+/* Opaque handle to immutable or structured data, owned by the caller until freed with
+ * data_chain_data_free. */
+typedef struct DataChainData DataChainData;
+
+/* Creates immutable data holding the `len` bytes at `value`. `value` may only be null if `len`
+ * is 0. */
+int32_t data_chain_immutable_data_new(const uint8_t *value, size_t len, DataChainData **out);
+
+/* Creates unsigned version `version` of the structured data of type `type_tag` named by the
+ * DATA_CHAIN_NAME_BYTES bytes at `name`, holding the `data_len` bytes at `data` and owned by the
+ * `owner_count` public keys packed at `owner_keys`. `data` may only be null if `data_len` is 0. */
+int32_t data_chain_structured_data_new(uint64_t type_tag,
+                                       const uint8_t *name,
+                                       uint64_t version,
+                                       const uint8_t *data,
+                                       size_t data_len,
+                                       const uint8_t *owner_keys,
+                                       size_t owner_count,
+                                       DataChainData **out);
+
+/* Signs the structured data `data` with the DATA_CHAIN_SECRET_KEY_BYTES bytes at `secret_key`
+ * and writes the number of signatures still required to `remaining`. */
+int32_t data_chain_structured_data_sign(DataChainData *data,
+                                        const uint8_t *secret_key,
+                                        size_t *remaining);
+
+/* Replaces the structured data `current` with `successor` if it is a valid update of it.
+ * `successor` is left as it was. */
+int32_t data_chain_structured_data_replace(DataChainData *current,
+                                           const DataChainData *successor);
+
+/* Writes the DATA_CHAIN_NAME_BYTES-byte name of `data` to `name`. */
+int32_t data_chain_data_name(const DataChainData *data, uint8_t *name);
+
+/* Checks the invariants of `data`. */
+int32_t data_chain_data_validate(const DataChainData *data);
+
+/* Serialises `data`, writing a buffer of the bytes to `out` and its length to `out_len`. Free
+ * the buffer with data_chain_bytes_free. */
+int32_t data_chain_data_serialise(const DataChainData *data, uint8_t **out, size_t *out_len);
+
+/* Parses and validates the `len` serialised bytes at `bytes`, received from an untrusted
+ * source. */
+int32_t data_chain_data_deserialise(const uint8_t *bytes, size_t len, DataChainData **out);
+
+/* Frees the data `data`, which may be null. */
+void data_chain_data_free(DataChainData *data);
+
+/* Frees the buffer of `len` bytes at `bytes`, as returned by data_chain_data_serialise. `bytes`
+ * may be null. */
+void data_chain_bytes_free(uint8_t *bytes, size_t len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* DATA_CHAIN_H */
@@ -0,0 +1,201 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Accounts
+//! A client's storage account as kept by the nodes managing it: how many PUTs the client has
+//! made, how much space, in cost units, their stored data uses and how much they have left.
+
+use data::{CostPolicy, Data, DataIdentifier, DefaultCostPolicy};
+use data::untrusted;
+use error::Error;
+use serialisation::serialise;
+use std::collections::BTreeMap;
+
+/// The storage account of one client.
+///
+/// Each `charge` for data adds its cost to the space used, and each `refund` of data removes
+/// everything charged for it, so the space used is always the sum of the charges and never more
+/// than the space available.
+#[derive(Hash, Clone, Debug, Eq, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Account {
+    puts: u64,
+    space_used: u64,
+    space_available: u64,
+    charges: BTreeMap<DataIdentifier, u64>,
+}
+
+impl Account {
+    /// Creates an account with nothing stored and `space_available` units of space.
+    pub fn new(space_available: u64) -> Account {
+        Account {
+            puts: 0,
+            space_used: 0,
+            space_available: space_available,
+            charges: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the number of charges made, including those since refunded.
+    pub fn puts(&self) -> u64 {
+        self.puts
+    }
+
+    /// Returns the units of space used by the data charged for.
+    pub fn space_used(&self) -> u64 {
+        self.space_used
+    }
+
+    /// Returns the units of space the account may use in total.
+    pub fn space_available(&self) -> u64 {
+        self.space_available
+    }
+
+    /// Returns the units of space left.
+    pub fn balance(&self) -> u64 {
+        self.space_available - self.space_used
+    }
+
+    /// Returns the units charged for the data with `identifier`, if any.
+    pub fn charged(&self, identifier: &DataIdentifier) -> Option<u64> {
+        self.charges.get(identifier).cloned()
+    }
+
+    /// Changes the units of space the account may use, failing with `NoSpace` if less than is
+    /// already used.
+    pub fn set_space_available(&mut self, space_available: u64) -> Result<(), Error> {
+        if space_available < self.space_used {
+            return Err(Error::NoSpace);
+        }
+        self.space_available = space_available;
+        Ok(())
+    }
+
+    /// Charges for storing `data` under the default cost policy and returns the cost.
+    pub fn charge(&mut self, data: &Data) -> Result<u64, Error> {
+        self.charge_with(data, &DefaultCostPolicy::default())
+    }
+
+    /// Charges for storing `data` under `policy` and returns the cost, failing with `NoSpace`
+    /// if the balance is too low. Charges for updates of data already charged for add up.
+    pub fn charge_with<P: CostPolicy>(&mut self, data: &Data, policy: &P) -> Result<u64, Error> {
+        let cost = policy.cost(data);
+        if cost > self.balance() {
+            return Err(Error::NoSpace);
+        }
+        *self.charges.entry(data.identifier()).or_insert(0) += cost;
+        self.space_used += cost;
+        self.puts += 1;
+        Ok(cost)
+    }
+
+    /// Refunds everything charged for the data with `identifier` and returns the units refunded,
+    /// failing with `NoSuchEntry` if nothing was charged for it.
+    pub fn refund(&mut self, identifier: &DataIdentifier) -> Result<u64, Error> {
+        let refund = self.charges.remove(identifier).ok_or(Error::NoSuchEntry)?;
+        self.space_used -= refund;
+        Ok(refund)
+    }
+
+    /// Checks the space used is the sum of the charges and no more than the space available.
+    pub fn validate(&self) -> Result<(), Error> {
+        let charged = self.charges
+            .values()
+            .fold(Some(0u64), |sum, &charge| sum.and_then(|sum| sum.checked_add(charge)));
+        if charged != Some(self.space_used) || self.space_used > self.space_available {
+            return Err(Error::Validation);
+        }
+        Ok(())
+    }
+
+    /// Serialises the account, e.g. for a refresh message.
+    pub fn serialise(&self) -> Result<Vec<u8>, Error> {
+        serialise(self).map_err(From::from)
+    }
+
+    /// Parses and validates a received account.
+    pub fn deserialise(bytes: &[u8]) -> Result<Account, Error> {
+        let account: Account = untrusted::deserialise(bytes)?;
+        account.validate()?;
+        Ok(account)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{Data, DataIdentifier, ImmutableData};
+    use data::cost::DEFAULT_UNIT_SIZE;
+    use error::Error;
+    use serialisation::serialise;
+    use std::collections::BTreeMap;
+
+    fn immutable(units: usize) -> Data {
+        Data::Immutable(ImmutableData::new(vec![units as u8; units * DEFAULT_UNIT_SIZE]))
+    }
+
+    #[test]
+    fn charges_and_refunds() {
+        let mut account = Account::new(10);
+        let (small, large) = (immutable(3), immutable(6));
+        assert_eq!(unwrap!(account.charge(&small)), 3);
+        assert_eq!(unwrap!(account.charge(&large)), 6);
+        assert_eq!(account.space_used(), 9);
+        assert_eq!(account.balance(), 1);
+        assert_eq!(account.charged(&large.identifier()), Some(6));
+        match account.charge(&immutable(2)) {
+            Err(Error::NoSpace) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert_eq!(account.puts(), 2);
+
+        assert_eq!(unwrap!(account.refund(&large.identifier())), 6);
+        assert_eq!(account.balance(), 7);
+        match account.refund(&large.identifier()) {
+            Err(Error::NoSuchEntry) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match account.set_space_available(2) {
+            Err(Error::NoSpace) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        unwrap!(account.set_space_available(3));
+        assert_eq!(account.balance(), 0);
+        unwrap!(account.validate());
+    }
+
+    #[test]
+    fn serialisation() {
+        let mut account = Account::new(10);
+        let _ = unwrap!(account.charge(&immutable(3)));
+        let serialised = unwrap!(account.serialise());
+        assert_eq!(unwrap!(Account::deserialise(&serialised)), account);
+
+        // Received accounts are validated.
+        let mut charges = BTreeMap::new();
+        let _ = charges.insert(DataIdentifier::Immutable([0; 32]), 3);
+        let inconsistent = Account {
+            puts: 1,
+            space_used: 2,
+            space_available: 10,
+            charges: charges,
+        };
+        match Account::deserialise(&unwrap!(serialise(&inconsistent))) {
+            Err(Error::Validation) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,285 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Audit log
+//! With the `audit` feature, the crate records each `StructuredData::replace_with_other` and
+//! signature added, and each mutation of a store, with the identifier and version of the data
+//! and whether it was accepted or why not, to a sink installed with `set_sink`. Operators can
+//! then reconstruct why an update was rejected in production.
+//!
+//! `MemorySink` keeps the latest records in a ring buffer and `FileSink` appends them to a file,
+//! one per line; other sinks implement `AuditSink`. Nothing is recorded until a sink is set.
+
+use data::{Data, DataIdentifier};
+use error::Error;
+use std::collections::VecDeque;
+use std::fmt::{self, Display, Formatter};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+lazy_static! {
+    static ref SINK: RwLock<Option<Box<AuditSink>>> = RwLock::new(None);
+}
+
+/// An audited operation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuditOperation {
+    /// `StructuredData::replace_with_other`.
+    ReplaceWithOther,
+    /// `StructuredData::add_signature` or `add_external_signature`.
+    AddSignature,
+    /// Storing data, e.g. `SecuredData::put_data` or `DataStore::put`.
+    Put,
+    /// Storing a new version of structured data with `SecuredData::post_data`.
+    Post,
+    /// Deleting data from a store.
+    Delete,
+}
+
+/// Whether an audited operation succeeded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AuditOutcome {
+    /// The operation succeeded.
+    Accepted,
+    /// The operation failed with the error described.
+    Rejected(String),
+}
+
+/// A record of an audited operation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuditRecord {
+    /// When the operation finished, in milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+    /// The operation.
+    pub operation: AuditOperation,
+    /// The identifier of the data operated on.
+    pub identifier: DataIdentifier,
+    /// The version of the data operated on, where it has one, e.g. the proposed successor's
+    /// version for `ReplaceWithOther`.
+    pub version: Option<u64>,
+    /// Whether the operation succeeded.
+    pub outcome: AuditOutcome,
+}
+
+impl Display for AuditRecord {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter,
+               "{} {:?} {}",
+               self.timestamp_ms,
+               self.operation,
+               self.identifier)?;
+        if let Some(version) = self.version {
+            write!(formatter, " version {}", version)?;
+        }
+        match self.outcome {
+            AuditOutcome::Accepted => write!(formatter, " accepted"),
+            AuditOutcome::Rejected(ref reason) => write!(formatter, " rejected: {}", reason),
+        }
+    }
+}
+
+/// A destination of audit records.
+pub trait AuditSink: Send + Sync {
+    /// Records `record`. Failures to record must not fail the operation, so are not reported.
+    fn record(&self, record: &AuditRecord);
+}
+
+/// A sink keeping the latest records in memory, dropping the oldest beyond its capacity.
+///
+/// Clones share the records, so a clone kept by the caller sees what the installed sink records.
+#[derive(Clone)]
+pub struct MemorySink {
+    capacity: usize,
+    records: Arc<Mutex<VecDeque<AuditRecord>>>,
+}
+
+impl MemorySink {
+    /// Creates a sink keeping at most `capacity` records.
+    pub fn new(capacity: usize) -> MemorySink {
+        MemorySink {
+            capacity: capacity,
+            records: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+
+    /// Returns the records kept, oldest first.
+    pub fn records(&self) -> Vec<AuditRecord> {
+        self.lock().iter().cloned().collect()
+    }
+
+    fn lock(&self) -> MutexGuard<VecDeque<AuditRecord>> {
+        self.records.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl AuditSink for MemorySink {
+    fn record(&self, record: &AuditRecord) {
+        let mut records = self.lock();
+        while !records.is_empty() && records.len() >= self.capacity {
+            let _ = records.pop_front();
+        }
+        if self.capacity > 0 {
+            records.push_back(record.clone());
+        }
+    }
+}
+
+/// A sink appending records to a file, one per line.
+pub struct FileSink {
+    file: Mutex<File>,
+}
+
+impl FileSink {
+    /// Opens the file at `path` for appending, creating it if it doesn't exist.
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<FileSink, Error> {
+        let file = OpenOptions::new().append(true).create(true).open(path)?;
+        Ok(FileSink { file: Mutex::new(file) })
+    }
+}
+
+impl AuditSink for FileSink {
+    fn record(&self, record: &AuditRecord) {
+        let mut file = self.file.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let _ = writeln!(file, "{}", record);
+    }
+}
+
+/// Installs `sink` as the destination of all audit records, replacing any sink set before.
+pub fn set_sink(sink: Box<AuditSink>) {
+    *SINK.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(sink);
+}
+
+/// Removes the installed sink, so nothing more is recorded.
+pub fn clear_sink() {
+    *SINK.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+}
+
+/// Records that `operation` on the data identified by `identifier`, at `version`, ended with
+/// `result`. The crate records its own operations; applications may record theirs too.
+pub fn record<T>(operation: AuditOperation,
+                 identifier: DataIdentifier,
+                 version: Option<u64>,
+                 result: &Result<T, Error>) {
+    let sink = SINK.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(ref sink) = *sink {
+        sink.record(&AuditRecord {
+            timestamp_ms: now_ms(),
+            operation: operation,
+            identifier: identifier,
+            version: version,
+            outcome: match *result {
+                Ok(_) => AuditOutcome::Accepted,
+                Err(ref error) => AuditOutcome::Rejected(error.to_string()),
+            },
+        });
+    }
+}
+
+/// Records that `operation` on `data` ended with `result`, as `record` does.
+pub fn record_data<T>(operation: AuditOperation, data: &Data, result: &Result<T, Error>) {
+    let version = match *data {
+        Data::Structured(ref data) => Some(data.version()),
+        Data::Mutable(ref data) => Some(data.version()),
+        Data::Immutable(_) | Data::Plain(_) => None,
+    };
+    record(operation, data.identifier(), version, result)
+}
+
+fn now_ms() -> u64 {
+    // A clock set before the epoch gives records timestamped 0 rather than none at all.
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() * 1000 + elapsed.subsec_nanos() as u64 / 1_000_000)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::StructuredData;
+    use rand;
+    use test_utils;
+
+    #[test]
+    fn structured_data_updates_recorded() {
+        let sink = MemorySink::new(10_000);
+        set_sink(Box::new(sink.clone()));
+
+        let keys = test_utils::gen_keypair();
+        let other_keys = test_utils::gen_keypair();
+        let name = rand::random();
+        let mut current = unwrap!(StructuredData::new(5,
+                                                      name,
+                                                      0,
+                                                      vec![1],
+                                                      vec![keys.0],
+                                                      vec![],
+                                                      Some(&keys.1),
+                                                      false));
+        let mut successor =
+            unwrap!(StructuredData::new(5, name, 1, vec![2], vec![keys.0], vec![], None, false));
+        let mut forged = successor.clone();
+        let _ = unwrap!(forged.add_signature(&other_keys.1));
+        assert!(current.replace_with_other(forged).is_err());
+        let _ = unwrap!(successor.add_signature(&keys.1));
+        unwrap!(current.replace_with_other(successor));
+
+        // Other tests may be recording at the same time, so only this data's records are checked.
+        let identifier = current.identifier();
+        let records: Vec<_> = sink.records()
+            .into_iter()
+            .filter(|record| record.identifier == identifier)
+            .collect();
+        let summary: Vec<_> = records.iter()
+            .map(|record| {
+                (record.operation, record.version, record.outcome == AuditOutcome::Accepted)
+            })
+            .collect();
+        assert_eq!(summary,
+                   vec![(AuditOperation::AddSignature, Some(0), true),
+                        (AuditOperation::AddSignature, Some(1), true),
+                        (AuditOperation::ReplaceWithOther, Some(1), false),
+                        (AuditOperation::AddSignature, Some(1), true),
+                        (AuditOperation::ReplaceWithOther, Some(1), true)]);
+        match records[2].outcome {
+            AuditOutcome::Rejected(ref reason) => assert!(!reason.is_empty()),
+            ref outcome => panic!("Unexpected outcome {:?}", outcome),
+        }
+        assert!(records[0].timestamp_ms > 0);
+        assert!(records[4].to_string().ends_with("version 1 accepted"));
+    }
+
+    #[test]
+    fn ring_buffer() {
+        let sink = MemorySink::new(2);
+        let identifier = DataIdentifier::Immutable(rand::random());
+        for version in 0..3 {
+            sink.record(&AuditRecord {
+                timestamp_ms: version,
+                operation: AuditOperation::Put,
+                identifier: identifier,
+                version: Some(version),
+                outcome: AuditOutcome::Accepted,
+            });
+        }
+        let versions: Vec<_> = sink.records().iter().map(|record| record.version).collect();
+        assert_eq!(versions, vec![Some(1), Some(2)]);
+    }
+}
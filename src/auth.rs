@@ -0,0 +1,146 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Authorisation
+//! Detached proofs that a requester owns data, for authorising GETs of private data without
+//! sending the data itself. The holder sends a fresh nonce; the requester answers with a
+//! signature over the data's identifier and the nonce, which the holder checks against the
+//! owners it has stored. The nonce keeps a proof from being replayed in another request.
+
+use crypto::ct;
+use crypto::sign::{self, PublicKey, SecretKey, Signature};
+use data::DataIdentifier;
+use data::canonical::{CanonicalWriter, OWNERSHIP_PROOF_DOMAIN};
+use error::Error;
+use serialisation::serialise;
+
+/// A signature proving the signer owns the data with an identifier, for a given nonce.
+#[derive(Hash, Eq, PartialEq, Clone, Debug, RustcEncodable, RustcDecodable)]
+pub struct OwnershipProof {
+    public_key: PublicKey,
+    signature: Signature,
+}
+
+impl OwnershipProof {
+    /// Signs `identifier` and `nonce` with `secret_key`.
+    pub fn new(identifier: &DataIdentifier,
+               nonce: &[u8; 32],
+               secret_key: &SecretKey)
+               -> Result<OwnershipProof, Error> {
+        // The secret key ends with its public key.
+        let mut public_key = PublicKey([0; sign::PUBLICKEYBYTES]);
+        public_key.0.copy_from_slice(&secret_key.0[sign::SECRETKEYBYTES - sign::PUBLICKEYBYTES..]);
+        let signature = sign::sign_detached(&Self::data_to_sign(identifier, nonce)?, secret_key);
+        Ok(OwnershipProof {
+            public_key: public_key,
+            signature: signature,
+        })
+    }
+
+    /// The key the proof is signed with.
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    /// Checks the proof is signed by `owner` over `identifier` and `nonce`.
+    pub fn verify(&self,
+                  identifier: &DataIdentifier,
+                  nonce: &[u8; 32],
+                  owner: &PublicKey)
+                  -> Result<(), Error> {
+        if !ct::key_eq(&self.public_key, owner) {
+            return Err(Error::Signature);
+        }
+        if sign::verify_detached(&self.signature,
+                                 &Self::data_to_sign(identifier, nonce)?,
+                                 owner) {
+            Ok(())
+        } else {
+            Err(Error::Signature)
+        }
+    }
+
+    /// Checks the proof is signed by one of `owners` over `identifier` and `nonce`.
+    pub fn verify_any(&self,
+                      identifier: &DataIdentifier,
+                      nonce: &[u8; 32],
+                      owners: &[PublicKey])
+                      -> Result<(), Error> {
+        if !ct::contains_key(owners, &self.public_key) {
+            return Err(Error::AccessDenied);
+        }
+        self.verify(identifier, nonce, &self.public_key)
+    }
+
+    fn data_to_sign(identifier: &DataIdentifier, nonce: &[u8; 32]) -> Result<Vec<u8>, Error> {
+        let mut writer = CanonicalWriter::new(OWNERSHIP_PROOF_DOMAIN);
+        let _ = writer.write_bytes(&serialise(identifier)?).write_fixed(nonce);
+        Ok(writer.into_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::DataIdentifier;
+    use error::Error;
+    use rand;
+    use serialisation::{deserialise, serialise};
+    use test_utils;
+
+    #[test]
+    fn sign_and_verify() {
+        let keys = test_utils::gen_keypair();
+        let other_keys = test_utils::gen_keypair();
+        let identifier = DataIdentifier::Structured(rand::random(), 10);
+        let nonce = rand::random();
+        let proof = unwrap!(OwnershipProof::new(&identifier, &nonce, &keys.1));
+        assert_eq!(*proof.public_key(), keys.0);
+        unwrap!(proof.verify(&identifier, &nonce, &keys.0));
+        unwrap!(proof.verify_any(&identifier, &nonce, &[other_keys.0, keys.0]));
+
+        let parsed: OwnershipProof = unwrap!(deserialise(&unwrap!(serialise(&proof))));
+        assert_eq!(parsed, proof);
+
+        match proof.verify(&identifier, &nonce, &other_keys.0) {
+            Err(Error::Signature) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match proof.verify_any(&identifier, &nonce, &[other_keys.0]) {
+            Err(Error::AccessDenied) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        // A proof for one nonce or identifier is not one for another.
+        match proof.verify(&identifier, &rand::random(), &keys.0) {
+            Err(Error::Signature) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match proof.verify(&DataIdentifier::Structured(rand::random(), 10), &nonce, &keys.0) {
+            Err(Error::Signature) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        // Claiming another key does not help.
+        let forged = OwnershipProof {
+            public_key: other_keys.0,
+            signature: proof.signature,
+        };
+        match forged.verify(&identifier, &nonce, &other_keys.0) {
+            Err(Error::Signature) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
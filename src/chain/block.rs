@@ -19,7 +19,7 @@ use chain::block_identifier::BlockIdentifier;
 use chain::proof::Proof;
 use chain::vote::Vote;
 use error::Error;
-use maidsafe_utilities::serialisation;
+use serialisation;
 
 /// Used to validate chain
 /// Block can be a data item or
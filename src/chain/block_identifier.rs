@@ -16,8 +16,8 @@
 // relating to use of the SAFE Network Software.
 
 use super::debug_bytes;
+use crypto::sign::PublicKey;
 use data::DataIdentifier;
-use rust_sodium::crypto::sign::PublicKey;
 use std::fmt::{self, Debug, Formatter};
 
 /// TODO Use real prefix
@@ -37,6 +37,7 @@ pub enum LinkDescriptor {
 }
 
 impl LinkDescriptor {
+    #[cfg(not(feature = "dilithium"))]
     pub fn name(&self) -> Option<&[u8; 32]> {
         match *self {
             LinkDescriptor::NodeLost(ref h) |
@@ -44,6 +45,12 @@ impl LinkDescriptor {
             _ => None,
         }
     }
+
+    // Dilithium public keys are not 32 bytes, so cannot double as node names.
+    #[cfg(feature = "dilithium")]
+    pub fn name(&self) -> Option<&[u8; 32]> {
+        None
+    }
 }
 /// Data identifiers for use in a data Chain.
 /// The hash of each data type is available to ensure there is no confusion
@@ -139,18 +146,19 @@ impl Debug for BlockIdentifier {
 mod tests {
     use super::*;
     use data::DataIdentifier;
-    use rust_sodium::crypto;
     use sha3::hash;
+    use test_utils;
 
     #[test]
     fn create_validate_link_identifier() {
+        #[cfg(feature = "sodium")]
         ::rust_sodium::init();
-        let keys = crypto::sign::gen_keypair();
+        let keys = test_utils::gen_keypair();
         let link = BlockIdentifier::Link(LinkDescriptor::NodeGained(keys.0));
 
         assert!(link.is_link());
         assert!(!link.is_block());
-        assert!(link.name().is_some());
+        assert_eq!(link.name().is_some(), cfg!(not(feature = "dilithium")));
     }
 
     #[test]
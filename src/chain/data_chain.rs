@@ -19,13 +19,16 @@ use bincode::rustc_serialize;
 use chain::block::Block;
 use chain::block_identifier::BlockIdentifier;
 use chain::vote::Vote;
+use crypto::sign::PublicKey;
 use error::Error;
+#[cfg(feature = "fs")]
 use fs2::FileExt;
 use itertools::Itertools;
-use maidsafe_utilities::serialisation;
-use rust_sodium::crypto::sign::PublicKey;
+use serialisation;
 use std::fmt::{self, Debug, Formatter};
+#[cfg(feature = "fs")]
 use std::fs;
+#[cfg(feature = "fs")]
 use std::io::{self, Read, Write};
 use std::path::PathBuf;
 
@@ -49,6 +52,7 @@ pub struct DataChain {
 impl DataChain {
     /// Create a new chain backed up on disk
     /// Provide the directory to create the files in
+    #[cfg(feature = "fs")]
     pub fn create_in_path(path: PathBuf, group_size: usize) -> io::Result<DataChain> {
         let path = path.join("data_chain");
         let file = fs::OpenOptions::new().read(true).write(true).create_new(true).open(&path)?;
@@ -62,6 +66,7 @@ impl DataChain {
     }
 
     /// Open from existing directory
+    #[cfg(feature = "fs")]
     pub fn from_path(path: PathBuf, group_size: usize) -> Result<DataChain, Error> {
         let path = path.join("data_chain");
         let mut file = fs::OpenOptions::new().read(true).write(true).create(false).open(&path)?;
@@ -86,6 +91,7 @@ impl DataChain {
     }
 
     /// Write current data chain to supplied path
+    #[cfg(feature = "fs")]
     pub fn write(&self) -> Result<(), Error> {
         if let Some(path) = self.path.to_owned() {
             let mut file = fs::OpenOptions::new().read(true)
@@ -98,6 +104,7 @@ impl DataChain {
     }
 
     /// Write current data chain to supplied path
+    #[cfg(feature = "fs")]
     pub fn write_to_new_path(&mut self, path: PathBuf) -> Result<(), Error> {
         let mut file = fs::OpenOptions::new().read(true)
             .write(true)
@@ -109,6 +116,7 @@ impl DataChain {
     }
 
     /// Unlock the lock file
+    #[cfg(feature = "fs")]
     pub fn unlock(&self) {
         if let Some(ref path) = self.path.to_owned() {
             if let Ok(file) = fs::File::open(path.as_path()) {
@@ -172,13 +180,19 @@ impl DataChain {
                 pos = self.chain.len();
                 self.chain.push(el);
             }
-            let blk = self.chain.get_mut(pos).unwrap();
+            let blk = match self.chain.get_mut(pos) {
+                Some(blk) => blk,
+                None => return None,
+            };
             if blk.proofs().iter().any(|x| x.key() == vote.proof().key()) {
                 info!("duplicate proof");
                 return None;
             }
 
-            blk.add_proof(vote.proof().clone()).unwrap();
+            if let Err(error) = blk.add_proof(vote.proof().clone()) {
+                info!("rejected proof: {:?}", error);
+                return None;
+            }
             info!("chain length {:?}", len);
             if links.map_or(false, |x| {
                 x.identifier() != vote.identifier() &&
@@ -446,10 +460,12 @@ mod tests {
     extern crate env_logger;
     use chain::block_identifier::{BlockIdentifier, LinkDescriptor};
     use chain::vote::Vote;
+    use crypto::sign::{PublicKey, SecretKey};
     use itertools::Itertools;
-    use rust_sodium::crypto::sign::{self, PublicKey, SecretKey};
     use super::*;
+    #[cfg(feature = "fs")]
     use tempdir::TempDir;
+    use test_utils;
 
     pub struct Node {
         pub sec_key: SecretKey,
@@ -457,7 +473,7 @@ mod tests {
     }
 
     pub fn node() -> Node {
-        let keys = sign::gen_keypair();
+        let keys = test_utils::gen_keypair();
         Node {
             sec_key: keys.1,
             pub_key: keys.0,
@@ -467,6 +483,7 @@ mod tests {
     #[test]
     fn genesis() {
         let _ = env_logger::init();
+        #[cfg(feature = "sodium")]
         ::rust_sodium::init();
         let nodes = (0..100).map(|_| node()).collect_vec();
         let add_node_1 =
@@ -557,13 +574,15 @@ mod tests {
                 "Add first node, should accumulate as valid.");
     }
 
+    #[cfg(feature = "fs")]
     #[test]
     fn file_based_chain() {
         let _ = env_logger::init();
+        #[cfg(feature = "sodium")]
         ::rust_sodium::init();
         info!("creating keys");
         let keys = (0..10)
-            .map(|_| sign::gen_keypair())
+            .map(|_| test_utils::gen_keypair())
             .collect_vec();
         let add_node_1 = BlockIdentifier::Link(LinkDescriptor::NodeGained(keys[1].0.clone()));
         let add_node_2 = BlockIdentifier::Link(LinkDescriptor::NodeGained(keys[2].0.clone()));
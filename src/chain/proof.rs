@@ -16,7 +16,7 @@
 // relating to use of the SAFE Network Software.
 
 use super::debug_bytes;
-use rust_sodium::crypto::sign::{self, PublicKey, Signature};
+use crypto::sign::{self, PublicKey, Signature};
 use std::fmt::{self, Debug, Formatter};
 
 /// Proof as provided by a close group member
@@ -17,9 +17,9 @@
 
 use chain::block_identifier::BlockIdentifier;
 use chain::proof::Proof;
+use crypto::sign::{self, PublicKey, SecretKey};
 use error::Error;
-use maidsafe_utilities::serialisation;
-use rust_sodium::crypto::sign::{self, PublicKey, SecretKey};
+use serialisation;
 
 /// If data block then this is sent by any group member when data is `Put`, `Post` or `Delete`.
 /// If this is a link then it is sent with a `churn` event.
@@ -62,7 +62,7 @@ impl Vote {
     /// Check vote is not for self added/removed
     pub fn is_self_vote(&self) -> bool {
         if let Some(name) = self.identifier.name() {
-            &self.proof.key().0 == name
+            self.proof.key().0[..] == name[..]
         } else {
             false
         }
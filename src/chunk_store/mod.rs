@@ -16,22 +16,37 @@
 // relating to use of the SAFE Network Software.
 
 //! # Chunk Store
-//! A simple, non-persistent, disk-based key-value store.
+//! A simple disk-based key-value store. A store left by an earlier run can be reopened with
+//! `open` or `open_validated`, which move chunks that cannot be read back into a `lost+found`
+//! directory rather than serving them. A chunk found corrupt later, e.g. by a scrubber, can be
+//! moved there with `quarantine_chunk`.
 
 
 use error::Error;
 
-use maidsafe_utilities::serialisation;
 use rustc_serialize::{Decodable, Encodable};
 use rustc_serialize::hex::{FromHex, ToHex};
+use serialisation;
 use std::cmp;
 use std::fs::{self, File};
-use std::io::{self, Read, Write};
+use std::io::{Read, Write};
 use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
+use xor_name::{self, XOR_NAME_LEN};
 
 /// The max name length for a chunk file.
 const MAX_CHUNK_FILE_NAME_LENGTH: usize = 104;
+/// The directory, inside the root, corrupt chunks are moved to when a store is reopened.
+pub const LOST_AND_FOUND_DIR: &'static str = "lost+found";
+
+/// The outcome of reopening a `ChunkStore`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RestoreReport {
+    /// Number of chunks found intact and kept.
+    pub restored: usize,
+    /// Paths in the `lost+found` directory of the chunks moved there.
+    pub quarantined: Vec<PathBuf>,
+}
 
 
 /// `ChunkStore` is a store of data held as serialised files on disk, implementing a maximum disk
@@ -79,6 +94,29 @@ impl<Key, Value> ChunkStore<Key, Value>
         })
     }
 
+    /// Reopens the store in `root` left by an earlier run, with `max_space` allowed storage space.
+    ///
+    /// The chunks are found from the names of the files, which are not read, so this is quick even
+    /// for large stores; chunks are only deserialised when they are fetched. Files not named after
+    /// a key are not chunks and are left alone. The space the chunks use is counted.
+    pub fn open(root: PathBuf,
+                max_space: u64)
+                -> Result<(ChunkStore<Key, Value>, RestoreReport), Error> {
+        ChunkStore::restore(root, max_space, None)
+    }
+
+    /// Reopens the store in `root` as `open` does, also reading every chunk and keeping it only if
+    /// it deserialises and `is_valid` accepts it, e.g. after checking its name or signatures.
+    /// Rejected chunks are moved to the `lost+found` directory.
+    pub fn open_validated<F>(root: PathBuf,
+                             max_space: u64,
+                             is_valid: F)
+                             -> Result<(ChunkStore<Key, Value>, RestoreReport), Error>
+        where F: Fn(&Key, &Value) -> bool
+    {
+        ChunkStore::restore(root, max_space, Some(&is_valid))
+    }
+
     /// Stores a new data chunk under `key`.
     ///
     /// If there is not enough storage space available, returns `Error::NotEnoughSpace`.  In case of
@@ -86,8 +124,13 @@ impl<Key, Value> ChunkStore<Key, Value>
     ///
     /// If the key already exists, it will be overwritten.
     pub fn put(&mut self, key: &Key, value: &Value) -> Result<(), Error> {
-        let serialised_value = serialisation::serialise(value)?;
-        if self.used_space + serialised_value.len() as u64 > self.max_space {
+        self.put_bytes(key, &serialisation::serialise(value)?)
+    }
+
+    /// Stores `bytes` as the data chunk under `key`, as `put` does with a serialised value, e.g.
+    /// for a value in an encoding of the caller's own.
+    pub fn put_bytes(&mut self, key: &Key, bytes: &[u8]) -> Result<(), Error> {
+        if self.used_space + bytes.len() as u64 > self.max_space {
             return Err(Error::NoSpace);
         }
 
@@ -98,7 +141,7 @@ impl<Key, Value> ChunkStore<Key, Value>
         // Write the file.
         File::create(&file_path)
             .and_then(|mut file| {
-                file.write_all(&serialised_value)
+                file.write_all(bytes)
                     .and_then(|()| file.sync_all())
                     .and_then(|()| file.metadata())
                     .map(|metadata| {
@@ -121,16 +164,38 @@ impl<Key, Value> ChunkStore<Key, Value>
     ///
     /// If the data file can't be accessed, it returns `Error::ChunkNotFound`.
     pub fn get(&self, key: &Key) -> Result<Value, Error> {
+        Ok(serialisation::deserialise::<Value>(&self.get_bytes(key)?)?)
+    }
+
+    /// Returns the bytes of the data chunk stored under `key`, without deserialising them.
+    ///
+    /// If the data file can't be accessed, it returns `Error::NoFile`.
+    pub fn get_bytes(&self, key: &Key) -> Result<Vec<u8>, Error> {
         match File::open(self.file_path(key)?) {
             Ok(mut file) => {
                 let mut contents = Vec::<u8>::new();
                 let _ = file.read_to_end(&mut contents)?;
-                Ok(serialisation::deserialise::<Value>(&contents)?)
+                Ok(contents)
             }
             Err(_) => Err(Error::NoFile),
         }
     }
 
+    /// Moves the chunk stored under `key` into the `lost+found` directory, e.g. once it has been
+    /// found corrupt, returning its path there. It no longer counts towards the space used.
+    ///
+    /// If there is no chunk under `key`, returns `Error::NoFile`.
+    pub fn quarantine_chunk(&mut self, key: &Key) -> Result<PathBuf, Error> {
+        let file_path = self.file_path(key)?;
+        let len = match fs::metadata(&file_path) {
+            Ok(metadata) => metadata.len(),
+            Err(_) => return Err(Error::NoFile),
+        };
+        let target = self.quarantine(&file_path)?;
+        self.used_space -= cmp::min(len, self.used_space);
+        Ok(target)
+    }
+
     /// Tests if a data chunk has been previously stored under `key`.
     pub fn has(&self, key: &Key) -> bool {
         let file_path = if let Ok(path) = self.file_path(key) {
@@ -147,17 +212,16 @@ impl<Key, Value> ChunkStore<Key, Value>
 
     /// Lists all keys of currently-data stored.
     pub fn keys(&self) -> Vec<Key> {
-        fs::read_dir(&self.rootdir)
-            .and_then(|dir_entries| {
-                let dir_entry_to_routing_name = |dir_entry: io::Result<fs::DirEntry>| {
-                    dir_entry.ok()
-                        .and_then(|entry| entry.file_name().into_string().ok())
-                        .and_then(|hex_name| hex_name.from_hex().ok())
-                        .and_then(|bytes| serialisation::deserialise::<Key>(&*bytes).ok())
-                };
-                Ok(dir_entries.filter_map(dir_entry_to_routing_name).collect())
-            })
-            .unwrap_or_else(|_| Vec::new())
+        self.iter_keys().collect()
+    }
+
+    /// Iterates over the keys of the data stored, reading them from the directory as it goes and
+    /// without reading any data.
+    pub fn iter_keys(&self) -> Keys<Key> {
+        Keys {
+            dir_entries: fs::read_dir(&self.rootdir).ok(),
+            phantom: PhantomData,
+        }
     }
 
     /// Returns the maximum amount of storage space available for this ChunkStore.
@@ -170,6 +234,60 @@ impl<Key, Value> ChunkStore<Key, Value>
         self.used_space
     }
 
+    fn restore(root: PathBuf,
+               max_space: u64,
+               is_valid: Option<&Fn(&Key, &Value) -> bool>)
+               -> Result<(ChunkStore<Key, Value>, RestoreReport), Error> {
+        let mut store = ChunkStore::new(root, max_space)?;
+        let mut report = RestoreReport::default();
+        for entry in fs::read_dir(&store.rootdir)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_file() {
+                continue;
+            }
+            let path = entry.path();
+            match Self::is_intact(&path, is_valid) {
+                Some(true) => {
+                    store.used_space += entry.metadata()?.len();
+                    report.restored += 1;
+                }
+                Some(false) => report.quarantined.push(store.quarantine(&path)?),
+                None => (),
+            }
+        }
+        Ok((store, report))
+    }
+
+    /// Returns whether the chunk in `path` is intact, or `None` if the file is not a chunk.
+    fn is_intact(path: &Path, is_valid: Option<&Fn(&Key, &Value) -> bool>) -> Option<bool> {
+        let key = path.file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|hex_name| hex_name.from_hex().ok())
+            .and_then(|bytes| serialisation::deserialise::<Key>(&bytes).ok());
+        let key = match key {
+            Some(key) => key,
+            None => return None,
+        };
+        let is_valid = match is_valid {
+            Some(is_valid) => is_valid,
+            None => return Some(true),
+        };
+        let mut contents = Vec::new();
+        if File::open(path).and_then(|mut file| file.read_to_end(&mut contents)).is_err() {
+            return Some(false);
+        }
+        Some(serialisation::deserialise::<Value>(&contents)
+            .map_or(false, |value| is_valid(&key, &value)))
+    }
+
+    fn quarantine(&self, path: &Path) -> Result<PathBuf, Error> {
+        let lost_and_found = self.rootdir.join(LOST_AND_FOUND_DIR);
+        fs::create_dir_all(&lost_and_found)?;
+        let target = lost_and_found.join(path.file_name().unwrap_or_default());
+        fs::rename(path, &target)?;
+        Ok(target)
+    }
+
     fn do_delete(&mut self, file_path: &Path) -> Result<(), Error> {
         if let Ok(metadata) = fs::metadata(file_path) {
             self.used_space -= cmp::min(metadata.len(), self.used_space);
@@ -185,3 +303,53 @@ impl<Key, Value> ChunkStore<Key, Value>
         Ok(self.rootdir.join(path_name))
     }
 }
+
+impl<Value> ChunkStore<[u8; XOR_NAME_LEN], Value>
+    where Value: Decodable + Encodable
+{
+    /// Lists the names of data stored from `start` to `end` inclusive, in order.
+    pub fn names_in_range(&self,
+                          start: &[u8; XOR_NAME_LEN],
+                          end: &[u8; XOR_NAME_LEN])
+                          -> Vec<[u8; XOR_NAME_LEN]> {
+        let mut names: Vec<_> =
+            self.iter_keys().filter(|name| name >= start && name <= end).collect();
+        names.sort();
+        names
+    }
+
+    /// Lists the (at most) `k` names of data stored closest to `target`, closest first.
+    pub fn names_close_to(&self,
+                          target: &[u8; XOR_NAME_LEN],
+                          k: usize)
+                          -> Vec<[u8; XOR_NAME_LEN]> {
+        xor_name::closest_nodes(target, &self.keys(), k)
+    }
+}
+
+/// Iterator over the keys of the data in a `ChunkStore`, returned by `iter_keys`.
+pub struct Keys<Key> {
+    dir_entries: Option<fs::ReadDir>,
+    phantom: PhantomData<Key>,
+}
+
+impl<Key: Decodable> Iterator for Keys<Key> {
+    type Item = Key;
+
+    fn next(&mut self) -> Option<Key> {
+        let dir_entries = match self.dir_entries {
+            Some(ref mut dir_entries) => dir_entries,
+            None => return None,
+        };
+        for dir_entry in dir_entries {
+            let key = dir_entry.ok()
+                .and_then(|entry| entry.file_name().into_string().ok())
+                .and_then(|hex_name| hex_name.from_hex().ok())
+                .and_then(|bytes| serialisation::deserialise::<Key>(&*bytes).ok());
+            if key.is_some() {
+                return key;
+            }
+        }
+        None
+    }
+}
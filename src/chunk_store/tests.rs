@@ -16,10 +16,13 @@
 // relating to use of the SAFE Network Software.
 
 #![cfg(test)]
-use chunk_store::ChunkStore;
+use chunk_store::{ChunkStore, LOST_AND_FOUND_DIR};
 use error::Error;
-use maidsafe_utilities::serialisation;
 use rand::{self, Rng};
+use rustc_serialize::hex::ToHex;
+use serialisation;
+use std::fs::File;
+use std::io::Write;
 use tempdir::TempDir;
 
 macro_rules! assert_err {
@@ -194,3 +197,74 @@ fn keys() {
                    chunks.data_and_sizes.len() - index - 1);
     }
 }
+
+#[test]
+fn names_in_range_and_close_to() {
+    let root = unwrap!(TempDir::new("test"));
+    let mut chunk_store = unwrap!(ChunkStore::<[u8; 32], u8>::new(root.path().to_path_buf(),
+                                                                  1000));
+    let names: Vec<[u8; 32]> = (0..4).map(|i| [i * 10; 32]).collect();
+    for name in &names {
+        unwrap!(chunk_store.put(name, &0));
+    }
+
+    assert_eq!(chunk_store.iter_keys().count(), names.len());
+    assert_eq!(chunk_store.names_in_range(&[5; 32], &[20; 32]),
+               vec![[10; 32], [20; 32]]);
+    assert!(chunk_store.names_in_range(&[31; 32], &[255; 32]).is_empty());
+    assert_eq!(chunk_store.names_close_to(&[21; 32], 2),
+               vec![[20; 32], [30; 32]]);
+}
+
+#[test]
+fn open_and_quarantine() {
+    let root = unwrap!(TempDir::new("test"));
+    let path = root.path().to_path_buf();
+    let used_space = {
+        let mut chunk_store = unwrap!(ChunkStore::<u8, Vec<u8>>::new(path.clone(), 1000));
+        for key in 0..3 {
+            unwrap!(chunk_store.put(&key, &vec![key; 10]));
+        }
+        chunk_store.used_space()
+    };
+    // Corrupt the chunk under key 1, and leave a file which isn't a chunk.
+    let corrupt = path.join(unwrap!(serialisation::serialise(&1u8)).to_hex());
+    unwrap!(unwrap!(File::create(&corrupt)).write_all(&[1, 2, 3]));
+    unwrap!(File::create(path.join("other")));
+
+    let (chunk_store, report) = unwrap!(ChunkStore::<u8, Vec<u8>>::open(path.clone(), 1000));
+    assert_eq!(report.restored, 3);
+    assert!(report.quarantined.is_empty());
+    assert_eq!(chunk_store.used_space(), used_space - 10 - 8 + 3);
+    assert_err!(chunk_store.get(&1), Error::Serialisation(_));
+
+    let (chunk_store, report) =
+        unwrap!(ChunkStore::<u8, Vec<u8>>::open_validated(path.clone(), 1000, |key, value| {
+            value.iter().all(|byte| byte == key)
+        }));
+    assert_eq!(report.restored, 2);
+    let quarantined = path.join(LOST_AND_FOUND_DIR).join(unwrap!(corrupt.file_name()));
+    assert_eq!(report.quarantined, vec![quarantined]);
+    assert!(!chunk_store.has(&1));
+    assert_eq!(unwrap!(chunk_store.get(&2)), vec![2; 10]);
+    assert!(path.join("other").exists());
+}
+
+
+#[test]
+fn quarantine_chunk() {
+    let root = unwrap!(TempDir::new("test"));
+    let path = root.path().to_path_buf();
+    let mut chunk_store = unwrap!(ChunkStore::<u8, Vec<u8>>::new(path.clone(), 1000));
+    unwrap!(chunk_store.put(&1, &vec![1; 10]));
+    unwrap!(chunk_store.put(&2, &vec![2; 10]));
+    let used_space = chunk_store.used_space();
+
+    let quarantined = unwrap!(chunk_store.quarantine_chunk(&1));
+    assert_eq!(quarantined.parent(), Some(path.join(LOST_AND_FOUND_DIR).as_path()));
+    assert!(quarantined.exists());
+    assert!(!chunk_store.has(&1));
+    assert_eq!(chunk_store.used_space(), used_space / 2);
+    assert_eq!(chunk_store.keys(), vec![2]);
+    assert_err!(chunk_store.quarantine_chunk(&1), Error::NoFile);
+}
@@ -0,0 +1,106 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+//! # Data addresses
+//! The names apps store their data under, derived deterministically from the app's id, a salt
+//! naming the item within the app and the type tag, so every implementation finds the same data
+//! at the same address.
+//!
+//! Each input is written in the canonical encoding after a domain-separation prefix, so distinct
+//! inputs can never run together into the same name, e.g. app `ab` with salt `c` and app `a` with
+//! salt `bc`. Public addresses are the SHA3-256 hash of that encoding, so anyone knowing the app
+//! and salt can find the data. Private addresses are derived with HKDF-SHA256 keyed by a secret
+//! of the owner's, so neither the name reveals which app or salt it belongs to nor can anyone
+//! without the secret enumerate likely salts to find the owner's containers.
+
+#[cfg(feature = "sodium")]
+use crypto::ratchet;
+use data::DataIdentifier;
+use data::canonical::CanonicalWriter;
+use sha3;
+
+/// Domain-separation prefix of the encoding hashed to a public address.
+const ADDRESS_DOMAIN: &'static [u8] = b"data_chain/Address/1";
+/// Domain-separation prefix of the encoding a private address is derived from.
+#[cfg(feature = "sodium")]
+const PRIVATE_ADDRESS_DOMAIN: &'static [u8] = b"data_chain/Address/private/1";
+
+/// Returns the address of the structured data of type `tag` which the app `app_id` stores under
+/// `salt`.
+pub fn derive_address(app_id: &[u8], salt: &[u8], tag: u64) -> DataIdentifier {
+    DataIdentifier::Structured(sha3::hash(&encode(ADDRESS_DOMAIN, app_id, salt, tag)), tag)
+}
+
+/// Returns the address of the private container of type `tag` which the app `app_id` stores
+/// under `salt`, keyed by `secret`, e.g. a key of the owner's account. Without `secret` the
+/// address can be neither found from the app and salt nor traced back to them.
+///
+/// HKDF-SHA256 is taken from rust_sodium, so this needs the `sodium` feature.
+#[cfg(feature = "sodium")]
+pub fn derive_private_address(secret: &[u8],
+                              app_id: &[u8],
+                              salt: &[u8],
+                              tag: u64)
+                              -> DataIdentifier {
+    let info = encode(PRIVATE_ADDRESS_DOMAIN, app_id, salt, tag);
+    DataIdentifier::Structured(ratchet::hkdf_sha256(secret, &info), tag)
+}
+
+fn encode(domain: &[u8], app_id: &[u8], salt: &[u8], tag: u64) -> Vec<u8> {
+    let mut writer = CanonicalWriter::new(domain);
+    let _ = writer.write_bytes(app_id).write_bytes(salt).write_u64(tag);
+    writer.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustc_serialize::hex::ToHex;
+
+    #[test]
+    fn public_addresses() {
+        let address = derive_address(b"app", b"salt", 5);
+        assert_eq!(address, derive_address(b"app", b"salt", 5));
+        match address {
+            DataIdentifier::Structured(name, 5) => {
+                assert_eq!(name.to_hex(),
+                           "6ad5d437275826d5b345d07b416b81faa4191781b0a0b1d5cb2368adbe2e8add")
+            }
+            address => panic!("Unexpected address {:?}", address),
+        }
+
+        assert!(derive_address(b"app", b"salt", 6).name() != address.name());
+        assert!(derive_address(b"app", b"pepper", 5) != address);
+        assert!(derive_address(b"other app", b"salt", 5) != address);
+        assert!(derive_address(b"ab", b"c", 5) != derive_address(b"a", b"bc", 5));
+    }
+
+    #[cfg(feature = "sodium")]
+    #[test]
+    fn private_addresses() {
+        let address = derive_private_address(b"secret", b"app", b"salt", 5);
+        assert_eq!(address, derive_private_address(b"secret", b"app", b"salt", 5));
+        match address {
+            DataIdentifier::Structured(_, 5) => (),
+            address => panic!("Unexpected address {:?}", address),
+        }
+        assert!(address != derive_address(b"app", b"salt", 5));
+        assert!(derive_private_address(b"other secret", b"app", b"salt", 5) != address);
+        assert!(derive_private_address(b"secret", b"app", b"pepper", 5) != address);
+        assert!(derive_private_address(b"secret", b"ab", b"c", 5) !=
+                derive_private_address(b"secret", b"a", b"bc", 5));
+    }
+}
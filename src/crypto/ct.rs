@@ -0,0 +1,111 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Constant-time comparisons
+//! Derived `PartialEq` stops at the first differing byte, so the time a comparison takes reveals
+//! how much of a guess was right. The helpers here compare names, keys and signatures in time
+//! depending only on their length, for use wherever a value received from a peer is checked
+//! against an expected one.
+
+use crypto::sign::{PublicKey, Signature};
+#[cfg(feature = "sodium")]
+use rust_sodium::utils;
+
+/// Compares two byte strings in time depending only on their lengths.
+///
+/// Byte strings of different lengths are unequal; the lengths themselves are not kept secret.
+#[cfg(feature = "sodium")]
+pub fn bytes_eq(lhs: &[u8], rhs: &[u8]) -> bool {
+    utils::memcmp(lhs, rhs)
+}
+
+/// Compares two byte strings in time depending only on their lengths.
+///
+/// Byte strings of different lengths are unequal; the lengths themselves are not kept secret.
+/// Without the `sodium` feature the differences of every byte are accumulated before any is
+/// tested, so the loop has no early exit.
+#[cfg(not(feature = "sodium"))]
+pub fn bytes_eq(lhs: &[u8], rhs: &[u8]) -> bool {
+    lhs.len() == rhs.len() &&
+    lhs.iter().zip(rhs).fold(0, |difference, (lhs, rhs)| difference | (lhs ^ rhs)) == 0
+}
+
+/// Compares two names in constant time.
+pub fn name_eq(lhs: &[u8; 32], rhs: &[u8; 32]) -> bool {
+    bytes_eq(lhs, rhs)
+}
+
+/// Compares two public keys in constant time.
+pub fn key_eq(lhs: &PublicKey, rhs: &PublicKey) -> bool {
+    bytes_eq(&lhs.0, &rhs.0)
+}
+
+/// Compares two signatures in constant time.
+pub fn signature_eq(lhs: &Signature, rhs: &Signature) -> bool {
+    bytes_eq(&lhs.0, &rhs.0)
+}
+
+/// Compares two lists of public keys, item by item, in time depending only on their lengths.
+pub fn keys_eq(lhs: &[PublicKey], rhs: &[PublicKey]) -> bool {
+    lhs.len() == rhs.len() &&
+    lhs.iter().zip(rhs).fold(true, |equal, (lhs, rhs)| key_eq(lhs, rhs) & equal)
+}
+
+/// Is `key` one of `keys`, checking every key whether or not an earlier one matched.
+pub fn contains_key(keys: &[PublicKey], key: &PublicKey) -> bool {
+    keys.iter().fold(false, |found, candidate| key_eq(candidate, key) | found)
+}
+
+/// Is `signature` one of `signatures`, checking every signature whether or not an earlier one
+/// matched.
+pub fn contains_signature(signatures: &[Signature], signature: &Signature) -> bool {
+    signatures.iter().fold(false, |found, candidate| signature_eq(candidate, signature) | found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crypto::sign;
+    use test_utils;
+
+    #[test]
+    fn comparisons() {
+        let name = [7u8; 32];
+        let mut other_name = name;
+        assert!(name_eq(&name, &other_name));
+        other_name[31] = 8;
+        assert!(!name_eq(&name, &other_name));
+        assert!(!bytes_eq(&name, &name[..31]));
+
+        let (key1, secret_key) = test_utils::gen_keypair();
+        let (key2, _) = test_utils::gen_keypair();
+        assert!(key_eq(&key1, &key1));
+        assert!(!key_eq(&key1, &key2));
+        assert!(keys_eq(&[key1, key2], &[key1, key2]));
+        assert!(!keys_eq(&[key1, key2], &[key2, key1]));
+        assert!(!keys_eq(&[key1], &[key1, key2]));
+        assert!(contains_key(&[key2, key1], &key1));
+        assert!(!contains_key(&[key2], &key1));
+
+        let signature1 = sign::sign_detached(b"one", &secret_key);
+        let signature2 = sign::sign_detached(b"two", &secret_key);
+        assert!(signature_eq(&signature1, &signature1));
+        assert!(!signature_eq(&signature1, &signature2));
+        assert!(contains_signature(&[signature2, signature1], &signature1));
+        assert!(!contains_signature(&[signature2], &signature1));
+    }
+}
@@ -0,0 +1,36 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+/// Deterministic per-app data addresses, public or keyed by a secret
+pub mod address;
+/// Constant-time comparisons of names, keys and signatures
+pub mod ct;
+/// Per-version symmetric keys derived along an HKDF chain
+#[cfg(feature = "sodium")]
+pub mod ratchet;
+/// Ed25519 signing, from rust_sodium or, with the `dalek` feature, ed25519-dalek; or experimental
+/// Dilithium2 signing with the `dilithium` feature
+pub mod sign;
+/// Caching of signature verification results
+pub mod validation_cache;
+
+pub use crypto::address::derive_address;
+#[cfg(feature = "sodium")]
+pub use crypto::address::derive_private_address;
+#[cfg(feature = "sodium")]
+pub use crypto::ratchet::KeyRatchet;
+pub use crypto::validation_cache::ValidationCache;
@@ -0,0 +1,148 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Key ratchet
+//! A chain of symmetric keys, one per version of some data, derived with HKDF-SHA256 (RFC 5869)
+//! from a root secret.
+//!
+//! Each step of the chain derives two keys from the chain key of a version: the key of the
+//! version, used to encrypt it, and the chain key of the next version, which replaces it. Neither
+//! can be used to derive the chain key they were derived from, so the key of one version exposes
+//! no other version, and a chain key exposes the versions from its own on but none before it.
+//! Only the root secret exposes every version.
+
+use rust_sodium::crypto::auth::hmacsha256;
+use rust_sodium::crypto::secretbox;
+
+/// HKDF `info` deriving the chain key of the first version from the root secret.
+const ROOT_INFO: &'static [u8] = b"data_chain/KeyRatchet/root/1";
+/// HKDF `info` deriving the chain key of the next version from a chain key.
+const CHAIN_INFO: &'static [u8] = b"data_chain/KeyRatchet/chain/1";
+/// HKDF `info` deriving the key of a version from its chain key.
+const VERSION_INFO: &'static [u8] = b"data_chain/KeyRatchet/version/1";
+
+/// HKDF-SHA256 of `input_key` with the default (all-zero) salt, producing 32 bytes.
+pub fn hkdf_sha256(input_key: &[u8], info: &[u8]) -> [u8; 32] {
+    let salt = hmacsha256::Key([0; hmacsha256::KEYBYTES]);
+    let pseudo_random_key = hmacsha256::Key(hmacsha256::authenticate(input_key, &salt).0);
+    expand(&pseudo_random_key, info)
+}
+
+/// HKDF-Expand producing a single block, which is all of the 32 bytes needed.
+fn expand(pseudo_random_key: &hmacsha256::Key, info: &[u8]) -> [u8; 32] {
+    let mut message = info.to_vec();
+    message.push(1);
+    hmacsha256::authenticate(&message, pseudo_random_key).0
+}
+
+/// The chain key of one version of a key ratchet.
+///
+/// It can move on to later versions, with `advance`, but never back.
+#[derive(Clone, PartialEq, Eq)]
+pub struct KeyRatchet {
+    chain_key: [u8; 32],
+    version: u64,
+}
+
+impl KeyRatchet {
+    /// Starts the chain of `root_secret` at version 0.
+    pub fn new(root_secret: &[u8]) -> KeyRatchet {
+        KeyRatchet {
+            chain_key: hkdf_sha256(root_secret, ROOT_INFO),
+            version: 0,
+        }
+    }
+
+    /// Returns the chain of `root_secret` at `version`, taking a step per version.
+    pub fn at_version(root_secret: &[u8], version: u64) -> KeyRatchet {
+        let mut ratchet = KeyRatchet::new(root_secret);
+        ratchet.advance_to(version);
+        ratchet
+    }
+
+    /// Resumes a chain from the chain key of `version`, as given by `chain_key`, e.g. to give a
+    /// new reader the versions from `version` on without those before.
+    pub fn from_chain_key(chain_key: [u8; 32], version: u64) -> KeyRatchet {
+        KeyRatchet {
+            chain_key: chain_key,
+            version: version,
+        }
+    }
+
+    /// The version this is the chain key of.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// The chain key, from which this and every later version's key can be derived.
+    pub fn chain_key(&self) -> &[u8; 32] {
+        &self.chain_key
+    }
+
+    /// The key encrypting this version.
+    pub fn version_key(&self) -> secretbox::Key {
+        secretbox::Key(expand(&hmacsha256::Key(self.chain_key), VERSION_INFO))
+    }
+
+    /// Moves on to the next version, forgetting the chain key of this one.
+    pub fn advance(&mut self) {
+        self.chain_key = expand(&hmacsha256::Key(self.chain_key), CHAIN_INFO);
+        self.version += 1;
+    }
+
+    /// Moves on to `version`, if it is not behind it already. Returns whether it is now at
+    /// `version`.
+    pub fn advance_to(&mut self, version: u64) -> bool {
+        while self.version < version {
+            self.advance();
+        }
+        self.version == version
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustc_serialize::hex::{FromHex, ToHex};
+
+    #[test]
+    fn rfc_5869_test_case_3() {
+        // Test case 3 of RFC 5869: SHA-256, zero-length salt and info, 42 bytes of output, of
+        // which the first 32 are derived here.
+        let input_key = unwrap!("0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b".from_hex());
+        assert_eq!(hkdf_sha256(&input_key, &[]).to_hex(),
+                   "8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d");
+    }
+
+    #[test]
+    fn versions() {
+        let root_secret = b"root secret";
+        let mut ratchet = KeyRatchet::new(root_secret);
+        let first_key = ratchet.version_key();
+        ratchet.advance();
+        assert_eq!(ratchet.version(), 1);
+        assert!(ratchet.version_key() != first_key);
+        assert!(ratchet == KeyRatchet::at_version(root_secret, 1));
+        assert!(KeyRatchet::new(b"another secret").version_key() != first_key);
+
+        // A chain key gives later versions but not earlier ones.
+        let mut resumed = KeyRatchet::from_chain_key(*ratchet.chain_key(), 1);
+        assert!(!resumed.advance_to(0));
+        assert!(resumed.advance_to(5));
+        assert!(resumed.version_key() == KeyRatchet::at_version(root_secret, 5).version_key());
+    }
+}
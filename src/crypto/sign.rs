@@ -0,0 +1,420 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Signing
+//! The Ed25519 signing primitives used throughout the crate. By default they are rust_sodium's;
+//! with the `dalek` feature they are a pure-Rust implementation on `ed25519-dalek` with the same
+//! API. Keys and signatures are the same bytes and serialise identically under both backends, so
+//! nodes built with either can verify each other's data. Builds without the `sodium` feature,
+//! e.g. for `wasm32-unknown-unknown`, must enable `dalek` (or `dilithium`).
+//!
+//! The experimental `dilithium` feature, which takes precedence over `dalek`, replaces Ed25519
+//! with the post-quantum Dilithium2 scheme on `crystals-dilithium`, again with the same API, as a
+//! migration path away from Ed25519. Its keys and signatures are much larger and cannot be read
+//! by nodes built without the feature, so a network has to switch over as a whole. Data holding
+//! them may grow beyond `MAX_BYTES` by their extra size, as given by `size_allowance`.
+
+#[cfg(all(feature = "sodium", not(any(feature = "dalek", feature = "dilithium"))))]
+pub use rust_sodium::crypto::sign::{PUBLICKEYBYTES, PublicKey, SECRETKEYBYTES, SEEDBYTES,
+                                    SIGNATUREBYTES, SecretKey, Seed, Signature, gen_keypair,
+                                    keypair_from_seed, sign_detached, verify_detached};
+
+#[cfg(all(feature = "dalek", not(feature = "dilithium")))]
+pub use self::dalek::{PUBLICKEYBYTES, PublicKey, SECRETKEYBYTES, SEEDBYTES, SIGNATUREBYTES,
+                      SecretKey, Seed, Signature, gen_keypair, keypair_from_seed, sign_detached,
+                      verify_detached};
+
+#[cfg(feature = "dilithium")]
+pub use self::dilithium::{PUBLICKEYBYTES, PublicKey, SECRETKEYBYTES, SEEDBYTES, SIGNATUREBYTES,
+                          SecretKey, Seed, Signature, gen_keypair, keypair_from_seed,
+                          sign_detached, verify_detached};
+
+/// Number of bytes a `PublicKey` takes beyond an Ed25519 one: zero unless the `dilithium` feature
+/// is on.
+pub const PUBLICKEY_OVERHEAD: usize = PUBLICKEYBYTES - 32;
+/// Number of bytes a `Signature` takes beyond an Ed25519 one: zero unless the `dilithium` feature
+/// is on.
+pub const SIGNATURE_OVERHEAD: usize = SIGNATUREBYTES - 64;
+
+/// Returns how many bytes beyond `MAX_BYTES` data holding `keys` public keys and `signatures`
+/// signatures may take, so that the larger keys and signatures of the `dilithium` feature do not
+/// eat into the room left for the content. Always zero with Ed25519.
+pub fn size_allowance(keys: usize, signatures: usize) -> usize {
+    keys * PUBLICKEY_OVERHEAD + signatures * SIGNATURE_OVERHEAD
+}
+
+// Implements the traits rust_sodium's key types have, encoding the bytes as a sequence as
+// rust_sodium does.
+#[cfg(any(feature = "dalek", feature = "dilithium"))]
+macro_rules! byte_newtype {
+    ($name:ident, $len:expr) => {
+        impl Clone for $name {
+            fn clone(&self) -> $name {
+                $name(self.0)
+            }
+        }
+
+        impl PartialEq for $name {
+            fn eq(&self, other: &$name) -> bool {
+                self.0[..] == other.0[..]
+            }
+        }
+
+        impl Eq for $name {}
+
+        impl PartialOrd for $name {
+            fn partial_cmp(&self, other: &$name) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for $name {
+            fn cmp(&self, other: &$name) -> Ordering {
+                self.0[..].cmp(&other.0[..])
+            }
+        }
+
+        impl Hash for $name {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.0[..].hash(state)
+            }
+        }
+
+        #[cfg(feature = "serialisation")]
+        impl Encodable for $name {
+            fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), E::Error> {
+                encoder.emit_seq($len, |encoder| {
+                    for (i, byte) in self.0.iter().enumerate() {
+                        encoder.emit_seq_elt(i, |encoder| byte.encode(encoder))?;
+                    }
+                    Ok(())
+                })
+            }
+        }
+
+        #[cfg(feature = "serialisation")]
+        impl Decodable for $name {
+            fn decode<D: Decoder>(decoder: &mut D) -> Result<$name, D::Error> {
+                decoder.read_seq(|decoder, len| {
+                    if len != $len {
+                        return Err(decoder.error(concat!("wrong length for ",
+                                                         stringify!($name))));
+                    }
+                    let mut bytes = [0; $len];
+                    for (i, byte) in bytes.iter_mut().enumerate() {
+                        *byte = decoder.read_seq_elt(i, Decodable::decode)?;
+                    }
+                    Ok($name(bytes))
+                })
+            }
+        }
+    }
+}
+
+#[cfg(all(feature = "dalek", not(feature = "dilithium")))]
+mod dalek {
+    use ed25519_dalek::{self, ExpandedSecretKey, Verifier};
+    use ed25519_dalek::signature::Signature as SignatureBytes;
+    use rand::{OsRng, Rng};
+    #[cfg(feature = "serialisation")]
+    use rustc_serialize::{Decodable, Decoder, Encodable, Encoder};
+    use std::cmp::Ordering;
+    use std::fmt::{self, Debug, Formatter};
+    use std::hash::{Hash, Hasher};
+
+    /// Number of bytes in a `PublicKey`.
+    pub const PUBLICKEYBYTES: usize = 32;
+    /// Number of bytes in a `SecretKey`.
+    pub const SECRETKEYBYTES: usize = 64;
+    /// Number of bytes in a `Seed`.
+    pub const SEEDBYTES: usize = 32;
+    /// Number of bytes in a `Signature`.
+    pub const SIGNATUREBYTES: usize = 64;
+
+    /// An Ed25519 public key.
+    pub struct PublicKey(pub [u8; PUBLICKEYBYTES]);
+    byte_newtype!(PublicKey, PUBLICKEYBYTES);
+
+    impl Copy for PublicKey {}
+
+    impl Debug for PublicKey {
+        fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+            write!(formatter, "PublicKey({:?})", &self.0[..])
+        }
+    }
+
+    /// An Ed25519 secret key: the seed followed by the public key, as in rust_sodium.
+    pub struct SecretKey(pub [u8; SECRETKEYBYTES]);
+    byte_newtype!(SecretKey, SECRETKEYBYTES);
+
+    impl Debug for SecretKey {
+        fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+            write!(formatter, "SecretKey(****)")
+        }
+    }
+
+    /// The seed a key pair is derived from.
+    pub struct Seed(pub [u8; SEEDBYTES]);
+    byte_newtype!(Seed, SEEDBYTES);
+
+    impl Debug for Seed {
+        fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+            write!(formatter, "Seed(****)")
+        }
+    }
+
+    /// A detached Ed25519 signature.
+    pub struct Signature(pub [u8; SIGNATUREBYTES]);
+    byte_newtype!(Signature, SIGNATUREBYTES);
+
+    impl Copy for Signature {}
+
+    impl Debug for Signature {
+        fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+            write!(formatter, "Signature({:?})", &self.0[..])
+        }
+    }
+
+    /// Generates a random key pair.
+    pub fn gen_keypair() -> (PublicKey, SecretKey) {
+        let mut rng = match OsRng::new() {
+            Ok(rng) => rng,
+            Err(error) => panic!("Failed to open the OS random number generator: {}", error),
+        };
+        let mut seed = Seed([0; SEEDBYTES]);
+        rng.fill_bytes(&mut seed.0);
+        keypair_from_seed(&seed)
+    }
+
+    /// Derives the key pair for `seed`.
+    pub fn keypair_from_seed(seed: &Seed) -> (PublicKey, SecretKey) {
+        let (_, public) = expand(&seed.0);
+        let mut secret_key = SecretKey([0; SECRETKEYBYTES]);
+        secret_key.0[..SEEDBYTES].copy_from_slice(&seed.0);
+        secret_key.0[SEEDBYTES..].copy_from_slice(public.as_bytes());
+        (PublicKey(public.to_bytes()), secret_key)
+    }
+
+    /// Signs `message` with `secret_key`.
+    ///
+    /// The public key is derived from the seed half of `secret_key` rather than read from its
+    /// second half, which makes no difference for keys from `gen_keypair` or `keypair_from_seed`.
+    pub fn sign_detached(message: &[u8], secret_key: &SecretKey) -> Signature {
+        let (secret, public) = expand(&secret_key.0[..SEEDBYTES]);
+        let signature = ExpandedSecretKey::from(&secret).sign(message, &public);
+        Signature(signature.to_bytes())
+    }
+
+    /// Returns whether `signature` is `public_key`'s valid signature of `message`.
+    pub fn verify_detached(signature: &Signature, message: &[u8], public_key: &PublicKey) -> bool {
+        let public = match ed25519_dalek::PublicKey::from_bytes(&public_key.0) {
+            Ok(public) => public,
+            Err(_) => return false,
+        };
+        let signature = match ed25519_dalek::Signature::from_bytes(&signature.0) {
+            Ok(signature) => signature,
+            Err(_) => return false,
+        };
+        public.verify(message, &signature).is_ok()
+    }
+
+    fn expand(seed: &[u8]) -> (ed25519_dalek::SecretKey, ed25519_dalek::PublicKey) {
+        match ed25519_dalek::SecretKey::from_bytes(seed) {
+            Ok(secret) => {
+                let public = ed25519_dalek::PublicKey::from(&secret);
+                (secret, public)
+            }
+            Err(error) => unreachable!("A {} byte seed was refused: {}", SEEDBYTES, error),
+        }
+    }
+}
+
+#[cfg(feature = "dilithium")]
+mod dilithium {
+    use crystals_dilithium::dilithium2;
+    use rand::{OsRng, Rng};
+    #[cfg(feature = "serialisation")]
+    use rustc_serialize::{Decodable, Decoder, Encodable, Encoder};
+    use std::cmp::Ordering;
+    use std::fmt::{self, Debug, Formatter};
+    use std::hash::{Hash, Hasher};
+
+    /// Number of bytes in a `PublicKey`.
+    pub const PUBLICKEYBYTES: usize = dilithium2::PUBLICKEYBYTES;
+    /// Number of bytes in a `SecretKey`.
+    pub const SECRETKEYBYTES: usize = dilithium2::SECRETKEYBYTES + PUBLICKEYBYTES;
+    /// Number of bytes in a `Seed`.
+    pub const SEEDBYTES: usize = 32;
+    /// Number of bytes in a `Signature`.
+    pub const SIGNATUREBYTES: usize = dilithium2::SIGNBYTES;
+
+    /// A Dilithium2 public key.
+    pub struct PublicKey(pub [u8; PUBLICKEYBYTES]);
+    byte_newtype!(PublicKey, PUBLICKEYBYTES);
+
+    impl Copy for PublicKey {}
+
+    impl Debug for PublicKey {
+        fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+            write!(formatter, "PublicKey({:?}..)", &self.0[..8])
+        }
+    }
+
+    /// A Dilithium2 secret key followed by its public key, so the public key can be read from
+    /// the end of it as from an Ed25519 one.
+    pub struct SecretKey(pub [u8; SECRETKEYBYTES]);
+    byte_newtype!(SecretKey, SECRETKEYBYTES);
+
+    impl Debug for SecretKey {
+        fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+            write!(formatter, "SecretKey(****)")
+        }
+    }
+
+    /// The seed a key pair is derived from.
+    pub struct Seed(pub [u8; SEEDBYTES]);
+    byte_newtype!(Seed, SEEDBYTES);
+
+    impl Debug for Seed {
+        fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+            write!(formatter, "Seed(****)")
+        }
+    }
+
+    /// A detached Dilithium2 signature.
+    pub struct Signature(pub [u8; SIGNATUREBYTES]);
+    byte_newtype!(Signature, SIGNATUREBYTES);
+
+    impl Copy for Signature {}
+
+    impl Debug for Signature {
+        fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+            write!(formatter, "Signature({:?}..)", &self.0[..8])
+        }
+    }
+
+    /// Generates a random key pair.
+    pub fn gen_keypair() -> (PublicKey, SecretKey) {
+        let mut rng = match OsRng::new() {
+            Ok(rng) => rng,
+            Err(error) => panic!("Failed to open the OS random number generator: {}", error),
+        };
+        let mut seed = Seed([0; SEEDBYTES]);
+        rng.fill_bytes(&mut seed.0);
+        keypair_from_seed(&seed)
+    }
+
+    /// Derives the key pair for `seed`.
+    pub fn keypair_from_seed(seed: &Seed) -> (PublicKey, SecretKey) {
+        // The key pair's bytes are its public key followed by its secret key.
+        let keypair = dilithium2::Keypair::generate(Some(&seed.0)).to_bytes();
+        let mut public_key = PublicKey([0; PUBLICKEYBYTES]);
+        public_key.0.copy_from_slice(&keypair[..PUBLICKEYBYTES]);
+        let mut secret_key = SecretKey([0; SECRETKEYBYTES]);
+        secret_key.0[..dilithium2::SECRETKEYBYTES].copy_from_slice(&keypair[PUBLICKEYBYTES..]);
+        secret_key.0[dilithium2::SECRETKEYBYTES..].copy_from_slice(&public_key.0);
+        (public_key, secret_key)
+    }
+
+    /// Signs `message` with `secret_key`.
+    pub fn sign_detached(message: &[u8], secret_key: &SecretKey) -> Signature {
+        let secret = dilithium2::SecretKey::from_bytes(&secret_key.0[..dilithium2::SECRETKEYBYTES]);
+        let mut signature = Signature([0; SIGNATUREBYTES]);
+        signature.0.copy_from_slice(&secret.sign(message)[..]);
+        signature
+    }
+
+    /// Returns whether `signature` is `public_key`'s valid signature of `message`.
+    pub fn verify_detached(signature: &Signature, message: &[u8], public_key: &PublicKey) -> bool {
+        dilithium2::PublicKey::from_bytes(&public_key.0).verify(message, &signature.0)
+    }
+}
+
+#[cfg(all(test, feature = "dilithium"))]
+mod tests {
+    use super::*;
+    use rand;
+    use serialisation::{deserialise, serialise};
+
+    #[test]
+    fn dilithium_keys_and_signatures() {
+        let seed = Seed(rand::random());
+        let (public_key, secret_key) = keypair_from_seed(&seed);
+        assert_eq!(keypair_from_seed(&seed), (public_key, secret_key.clone()));
+        assert_eq!(&secret_key.0[SECRETKEYBYTES - PUBLICKEYBYTES..], &public_key.0[..]);
+
+        let message = b"message";
+        let signature = sign_detached(message, &secret_key);
+        assert!(verify_detached(&signature, message, &public_key));
+        assert!(!verify_detached(&signature, b"other message", &public_key));
+        let (other_public_key, _) = gen_keypair();
+        assert!(!verify_detached(&signature, message, &other_public_key));
+
+        let parsed: Signature = unwrap!(deserialise(&unwrap!(serialise(&signature))));
+        assert_eq!(parsed, signature);
+        let parsed: PublicKey = unwrap!(deserialise(&unwrap!(serialise(&public_key))));
+        assert_eq!(parsed, public_key);
+        let parsed: SecretKey = unwrap!(deserialise(&unwrap!(serialise(&secret_key))));
+        assert_eq!(parsed, secret_key);
+    }
+
+    #[test]
+    fn size_allowance() {
+        assert_eq!(super::size_allowance(0, 0), 0);
+        assert_eq!(super::size_allowance(2, 1),
+                   2 * (PUBLICKEYBYTES - 32) + SIGNATUREBYTES - 64);
+    }
+}
+
+#[cfg(all(test, feature = "dalek", feature = "sodium", not(feature = "dilithium")))]
+mod tests {
+    use super::*;
+    use rand;
+    use rust_sodium::crypto::sign as sodium;
+    use serialisation::{deserialise, serialise};
+
+    #[test]
+    fn compatible_with_rust_sodium() {
+        let seed: [u8; SEEDBYTES] = rand::random();
+        let (public_key, secret_key) = keypair_from_seed(&Seed(seed));
+        let (sodium_public_key, sodium_secret_key) = sodium::keypair_from_seed(&sodium::Seed(seed));
+        assert_eq!(public_key.0, sodium_public_key.0);
+        assert_eq!(&secret_key.0[..], &sodium_secret_key.0[..]);
+        assert_eq!(unwrap!(serialise(&public_key)), unwrap!(serialise(&sodium_public_key)));
+        assert_eq!(unwrap!(serialise(&secret_key)), unwrap!(serialise(&sodium_secret_key)));
+
+        // Ed25519 signatures are deterministic, so both backends produce the same bytes.
+        let message = b"message";
+        let signature = sign_detached(message, &secret_key);
+        let sodium_signature = sodium::sign_detached(message, &sodium_secret_key);
+        assert_eq!(&signature.0[..], &sodium_signature.0[..]);
+        assert_eq!(unwrap!(serialise(&signature)), unwrap!(serialise(&sodium_signature)));
+        assert!(sodium::verify_detached(&sodium::Signature(signature.0),
+                                        message,
+                                        &sodium_public_key));
+        assert!(verify_detached(&Signature(sodium_signature.0), message, &public_key));
+        assert!(!verify_detached(&signature, b"other message", &public_key));
+
+        let (other_public_key, _) = gen_keypair();
+        assert!(!verify_detached(&signature, message, &other_public_key));
+
+        let parsed: Signature = unwrap!(deserialise(&unwrap!(serialise(&sodium_signature))));
+        assert_eq!(parsed, signature);
+        let parsed: PublicKey = unwrap!(deserialise(&unwrap!(serialise(&sodium_public_key))));
+        assert_eq!(parsed, public_key);
+    }
+}
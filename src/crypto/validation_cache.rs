@@ -0,0 +1,173 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Signature validation cache
+//! Groups validate the same data many times over while agreeing on it, each time verifying the
+//! same signatures. A `ValidationCache` remembers the result of verifying each signature, keyed by
+//! the hash of the signed bytes, the public key and the signature, so each is verified once.
+//!
+//! The key is the SHA3-256 hash of those three together: a cached result is only reused for
+//! exactly the same signed bytes, key and signature, which always verify the same way.
+
+use crypto::sign::{self, PublicKey, Signature};
+use sha3::hash;
+use std::collections::{BTreeMap, HashMap};
+use store::CacheMetrics;
+
+struct Entry {
+    valid: bool,
+    last_used: u64,
+}
+
+/// A bounded cache of signature verification results, evicting the least recently used first.
+pub struct ValidationCache {
+    capacity: usize,
+    clock: u64,
+    entries: HashMap<[u8; 32], Entry>,
+    recency: BTreeMap<u64, [u8; 32]>,
+    metrics: CacheMetrics,
+}
+
+impl ValidationCache {
+    /// Creates an empty cache holding up to `capacity` results.
+    pub fn new(capacity: usize) -> ValidationCache {
+        ValidationCache {
+            capacity: capacity,
+            clock: 0,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            metrics: CacheMetrics::default(),
+        }
+    }
+
+    /// Returns whether `signature` is `public_key`'s valid signature of `data`, as
+    /// `sign::verify_detached` does, verifying it only if the result is not cached.
+    pub fn verify_detached(&mut self,
+                           signature: &Signature,
+                           data: &[u8],
+                           public_key: &PublicKey)
+                           -> bool {
+        self.verify(&hash(data), data, signature, public_key)
+    }
+
+    /// As `verify_detached`, given `data_hash`, the SHA3-256 hash of `data`, so that checking
+    /// several signatures of the same data hashes it once.
+    pub fn verify(&mut self,
+                  data_hash: &[u8; 32],
+                  data: &[u8],
+                  signature: &Signature,
+                  public_key: &PublicKey)
+                  -> bool {
+        let mut key_input = Vec::with_capacity(32 + sign::PUBLICKEYBYTES + sign::SIGNATUREBYTES);
+        key_input.extend_from_slice(data_hash);
+        key_input.extend_from_slice(&public_key.0);
+        key_input.extend_from_slice(&signature.0);
+        let key = hash(&key_input);
+
+        self.clock += 1;
+        if let Some(entry) = self.entries.get_mut(&key) {
+            let _ = self.recency.remove(&entry.last_used);
+            let _ = self.recency.insert(self.clock, key);
+            entry.last_used = self.clock;
+            self.metrics.hits += 1;
+            return entry.valid;
+        }
+        self.metrics.misses += 1;
+
+        let valid = sign::verify_detached(signature, data, public_key);
+        if self.capacity > 0 {
+            while self.entries.len() >= self.capacity {
+                self.evict();
+            }
+            let _ = self.recency.insert(self.clock, key);
+            let _ = self.entries.insert(key,
+                                        Entry {
+                                            valid: valid,
+                                            last_used: self.clock,
+                                        });
+        }
+        valid
+    }
+
+    /// Returns the number of cached results.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if no results are cached.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Removes every cached result. The metrics are kept.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+    }
+
+    /// Returns the hit, miss and eviction counts so far.
+    pub fn metrics(&self) -> CacheMetrics {
+        self.metrics
+    }
+
+    fn evict(&mut self) {
+        let oldest = match self.recency.keys().next() {
+            Some(&last_used) => last_used,
+            None => return,
+        };
+        if let Some(key) = self.recency.remove(&oldest) {
+            if self.entries.remove(&key).is_some() {
+                self.metrics.evictions += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crypto::sign;
+    use test_utils;
+
+    #[test]
+    fn caches_results() {
+        let keys = test_utils::gen_keypair();
+        let other_keys = test_utils::gen_keypair();
+        let signature = sign::sign_detached(b"data", &keys.1);
+        let mut cache = ValidationCache::new(2);
+
+        assert!(cache.verify_detached(&signature, b"data", &keys.0));
+        assert!(cache.verify_detached(&signature, b"data", &keys.0));
+        assert!(!cache.verify_detached(&signature, b"other data", &keys.0));
+        assert!(!cache.verify_detached(&signature, b"other data", &keys.0));
+        assert_eq!(cache.metrics().hits, 2);
+        assert_eq!(cache.metrics().misses, 2);
+        assert_eq!(cache.metrics().hit_rate(), 0.5);
+
+        // The least recently used result is evicted.
+        assert!(cache.verify_detached(&signature, b"data", &keys.0));
+        assert!(!cache.verify_detached(&signature, b"data", &other_keys.0));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.metrics().evictions, 1);
+        assert!(!cache.verify_detached(&signature, b"other data", &keys.0));
+        assert_eq!(cache.metrics().misses, 4);
+
+        cache.clear();
+        assert!(cache.is_empty());
+        assert!(ValidationCache::new(0).verify_detached(&signature, b"data", &keys.0));
+    }
+}
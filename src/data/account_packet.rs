@@ -0,0 +1,219 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Account packets
+//! The packet a client stores its account details in, found again from a keyword and PIN alone
+//! and readable only with the password.
+//!
+//! Both the name and the encryption key are derived with scrypt (`pwhash`, interactive limits),
+//! each with its own 32-byte salt, the SHA3-256 hash of a canonical encoding:
+//!
+//! - name : scrypt of the keyword, salted with the hash of `ACCOUNT_NAME_DOMAIN`, PIN.
+//! - key : scrypt of the password, salted with the hash of `ACCOUNT_KEY_DOMAIN`, keyword, PIN.
+//!
+//! The stored data is the serialised nonce and `secretbox` cipher text of the account details.
+//! Each update is encrypted under a fresh nonce.
+
+use crypto::sign::{PublicKey, SecretKey};
+use data::StructuredData;
+use data::canonical::CanonicalWriter;
+use error::Error;
+use rust_sodium::crypto::{pwhash, secretbox};
+use serialisation::{deserialise, serialise};
+use sha3::hash;
+
+/// Type tag of the `StructuredData` holding an account packet.
+pub const ACCOUNT_PACKET_TYPE_TAG: u64 = 0x5afe_acc7;
+/// Domain-separation prefix of the salt of the account packet name.
+pub const ACCOUNT_NAME_DOMAIN: &'static [u8] = b"data_chain/AccountPacket/name/1";
+/// Domain-separation prefix of the salt of the account packet encryption key.
+pub const ACCOUNT_KEY_DOMAIN: &'static [u8] = b"data_chain/AccountPacket/key/1";
+
+/// A client's account details, encrypted and stored as `StructuredData` at a name derived from a
+/// keyword and PIN.
+#[derive(Hash, Eq, PartialEq, PartialOrd, Ord, Clone, Debug)]
+pub struct AccountPacket {
+    structured_data: StructuredData,
+}
+
+impl AccountPacket {
+    /// Creates the first version of the account packet holding `account`, owned by and signed
+    /// with `owner`.
+    pub fn create(keyword: &[u8],
+                  pin: &[u8],
+                  password: &[u8],
+                  account: &[u8],
+                  owner: (&PublicKey, &SecretKey))
+                  -> Result<AccountPacket, Error> {
+        let structured_data = StructuredData::new(ACCOUNT_PACKET_TYPE_TAG,
+                                                  AccountPacket::login_name(keyword, pin)?,
+                                                  0,
+                                                  encrypt(keyword, pin, password, account)?,
+                                                  vec![*owner.0],
+                                                  vec![],
+                                                  Some(owner.1),
+                                                  false)?;
+        Ok(AccountPacket { structured_data: structured_data })
+    }
+
+    /// Returns the name of the account packet for `keyword` and `pin`.
+    pub fn login_name(keyword: &[u8], pin: &[u8]) -> Result<[u8; 32], Error> {
+        let mut salt = CanonicalWriter::new(ACCOUNT_NAME_DOMAIN);
+        let _ = salt.write_bytes(pin);
+        derive(keyword, &salt.into_bytes())
+    }
+
+    /// Wraps `structured_data` retrieved from the network, which must have the account packet
+    /// type tag.
+    pub fn from_structured_data(structured_data: StructuredData) -> Result<AccountPacket, Error> {
+        if structured_data.get_type_tag() != ACCOUNT_PACKET_TYPE_TAG {
+            return Err(Error::BadIdentifier);
+        }
+        Ok(AccountPacket { structured_data: structured_data })
+    }
+
+    /// Creates the next version of the account packet holding `account`, signed with `owner`.
+    ///
+    /// The owners are kept, so the result is a valid successor of this packet.
+    pub fn update(&self,
+                  keyword: &[u8],
+                  pin: &[u8],
+                  password: &[u8],
+                  account: &[u8],
+                  owner: &SecretKey)
+                  -> Result<AccountPacket, Error> {
+        let structured_data = StructuredData::new(ACCOUNT_PACKET_TYPE_TAG,
+                                                  *self.structured_data.name(),
+                                                  self.structured_data.get_version() + 1,
+                                                  encrypt(keyword, pin, password, account)?,
+                                                  self.structured_data.get_owner_keys().to_vec(),
+                                                  vec![],
+                                                  Some(owner),
+                                                  false)?;
+        Ok(AccountPacket { structured_data: structured_data })
+    }
+
+    /// Decrypts the account details.
+    pub fn decrypt(&self, keyword: &[u8], pin: &[u8], password: &[u8]) -> Result<Vec<u8>, Error> {
+        let (nonce, cipher_text): (secretbox::Nonce, Vec<u8>) =
+            deserialise(self.structured_data.get_data())?;
+        let key = encryption_key(keyword, pin, password)?;
+        Ok(secretbox::open(&cipher_text, &nonce, &key)?)
+    }
+
+    /// Returns the underlying `StructuredData`.
+    pub fn structured_data(&self) -> &StructuredData {
+        &self.structured_data
+    }
+
+    /// Unwraps the underlying `StructuredData`, e.g. to store it.
+    pub fn into_structured_data(self) -> StructuredData {
+        self.structured_data
+    }
+}
+
+/// Derives 32 bytes from `secret` with scrypt, salted with the hash of `salt_input`.
+fn derive(secret: &[u8], salt_input: &[u8]) -> Result<[u8; 32], Error> {
+    let salt = pwhash::Salt(hash(salt_input));
+    let mut output = [0u8; 32];
+    let _ = pwhash::derive_key(&mut output,
+                               secret,
+                               &salt,
+                               pwhash::OPSLIMIT_INTERACTIVE,
+                               pwhash::MEMLIMIT_INTERACTIVE)?;
+    Ok(output)
+}
+
+fn encryption_key(keyword: &[u8], pin: &[u8], password: &[u8]) -> Result<secretbox::Key, Error> {
+    let mut salt = CanonicalWriter::new(ACCOUNT_KEY_DOMAIN);
+    let _ = salt.write_bytes(keyword).write_bytes(pin);
+    Ok(secretbox::Key(derive(password, &salt.into_bytes())?))
+}
+
+fn encrypt(keyword: &[u8], pin: &[u8], password: &[u8], account: &[u8]) -> Result<Vec<u8>, Error> {
+    let key = encryption_key(keyword, pin, password)?;
+    let nonce = secretbox::gen_nonce();
+    Ok(serialise(&(nonce, secretbox::seal(account, &nonce, &key)))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use error::Error;
+    use test_utils;
+
+    #[test]
+    fn create_update_and_decrypt() {
+        let (public_key, secret_key) = test_utils::gen_keypair();
+        let packet = unwrap!(AccountPacket::create(b"keyword",
+                                                   b"1234",
+                                                   b"password",
+                                                   b"account v0",
+                                                   (&public_key, &secret_key)));
+        let structured_data = packet.structured_data();
+        assert_eq!(structured_data.name(),
+                   &unwrap!(AccountPacket::login_name(b"keyword", b"1234")));
+        assert_eq!(structured_data.get_type_tag(), ACCOUNT_PACKET_TYPE_TAG);
+        assert!(!structured_data.get_data().windows(10).any(|window| window == b"account v0"));
+        assert_eq!(unwrap!(packet.decrypt(b"keyword", b"1234", b"password")),
+                   b"account v0".to_vec());
+        match packet.decrypt(b"keyword", b"1234", b"wrong password") {
+            Err(Error::Crypto) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        let updated =
+            unwrap!(packet.update(b"keyword", b"1234", b"password", b"account v1", &secret_key));
+        assert_eq!(updated.structured_data().get_version(), 1);
+        assert!(structured_data.validate_self_against_successor(updated.structured_data()).is_ok());
+        assert_eq!(unwrap!(updated.decrypt(b"keyword", b"1234", b"password")),
+                   b"account v1".to_vec());
+
+        let (_, other_secret_key) = test_utils::gen_keypair();
+        let forged =
+            unwrap!(packet.update(b"keyword", b"1234", b"password", b"forged", &other_secret_key));
+        assert!(structured_data.validate_self_against_successor(forged.structured_data()).is_err());
+    }
+
+    #[test]
+    fn login_names() {
+        let name = unwrap!(AccountPacket::login_name(b"keyword", b"1234"));
+        assert_eq!(name, unwrap!(AccountPacket::login_name(b"keyword", b"1234")));
+        assert!(name != unwrap!(AccountPacket::login_name(b"keyword", b"1235")));
+        assert!(name != unwrap!(AccountPacket::login_name(b"keyword2", b"1234")));
+        // Splitting the same bytes differently between keyword and PIN gives a different name.
+        assert!(unwrap!(AccountPacket::login_name(b"ab", b"c")) !=
+                unwrap!(AccountPacket::login_name(b"a", b"bc")));
+    }
+
+    #[test]
+    fn wrong_type_tag() {
+        let (public_key, secret_key) = test_utils::gen_keypair();
+        let structured_data = unwrap!(StructuredData::new(ACCOUNT_PACKET_TYPE_TAG + 1,
+                                                          [0; 32],
+                                                          0,
+                                                          vec![],
+                                                          vec![public_key],
+                                                          vec![],
+                                                          Some(&secret_key),
+                                                          false));
+        match AccountPacket::from_structured_data(structured_data) {
+            Err(Error::BadIdentifier) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
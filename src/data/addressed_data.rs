@@ -0,0 +1,87 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Addressed data
+//! Vaults look up where data is stored by its identifier's local name on every request. An
+//! `AddressedData` holds data with its identifier and local name worked out once, when it is
+//! wrapped, rather than on each lookup.
+
+use data::{Data, DataIdentifier};
+
+/// `Data` with its identifier and local name.
+#[derive(Hash, PartialEq, Eq, Clone, Debug)]
+pub struct AddressedData {
+    data: Data,
+    identifier: DataIdentifier,
+    local_name: [u8; 32],
+}
+
+impl AddressedData {
+    /// Wraps `data`, working out its identifier and local name.
+    pub fn new(data: Data) -> AddressedData {
+        let identifier = data.identifier();
+        AddressedData {
+            data: data,
+            identifier: identifier,
+            local_name: identifier.local_name(),
+        }
+    }
+
+    /// Returns the data.
+    pub fn data(&self) -> &Data {
+        &self.data
+    }
+
+    /// Returns the data's identifier.
+    pub fn identifier(&self) -> &DataIdentifier {
+        &self.identifier
+    }
+
+    /// Returns the local name of the data's identifier.
+    pub fn local_name(&self) -> &[u8; 32] {
+        &self.local_name
+    }
+
+    /// Unwraps the data.
+    pub fn into_data(self) -> Data {
+        self.data
+    }
+}
+
+impl From<Data> for AddressedData {
+    fn from(data: Data) -> AddressedData {
+        AddressedData::new(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{Data, ImmutableData, PlainData};
+    use rand;
+
+    #[test]
+    fn caches_local_name() {
+        for data in vec![Data::Immutable(ImmutableData::new(vec![1, 2, 3])),
+                         Data::Plain(PlainData::new(rand::random(), vec![4, 5]))] {
+            let addressed = AddressedData::from(data.clone());
+            assert_eq!(*addressed.identifier(), data.identifier());
+            assert_eq!(*addressed.local_name(), data.identifier().local_name());
+            assert_eq!(addressed.into_data(), data);
+        }
+    }
+}
@@ -0,0 +1,412 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use crypto::sign::{self, PublicKey, SecretKey, Signature};
+use data::MAX_BYTES;
+use data::canonical::{CanonicalWriter, LOG_CONTROL_DOMAIN, LOG_ENTRY_DOMAIN};
+use error::Error;
+use serialisation::serialise;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Debug, Formatter};
+use std::mem;
+
+/// An entry of an `AppendOnlyLog`.
+#[derive(Hash, Eq, PartialEq, PartialOrd, Ord, Clone, Debug, RustcEncodable, RustcDecodable)]
+pub struct LogEntry {
+    /// Sequence number of the entry, starting at 0 and incremented by each append.
+    pub index: u64,
+    /// Content of the entry.
+    pub payload: Vec<u8>,
+    /// The writer who appended the entry.
+    pub writer: PublicKey,
+    /// The writer's signature over the entry.
+    pub signature: Signature,
+}
+
+/// A change to an `AppendOnlyLog` which needs the signatures of a majority of its owners.
+#[derive(Hash, Eq, PartialEq, PartialOrd, Ord, Clone, Debug, RustcEncodable, RustcDecodable)]
+pub enum LogControl {
+    /// Replaces the set of writers.
+    SetWriters(BTreeSet<PublicKey>),
+    /// Removes the entries before this index.
+    Prune(u64),
+}
+
+/// A log of signed entries which can only be appended to, e.g. a messaging inbox.
+///
+/// Entries are appended by writers and numbered in sequence. The owners, a majority of whom must
+/// sign each change, control who the writers are and can prune old entries, which are returned
+/// so they can be kept elsewhere as a snapshot. Pruning never reuses indices.
+#[derive(Hash, Eq, PartialEq, PartialOrd, Ord, Clone, RustcEncodable, RustcDecodable)]
+pub struct AppendOnlyLog {
+    name: [u8; 32],
+    type_tag: u64,
+    entries: Vec<LogEntry>,
+    first_index: u64,
+    writers: BTreeSet<PublicKey>,
+    version: u64,
+    owners: BTreeSet<PublicKey>,
+}
+
+impl AppendOnlyLog {
+    /// Creates a new, empty log at version 0. There must be at least one owner.
+    pub fn new(name: [u8; 32],
+               type_tag: u64,
+               writers: BTreeSet<PublicKey>,
+               owners: BTreeSet<PublicKey>)
+               -> Result<AppendOnlyLog, Error> {
+        if owners.is_empty() {
+            return Err(Error::Validation);
+        }
+        let log = AppendOnlyLog {
+            name: name,
+            type_tag: type_tag,
+            entries: Vec::new(),
+            first_index: 0,
+            writers: writers,
+            version: 0,
+            owners: owners,
+        };
+        log.validate_size()?;
+        Ok(log)
+    }
+
+    /// Returns the name.
+    pub fn name(&self) -> &[u8; 32] {
+        &self.name
+    }
+
+    /// Returns the type tag.
+    pub fn type_tag(&self) -> u64 {
+        self.type_tag
+    }
+
+    /// Returns the version, incremented by each change made by the owners.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Returns the owners.
+    pub fn owners(&self) -> &BTreeSet<PublicKey> {
+        &self.owners
+    }
+
+    /// Returns the writers. Owners can't append unless they are writers too.
+    pub fn writers(&self) -> &BTreeSet<PublicKey> {
+        &self.writers
+    }
+
+    /// Returns the entries which haven't been pruned, in order.
+    pub fn entries(&self) -> &[LogEntry] {
+        &self.entries
+    }
+
+    /// Returns the index of the first entry which hasn't been pruned.
+    pub fn first_index(&self) -> u64 {
+        self.first_index
+    }
+
+    /// Returns the index the next entry will be appended at.
+    pub fn next_index(&self) -> u64 {
+        self.first_index + self.entries.len() as u64
+    }
+
+    /// Returns the entries at `index` and after which haven't been pruned.
+    pub fn entries_since(&self, index: u64) -> &[LogEntry] {
+        if index <= self.first_index {
+            &self.entries
+        } else if index >= self.next_index() {
+            &[]
+        } else {
+            &self.entries[(index - self.first_index) as usize..]
+        }
+    }
+
+    /// Returns the size of the entry payloads.
+    pub fn payload_size(&self) -> usize {
+        self.entries.iter().map(|entry| entry.payload.len()).sum()
+    }
+
+    /// Returns an error if the serialised data is larger than `MAX_BYTES` plus the
+    /// `sign::size_allowance` for its keys and signatures.
+    pub fn validate_size(&self) -> Result<(), Error> {
+        let keys = self.writers.len() + self.owners.len() + self.entries.len();
+        let allowance = sign::size_allowance(keys, self.entries.len());
+        if serialise(self)?.len() > MAX_BYTES + allowance {
+            return Err(Error::DataTooLarge);
+        }
+        Ok(())
+    }
+
+    /// Signs `payload` as the next entry, for use with `append`.
+    pub fn sign_entry(&self, payload: &[u8], secret_key: &SecretKey) -> Signature {
+        sign::sign_detached(&self.entry_to_sign(self.next_index(), payload), secret_key)
+    }
+
+    /// Appends `payload` as the next entry and returns its index.
+    ///
+    /// `writer` must be a writer and `signature` their signature over the entry.
+    pub fn append(&mut self,
+                  payload: Vec<u8>,
+                  writer: PublicKey,
+                  signature: Signature)
+                  -> Result<u64, Error> {
+        if !self.writers.contains(&writer) {
+            return Err(Error::AccessDenied);
+        }
+        let index = self.next_index();
+        if !sign::verify_detached(&signature, &self.entry_to_sign(index, &payload), &writer) {
+            return Err(Error::Signature);
+        }
+        self.entries.push(LogEntry {
+            index: index,
+            payload: payload,
+            writer: writer,
+            signature: signature,
+        });
+        if let Err(error) = self.validate_size() {
+            let _ = self.entries.pop();
+            return Err(error);
+        }
+        Ok(index)
+    }
+
+    /// Returns whether `entry` is one of this log's entries, signed by its writer. The writer need
+    /// not still be a writer.
+    pub fn verify_entry(&self, entry: &LogEntry) -> bool {
+        sign::verify_detached(&entry.signature,
+                              &self.entry_to_sign(entry.index, &entry.payload),
+                              &entry.writer)
+    }
+
+    /// Signs `control` as the next version, for use with `apply_control`.
+    pub fn sign_control(&self, control: &LogControl, secret_key: &SecretKey) -> Signature {
+        sign::sign_detached(&self.control_to_sign(control, self.version + 1), secret_key)
+    }
+
+    /// Applies `control` as the next version and returns the entries it prunes, if any.
+    ///
+    /// `signatures` must hold valid signatures over the change from a strict majority of the
+    /// owners. Pruning up to an index after the last entry fails with `NoSuchEntry`.
+    pub fn apply_control(&mut self,
+                         control: LogControl,
+                         signatures: &BTreeMap<PublicKey, Signature>)
+                         -> Result<Vec<LogEntry>, Error> {
+        let data = self.control_to_sign(&control, self.version + 1);
+        let valid = signatures.iter()
+            .filter(|&(key, signature)| {
+                self.owners.contains(key) && sign::verify_detached(signature, &data, key)
+            })
+            .count();
+        if valid * 2 <= self.owners.len() {
+            return Err(Error::Majority);
+        }
+
+        let pruned = match control {
+            LogControl::SetWriters(writers) => {
+                let old_writers = mem::replace(&mut self.writers, writers);
+                if let Err(error) = self.validate_size() {
+                    self.writers = old_writers;
+                    return Err(error);
+                }
+                Vec::new()
+            }
+            LogControl::Prune(index) => {
+                if index > self.next_index() {
+                    return Err(Error::NoSuchEntry);
+                }
+                let count = index.saturating_sub(self.first_index) as usize;
+                self.first_index += count as u64;
+                self.entries.drain(..count).collect()
+            }
+        };
+        self.version += 1;
+        Ok(pruned)
+    }
+
+    fn entry_to_sign(&self, index: u64, payload: &[u8]) -> Vec<u8> {
+        let mut writer = CanonicalWriter::new(LOG_ENTRY_DOMAIN);
+        let _ = writer.write_fixed(&self.name)
+            .write_u64(self.type_tag)
+            .write_u64(index)
+            .write_bytes(payload);
+        writer.into_bytes()
+    }
+
+    fn control_to_sign(&self, control: &LogControl, version: u64) -> Vec<u8> {
+        let mut writer = CanonicalWriter::new(LOG_CONTROL_DOMAIN);
+        let _ = writer.write_fixed(&self.name)
+            .write_u64(self.type_tag)
+            .write_u64(version);
+        match *control {
+            LogControl::SetWriters(ref writers) => {
+                let _ = writer.write_u8(0).write_len(writers.len());
+                for key in writers {
+                    let _ = writer.write_fixed(&key.0);
+                }
+            }
+            LogControl::Prune(index) => {
+                let _ = writer.write_u8(1).write_u64(index);
+            }
+        }
+        writer.into_bytes()
+    }
+}
+
+impl Debug for AppendOnlyLog {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter,
+               "AppendOnlyLog {{ name: {:?}, type_tag: {}, version: {}, first_index: {}, \
+                entries: {}, writers: {}, owners: {} }}",
+               self.name,
+               self.type_tag,
+               self.version,
+               self.first_index,
+               self.entries.len(),
+               self.writers.len(),
+               self.owners.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use error::Error;
+    use rand;
+    use serialisation::{deserialise, serialise};
+    use std::collections::BTreeMap;
+    use test_utils;
+
+    #[test]
+    fn append() {
+        let owner = test_utils::gen_keypair();
+        let writer = test_utils::gen_keypair();
+        let stranger = test_utils::gen_keypair();
+        let mut log = unwrap!(AppendOnlyLog::new(rand::random(),
+                                                 5,
+                                                 vec![writer.0].into_iter().collect(),
+                                                 vec![owner.0].into_iter().collect()));
+
+        let signature = log.sign_entry(b"first", &writer.1);
+        assert_eq!(unwrap!(log.append(b"first".to_vec(), writer.0, signature)), 0);
+        let signature = log.sign_entry(b"second", &writer.1);
+        assert_eq!(unwrap!(log.append(b"second".to_vec(), writer.0, signature)), 1);
+        assert_eq!(log.next_index(), 2);
+        assert!(log.entries().iter().all(|entry| log.verify_entry(entry)));
+
+        // Only writers can append, not even owners.
+        let signature = log.sign_entry(b"third", &stranger.1);
+        match log.append(b"third".to_vec(), stranger.0, signature) {
+            Err(Error::AccessDenied) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        let signature = log.sign_entry(b"third", &owner.1);
+        match log.append(b"third".to_vec(), owner.0, signature) {
+            Err(Error::AccessDenied) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // A signature for another index or payload is rejected.
+        let signature = log.sign_entry(b"third", &writer.1);
+        match log.append(b"fourth".to_vec(), writer.0, signature) {
+            Err(Error::Signature) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        let _ = unwrap!(log.append(b"third".to_vec(), writer.0, signature));
+        match log.append(b"third".to_vec(), writer.0, signature) {
+            Err(Error::Signature) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        let payloads: Vec<_> =
+            log.entries_since(1).iter().map(|entry| &entry.payload[..]).collect();
+        assert_eq!(payloads, vec![&b"second"[..], &b"third"[..]]);
+        assert!(log.entries_since(3).is_empty());
+
+        let serialised = unwrap!(serialise(&log));
+        assert_eq!(log, unwrap!(deserialise::<AppendOnlyLog>(&serialised)));
+    }
+
+    #[test]
+    fn owner_control() {
+        let owners: Vec<_> = (0..3).map(|_| test_utils::gen_keypair()).collect();
+        let writer = test_utils::gen_keypair();
+        let new_writer = test_utils::gen_keypair();
+        let mut log = unwrap!(AppendOnlyLog::new(rand::random(),
+                                                 5,
+                                                 vec![writer.0].into_iter().collect(),
+                                                 owners.iter().map(|keys| keys.0).collect()));
+        for payload in &[b"a", b"b", b"c"] {
+            let signature = log.sign_entry(&payload[..], &writer.1);
+            let _ = unwrap!(log.append(payload.to_vec(), writer.0, signature));
+        }
+
+        // One of three owners isn't a majority.
+        let control = LogControl::SetWriters(vec![new_writer.0].into_iter().collect());
+        let mut signatures = BTreeMap::new();
+        let _ = signatures.insert(owners[0].0, log.sign_control(&control, &owners[0].1));
+        match log.apply_control(control.clone(), &signatures) {
+            Err(Error::Majority) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // Signatures by non-owners don't count.
+        let _ = signatures.insert(writer.0, log.sign_control(&control, &writer.1));
+        match log.apply_control(control.clone(), &signatures) {
+            Err(Error::Majority) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        let _ = signatures.insert(owners[1].0, log.sign_control(&control, &owners[1].1));
+        assert!(unwrap!(log.apply_control(control.clone(), &signatures)).is_empty());
+        assert_eq!(log.version(), 1);
+        let signature = log.sign_entry(b"d", &writer.1);
+        match log.append(b"d".to_vec(), writer.0, signature) {
+            Err(Error::AccessDenied) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // The signatures are for version 1 and can't be replayed.
+        match log.apply_control(control, &signatures) {
+            Err(Error::Majority) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        let control = LogControl::Prune(2);
+        let signatures: BTreeMap<_, _> = owners.iter()
+            .map(|keys| (keys.0, log.sign_control(&control, &keys.1)))
+            .collect();
+        let pruned = unwrap!(log.apply_control(control, &signatures));
+        assert_eq!(pruned.iter().map(|entry| entry.index).collect::<Vec<_>>(), vec![0, 1]);
+        assert_eq!(log.first_index(), 2);
+        assert_eq!(log.entries_since(0).len(), 1);
+        assert!(log.entries().iter().all(|entry| log.verify_entry(entry)));
+
+        // Indices carry on after pruning.
+        let signature = log.sign_entry(b"d", &new_writer.1);
+        assert_eq!(unwrap!(log.append(b"d".to_vec(), new_writer.0, signature)), 3);
+
+        let control = LogControl::Prune(5);
+        let signatures: BTreeMap<_, _> = owners.iter()
+            .map(|keys| (keys.0, log.sign_control(&control, &keys.1)))
+            .collect();
+        match log.apply_control(control, &signatures) {
+            Err(Error::NoSuchEntry) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,139 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Arbitrary instances
+//! `quickcheck::Arbitrary` implementations of the data types (feature `arbitrary`), for property
+//! tests and for seeding fuzzers. Structured data is built through the normal constructor and
+//! signed by its owners, so the instances are valid unless a test tampers with them.
+
+use crypto::sign::{self, Seed};
+use data::{Data, DataIdentifier, ImmutableData, PlainData, StructuredData};
+use quickcheck::{Arbitrary, Gen};
+use rand::Rng;
+use std::cmp;
+
+/// Upper bound on the number of owners of arbitrary structured data.
+const MAX_OWNERS: usize = 4;
+/// Upper bound on the size of arbitrary payloads, well within the size limits of every type.
+const MAX_PAYLOAD_BYTES: usize = 4096;
+
+fn arbitrary_payload<G: Gen>(g: &mut G) -> Vec<u8> {
+    let len = g.gen_range(0, cmp::min(g.size(), MAX_PAYLOAD_BYTES) + 1);
+    (0..len).map(|_| g.gen()).collect()
+}
+
+impl Arbitrary for ImmutableData {
+    fn arbitrary<G: Gen>(g: &mut G) -> ImmutableData {
+        ImmutableData::new(arbitrary_payload(g))
+    }
+
+    fn shrink(&self) -> Box<Iterator<Item = ImmutableData>> {
+        Box::new(self.value().to_vec().shrink().map(ImmutableData::new))
+    }
+}
+
+impl Arbitrary for StructuredData {
+    fn arbitrary<G: Gen>(g: &mut G) -> StructuredData {
+        let owners: Vec<_> = (0..g.gen_range(1, MAX_OWNERS + 1))
+            .map(|_| sign::keypair_from_seed(&Seed(g.gen())))
+            .collect();
+        let owner_keys = owners.iter().map(|keys| keys.0).collect();
+        let mut structured_data = match StructuredData::new(g.gen(),
+                                                            g.gen(),
+                                                            g.gen_range(0, 3),
+                                                            arbitrary_payload(g),
+                                                            owner_keys,
+                                                            vec![],
+                                                            None,
+                                                            g.gen()) {
+            Ok(structured_data) => structured_data,
+            Err(error) => unreachable!("Arbitrary structured data is too large: {:?}", error),
+        };
+        for keys in &owners {
+            let _ = structured_data.add_signature(&keys.1);
+        }
+        structured_data
+    }
+}
+
+impl Arbitrary for DataIdentifier {
+    fn arbitrary<G: Gen>(g: &mut G) -> DataIdentifier {
+        match g.gen_range(0, 4) {
+            0 => DataIdentifier::Structured(g.gen(), g.gen()),
+            1 => DataIdentifier::Immutable(g.gen()),
+            2 => DataIdentifier::Plain(g.gen()),
+            _ => DataIdentifier::Mutable(g.gen(), g.gen()),
+        }
+    }
+}
+
+impl Arbitrary for Data {
+    fn arbitrary<G: Gen>(g: &mut G) -> Data {
+        match g.gen_range(0, 3) {
+            0 => Data::Structured(Arbitrary::arbitrary(g)),
+            1 => Data::Immutable(Arbitrary::arbitrary(g)),
+            _ => Data::Plain(PlainData::new(g.gen(), arbitrary_payload(g))),
+        }
+    }
+
+    fn shrink(&self) -> Box<Iterator<Item = Data>> {
+        match *self {
+            Data::Immutable(ref data) => Box::new(data.shrink().map(Data::Immutable)),
+            _ => Box::new(None.into_iter()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use data::{Data, DataIdentifier, StructuredData};
+    use quickcheck::quickcheck;
+    use serialisation::{deserialise, serialise};
+
+    #[test]
+    fn serialisation_round_trips() {
+        fn data(data: Data) -> bool {
+            deserialise::<Data>(&unwrap!(serialise(&data))).ok() == Some(data)
+        }
+        fn identifier(identifier: DataIdentifier) -> bool {
+            deserialise::<DataIdentifier>(&unwrap!(serialise(&identifier))).ok() ==
+            Some(identifier)
+        }
+        quickcheck(data as fn(Data) -> bool);
+        quickcheck(identifier as fn(DataIdentifier) -> bool);
+    }
+
+    #[test]
+    fn structured_data_is_valid() {
+        fn valid(structured_data: StructuredData) -> bool {
+            structured_data.is_canonical() && structured_data.validate_size().is_ok() &&
+            structured_data.get_previous_owner_signatures().len() ==
+            structured_data.get_owner_keys().len()
+        }
+        quickcheck(valid as fn(StructuredData) -> bool);
+    }
+
+    #[test]
+    fn truncated_bytes_never_panic() {
+        fn truncated(data: Data, len: usize) -> bool {
+            let serialised = unwrap!(serialise(&data));
+            let _ = deserialise::<Data>(&serialised[..len % (serialised.len() + 1)]);
+            true
+        }
+        quickcheck(truncated as fn(Data, usize) -> bool);
+    }
+}
@@ -0,0 +1,131 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use data::Data;
+use error::Error;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+/// Validates every item of `batch`, e.g. a batch of data received for replication, and returns
+/// the result for each item in the same order.
+///
+/// Every item must hold the invariants checked by `Data::validate` and structured data must also
+/// be signed as its signature policy requires (see `StructuredData::verify_own_signatures`).
+/// Identical items, as when several replicas of one chunk arrive together, are validated once.
+/// With the `parallel` feature the distinct items are validated in parallel.
+pub fn validate_batch(batch: &[Data]) -> Vec<Result<(), Error>> {
+    let mut first_index = HashMap::new();
+    let mut distinct = Vec::new();
+    let firsts: Vec<usize> = batch.iter()
+        .enumerate()
+        .map(|(index, data)| {
+            *first_index.entry(data).or_insert_with(|| {
+                distinct.push(index);
+                index
+            })
+        })
+        .collect();
+
+    let mut results: HashMap<usize, Result<(), Error>> =
+        distinct.iter().cloned().zip(validate_all(batch, &distinct)).collect();
+    firsts.iter()
+        .enumerate()
+        .map(|(index, &first)| if index == first {
+            results.remove(&index).unwrap_or_else(|| validate(&batch[index]))
+        } else {
+            match results.get(&first) {
+                Some(&Ok(())) | None => Ok(()),
+                // Errors can't be cloned, so failures are rare enough to be worked out again.
+                Some(&Err(_)) => validate(&batch[index]),
+            }
+        })
+        .collect()
+}
+
+fn validate(data: &Data) -> Result<(), Error> {
+    data.validate()?;
+    match *data {
+        Data::Structured(ref structured_data) => structured_data.verify_own_signatures(),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(not(feature = "parallel"))]
+fn validate_all(batch: &[Data], indices: &[usize]) -> Vec<Result<(), Error>> {
+    indices.iter().map(|&index| validate(&batch[index])).collect()
+}
+
+#[cfg(feature = "parallel")]
+fn validate_all(batch: &[Data], indices: &[usize]) -> Vec<Result<(), Error>> {
+    let mut results = Vec::with_capacity(indices.len());
+    indices.par_iter().map(|&index| validate(&batch[index])).collect_into(&mut results);
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{Data, ImmutableData, PlainData, StructuredData};
+    use error::Error;
+    use rand;
+    use test_utils;
+
+    #[test]
+    fn per_item_results() {
+        let keys = test_utils::gen_keypair();
+        let structured_data = |signing_key| {
+            Data::Structured(unwrap!(StructuredData::new(0,
+                                                         rand::random(),
+                                                         0,
+                                                         vec![],
+                                                         vec![keys.0],
+                                                         vec![],
+                                                         signing_key,
+                                                         false)))
+        };
+        let signed = structured_data(Some(&keys.1));
+        let unsigned = structured_data(None);
+        let oversized = Data::Immutable(ImmutableData::new(vec![0; ImmutableData::MAX_BYTES + 1]));
+        let batch = vec![signed.clone(),
+                         Data::Plain(PlainData::new(rand::random(), vec![1])),
+                         unsigned.clone(),
+                         oversized.clone(),
+                         signed,
+                         unsigned,
+                         oversized];
+
+        let results = validate_batch(&batch);
+        assert_eq!(results.len(), batch.len());
+        assert!(results[0].is_ok());
+        assert!(results[1].is_ok());
+        assert!(results[4].is_ok());
+        for &index in &[2, 5] {
+            match results[index] {
+                Err(Error::Validation) => (),
+                ref result => panic!("Unexpected result {:?} for item {}", result, index),
+            }
+        }
+        for &index in &[3, 6] {
+            match results[index] {
+                Err(Error::DataTooLarge) => (),
+                ref result => panic!("Unexpected result {:?} for item {}", result, index),
+            }
+        }
+        assert!(validate_batch(&[]).is_empty());
+    }
+}
@@ -0,0 +1,125 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Canonical encoding
+//! The encoding of the bytes owners sign, defined here rather than by a serialiser so signatures
+//! stay valid across serialiser versions and can be reproduced by other implementations.
+//!
+//! Every encoding starts with a domain-separation prefix naming the signed type and the version
+//! of its encoding, so a signature over one type can never be replayed as one over another. The
+//! prefix is written as a byte string. After it, values are written in order as:
+//!
+//! - `u8`, `bool` : one byte (`bool` as 0 or 1).
+//! - `u64` : eight bytes, big-endian.
+//! - fixed-size values (names, keys) : their bytes, without a length.
+//! - byte strings : the length as a `u64`, then the bytes.
+//! - sequences : the number of items as a `u64`, then each item.
+//! - enums : the variant index as a `u8`, then the variant's fields.
+
+/// Domain-separation prefix of the bytes signed for a `StructuredData`.
+pub const STRUCTURED_DATA_DOMAIN: &'static [u8] = b"data_chain/StructuredData/2";
+/// Domain-separation prefix of the bytes signed for an `AppendOnlyLog` entry.
+pub const LOG_ENTRY_DOMAIN: &'static [u8] = b"data_chain/AppendOnlyLog/Entry/1";
+/// Domain-separation prefix of the bytes signed for an `AppendOnlyLog` writer change or pruning.
+pub const LOG_CONTROL_DOMAIN: &'static [u8] = b"data_chain/AppendOnlyLog/Control/1";
+/// Domain-separation prefix of the bytes signed for `MutableData` entry actions.
+pub const ENTRY_ACTIONS_DOMAIN: &'static [u8] = b"data_chain/MutableData/EntryActions/1";
+/// Domain-separation prefix of the bytes signed for a `MutableData` permission change.
+pub const USER_PERMISSIONS_DOMAIN: &'static [u8] = b"data_chain/MutableData/UserPermissions/1";
+/// Domain-separation prefix of the bytes signed for a `SignedRequest`.
+pub const SIGNED_REQUEST_DOMAIN: &'static [u8] = b"data_chain/SignedRequest/1";
+/// Domain-separation prefix of the bytes signed for an `OwnershipProof`.
+pub const OWNERSHIP_PROOF_DOMAIN: &'static [u8] = b"data_chain/OwnershipProof/1";
+
+/// Writes values in the canonical encoding.
+pub struct CanonicalWriter {
+    bytes: Vec<u8>,
+}
+
+impl CanonicalWriter {
+    /// Starts an encoding with the domain-separation prefix `domain`.
+    pub fn new(domain: &[u8]) -> CanonicalWriter {
+        let mut writer = CanonicalWriter { bytes: Vec::new() };
+        let _ = writer.write_bytes(domain);
+        writer
+    }
+
+    /// Writes a single byte.
+    pub fn write_u8(&mut self, value: u8) -> &mut CanonicalWriter {
+        self.bytes.push(value);
+        self
+    }
+
+    /// Writes a `bool` as a single byte.
+    pub fn write_bool(&mut self, value: bool) -> &mut CanonicalWriter {
+        self.write_u8(value as u8)
+    }
+
+    /// Writes eight big-endian bytes.
+    pub fn write_u64(&mut self, value: u64) -> &mut CanonicalWriter {
+        for shift in (0..8).rev() {
+            self.bytes.push((value >> (shift * 8)) as u8);
+        }
+        self
+    }
+
+    /// Writes the length of a byte string or sequence.
+    pub fn write_len(&mut self, len: usize) -> &mut CanonicalWriter {
+        self.write_u64(len as u64)
+    }
+
+    /// Writes a value of fixed size, such as a name or key, without its length.
+    pub fn write_fixed(&mut self, value: &[u8]) -> &mut CanonicalWriter {
+        self.bytes.extend_from_slice(value);
+        self
+    }
+
+    /// Writes a byte string preceded by its length.
+    pub fn write_bytes(&mut self, value: &[u8]) -> &mut CanonicalWriter {
+        self.write_len(value.len()).write_fixed(value)
+    }
+
+    /// Returns the encoded bytes.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustc_serialize::hex::ToHex;
+
+    #[test]
+    fn encoding() {
+        let mut writer = CanonicalWriter::new(b"test");
+        let _ = writer.write_u8(7)
+            .write_bool(true)
+            .write_u64(0x0102030405060708)
+            .write_fixed(&[0xaa, 0xbb])
+            .write_bytes(b"xyz")
+            .write_len(2);
+        assert_eq!(writer.into_bytes().to_hex(),
+                   "000000000000000474657374\
+                    07\
+                    01\
+                    0102030405060708\
+                    aabb\
+                    000000000000000378797a\
+                    0000000000000002");
+    }
+}
@@ -0,0 +1,251 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Capabilities
+//! Licensing of the key encrypting a payload to specific readers. A `CapabilityList` holds the
+//! data key sealed to each reader's encryption key, so any reader can recover it with their secret
+//! key while the list itself, stored as `StructuredData`, reveals nothing of it.
+//!
+//! The data key encrypts the payload with `secretbox` (see `encrypt_payload`), whether the payload
+//! is then stored as `ImmutableData` or as the data of a `StructuredData`. Granting a reader seals
+//! the existing key to them; revoking one replaces the key, which the owner must then use to
+//! re-encrypt the payload, since the revoked reader may have kept the old one.
+
+use crypto::sign::{PublicKey, SecretKey};
+use data::StructuredData;
+use data::canonical::CanonicalWriter;
+use error::Error;
+use rust_sodium::crypto::{box_, sealedbox, secretbox};
+use serialisation::{deserialise, serialise};
+use sha3::hash;
+
+/// Type tag of the `StructuredData` holding a capability list.
+pub const CAPABILITIES_TYPE_TAG: u64 = 0x5afe_cab5;
+/// Domain-separation prefix of the hash identifying a data key.
+pub const DATA_KEY_DOMAIN: &'static [u8] = b"data_chain/Capabilities/key/1";
+
+/// A data key sealed to each of a list of readers.
+#[derive(Eq, PartialEq, Clone, Debug, RustcEncodable, RustcDecodable)]
+pub struct CapabilityList {
+    key_version: u64,
+    key_hash: [u8; 32],
+    sealed_keys: Vec<(box_::PublicKey, Vec<u8>)>,
+}
+
+impl CapabilityList {
+    /// Creates a capability list sealing `key` to each of `readers`.
+    pub fn new(key: &secretbox::Key, readers: &[box_::PublicKey]) -> CapabilityList {
+        let mut capabilities = CapabilityList {
+            key_version: 0,
+            key_hash: key_hash(key),
+            sealed_keys: vec![],
+        };
+        for reader in readers {
+            let _ = capabilities.grant(key, reader);
+        }
+        capabilities
+    }
+
+    /// Parses the capability list stored in `structured_data`, which must have the capabilities
+    /// type tag.
+    pub fn from_structured_data(structured_data: &StructuredData) -> Result<CapabilityList, Error> {
+        if structured_data.get_type_tag() != CAPABILITIES_TYPE_TAG {
+            return Err(Error::BadIdentifier);
+        }
+        Ok(deserialise(structured_data.get_data())?)
+    }
+
+    /// Stores the capability list as version `version` of the `StructuredData` called `name`,
+    /// owned by `owners` and signed with `signing_key`.
+    pub fn to_structured_data(&self,
+                              name: [u8; 32],
+                              version: u64,
+                              owners: Vec<PublicKey>,
+                              signing_key: &SecretKey)
+                              -> Result<StructuredData, Error> {
+        StructuredData::new(CAPABILITIES_TYPE_TAG,
+                            name,
+                            version,
+                            serialise(self)?,
+                            owners,
+                            vec![],
+                            Some(signing_key),
+                            false)
+    }
+
+    /// Seals `key` to `reader`.
+    ///
+    /// Fails with `Crypto` if `key` is not the current data key and with `EntryExists` if `reader`
+    /// already holds it.
+    pub fn grant(&mut self, key: &secretbox::Key, reader: &box_::PublicKey) -> Result<(), Error> {
+        if key_hash(key) != self.key_hash {
+            return Err(Error::Crypto);
+        }
+        if self.is_reader(reader) {
+            return Err(Error::EntryExists);
+        }
+        self.sealed_keys.push((*reader, sealedbox::seal(&key.0, reader)));
+        Ok(())
+    }
+
+    /// Removes `reader` and replaces the data key, sealing the new one to the remaining readers.
+    ///
+    /// Returns the new key, which the payload must be re-encrypted with. Fails with
+    /// `NoSuchEntry` if `reader` does not hold the key.
+    pub fn revoke(&mut self, reader: &box_::PublicKey) -> Result<secretbox::Key, Error> {
+        if !self.is_reader(reader) {
+            return Err(Error::NoSuchEntry);
+        }
+        let key = secretbox::gen_key();
+        let readers: Vec<_> = self.readers().into_iter().filter(|other| other != reader).collect();
+        *self = CapabilityList {
+            key_version: self.key_version + 1,
+            ..CapabilityList::new(&key, &readers)
+        };
+        Ok(key)
+    }
+
+    /// Recovers the data key with a reader's encryption keypair.
+    pub fn open(&self,
+                public_key: &box_::PublicKey,
+                secret_key: &box_::SecretKey)
+                -> Result<secretbox::Key, Error> {
+        let sealed_key = match self.sealed_keys
+            .iter()
+            .find(|&&(ref reader, _)| reader == public_key) {
+            Some(&(_, ref sealed_key)) => sealed_key,
+            None => return Err(Error::Crypto),
+        };
+        let key_bytes = sealedbox::open(sealed_key, public_key, secret_key)?;
+        let key = secretbox::Key::from_slice(&key_bytes).ok_or(Error::Crypto)?;
+        if key_hash(&key) != self.key_hash {
+            return Err(Error::Crypto);
+        }
+        Ok(key)
+    }
+
+    /// Returns whether `reader` holds the data key.
+    pub fn is_reader(&self, reader: &box_::PublicKey) -> bool {
+        self.sealed_keys.iter().any(|&(ref other, _)| other == reader)
+    }
+
+    /// The readers holding the data key, in the order they were granted it.
+    pub fn readers(&self) -> Vec<box_::PublicKey> {
+        self.sealed_keys.iter().map(|&(reader, _)| reader).collect()
+    }
+
+    /// Number of times the data key has been replaced.
+    pub fn key_version(&self) -> u64 {
+        self.key_version
+    }
+}
+
+/// Encrypts `plain_text` with the data key `key` under a fresh nonce.
+pub fn encrypt_payload(key: &secretbox::Key, plain_text: &[u8]) -> Result<Vec<u8>, Error> {
+    let nonce = secretbox::gen_nonce();
+    Ok(serialise(&(nonce, secretbox::seal(plain_text, &nonce, key)))?)
+}
+
+/// Decrypts a payload encrypted by `encrypt_payload`.
+pub fn decrypt_payload(key: &secretbox::Key, payload: &[u8]) -> Result<Vec<u8>, Error> {
+    let (nonce, cipher_text): (secretbox::Nonce, Vec<u8>) = deserialise(payload)?;
+    Ok(secretbox::open(&cipher_text, &nonce, key)?)
+}
+
+fn key_hash(key: &secretbox::Key) -> [u8; 32] {
+    let mut writer = CanonicalWriter::new(DATA_KEY_DOMAIN);
+    let _ = writer.write_fixed(&key.0);
+    hash(&writer.into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{ImmutableData, StructuredData};
+    use error::Error;
+    use rand;
+    use rust_sodium::crypto::{box_, secretbox};
+    use test_utils;
+
+    #[test]
+    fn grant_open_and_revoke() {
+        let (owner, reader) = (box_::gen_keypair(), box_::gen_keypair());
+        let other = box_::gen_keypair();
+        let key = secretbox::gen_key();
+        let mut capabilities = CapabilityList::new(&key, &[owner.0]);
+        let chunk = ImmutableData::new(unwrap!(encrypt_payload(&key, b"content")));
+
+        assert_eq!(unwrap!(capabilities.open(&owner.0, &owner.1)).0, key.0);
+        match capabilities.open(&reader.0, &reader.1).map(|key| key.0) {
+            Err(Error::Crypto) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        unwrap!(capabilities.grant(&key, &reader.0));
+        let opened = unwrap!(capabilities.open(&reader.0, &reader.1));
+        assert_eq!(unwrap!(decrypt_payload(&opened, chunk.value())), b"content");
+        assert!(capabilities.open(&reader.0, &other.1).is_err());
+        match capabilities.grant(&key, &reader.0) {
+            Err(Error::EntryExists) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match capabilities.grant(&secretbox::gen_key(), &other.0) {
+            Err(Error::Crypto) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // Revoking replaces the key, which the revoked reader cannot recover.
+        let new_key = unwrap!(capabilities.revoke(&reader.0));
+        assert!(new_key.0 != key.0);
+        assert_eq!(capabilities.key_version(), 1);
+        assert_eq!(capabilities.readers(), vec![owner.0]);
+        assert!(capabilities.open(&reader.0, &reader.1).is_err());
+        assert_eq!(unwrap!(capabilities.open(&owner.0, &owner.1)).0, new_key.0);
+        assert!(decrypt_payload(&new_key, chunk.value()).is_err());
+        match capabilities.revoke(&reader.0).map(|key| key.0) {
+            Err(Error::NoSuchEntry) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn stored_as_structured_data() {
+        let keys = test_utils::gen_keypair();
+        let reader = box_::gen_keypair();
+        let key = secretbox::gen_key();
+        let capabilities = CapabilityList::new(&key, &[reader.0]);
+        let structured_data =
+            unwrap!(capabilities.to_structured_data(rand::random(), 0, vec![keys.0], &keys.1));
+        unwrap!(structured_data.verify_own_signatures());
+        let parsed = unwrap!(CapabilityList::from_structured_data(&structured_data));
+        assert_eq!(parsed, capabilities);
+        assert_eq!(unwrap!(parsed.open(&reader.0, &reader.1)).0, key.0);
+
+        let other = unwrap!(StructuredData::new(0,
+                                                rand::random(),
+                                                0,
+                                                structured_data.get_data().clone(),
+                                                vec![keys.0],
+                                                vec![],
+                                                Some(&keys.1),
+                                                false));
+        match CapabilityList::from_structured_data(&other) {
+            Err(Error::BadIdentifier) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
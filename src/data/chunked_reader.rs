@@ -0,0 +1,144 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use data::ImmutableData;
+use std::cmp;
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// Reads content split over a sequence of `ImmutableData` chunks as one stream, without copying
+/// the chunk values.
+///
+/// Seeking lets a client read just the byte range it needs, e.g. when streaming media. Chunks are
+/// read as stored, so they should not be compressed.
+pub struct ChunkedReader<'a> {
+    chunks: &'a [ImmutableData],
+    position: u64,
+    total_size: u64,
+}
+
+impl<'a> ChunkedReader<'a> {
+    /// Creates a reader over the concatenated values of `chunks`, positioned at the start.
+    pub fn new(chunks: &'a [ImmutableData]) -> ChunkedReader<'a> {
+        ChunkedReader {
+            chunks: chunks,
+            position: 0,
+            total_size: chunks.iter().map(|chunk| chunk.payload_size() as u64).sum(),
+        }
+    }
+
+    /// Returns the size of the whole content.
+    pub fn total_size(&self) -> u64 {
+        self.total_size
+    }
+
+    /// Returns the index of the chunk holding the byte at `position` and the offset of that byte
+    /// within the chunk, or `None` if `position` is at or past the end.
+    fn locate(&self, position: u64) -> Option<(usize, usize)> {
+        let mut start = 0u64;
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            let end = start + chunk.payload_size() as u64;
+            if position < end {
+                return Some((index, (position - start) as usize));
+            }
+            start = end;
+        }
+        None
+    }
+}
+
+impl<'a> Read for ChunkedReader<'a> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let (index, offset) = match self.locate(self.position) {
+            Some(location) => location,
+            None => return Ok(0),
+        };
+        // Read from one chunk at a time; callers wanting more call again.
+        let value = &self.chunks[index].value()[offset..];
+        let size = cmp::min(value.len(), buffer.len());
+        buffer[..size].copy_from_slice(&value[..size]);
+        self.position += size as u64;
+        Ok(size)
+    }
+}
+
+impl<'a> Seek for ChunkedReader<'a> {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match position {
+            SeekFrom::Start(offset) => {
+                self.position = offset;
+                return Ok(offset);
+            }
+            SeekFrom::End(offset) => (self.total_size, offset),
+            SeekFrom::Current(offset) => (self.position, offset),
+        };
+        let new_position = if offset >= 0 {
+            base.checked_add(offset as u64)
+        } else {
+            base.checked_sub(offset.wrapping_neg() as u64)
+        };
+        match new_position {
+            Some(new_position) => {
+                self.position = new_position;
+                Ok(new_position)
+            }
+            None => {
+                Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                   "invalid seek to a negative or overflowing position"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::ImmutableData;
+    use std::io::{Read, Seek, SeekFrom};
+
+    #[test]
+    fn read_and_seek() {
+        let content: Vec<u8> = (0..250).collect();
+        let chunks: Vec<_> = content.chunks(100)
+            .map(|chunk| ImmutableData::new(chunk.to_vec()))
+            .collect();
+        let mut reader = ChunkedReader::new(&chunks);
+        assert_eq!(reader.total_size(), 250);
+
+        let mut all = Vec::new();
+        assert_eq!(unwrap!(reader.read_to_end(&mut all)), 250);
+        assert_eq!(all, content);
+
+        // A range spanning the boundary between the first and second chunks.
+        assert_eq!(unwrap!(reader.seek(SeekFrom::Start(95))), 95);
+        let mut range = [0u8; 10];
+        unwrap!(reader.read_exact(&mut range));
+        assert_eq!(&range[..], &content[95..105]);
+
+        assert_eq!(unwrap!(reader.seek(SeekFrom::Current(-5))), 100);
+        assert_eq!(unwrap!(reader.seek(SeekFrom::End(-10))), 240);
+        let mut tail = Vec::new();
+        assert_eq!(unwrap!(reader.read_to_end(&mut tail)), 10);
+        assert_eq!(&tail[..], &content[240..]);
+
+        assert!(reader.seek(SeekFrom::Current(-1000)).is_err());
+        assert_eq!(unwrap!(reader.seek(SeekFrom::Start(1000))), 1000);
+        assert_eq!(unwrap!(reader.read(&mut range)), 0);
+
+        let mut empty = ChunkedReader::new(&[]);
+        assert_eq!(unwrap!(empty.read(&mut range)), 0);
+    }
+}
@@ -0,0 +1,167 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Content-defined chunking
+//! Splits content into `ImmutableData` chunks at boundaries chosen by the content itself rather
+//! than at fixed offsets, so an insertion or deletion only changes the chunks around it and
+//! similar files, such as successive versions of a backup, share most of their chunks.
+//!
+//! Boundaries are found with a gear rolling hash: a boundary follows any byte after which the
+//! top bits of the hash are all zero, giving chunks of about the requested average size, bounded
+//! by the minimum and maximum.
+
+use data::{ChunkDetails, DataMap, ImmutableData};
+use error::Error;
+use std::io::{self, Read};
+
+/// Size of the buffer used when reading content.
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Splits the content of `reader` into chunks of between `min` and `max` bytes, about `avg` on
+/// average, and returns them with the `DataMap` listing them.
+///
+/// The sizes must satisfy `0 < min <= avg <= max <= ImmutableData::MAX_BYTES`, otherwise this
+/// fails with `Validation`. The chunks are neither compressed nor encrypted, so each chunk's
+/// pre-encryption hash is its name.
+pub fn chunk_cdc<R: Read>(mut reader: R,
+                          min: usize,
+                          avg: usize,
+                          max: usize)
+                          -> Result<(Vec<ImmutableData>, DataMap), Error> {
+    if min == 0 || min > avg || avg > max || max > ImmutableData::MAX_BYTES {
+        return Err(Error::Validation);
+    }
+    let gear = gear_table();
+    let mask = boundary_mask(avg);
+
+    let mut chunks = Vec::new();
+    let mut current = Vec::with_capacity(max);
+    let mut hash = 0u64;
+    let mut buffer = [0u8; READ_BUFFER_SIZE];
+    loop {
+        let size = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(size) => size,
+            Err(ref error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(From::from(error)),
+        };
+        for &byte in &buffer[..size] {
+            current.push(byte);
+            hash = (hash << 1).wrapping_add(gear[byte as usize]);
+            if current.len() >= max || (current.len() >= min && hash & mask == 0) {
+                chunks.push(ImmutableData::new(current));
+                current = Vec::with_capacity(max);
+                hash = 0;
+            }
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(ImmutableData::new(current));
+    }
+
+    let details = chunks.iter()
+        .map(|chunk| {
+            ChunkDetails {
+                name: *chunk.name(),
+                size: chunk.payload_size() as u64,
+                pre_encryption_hash: *chunk.name(),
+            }
+        })
+        .collect();
+    Ok((chunks, DataMap::new(details)))
+}
+
+/// Returns the mask of the top bits of the rolling hash which must all be zero at a boundary, as
+/// many as give a boundary every `avg` bytes on average.
+fn boundary_mask(avg: usize) -> u64 {
+    let bits = avg.next_power_of_two().trailing_zeros();
+    if bits == 0 {
+        0
+    } else {
+        !0u64 << (64 - bits)
+    }
+}
+
+/// Returns the table of pseudo-random values the rolling hash adds for each byte value. It is
+/// fixed, since changing it would move every boundary.
+fn gear_table() -> [u64; 256] {
+    // SplitMix64 from a fixed seed.
+    let mut state = 0x6a09_e667_f3bc_c908u64;
+    let mut table = [0u64; 256];
+    for value in table.iter_mut() {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut mixed = state;
+        mixed = (mixed ^ (mixed >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        mixed = (mixed ^ (mixed >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        *value = mixed ^ (mixed >> 31);
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::ChunkedReader;
+    use error::Error;
+    use rand;
+    use std::collections::HashSet;
+    use std::io::Read;
+
+    #[test]
+    fn chunk_sizes_and_content() {
+        let content: Vec<u8> = (0..100_000).map(|_| rand::random()).collect();
+        let (chunks, data_map) = unwrap!(chunk_cdc(&content[..], 512, 2048, 8192));
+        assert_eq!(data_map.total_size(), content.len() as u64);
+        assert_eq!(data_map.chunks().len(), chunks.len());
+        let (last, others) = unwrap!(chunks.split_last());
+        assert!(others.iter().all(|chunk| chunk.payload_size() >= 512));
+        assert!(chunks.iter().all(|chunk| chunk.payload_size() <= 8192));
+        assert!(last.payload_size() > 0);
+
+        let mut read = Vec::new();
+        let _ = unwrap!(ChunkedReader::new(&chunks).read_to_end(&mut read));
+        assert_eq!(read, content);
+
+        // Chunking is deterministic.
+        let (_, again) = unwrap!(chunk_cdc(&content[..], 512, 2048, 8192));
+        assert_eq!(again, data_map);
+    }
+
+    #[test]
+    fn insertion_keeps_most_chunks() {
+        let content: Vec<u8> = (0..100_000).map(|_| rand::random()).collect();
+        let mut edited = b"inserted at the start".to_vec();
+        edited.extend_from_slice(&content);
+
+        let (_, original) = unwrap!(chunk_cdc(&content[..], 512, 2048, 8192));
+        let (_, edited) = unwrap!(chunk_cdc(&edited[..], 512, 2048, 8192));
+        let names: HashSet<_> = original.chunks().iter().map(|chunk| chunk.name).collect();
+        let shared = edited.chunks().iter().filter(|chunk| names.contains(&chunk.name)).count();
+        assert!(shared + 2 >= original.chunks().len());
+    }
+
+    #[test]
+    fn invalid_sizes() {
+        let too_large = ImmutableData::MAX_BYTES + 1;
+        for &(min, avg, max) in &[(0, 1, 1), (2, 1, 4), (1, 4, 2), (1, 1, too_large)] {
+            match chunk_cdc(&[0u8; 10][..], min, avg, max) {
+                Err(Error::Validation) => (),
+                result => panic!("Unexpected result {:?}", result),
+            }
+        }
+    }
+}
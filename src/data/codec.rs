@@ -0,0 +1,111 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Codecs
+//! Encoding of `Data` to and from bytes. `DefaultCodec` is the crate's own serialisation,
+//! available with the `serialisation` feature; without it, e.g. on embedded targets, callers
+//! implement `Codec` with an encoding of their own and rebuild the data through the constructors
+//! of the data types, which name and validate it without serialising. `CompactCodec` is a
+//! smaller encoding for constrained links.
+
+use data::Data;
+use error::Error;
+#[cfg(feature = "serialisation")]
+use serialisation::{deserialise, serialise};
+
+/// An encoding of `Data` to and from bytes.
+pub trait Codec {
+    /// Encodes `data`.
+    fn encode(&self, data: &Data) -> Result<Vec<u8>, Error>;
+
+    /// Decodes data encoded by `encode`. Implementations should check the result with
+    /// `Data::validate` before using it.
+    fn decode(&self, bytes: &[u8]) -> Result<Data, Error>;
+}
+
+/// The crate's own serialisation of `Data`, as stored and sent by the rest of the crate.
+#[cfg(feature = "serialisation")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultCodec;
+
+#[cfg(feature = "serialisation")]
+impl Codec for DefaultCodec {
+    fn encode(&self, data: &Data) -> Result<Vec<u8>, Error> {
+        Ok(serialise(data)?)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Data, Error> {
+        Ok(deserialise(bytes)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{ImmutableData, PlainData};
+    use error::Error;
+    use rand;
+
+    // A codec of the kind an embedded caller might bring: plain data only, as the name followed
+    // by the value.
+    struct PlainCodec;
+
+    impl Codec for PlainCodec {
+        fn encode(&self, data: &Data) -> Result<Vec<u8>, Error> {
+            match *data {
+                Data::Plain(ref data) => {
+                    let mut bytes = data.name().to_vec();
+                    bytes.extend_from_slice(data.value());
+                    Ok(bytes)
+                }
+                _ => Err(Error::Encoding("unsupported data".to_owned())),
+            }
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Result<Data, Error> {
+            if bytes.len() < 32 {
+                return Err(Error::Encoding("truncated plain data".to_owned()));
+            }
+            let mut name = [0; 32];
+            name.copy_from_slice(&bytes[..32]);
+            Ok(Data::Plain(PlainData::new(name, bytes[32..].to_vec())))
+        }
+    }
+
+    #[test]
+    fn default_codec() {
+        let data = Data::Immutable(ImmutableData::new(vec![1, 2, 3]));
+        let encoded = unwrap!(DefaultCodec.encode(&data));
+        assert_eq!(encoded.len(), unwrap!(data.serialised_size()));
+        assert_eq!(unwrap!(DefaultCodec.decode(&encoded)), data);
+        assert!(DefaultCodec.decode(&encoded[1..]).is_err());
+    }
+
+    #[test]
+    fn own_codec() {
+        let data = Data::Plain(PlainData::new(rand::random(), vec![4, 5, 6]));
+        let encoded = unwrap!(PlainCodec.encode(&data));
+        let decoded = unwrap!(PlainCodec.decode(&encoded));
+        assert_eq!(decoded, data);
+        assert_eq!(decoded.identifier(), data.identifier());
+
+        match PlainCodec.encode(&Data::Immutable(ImmutableData::new(vec![]))) {
+            Err(Error::Encoding(_)) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
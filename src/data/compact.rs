@@ -0,0 +1,651 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Compact wire format
+//! An alternative to the crate's own serialisation for constrained links, e.g. mobile or radio,
+//! where the fixed-width integers, lengths and variant indices of the default format are a large
+//! part of small messages.
+//!
+//! The format follows the same `Encodable` implementations as the default one, so anything which
+//! serialises can be encoded compactly. Unsigned integers, lengths and variant indices are LEB128
+//! varints, signed integers are zigzag-encoded varints, bytes and booleans are single bytes and
+//! floats are little-endian; structs and tuples add nothing to their fields. Decoding rejects
+//! over-long varints and trailing bytes, so each value has one encoding.
+
+use data::Data;
+use data::codec::Codec;
+use error::Error;
+use rustc_serialize::{Decodable, Decoder, Encodable, Encoder};
+use std::char;
+
+/// Encodes `value` in the compact format.
+pub fn serialise<T: Encodable>(value: &T) -> Result<Vec<u8>, Error> {
+    let mut encoder = CompactEncoder { bytes: Vec::new() };
+    value.encode(&mut encoder)?;
+    Ok(encoder.bytes)
+}
+
+/// Decodes a `T` from `bytes` in the compact format, failing with `Encoding` if they are
+/// malformed or there are bytes left over.
+pub fn deserialise<T: Decodable>(bytes: &[u8]) -> Result<T, Error> {
+    let mut decoder = CompactDecoder { bytes: bytes };
+    let value = T::decode(&mut decoder)?;
+    if !decoder.bytes.is_empty() {
+        return Err(Error::Encoding(format!("{} bytes left over", decoder.bytes.len())));
+    }
+    Ok(value)
+}
+
+/// A `Codec` encoding `Data` in the compact format.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CompactCodec;
+
+impl Codec for CompactCodec {
+    fn encode(&self, data: &Data) -> Result<Vec<u8>, Error> {
+        serialise(data)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Data, Error> {
+        deserialise(bytes)
+    }
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+struct CompactEncoder {
+    bytes: Vec<u8>,
+}
+
+impl CompactEncoder {
+    fn write_varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.bytes.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.bytes.push(value as u8);
+    }
+
+    fn write_le(&mut self, value: u64, len: usize) {
+        self.bytes.extend((0..len).map(|index| (value >> (8 * index)) as u8));
+    }
+}
+
+impl Encoder for CompactEncoder {
+    type Error = Error;
+
+    fn emit_nil(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn emit_usize(&mut self, value: usize) -> Result<(), Error> {
+        self.emit_u64(value as u64)
+    }
+
+    fn emit_u64(&mut self, value: u64) -> Result<(), Error> {
+        self.write_varint(value);
+        Ok(())
+    }
+
+    fn emit_u32(&mut self, value: u32) -> Result<(), Error> {
+        self.emit_u64(value as u64)
+    }
+
+    fn emit_u16(&mut self, value: u16) -> Result<(), Error> {
+        self.emit_u64(value as u64)
+    }
+
+    fn emit_u8(&mut self, value: u8) -> Result<(), Error> {
+        self.bytes.push(value);
+        Ok(())
+    }
+
+    fn emit_isize(&mut self, value: isize) -> Result<(), Error> {
+        self.emit_i64(value as i64)
+    }
+
+    fn emit_i64(&mut self, value: i64) -> Result<(), Error> {
+        self.emit_u64(zigzag(value))
+    }
+
+    fn emit_i32(&mut self, value: i32) -> Result<(), Error> {
+        self.emit_i64(value as i64)
+    }
+
+    fn emit_i16(&mut self, value: i16) -> Result<(), Error> {
+        self.emit_i64(value as i64)
+    }
+
+    fn emit_i8(&mut self, value: i8) -> Result<(), Error> {
+        self.emit_u8(value as u8)
+    }
+
+    fn emit_bool(&mut self, value: bool) -> Result<(), Error> {
+        self.emit_u8(value as u8)
+    }
+
+    fn emit_f64(&mut self, value: f64) -> Result<(), Error> {
+        self.write_le(value.to_bits(), 8);
+        Ok(())
+    }
+
+    fn emit_f32(&mut self, value: f32) -> Result<(), Error> {
+        self.write_le(value.to_bits() as u64, 4);
+        Ok(())
+    }
+
+    fn emit_char(&mut self, value: char) -> Result<(), Error> {
+        self.emit_u64(value as u64)
+    }
+
+    fn emit_str(&mut self, value: &str) -> Result<(), Error> {
+        self.emit_usize(value.len())?;
+        self.bytes.extend_from_slice(value.as_bytes());
+        Ok(())
+    }
+
+    fn emit_enum<F>(&mut self, _name: &str, f: F) -> Result<(), Error>
+        where F: FnOnce(&mut Self) -> Result<(), Error>
+    {
+        f(self)
+    }
+
+    fn emit_enum_variant<F>(&mut self,
+                            _name: &str,
+                            id: usize,
+                            _len: usize,
+                            f: F)
+                            -> Result<(), Error>
+        where F: FnOnce(&mut Self) -> Result<(), Error>
+    {
+        self.emit_usize(id)?;
+        f(self)
+    }
+
+    fn emit_enum_variant_arg<F>(&mut self, _index: usize, f: F) -> Result<(), Error>
+        where F: FnOnce(&mut Self) -> Result<(), Error>
+    {
+        f(self)
+    }
+
+    fn emit_enum_struct_variant<F>(&mut self,
+                                   name: &str,
+                                   id: usize,
+                                   len: usize,
+                                   f: F)
+                                   -> Result<(), Error>
+        where F: FnOnce(&mut Self) -> Result<(), Error>
+    {
+        self.emit_enum_variant(name, id, len, f)
+    }
+
+    fn emit_enum_struct_variant_field<F>(&mut self,
+                                         _name: &str,
+                                         _index: usize,
+                                         f: F)
+                                         -> Result<(), Error>
+        where F: FnOnce(&mut Self) -> Result<(), Error>
+    {
+        f(self)
+    }
+
+    fn emit_struct<F>(&mut self, _name: &str, _len: usize, f: F) -> Result<(), Error>
+        where F: FnOnce(&mut Self) -> Result<(), Error>
+    {
+        f(self)
+    }
+
+    fn emit_struct_field<F>(&mut self, _name: &str, _index: usize, f: F) -> Result<(), Error>
+        where F: FnOnce(&mut Self) -> Result<(), Error>
+    {
+        f(self)
+    }
+
+    fn emit_tuple<F>(&mut self, _len: usize, f: F) -> Result<(), Error>
+        where F: FnOnce(&mut Self) -> Result<(), Error>
+    {
+        f(self)
+    }
+
+    fn emit_tuple_arg<F>(&mut self, _index: usize, f: F) -> Result<(), Error>
+        where F: FnOnce(&mut Self) -> Result<(), Error>
+    {
+        f(self)
+    }
+
+    fn emit_tuple_struct<F>(&mut self, _name: &str, _len: usize, f: F) -> Result<(), Error>
+        where F: FnOnce(&mut Self) -> Result<(), Error>
+    {
+        f(self)
+    }
+
+    fn emit_tuple_struct_arg<F>(&mut self, _index: usize, f: F) -> Result<(), Error>
+        where F: FnOnce(&mut Self) -> Result<(), Error>
+    {
+        f(self)
+    }
+
+    fn emit_option<F>(&mut self, f: F) -> Result<(), Error>
+        where F: FnOnce(&mut Self) -> Result<(), Error>
+    {
+        f(self)
+    }
+
+    fn emit_option_none(&mut self) -> Result<(), Error> {
+        self.emit_u8(0)
+    }
+
+    fn emit_option_some<F>(&mut self, f: F) -> Result<(), Error>
+        where F: FnOnce(&mut Self) -> Result<(), Error>
+    {
+        self.emit_u8(1)?;
+        f(self)
+    }
+
+    fn emit_seq<F>(&mut self, len: usize, f: F) -> Result<(), Error>
+        where F: FnOnce(&mut Self) -> Result<(), Error>
+    {
+        self.emit_usize(len)?;
+        f(self)
+    }
+
+    fn emit_seq_elt<F>(&mut self, _index: usize, f: F) -> Result<(), Error>
+        where F: FnOnce(&mut Self) -> Result<(), Error>
+    {
+        f(self)
+    }
+
+    fn emit_map<F>(&mut self, len: usize, f: F) -> Result<(), Error>
+        where F: FnOnce(&mut Self) -> Result<(), Error>
+    {
+        self.emit_usize(len)?;
+        f(self)
+    }
+
+    fn emit_map_elt_key<F>(&mut self, _index: usize, f: F) -> Result<(), Error>
+        where F: FnOnce(&mut Self) -> Result<(), Error>
+    {
+        f(self)
+    }
+
+    fn emit_map_elt_val<F>(&mut self, _index: usize, f: F) -> Result<(), Error>
+        where F: FnOnce(&mut Self) -> Result<(), Error>
+    {
+        f(self)
+    }
+}
+
+struct CompactDecoder<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> CompactDecoder<'a> {
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if len > self.bytes.len() {
+            return Err(Error::Encoding("truncated compact encoding".to_owned()));
+        }
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(bytes)
+    }
+
+    fn read_byte(&mut self) -> Result<u8, Error> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_varint(&mut self) -> Result<u64, Error> {
+        let mut value = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = self.read_byte()?;
+            // The tenth byte holds only the top bit of a `u64`.
+            if shift == 63 && byte > 1 {
+                return Err(Error::Encoding("varint overflows 64 bits".to_owned()));
+            }
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                if byte == 0 && shift > 0 {
+                    return Err(Error::Encoding("over-long varint".to_owned()));
+                }
+                return Ok(value);
+            }
+            shift += 7;
+        }
+    }
+
+    fn read_unsigned(&mut self, max: u64) -> Result<u64, Error> {
+        let value = self.read_varint()?;
+        if value > max {
+            return Err(Error::Encoding(format!("{} out of range", value)));
+        }
+        Ok(value)
+    }
+
+    fn read_signed(&mut self, min: i64, max: i64) -> Result<i64, Error> {
+        let value = unzigzag(self.read_varint()?);
+        if value < min || value > max {
+            return Err(Error::Encoding(format!("{} out of range", value)));
+        }
+        Ok(value)
+    }
+
+    /// Reads the length of a sequence or map. Every element takes at least one byte, so a
+    /// length beyond the bytes left is refused before anything is allocated for it.
+    fn read_len(&mut self) -> Result<usize, Error> {
+        let len = self.read_varint()?;
+        if len > self.bytes.len() as u64 {
+            return Err(Error::Encoding(format!("length {} exceeds the {} bytes left",
+                                               len,
+                                               self.bytes.len())));
+        }
+        Ok(len as usize)
+    }
+
+    fn read_le(&mut self, len: usize) -> Result<u64, Error> {
+        let bytes = self.read_bytes(len)?;
+        Ok(bytes.iter().rev().fold(0, |value, &byte| (value << 8) | byte as u64))
+    }
+}
+
+impl<'a> Decoder for CompactDecoder<'a> {
+    type Error = Error;
+
+    fn read_nil(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn read_usize(&mut self) -> Result<usize, Error> {
+        Ok(self.read_unsigned(usize::max_value() as u64)? as usize)
+    }
+
+    fn read_u64(&mut self) -> Result<u64, Error> {
+        self.read_varint()
+    }
+
+    fn read_u32(&mut self) -> Result<u32, Error> {
+        Ok(self.read_unsigned(u32::max_value() as u64)? as u32)
+    }
+
+    fn read_u16(&mut self) -> Result<u16, Error> {
+        Ok(self.read_unsigned(u16::max_value() as u64)? as u16)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        self.read_byte()
+    }
+
+    fn read_isize(&mut self) -> Result<isize, Error> {
+        Ok(self.read_signed(isize::min_value() as i64, isize::max_value() as i64)? as isize)
+    }
+
+    fn read_i64(&mut self) -> Result<i64, Error> {
+        Ok(unzigzag(self.read_varint()?))
+    }
+
+    fn read_i32(&mut self) -> Result<i32, Error> {
+        Ok(self.read_signed(i32::min_value() as i64, i32::max_value() as i64)? as i32)
+    }
+
+    fn read_i16(&mut self) -> Result<i16, Error> {
+        Ok(self.read_signed(i16::min_value() as i64, i16::max_value() as i64)? as i16)
+    }
+
+    fn read_i8(&mut self) -> Result<i8, Error> {
+        Ok(self.read_byte()? as i8)
+    }
+
+    fn read_bool(&mut self) -> Result<bool, Error> {
+        match self.read_byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            byte => Err(Error::Encoding(format!("invalid bool {}", byte))),
+        }
+    }
+
+    fn read_f64(&mut self) -> Result<f64, Error> {
+        Ok(f64::from_bits(self.read_le(8)?))
+    }
+
+    fn read_f32(&mut self) -> Result<f32, Error> {
+        Ok(f32::from_bits(self.read_le(4)? as u32))
+    }
+
+    fn read_char(&mut self) -> Result<char, Error> {
+        let value = self.read_unsigned(u32::max_value() as u64)? as u32;
+        char::from_u32(value).ok_or_else(|| Error::Encoding(format!("invalid char {}", value)))
+    }
+
+    fn read_str(&mut self) -> Result<String, Error> {
+        let len = self.read_len()?;
+        String::from_utf8(self.read_bytes(len)?.to_vec())
+            .map_err(|_| Error::Encoding("invalid UTF-8".to_owned()))
+    }
+
+    fn read_enum<T, F>(&mut self, _name: &str, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self) -> Result<T, Error>
+    {
+        f(self)
+    }
+
+    fn read_enum_variant<T, F>(&mut self, names: &[&str], mut f: F) -> Result<T, Error>
+        where F: FnMut(&mut Self, usize) -> Result<T, Error>
+    {
+        let index = self.read_unsigned(names.len() as u64)? as usize;
+        if index == names.len() {
+            return Err(Error::Encoding(format!("invalid variant {}", index)));
+        }
+        f(self, index)
+    }
+
+    fn read_enum_variant_arg<T, F>(&mut self, _index: usize, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self) -> Result<T, Error>
+    {
+        f(self)
+    }
+
+    fn read_enum_struct_variant<T, F>(&mut self, names: &[&str], f: F) -> Result<T, Error>
+        where F: FnMut(&mut Self, usize) -> Result<T, Error>
+    {
+        self.read_enum_variant(names, f)
+    }
+
+    fn read_enum_struct_variant_field<T, F>(&mut self,
+                                            _name: &str,
+                                            _index: usize,
+                                            f: F)
+                                            -> Result<T, Error>
+        where F: FnOnce(&mut Self) -> Result<T, Error>
+    {
+        f(self)
+    }
+
+    fn read_struct<T, F>(&mut self, _name: &str, _len: usize, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self) -> Result<T, Error>
+    {
+        f(self)
+    }
+
+    fn read_struct_field<T, F>(&mut self, _name: &str, _index: usize, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self) -> Result<T, Error>
+    {
+        f(self)
+    }
+
+    fn read_tuple<T, F>(&mut self, _len: usize, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self) -> Result<T, Error>
+    {
+        f(self)
+    }
+
+    fn read_tuple_arg<T, F>(&mut self, _index: usize, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self) -> Result<T, Error>
+    {
+        f(self)
+    }
+
+    fn read_tuple_struct<T, F>(&mut self, _name: &str, _len: usize, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self) -> Result<T, Error>
+    {
+        f(self)
+    }
+
+    fn read_tuple_struct_arg<T, F>(&mut self, _index: usize, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self) -> Result<T, Error>
+    {
+        f(self)
+    }
+
+    fn read_option<T, F>(&mut self, mut f: F) -> Result<T, Error>
+        where F: FnMut(&mut Self, bool) -> Result<T, Error>
+    {
+        match self.read_byte()? {
+            0 => f(self, false),
+            1 => f(self, true),
+            byte => Err(Error::Encoding(format!("invalid option tag {}", byte))),
+        }
+    }
+
+    fn read_seq<T, F>(&mut self, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self, usize) -> Result<T, Error>
+    {
+        let len = self.read_len()?;
+        f(self, len)
+    }
+
+    fn read_seq_elt<T, F>(&mut self, _index: usize, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self) -> Result<T, Error>
+    {
+        f(self)
+    }
+
+    fn read_map<T, F>(&mut self, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self, usize) -> Result<T, Error>
+    {
+        let len = self.read_len()?;
+        f(self, len)
+    }
+
+    fn read_map_elt_key<T, F>(&mut self, _index: usize, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self) -> Result<T, Error>
+    {
+        f(self)
+    }
+
+    fn read_map_elt_val<T, F>(&mut self, _index: usize, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self) -> Result<T, Error>
+    {
+        f(self)
+    }
+
+    fn error(&mut self, error: &str) -> Error {
+        Error::Encoding(error.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{Data, DataIdentifier, ImmutableData, PlainData, StructuredData};
+    use error::Error;
+    use rand;
+    use serialisation;
+    use test_utils;
+
+    #[test]
+    fn integers() {
+        for &value in &[0, 1, 127, 128, 300, u64::max_value()] {
+            let encoded = unwrap!(serialise(&value));
+            assert_eq!(unwrap!(deserialise::<u64>(&encoded)), value);
+        }
+        assert_eq!(unwrap!(serialise(&127u64)), vec![0x7f]);
+        assert_eq!(unwrap!(serialise(&300u64)), vec![0xac, 0x02]);
+        assert_eq!(unwrap!(serialise(&u64::max_value())).len(), 10);
+        for &value in &[0, -1, 1, -64, 64, i64::min_value(), i64::max_value()] {
+            assert_eq!(unwrap!(deserialise::<i64>(&unwrap!(serialise(&value)))), value);
+        }
+        assert_eq!(unwrap!(serialise(&-1i64)), vec![1]);
+
+        // Over-long, overflowing and out of range integers are refused.
+        for bytes in &[vec![0x80, 0x00],
+                       vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02]] {
+            match deserialise::<u64>(bytes) {
+                Err(Error::Encoding(_)) => (),
+                result => panic!("Unexpected result {:?}", result),
+            }
+        }
+        match deserialise::<u16>(&unwrap!(serialise(&70000u64))) {
+            Err(Error::Encoding(_)) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn data_round_trip() {
+        let keys = test_utils::gen_keypair();
+        let structured_data = unwrap!(StructuredData::new(5,
+                                                          rand::random(),
+                                                          3,
+                                                          vec![1, 2, 3],
+                                                          vec![keys.0],
+                                                          vec![],
+                                                          Some(&keys.1),
+                                                          false));
+        let items = vec![Data::Structured(structured_data),
+                         Data::Immutable(ImmutableData::new(vec![4, 5, 6])),
+                         Data::Plain(PlainData::new(rand::random(), vec![7, 8, 9]))];
+        for data in items {
+            let encoded = unwrap!(CompactCodec.encode(&data));
+            assert_eq!(unwrap!(CompactCodec.decode(&encoded)), data);
+            assert_eq!(unwrap!(Data::decode_compact(&unwrap!(data.encode_compact()))), data);
+            let identifier = data.identifier();
+            let encoded_identifier = unwrap!(identifier.encode_compact());
+            assert_eq!(unwrap!(DataIdentifier::decode_compact(&encoded_identifier)), identifier);
+
+            // Small payloads spend a double-digit percentage less than the default format.
+            let default_size = unwrap!(serialisation::serialise(&data)).len();
+            assert!(encoded.len() * 10 < default_size * 9,
+                    "{} compact vs {} default bytes",
+                    encoded.len(),
+                    default_size);
+
+            let mut trailing = encoded.clone();
+            trailing.push(0);
+            match CompactCodec.decode(&trailing) {
+                Err(Error::Encoding(_)) => (),
+                result => panic!("Unexpected result {:?}", result),
+            }
+            assert!(CompactCodec.decode(&encoded[..encoded.len() - 1]).is_err());
+        }
+    }
+
+    #[test]
+    fn huge_lengths_refused() {
+        // The variant index of `Data::Plain`, a 32-byte name, then a value of length 2^60.
+        let mut bytes = vec![2, 32];
+        bytes.extend_from_slice(&[0; 32]);
+        bytes.extend_from_slice(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x10]);
+        match deserialise::<Data>(&bytes) {
+            Err(Error::Encoding(_)) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
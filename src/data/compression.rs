@@ -0,0 +1,120 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use error::Error;
+#[cfg(feature = "compression")]
+use flate2::Compression as Level;
+#[cfg(feature = "compression")]
+use flate2::read::ZlibDecoder;
+#[cfg(feature = "compression")]
+use flate2::write::ZlibEncoder;
+use std::borrow::Cow;
+#[cfg(feature = "compression")]
+use std::io::{Read, Write};
+
+/// Maximum size a compressed payload may expand to when decompressed.
+pub const MAX_DECOMPRESSED_BYTES: usize = 1024 * 1024;
+
+/// Algorithm a payload is compressed with.
+///
+/// Data recording a compression can always be stored and relayed, but compressing and
+/// decompressing payloads needs the `compression` feature.
+#[derive(Hash, Eq, PartialEq, PartialOrd, Ord, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serialisation", derive(RustcEncodable, RustcDecodable))]
+pub enum Compression {
+    /// DEFLATE in a zlib wrapper.
+    Deflate,
+}
+
+impl Compression {
+    /// Index of the algorithm, as used in names and signed bytes.
+    pub fn index(&self) -> u8 {
+        match *self {
+            Compression::Deflate => 0,
+        }
+    }
+
+    /// Compresses `data`.
+    #[cfg(feature = "compression")]
+    pub fn compress(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        match *self {
+            Compression::Deflate => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Level::Default);
+                encoder.write_all(data)?;
+                Ok(encoder.finish()?)
+            }
+        }
+    }
+
+    /// Decompresses `data`, refusing output larger than `MAX_DECOMPRESSED_BYTES`.
+    #[cfg(feature = "compression")]
+    pub fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut decompressed = Vec::new();
+        match *self {
+            Compression::Deflate => {
+                let decoder = ZlibDecoder::new(data);
+                let _ = decoder.take(MAX_DECOMPRESSED_BYTES as u64 + 1)
+                    .read_to_end(&mut decompressed)?;
+            }
+        }
+        if decompressed.len() > MAX_DECOMPRESSED_BYTES {
+            return Err(Error::DataTooLarge);
+        }
+        Ok(decompressed)
+    }
+
+    /// Decompression needs the `compression` feature; without it this always fails.
+    #[cfg(not(feature = "compression"))]
+    pub fn decompress(&self, _data: &[u8]) -> Result<Vec<u8>, Error> {
+        Err(Error::Encoding("built without compression support".to_owned()))
+    }
+}
+
+/// Returns `payload` decompressed according to `compression`, or borrowed if uncompressed.
+pub fn decompressed(payload: &[u8], compression: Option<Compression>) -> Result<Cow<[u8]>, Error> {
+    match compression {
+        Some(compression) => Ok(Cow::Owned(compression.decompress(payload)?)),
+        None => Ok(Cow::Borrowed(payload)),
+    }
+}
+
+#[cfg(all(test, feature = "compression"))]
+mod tests {
+    use super::*;
+    use error::Error;
+
+    #[test]
+    fn round_trip() {
+        let data = vec![7u8; 10000];
+        let compressed = unwrap!(Compression::Deflate.compress(&data));
+        assert!(compressed.len() < data.len());
+        assert_eq!(unwrap!(Compression::Deflate.decompress(&compressed)), data);
+        assert_eq!(&*unwrap!(decompressed(&compressed, Some(Compression::Deflate))),
+                   &data[..]);
+        assert_eq!(&*unwrap!(decompressed(&data, None)), &data[..]);
+        assert!(Compression::Deflate.decompress(b"not deflate").is_err());
+    }
+
+    #[test]
+    fn decompression_bomb() {
+        let bomb = unwrap!(Compression::Deflate.compress(&vec![0; MAX_DECOMPRESSED_BYTES + 1]));
+        match Compression::Deflate.decompress(&bomb) {
+            Err(Error::DataTooLarge) => (),
+            result => panic!("Unexpected result {:?}", result.map(|data| data.len())),
+        }
+    }
+}
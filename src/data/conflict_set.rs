@@ -0,0 +1,199 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Conflict sets
+//! Competing successors of one version of a `StructuredData`. Owners signing different updates
+//! of the same version leave holders with two or more valid successors, received in any order.
+//! Collecting them in a `ConflictSet` and keeping its `winner` lets every holder settle on the
+//! same one, as decided by `StructuredData::resolve_conflict`.
+
+use data::{StructuredData, Winner};
+use error::Error;
+
+/// The valid successors of one version of a `StructuredData` received so far.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConflictSet {
+    current: StructuredData,
+    candidates: Vec<StructuredData>,
+}
+
+impl ConflictSet {
+    /// Starts collecting the successors of `current`.
+    pub fn new(current: StructuredData) -> ConflictSet {
+        ConflictSet {
+            current: current,
+            candidates: Vec::new(),
+        }
+    }
+
+    /// Returns the version whose successors are collected.
+    pub fn current(&self) -> &StructuredData {
+        &self.current
+    }
+
+    /// Adds `candidate` if it is a valid successor of the current version, failing as
+    /// `validate_self_against_successor` does if not. Returns whether it was not already held.
+    pub fn insert(&mut self, candidate: StructuredData) -> Result<bool, Error> {
+        self.current.validate_self_against_successor(&candidate)?;
+        if self.candidates.contains(&candidate) {
+            return Ok(false);
+        }
+        self.candidates.push(candidate);
+        Ok(true)
+    }
+
+    /// Returns the successors held, in the order they were added.
+    pub fn candidates(&self) -> &[StructuredData] {
+        &self.candidates
+    }
+
+    /// Returns the number of successors held.
+    pub fn len(&self) -> usize {
+        self.candidates.len()
+    }
+
+    /// Returns whether no successor is held.
+    pub fn is_empty(&self) -> bool {
+        self.candidates.is_empty()
+    }
+
+    /// Returns the successor which wins over all others, or `None` if none is held.
+    pub fn winner(&self) -> Result<Option<&StructuredData>, Error> {
+        Ok(self.winner_index()?.map(|index| &self.candidates[index]))
+    }
+
+    /// Returns the successor which wins over all others, or `None` if none is held.
+    pub fn into_winner(mut self) -> Result<Option<StructuredData>, Error> {
+        Ok(match self.winner_index()? {
+            Some(index) => Some(self.candidates.swap_remove(index)),
+            None => None,
+        })
+    }
+
+    fn winner_index(&self) -> Result<Option<usize>, Error> {
+        let mut winner = None;
+        for (index, candidate) in self.candidates.iter().enumerate() {
+            winner = match winner {
+                Some(best) => {
+                    match StructuredData::resolve_conflict(&self.candidates[best], candidate)? {
+                        Winner::First => Some(best),
+                        Winner::Second => Some(index),
+                    }
+                }
+                None => Some(index),
+            };
+        }
+        Ok(winner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{StructuredData, Winner};
+    use error::Error;
+    use rand;
+    use test_utils;
+
+    #[test]
+    fn converges_on_one_successor() {
+        let keys: Vec<_> = (0..3).map(|_| test_utils::gen_keypair()).collect();
+        let owners: Vec<_> = keys.iter().map(|key| key.0).collect();
+        let current = unwrap!(StructuredData::new(0,
+                                                  rand::random(),
+                                                  0,
+                                                  vec![0],
+                                                  owners.clone(),
+                                                  vec![],
+                                                  None,
+                                                  false));
+        let successor = |data: Vec<u8>, signers: &[usize]| {
+            let mut successor = unwrap!(StructuredData::new(0,
+                                                            *current.name(),
+                                                            1,
+                                                            data,
+                                                            owners.clone(),
+                                                            vec![],
+                                                            None,
+                                                            false));
+            for &signer in signers {
+                let _ = unwrap!(successor.add_signature(&keys[signer].1));
+            }
+            successor
+        };
+        let first = successor(vec![1], &[0, 1]);
+        let second = successor(vec![2], &[1, 2]);
+        // The same content signed by other owners competes too.
+        let resigned = successor(vec![1], &[1, 2]);
+
+        let mut forward = ConflictSet::new(current.clone());
+        let mut backward = ConflictSet::new(current.clone());
+        assert!(unwrap!(forward.winner()).is_none());
+        for candidate in &[&first, &second, &resigned] {
+            assert!(unwrap!(forward.insert((*candidate).clone())));
+        }
+        for candidate in &[&resigned, &second, &first] {
+            assert!(unwrap!(backward.insert((*candidate).clone())));
+        }
+        assert!(!unwrap!(forward.insert(first.clone())));
+        assert_eq!(forward.len(), 3);
+
+        let winner = unwrap!(unwrap!(forward.winner())).clone();
+        assert_eq!(unwrap!(backward.winner()), Some(&winner));
+        for candidate in &[&first, &second, &resigned] {
+            assert_eq!(unwrap!(StructuredData::resolve_conflict(&winner, candidate)),
+                       Winner::First);
+        }
+        assert_eq!(unwrap!(backward.into_winner()), Some(winner));
+
+        // Only valid successors are held.
+        let unsigned = successor(vec![3], &[0]);
+        assert!(forward.insert(unsigned).is_err());
+        assert_eq!(forward.len(), 3);
+    }
+
+    #[test]
+    fn resolve_conflict() {
+        let keys = test_utils::gen_keypair();
+        let data = unwrap!(StructuredData::new(0,
+                                               rand::random(),
+                                               0,
+                                               vec![0],
+                                               vec![keys.0],
+                                               vec![],
+                                               Some(&keys.1),
+                                               false));
+        let successor = unwrap!(data.transfer_ownership(vec![keys.0], &keys.1));
+        let other = unwrap!(StructuredData::new(0,
+                                                rand::random(),
+                                                0,
+                                                vec![0],
+                                                vec![keys.0],
+                                                vec![],
+                                                Some(&keys.1),
+                                                false));
+        assert_eq!(unwrap!(StructuredData::resolve_conflict(&data, &data)), Winner::First);
+        match StructuredData::resolve_conflict(&data, &successor) {
+            Err(Error::InvalidVersion) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match StructuredData::resolve_conflict(&data, &other) {
+            Err(Error::BadIdentifier) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,263 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Store costs
+//! How much storing a piece of data costs, so economic layers charging for PUTs agree on the
+//! price. The cost is in abstract units; converting units to safecoin is left to the caller.
+//!
+//! A `QuotaPolicy` is a cost policy vaults can agree on as a group, since it is plain serialisable
+//! data. It also charges structured and mutable data for how often they are updated and caps the
+//! units each owner may use, so no owner can fill the network with garbage.
+
+use data::Data;
+use error::Error;
+use std::cmp;
+
+/// Default size of one unit of storage, in bytes.
+pub const DEFAULT_UNIT_SIZE: usize = 1024;
+
+/// Decides the cost of storing data.
+pub trait CostPolicy {
+    /// Returns the cost, in units, of storing `data`.
+    fn cost(&self, data: &Data) -> u64;
+}
+
+/// The default `CostPolicy`.
+///
+/// Storing data costs its payload size in units, rounded up and at least one. An update of
+/// structured data (any version after the first) costs half as much as the initial PUT, rounded
+/// up, since the name it occupies has already been paid for.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DefaultCostPolicy {
+    unit_size: usize,
+}
+
+impl DefaultCostPolicy {
+    /// Creates a policy charging one unit per `unit_size` bytes. A size of 0 charges one unit per
+    /// byte.
+    pub fn new(unit_size: usize) -> DefaultCostPolicy {
+        DefaultCostPolicy { unit_size: cmp::max(unit_size, 1) }
+    }
+
+    /// Returns the size of one unit, in bytes.
+    pub fn unit_size(&self) -> usize {
+        self.unit_size
+    }
+}
+
+impl Default for DefaultCostPolicy {
+    fn default() -> DefaultCostPolicy {
+        DefaultCostPolicy::new(DEFAULT_UNIT_SIZE)
+    }
+}
+
+impl CostPolicy for DefaultCostPolicy {
+    fn cost(&self, data: &Data) -> u64 {
+        let size = data.payload_size();
+        let units = cmp::max(size / self.unit_size + (size % self.unit_size != 0) as usize, 1);
+        let units = units as u64;
+        match *data {
+            Data::Structured(ref structured_data) if structured_data.version() > 0 => {
+                units / 2 + units % 2
+            }
+            _ => units,
+        }
+    }
+}
+
+/// The `QuotaPolicy` vaults apply unless their group agrees on another: one unit per KiB, one
+/// unit more for each update, rising by a unit every 1000 versions, and a quota of 2^20 units
+/// (1 GiB of payload) per owner.
+pub const DEFAULT_QUOTA_POLICY: QuotaPolicy = QuotaPolicy {
+    unit_size: DEFAULT_UNIT_SIZE as u64,
+    min_units: 1,
+    update_units: 1,
+    surcharge_interval: 1000,
+    owner_quota: 1 << 20,
+};
+
+/// A `CostPolicy` agreed on by a group of vaults, pricing data by size and updates of
+/// structured and mutable data by how often the data has been updated, and capping the units
+/// each owner may use.
+///
+/// Data costs its payload size in units, rounded up and at least `min_units`. An update (any
+/// version after the first) costs `update_units` more, and `update_units` more again for every
+/// `surcharge_interval` versions the data has been through, so data rewritten constantly costs
+/// ever more to keep rewriting.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serialisation", derive(RustcEncodable, RustcDecodable))]
+pub struct QuotaPolicy {
+    /// Size of one unit, in bytes. A size of 0 charges one unit per byte.
+    pub unit_size: u64,
+    /// Fewest units any data costs.
+    pub min_units: u64,
+    /// Units added to the cost of an update, and again for every `surcharge_interval` versions.
+    pub update_units: u64,
+    /// Number of versions after which an update costs `update_units` more. 0 means updates cost
+    /// the same however many versions the data has been through.
+    pub surcharge_interval: u64,
+    /// Most units one owner may use in total.
+    pub owner_quota: u64,
+}
+
+impl QuotaPolicy {
+    /// Returns the units owners who have used `used_units` so far use once `data` is charged to
+    /// them, or fails with `NoSpace` if that exceeds the owner quota.
+    pub fn charge(&self, used_units: u64, data: &Data) -> Result<u64, Error> {
+        match used_units.checked_add(self.cost(data)) {
+            Some(total) if total <= self.owner_quota => Ok(total),
+            _ => Err(Error::NoSpace),
+        }
+    }
+
+    fn update_surcharge(&self, version: u64) -> u64 {
+        if version == 0 {
+            return 0;
+        }
+        let steps = if self.surcharge_interval == 0 {
+            0
+        } else {
+            version / self.surcharge_interval
+        };
+        self.update_units.saturating_mul(steps.saturating_add(1))
+    }
+}
+
+impl Default for QuotaPolicy {
+    fn default() -> QuotaPolicy {
+        DEFAULT_QUOTA_POLICY
+    }
+}
+
+impl CostPolicy for QuotaPolicy {
+    fn cost(&self, data: &Data) -> u64 {
+        let size = data.payload_size() as u64;
+        let unit_size = cmp::max(self.unit_size, 1);
+        let units = cmp::max(size / unit_size + (size % unit_size != 0) as u64, self.min_units);
+        let version = match *data {
+            Data::Structured(ref structured_data) => structured_data.version(),
+            Data::Mutable(ref mutable_data) => mutable_data.version(),
+            Data::Immutable(_) | Data::Plain(_) => 0,
+        };
+        units.saturating_add(self.update_surcharge(version))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{Data, ImmutableData, StructuredData};
+    use error::Error;
+    #[cfg(feature = "serialisation")]
+    use serialisation::{deserialise, serialise};
+    use std::u64;
+    use test_utils;
+
+    #[test]
+    fn default_policy() {
+        let policy = DefaultCostPolicy::new(100);
+        let immutable = |size| Data::Immutable(ImmutableData::new(vec![0; size]));
+        assert_eq!(policy.cost(&immutable(0)), 1);
+        assert_eq!(policy.cost(&immutable(100)), 1);
+        assert_eq!(policy.cost(&immutable(101)), 2);
+        assert_eq!(policy.cost(&immutable(1000)), 10);
+        assert_eq!(immutable(1000).store_cost(),
+                   DefaultCostPolicy::default().cost(&immutable(1000)));
+
+        let keys = test_utils::gen_keypair();
+        let structured = |version| {
+            Data::Structured(unwrap!(StructuredData::new(0,
+                                                         [0; 32],
+                                                         version,
+                                                         vec![0; 1000],
+                                                         vec![keys.0],
+                                                         vec![],
+                                                         Some(&keys.1),
+                                                         false)))
+        };
+        assert_eq!(policy.cost(&structured(0)), 10);
+        assert_eq!(policy.cost(&structured(1)), 5);
+
+        let per_byte = DefaultCostPolicy::new(0);
+        assert_eq!(per_byte.unit_size(), 1);
+        assert_eq!(per_byte.cost(&immutable(0)), 1);
+        assert_eq!(per_byte.cost(&immutable(7)), 7);
+    }
+
+    #[test]
+    fn quota_policy() {
+        let policy = QuotaPolicy {
+            unit_size: 100,
+            min_units: 2,
+            update_units: 3,
+            surcharge_interval: 10,
+            owner_quota: 20,
+        };
+        let immutable = |size| Data::Immutable(ImmutableData::new(vec![0; size]));
+        assert_eq!(immutable(0).charge_units(&policy), 2);
+        assert_eq!(immutable(250).charge_units(&policy), 3);
+        assert_eq!(immutable(250).charge_units(&QuotaPolicy::default()), 1);
+
+        let keys = test_utils::gen_keypair();
+        let structured = |version| {
+            Data::Structured(unwrap!(StructuredData::new(0,
+                                                         [0; 32],
+                                                         version,
+                                                         vec![0; 250],
+                                                         vec![keys.0],
+                                                         vec![],
+                                                         Some(&keys.1),
+                                                         false)))
+        };
+        assert_eq!(structured(0).charge_units(&policy), 3);
+        assert_eq!(structured(1).charge_units(&policy), 6);
+        assert_eq!(structured(9).charge_units(&policy), 6);
+        assert_eq!(structured(10).charge_units(&policy), 9);
+        assert_eq!(structured(25).charge_units(&policy), 12);
+
+        assert_eq!(unwrap!(policy.charge(0, &structured(1))), 6);
+        assert_eq!(unwrap!(policy.charge(14, &structured(1))), 20);
+        match policy.charge(15, &structured(1)) {
+            Err(Error::NoSpace) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match policy.charge(u64::MAX, &immutable(0)) {
+            Err(Error::NoSpace) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[cfg(feature = "serialisation")]
+    #[test]
+    fn quota_policy_serialisation() {
+        let serialised = unwrap!(serialise(&DEFAULT_QUOTA_POLICY));
+        let parsed: QuotaPolicy = unwrap!(deserialise(&serialised));
+        assert_eq!(parsed, DEFAULT_QUOTA_POLICY);
+    }
+
+    #[test]
+    fn custom_policy() {
+        struct Free;
+        impl CostPolicy for Free {
+            fn cost(&self, _data: &Data) -> u64 {
+                0
+            }
+        }
+        let data = Data::Immutable(ImmutableData::new(vec![0; 10000]));
+        assert_eq!(data.store_cost_with(&Free), 0);
+    }
+}
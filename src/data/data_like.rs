@@ -0,0 +1,118 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use data::{DataIdentifier, ImmutableData, MutableData, PlainData, StructuredData};
+use error::Error;
+
+/// Behaviour common to every kind of data.
+///
+/// `Data` delegates to this trait, so a new kind of data only needs to implement it and be added
+/// to `Data::as_data_like`.
+pub trait DataLike {
+    /// Returns the name.
+    fn name(&self) -> &[u8; 32];
+
+    /// Returns the `DataIdentifier` for this data element.
+    fn identifier(&self) -> DataIdentifier;
+
+    /// Returns the size of the payload.
+    fn payload_size(&self) -> usize;
+
+    /// Checks the invariants a received copy of this data must hold, returning an error if any is
+    /// broken. This does not check signatures against a previous version.
+    fn validate(&self) -> Result<(), Error>;
+}
+
+impl DataLike for ImmutableData {
+    fn name(&self) -> &[u8; 32] {
+        ImmutableData::name(self)
+    }
+
+    fn identifier(&self) -> DataIdentifier {
+        ImmutableData::identifier(self)
+    }
+
+    fn payload_size(&self) -> usize {
+        ImmutableData::payload_size(self)
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        if self.payload_size() > ImmutableData::MAX_BYTES {
+            return Err(Error::DataTooLarge);
+        }
+        self.validate_name()
+    }
+}
+
+impl DataLike for StructuredData {
+    fn name(&self) -> &[u8; 32] {
+        StructuredData::name(self)
+    }
+
+    fn identifier(&self) -> DataIdentifier {
+        StructuredData::identifier(self)
+    }
+
+    fn payload_size(&self) -> usize {
+        StructuredData::payload_size(self)
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        if !self.is_canonical() {
+            return Err(Error::Validation);
+        }
+        self.validate_owners()?;
+        self.validate_size()
+    }
+}
+
+impl DataLike for PlainData {
+    fn name(&self) -> &[u8; 32] {
+        PlainData::name(self)
+    }
+
+    fn identifier(&self) -> DataIdentifier {
+        PlainData::identifier(self)
+    }
+
+    fn payload_size(&self) -> usize {
+        PlainData::payload_size(self)
+    }
+
+    /// Plain data is not validated.
+    fn validate(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl DataLike for MutableData {
+    fn name(&self) -> &[u8; 32] {
+        MutableData::name(self)
+    }
+
+    fn identifier(&self) -> DataIdentifier {
+        MutableData::identifier(self)
+    }
+
+    fn payload_size(&self) -> usize {
+        MutableData::payload_size(self)
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        self.validate_size()
+    }
+}
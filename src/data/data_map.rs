@@ -0,0 +1,113 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+#[cfg(feature = "serialisation")]
+use data::ImmutableData;
+#[cfg(feature = "serialisation")]
+use error::Error;
+#[cfg(feature = "serialisation")]
+use serialisation::{deserialise, serialise};
+
+/// Details of one chunk of a file.
+#[derive(Hash, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug)]
+#[cfg_attr(feature = "serialisation", derive(RustcEncodable, RustcDecodable))]
+pub struct ChunkDetails {
+    /// Name of the `ImmutableData` holding the chunk.
+    pub name: [u8; 32],
+    /// Size of the chunk content before encryption.
+    pub size: u64,
+    /// Hash of the chunk content before encryption.
+    pub pre_encryption_hash: [u8; 32],
+}
+
+/// The map of a file split into chunks: the details of each chunk, in file order.
+///
+/// A map which is small enough can itself be stored as an `ImmutableData`, so a file is referred
+/// to by a single name.
+#[derive(Hash, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default)]
+#[cfg_attr(feature = "serialisation", derive(RustcEncodable, RustcDecodable))]
+pub struct DataMap {
+    chunks: Vec<ChunkDetails>,
+}
+
+impl DataMap {
+    /// Creates a map of the given chunks, in file order.
+    pub fn new(chunks: Vec<ChunkDetails>) -> DataMap {
+        DataMap { chunks: chunks }
+    }
+
+    /// Returns the details of the chunks, in file order.
+    pub fn chunks(&self) -> &[ChunkDetails] {
+        &self.chunks
+    }
+
+    /// Returns the size of the file, i.e. the sum of the chunk sizes.
+    pub fn total_size(&self) -> u64 {
+        self.chunks.iter().map(|chunk| chunk.size).sum()
+    }
+
+    /// Stores the serialised map as an `ImmutableData`, failing with `DataTooLarge` if it is too
+    /// large to inline.
+    #[cfg(feature = "serialisation")]
+    pub fn to_immutable_data(&self) -> Result<ImmutableData, Error> {
+        ImmutableData::try_new(serialise(self)?)
+    }
+
+    /// Reads a map stored by `to_immutable_data`.
+    #[cfg(feature = "serialisation")]
+    pub fn from_immutable_data(data: &ImmutableData) -> Result<DataMap, Error> {
+        Ok(deserialise(&data.decompressed_value()?)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::ImmutableData;
+    use error::Error;
+    use rand;
+
+    fn chunk(size: u64) -> ChunkDetails {
+        ChunkDetails {
+            name: rand::random(),
+            size: size,
+            pre_encryption_hash: rand::random(),
+        }
+    }
+
+    #[test]
+    fn inline_round_trip() {
+        let data_map = DataMap::new(vec![chunk(1000), chunk(1000), chunk(17)]);
+        assert_eq!(data_map.total_size(), 2017);
+        assert_eq!(DataMap::default().total_size(), 0);
+
+        let immutable_data = unwrap!(data_map.to_immutable_data());
+        assert_eq!(unwrap!(DataMap::from_immutable_data(&immutable_data)), data_map);
+        assert!(DataMap::from_immutable_data(&ImmutableData::new(vec![1, 2, 3])).is_err());
+    }
+
+    #[test]
+    fn too_large_to_inline() {
+        let chunk_size = unwrap!(serialise(&chunk(0))).len();
+        let count = ImmutableData::MAX_BYTES / chunk_size + 1;
+        let data_map = DataMap::new((0..count).map(|_| chunk(1)).collect());
+        match data_map.to_immutable_data() {
+            Err(Error::DataTooLarge) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,305 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Directories
+//! Listings of named files and subdirectories, each stored as `StructuredData` with the
+//! directory type tag, on which filesystem layers can be built.
+//!
+//! A file entry holds the `DataMap` of its content, or refers to data holding it, e.g. structured
+//! data holding the map of each version. A subdirectory entry refers to the structured data of
+//! the subdirectory's own listing, which is fetched separately. Entries are kept sorted by name,
+//! so equal listings serialise to equal bytes.
+//!
+//! Timestamps are whatever the caller passes as `now`, typically seconds since the Unix epoch.
+
+use crypto::sign::{PublicKey, SecretKey};
+use data::{DataIdentifier, DataMap, StructuredData};
+use error::Error;
+use serialisation::{deserialise, serialise};
+use std::mem;
+
+/// Type tag of the `StructuredData` holding a directory.
+pub const DIRECTORY_TYPE_TAG: u64 = 0x5afe_d1e0;
+
+/// Metadata common to files and directories.
+#[derive(Hash, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, RustcEncodable, RustcDecodable)]
+pub struct Metadata {
+    /// Name, unique within the parent directory.
+    pub name: String,
+    /// Time of creation.
+    pub created: u64,
+    /// Time of the last modification.
+    pub modified: u64,
+    /// Application-defined metadata, e.g. a MIME type.
+    pub user_metadata: Vec<u8>,
+}
+
+impl Metadata {
+    /// Creates metadata named `name`, created and last modified at `now`.
+    pub fn new(name: String, now: u64) -> Metadata {
+        Metadata {
+            name: name,
+            created: now,
+            modified: now,
+            user_metadata: Vec::new(),
+        }
+    }
+}
+
+/// Where the content of a file is found.
+#[derive(Hash, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, RustcEncodable, RustcDecodable)]
+pub enum FileContent {
+    /// The map of the content's chunks.
+    DataMap(DataMap),
+    /// Data holding the content or its map.
+    Reference(DataIdentifier),
+}
+
+/// A file in a directory.
+#[derive(Hash, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, RustcEncodable, RustcDecodable)]
+pub struct File {
+    /// The file's metadata.
+    pub metadata: Metadata,
+    /// The file's content.
+    pub content: FileContent,
+}
+
+/// An entry of a directory.
+#[derive(Hash, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, RustcEncodable, RustcDecodable)]
+pub enum Entry {
+    /// A file.
+    File(File),
+    /// A subdirectory, with the identifier of the structured data holding its listing.
+    Directory(Metadata, DataIdentifier),
+}
+
+impl Entry {
+    /// Returns the entry's metadata.
+    pub fn metadata(&self) -> &Metadata {
+        match *self {
+            Entry::File(ref file) => &file.metadata,
+            Entry::Directory(ref metadata, _) => metadata,
+        }
+    }
+
+    /// Returns the entry's name.
+    pub fn name(&self) -> &str {
+        &self.metadata().name
+    }
+}
+
+/// A directory: its own metadata and its entries, sorted by name.
+#[derive(Hash, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, RustcEncodable, RustcDecodable)]
+pub struct Directory {
+    metadata: Metadata,
+    entries: Vec<Entry>,
+}
+
+impl Directory {
+    /// Creates an empty directory named `name`, created at `now`.
+    pub fn new(name: String, now: u64) -> Directory {
+        Directory {
+            metadata: Metadata::new(name, now),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Returns the directory's metadata.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Sets the application-defined metadata, marking the directory modified at `now`.
+    pub fn set_user_metadata(&mut self, user_metadata: Vec<u8>, now: u64) {
+        self.metadata.user_metadata = user_metadata;
+        self.metadata.modified = now;
+    }
+
+    /// Returns the entries, sorted by name.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Returns the entry named `name`, if any.
+    pub fn find(&self, name: &str) -> Option<&Entry> {
+        self.position(name).ok().map(|index| &self.entries[index])
+    }
+
+    /// Adds `entry`, marking the directory modified at `now`. Fails with `EntryExists` if there
+    /// is already an entry of the same name.
+    pub fn add_entry(&mut self, entry: Entry, now: u64) -> Result<(), Error> {
+        match self.position(entry.name()) {
+            Ok(_) => Err(Error::EntryExists),
+            Err(index) => {
+                self.entries.insert(index, entry);
+                self.metadata.modified = now;
+                Ok(())
+            }
+        }
+    }
+
+    /// Replaces the entry of the same name as `entry`, marking the directory modified at `now`,
+    /// and returns the old entry. Fails with `NoSuchEntry` if there is none.
+    pub fn update_entry(&mut self, entry: Entry, now: u64) -> Result<Entry, Error> {
+        let index = self.position(entry.name()).map_err(|_| Error::NoSuchEntry)?;
+        self.metadata.modified = now;
+        Ok(mem::replace(&mut self.entries[index], entry))
+    }
+
+    /// Removes and returns the entry named `name`, marking the directory modified at `now`.
+    /// Fails with `NoSuchEntry` if there is none.
+    pub fn remove_entry(&mut self, name: &str, now: u64) -> Result<Entry, Error> {
+        let index = self.position(name).map_err(|_| Error::NoSuchEntry)?;
+        self.metadata.modified = now;
+        Ok(self.entries.remove(index))
+    }
+
+    /// Stores the directory as version `version` of the structured data called `name`, owned by
+    /// `owners` and signed with `signing_key`.
+    ///
+    /// Fails with `DataTooLarge` if the listing does not fit in one `StructuredData`.
+    pub fn to_structured_data(&self,
+                              name: [u8; 32],
+                              version: u64,
+                              owners: Vec<PublicKey>,
+                              signing_key: &SecretKey)
+                              -> Result<StructuredData, Error> {
+        StructuredData::new(DIRECTORY_TYPE_TAG,
+                            name,
+                            version,
+                            serialise(self)?,
+                            owners,
+                            vec![],
+                            Some(signing_key),
+                            false)
+    }
+
+    /// Reads a directory stored by `to_structured_data`. Fails with `BadIdentifier` if the data
+    /// does not have the directory type tag.
+    pub fn from_structured_data(structured_data: &StructuredData) -> Result<Directory, Error> {
+        if structured_data.get_type_tag() != DIRECTORY_TYPE_TAG {
+            return Err(Error::BadIdentifier);
+        }
+        let directory: Directory = deserialise(&structured_data.decompressed_data()?)?;
+        // Entries out of order, or with duplicate names, would break the lookups.
+        if directory.entries.windows(2).any(|pair| pair[0].name() >= pair[1].name()) {
+            return Err(Error::Validation);
+        }
+        Ok(directory)
+    }
+
+    fn position(&self, name: &str) -> Result<usize, usize> {
+        self.entries.binary_search_by(|entry| entry.name().cmp(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{ChunkDetails, DataIdentifier, DataMap, StructuredData};
+    use error::Error;
+    use rand;
+    use serialisation::serialise;
+    use test_utils;
+
+    fn file(name: &str, now: u64) -> Entry {
+        let chunk = ChunkDetails {
+            name: rand::random(),
+            size: 10,
+            pre_encryption_hash: rand::random(),
+        };
+        Entry::File(File {
+            metadata: Metadata::new(name.to_owned(), now),
+            content: FileContent::DataMap(DataMap::new(vec![chunk])),
+        })
+    }
+
+    #[test]
+    fn add_find_and_remove() {
+        let mut directory = Directory::new("root".to_owned(), 1);
+        unwrap!(directory.add_entry(file("b.txt", 2), 2));
+        unwrap!(directory.add_entry(file("a.txt", 3), 3));
+        let subdirectory = DataIdentifier::Structured(rand::random(), DIRECTORY_TYPE_TAG);
+        let metadata = Metadata::new("c".to_owned(), 4);
+        unwrap!(directory.add_entry(Entry::Directory(metadata, subdirectory), 4));
+        let names: Vec<_> = directory.entries().iter().map(Entry::name).collect();
+        assert_eq!(names, vec!["a.txt", "b.txt", "c"]);
+        assert_eq!(directory.metadata().created, 1);
+        assert_eq!(directory.metadata().modified, 4);
+
+        match directory.add_entry(file("a.txt", 5), 5) {
+            Err(Error::EntryExists) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match directory.find("c") {
+            Some(&Entry::Directory(_, identifier)) => assert_eq!(identifier, subdirectory),
+            entry => panic!("Unexpected entry {:?}", entry),
+        }
+        assert!(directory.find("d").is_none());
+
+        let updated = file("b.txt", 6);
+        let old = unwrap!(directory.update_entry(updated.clone(), 6));
+        assert_eq!(old.metadata().created, 2);
+        assert_eq!(directory.find("b.txt"), Some(&updated));
+
+        let removed = unwrap!(directory.remove_entry("a.txt", 7));
+        assert_eq!(removed.name(), "a.txt");
+        assert!(directory.find("a.txt").is_none());
+        assert_eq!(directory.metadata().modified, 7);
+        match directory.remove_entry("a.txt", 8) {
+            Err(Error::NoSuchEntry) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn structured_data_round_trip() {
+        let keys = test_utils::gen_keypair();
+        let mut directory = Directory::new("root".to_owned(), 1);
+        unwrap!(directory.add_entry(file("a.txt", 2), 2));
+        directory.set_user_metadata(b"metadata".to_vec(), 3);
+
+        let structured_data =
+            unwrap!(directory.to_structured_data(rand::random(), 0, vec![keys.0], &keys.1));
+        assert_eq!(structured_data.get_type_tag(), DIRECTORY_TYPE_TAG);
+        assert_eq!(unwrap!(Directory::from_structured_data(&structured_data)), directory);
+
+        let other = unwrap!(StructuredData::new(DIRECTORY_TYPE_TAG + 1,
+                                                rand::random(),
+                                                0,
+                                                unwrap!(serialise(&directory)),
+                                                vec![keys.0],
+                                                vec![],
+                                                Some(&keys.1),
+                                                false));
+        match Directory::from_structured_data(&other) {
+            Err(Error::BadIdentifier) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        let unsorted = Directory {
+            metadata: Metadata::new("root".to_owned(), 1),
+            entries: vec![file("b", 1), file("a", 1)],
+        };
+        let structured_data =
+            unwrap!(unsorted.to_structured_data(rand::random(), 0, vec![keys.0], &keys.1));
+        match Directory::from_structured_data(&structured_data) {
+            Err(Error::Validation) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
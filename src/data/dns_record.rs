@@ -0,0 +1,203 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # DNS records
+//! The convention for resolving a human-readable domain to the data of its services, e.g.
+//! `www` or `email`, stored as `StructuredData` with the DNS type tag.
+//!
+//! The record of a domain is named by the SHA3-256 hash of the canonical encoding of
+//! `DNS_NAME_DOMAIN` and the domain in ASCII lower case, so anyone knowing the domain finds its
+//! record. Only the owners who registered the domain can sign updates to its services.
+
+use crypto::sign::{PublicKey, SecretKey};
+use data::{DataIdentifier, StructuredData, TypedStructuredData};
+use data::canonical::CanonicalWriter;
+use error::Error;
+use sha3::hash;
+use std::collections::BTreeMap;
+
+/// Type tag of the `StructuredData` holding a DNS record.
+pub const DNS_TYPE_TAG: u64 = 0x5afe_d115;
+/// Domain-separation prefix of the name of a DNS record.
+pub const DNS_NAME_DOMAIN: &'static [u8] = b"data_chain/DnsRecord/name/1";
+
+#[derive(Clone, Debug, PartialEq, Eq, RustcEncodable, RustcDecodable)]
+struct Services {
+    domain: String,
+    services: BTreeMap<String, DataIdentifier>,
+}
+
+/// The record of a domain, mapping each of its services to the data it resolves to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DnsRecord {
+    data: TypedStructuredData<Services>,
+}
+
+impl DnsRecord {
+    /// Returns the name of the record of `domain`.
+    pub fn name_of(domain: &str) -> [u8; 32] {
+        let mut writer = CanonicalWriter::new(DNS_NAME_DOMAIN);
+        let _ = writer.write_bytes(domain.to_ascii_lowercase().as_bytes());
+        hash(&writer.into_bytes())
+    }
+
+    /// Creates the first version of the record of `domain`, with no services, owned by `owners`
+    /// and signed with `signing_key`.
+    pub fn register(domain: &str,
+                    owners: Vec<PublicKey>,
+                    signing_key: &SecretKey)
+                    -> Result<DnsRecord, Error> {
+        let services = Services {
+            domain: domain.to_ascii_lowercase(),
+            services: BTreeMap::new(),
+        };
+        let data = TypedStructuredData::new(DNS_TYPE_TAG,
+                                            DnsRecord::name_of(domain),
+                                            services,
+                                            owners,
+                                            signing_key)?;
+        Ok(DnsRecord { data: data })
+    }
+
+    /// Wraps `structured_data` retrieved from the network. Fails with `BadIdentifier` unless it
+    /// has the DNS type tag and the name of the domain it holds.
+    pub fn from_structured_data(structured_data: StructuredData) -> Result<DnsRecord, Error> {
+        if structured_data.get_type_tag() != DNS_TYPE_TAG {
+            return Err(Error::BadIdentifier);
+        }
+        let data = TypedStructuredData::<Services>::from_structured_data(structured_data)?;
+        if *data.structured_data().name() != DnsRecord::name_of(&data.content().domain) {
+            return Err(Error::BadIdentifier);
+        }
+        Ok(DnsRecord { data: data })
+    }
+
+    /// Returns the domain, in lower case.
+    pub fn domain(&self) -> &str {
+        &self.data.content().domain
+    }
+
+    /// Returns the services and the data each resolves to.
+    pub fn services(&self) -> &BTreeMap<String, DataIdentifier> {
+        &self.data.content().services
+    }
+
+    /// Returns the data `service` resolves to, if the domain has such a service.
+    pub fn resolve(&self, service: &str) -> Option<&DataIdentifier> {
+        self.services().get(service)
+    }
+
+    /// Creates the next version of the record, in which `service` resolves to `target`, signed
+    /// with `signing_key`. Fails with `EntryExists` if the domain already has `service`.
+    pub fn add_service(&self,
+                       service: &str,
+                       target: DataIdentifier,
+                       signing_key: &SecretKey)
+                       -> Result<DnsRecord, Error> {
+        let mut services = self.data.content().clone();
+        if services.services.insert(service.to_owned(), target).is_some() {
+            return Err(Error::EntryExists);
+        }
+        Ok(DnsRecord { data: self.data.update_content(services, signing_key)? })
+    }
+
+    /// Creates the next version of the record, without `service`, signed with `signing_key`.
+    /// Fails with `NoSuchEntry` if the domain has no such service.
+    pub fn remove_service(&self,
+                          service: &str,
+                          signing_key: &SecretKey)
+                          -> Result<DnsRecord, Error> {
+        let mut services = self.data.content().clone();
+        if services.services.remove(service).is_none() {
+            return Err(Error::NoSuchEntry);
+        }
+        Ok(DnsRecord { data: self.data.update_content(services, signing_key)? })
+    }
+
+    /// Returns the underlying `StructuredData`.
+    pub fn structured_data(&self) -> &StructuredData {
+        self.data.structured_data()
+    }
+
+    /// Unwraps the underlying `StructuredData`, e.g. to store it.
+    pub fn into_structured_data(self) -> StructuredData {
+        self.data.into_structured_data()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{DataIdentifier, StructuredData};
+    use error::Error;
+    use rand;
+    use test_utils;
+
+    #[test]
+    fn register_and_resolve() {
+        let keys = test_utils::gen_keypair();
+        let record = unwrap!(DnsRecord::register("Example.safe", vec![keys.0], &keys.1));
+        assert_eq!(record.domain(), "example.safe");
+        assert_eq!(*record.structured_data().name(), DnsRecord::name_of("example.SAFE"));
+        assert!(record.resolve("www").is_none());
+
+        let www = DataIdentifier::Structured(rand::random(), 1);
+        let updated = unwrap!(record.add_service("www", www, &keys.1));
+        assert_eq!(updated.resolve("www"), Some(&www));
+        unwrap!(record.structured_data()
+            .validate_self_against_successor(updated.structured_data()));
+        match updated.add_service("www", DataIdentifier::Immutable(rand::random()), &keys.1) {
+            Err(Error::EntryExists) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        let parsed = unwrap!(DnsRecord::from_structured_data(updated.clone()
+            .into_structured_data()));
+        assert_eq!(parsed, updated);
+
+        let removed = unwrap!(parsed.remove_service("www", &keys.1));
+        assert!(removed.services().is_empty());
+        match removed.remove_service("www", &keys.1) {
+            Err(Error::NoSuchEntry) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        let forger = test_utils::gen_keypair();
+        let forged = unwrap!(updated.add_service("email", www, &forger.1));
+        assert!(updated.structured_data()
+            .validate_self_against_successor(forged.structured_data())
+            .is_err());
+    }
+
+    #[test]
+    fn name_must_match_domain() {
+        let keys = test_utils::gen_keypair();
+        let record = unwrap!(DnsRecord::register("example.safe", vec![keys.0], &keys.1));
+        let moved = unwrap!(StructuredData::new(DNS_TYPE_TAG,
+                                                rand::random(),
+                                                0,
+                                                record.structured_data().get_data().clone(),
+                                                vec![keys.0],
+                                                vec![],
+                                                Some(&keys.1),
+                                                false));
+        match DnsRecord::from_structured_data(moved) {
+            Err(Error::BadIdentifier) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
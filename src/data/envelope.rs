@@ -0,0 +1,371 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Versioned envelopes
+//! Serialisation of `Data` wrapped in an envelope recording the format it was serialised in, so a
+//! later change to the fields of a data type can still decode chunks stored before it.
+//!
+//! An envelope is `MAGIC` followed by the serialised `Envelope`: the format version and the
+//! serialised `Data`. Unversioned `Data`, as stored before envelopes were introduced, starts with
+//! its variant index as a big-endian `u32` and so with a zero byte, which `MAGIC` never does. Such
+//! bytes are decoded as `LEGACY_FORMAT_VERSION`.
+//!
+//! Every change to the serialised fields bumps `FORMAT_VERSION`, and the earlier layouts are
+//! still decoded, giving the fields added since their defaults:
+//!
+//! - `LEGACY_FORMAT_VERSION`: `Data` is only structured or immutable data, `StructuredData` ends
+//!   with its `ledger` flag and `ImmutableData` is only its value.
+//! - `ENVELOPE_FORMAT_VERSION`: adds plain and mutable data, the signature policy and compression
+//!   of `StructuredData` and the compression of `ImmutableData`.
+//! - `EXPIRY_FORMAT_VERSION`: adds the expiry time of `StructuredData`.
+//! - `LEDGER_LINK_FORMAT_VERSION`: adds the hash of the previous version of ledger data.
+//! - `APP_METADATA_FORMAT_VERSION`: adds the application-defined metadata of `StructuredData`.
+//! - `SOFT_DELETE_FORMAT_VERSION`: turns the `ledger` flag of `StructuredData` into a byte of
+//!   flags, adding the soft-deleted mark.
+//! - `WRITERS_FORMAT_VERSION`: adds the writers of `StructuredData`, following its flags when it
+//!   has any.
+//! - `FROZEN_FORMAT_VERSION`: adds the frozen mark of `StructuredData` to its flags.
+
+use data::{Data, ImmutableData, StructuredData};
+use error::Error;
+use rustc_serialize::{Decodable, Decoder};
+use serialisation::{deserialise, deserialise_from, serialise};
+use std::io::Cursor;
+
+/// Prefix marking bytes as an envelope rather than unversioned `Data`.
+pub const MAGIC: &'static [u8] = b"\xffDCE";
+/// Format version of unversioned `Data`.
+pub const LEGACY_FORMAT_VERSION: u32 = 0;
+/// Format version of the first envelopes.
+pub const ENVELOPE_FORMAT_VERSION: u32 = 1;
+/// Format version adding the expiry time of `StructuredData`.
+pub const EXPIRY_FORMAT_VERSION: u32 = 2;
+/// Format version adding the hash of the previous version of ledger `StructuredData`.
+pub const LEDGER_LINK_FORMAT_VERSION: u32 = 3;
+/// Format version adding the application-defined metadata of `StructuredData`.
+pub const APP_METADATA_FORMAT_VERSION: u32 = 4;
+/// Format version adding the soft-deleted mark of `StructuredData`.
+pub const SOFT_DELETE_FORMAT_VERSION: u32 = 5;
+/// Format version adding the writers of `StructuredData`.
+pub const WRITERS_FORMAT_VERSION: u32 = 6;
+/// Format version adding the frozen mark of `StructuredData`.
+pub const FROZEN_FORMAT_VERSION: u32 = 7;
+/// Format version written by `encode`.
+pub const FORMAT_VERSION: u32 = FROZEN_FORMAT_VERSION;
+
+/// `Data` serialised in a given format version.
+#[derive(Clone, Debug, Eq, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Envelope {
+    format_version: u32,
+    payload: Vec<u8>,
+}
+
+impl Envelope {
+    /// Wraps `data` serialised in the current format.
+    pub fn new(data: &Data) -> Result<Envelope, Error> {
+        Ok(Envelope {
+            format_version: FORMAT_VERSION,
+            payload: serialise(data)?,
+        })
+    }
+
+    /// Parses envelope or unversioned bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Envelope, Error> {
+        if bytes.starts_with(MAGIC) {
+            Ok(deserialise(&bytes[MAGIC.len()..])?)
+        } else {
+            Ok(Envelope {
+                format_version: LEGACY_FORMAT_VERSION,
+                payload: bytes.to_vec(),
+            })
+        }
+    }
+
+    /// Returns the format version the payload is serialised in.
+    pub fn format_version(&self) -> u32 {
+        self.format_version
+    }
+
+    /// Returns the envelope bytes, as written by `encode`.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&serialise(self)?);
+        Ok(bytes)
+    }
+
+    /// Decodes the payload according to its format version.
+    pub fn open(&self) -> Result<Data, Error> {
+        match self.format_version {
+            LEGACY_FORMAT_VERSION => Ok(deserialise_payload::<DataV0>(&self.payload)?.0),
+            ENVELOPE_FORMAT_VERSION => Ok(deserialise_payload::<DataV1>(&self.payload)?.0),
+            EXPIRY_FORMAT_VERSION => Ok(deserialise_payload::<DataV2>(&self.payload)?.0),
+            LEDGER_LINK_FORMAT_VERSION => Ok(deserialise_payload::<DataV3>(&self.payload)?.0),
+            APP_METADATA_FORMAT_VERSION => Ok(deserialise_payload::<DataV4>(&self.payload)?.0),
+            SOFT_DELETE_FORMAT_VERSION => Ok(deserialise_payload::<DataV5>(&self.payload)?.0),
+            WRITERS_FORMAT_VERSION => Ok(deserialise_payload::<DataV6>(&self.payload)?.0),
+            FORMAT_VERSION => deserialise_payload(&self.payload),
+            _ => Err(Error::InvalidVersion),
+        }
+    }
+}
+
+/// Deserialises all of `payload`, failing if bytes are left over, as they are when it is in a
+/// layout with fewer fields.
+fn deserialise_payload<T: Decodable>(payload: &[u8]) -> Result<T, Error> {
+    let mut cursor = Cursor::new(payload);
+    let value = deserialise_from(&mut cursor)?;
+    if cursor.position() != payload.len() as u64 {
+        return Err(Error::Encoding(format!("{} bytes left over",
+                                           payload.len() as u64 - cursor.position())));
+    }
+    Ok(value)
+}
+
+/// Declares `$name`, holding `Data` decoded from the layout of the earlier format `$format`.
+macro_rules! earlier_format {
+    ($name:ident, $format:expr) => {
+        struct $name(Data);
+
+        impl Decodable for $name {
+            fn decode<D: Decoder>(decoder: &mut D) -> Result<$name, D::Error> {
+                decode_data(decoder, $format).map($name)
+            }
+        }
+    }
+}
+
+earlier_format!(DataV0, LEGACY_FORMAT_VERSION);
+earlier_format!(DataV1, ENVELOPE_FORMAT_VERSION);
+earlier_format!(DataV2, EXPIRY_FORMAT_VERSION);
+earlier_format!(DataV3, LEDGER_LINK_FORMAT_VERSION);
+earlier_format!(DataV4, APP_METADATA_FORMAT_VERSION);
+earlier_format!(DataV5, SOFT_DELETE_FORMAT_VERSION);
+earlier_format!(DataV6, WRITERS_FORMAT_VERSION);
+
+/// Decodes `Data` serialised in the layout of format version `format`.
+fn decode_data<D: Decoder>(decoder: &mut D, format: u32) -> Result<Data, D::Error> {
+    let variants: &[&str] = if format == LEGACY_FORMAT_VERSION {
+        &["Structured", "Immutable"]
+    } else {
+        &["Structured", "Immutable", "Plain", "Mutable"]
+    };
+    decoder.read_enum("Data", |decoder| {
+        decoder.read_enum_variant(variants, |decoder, index| {
+            decoder.read_enum_variant_arg(0, |decoder| match index {
+                0 => StructuredData::decode_in_format(decoder, format).map(Data::Structured),
+                1 => ImmutableData::decode_in_format(decoder, format).map(Data::Immutable),
+                2 if index < variants.len() => Decodable::decode(decoder).map(Data::Plain),
+                3 if index < variants.len() => Decodable::decode(decoder).map(Data::Mutable),
+                _ => Err(decoder.error("unknown Data variant")),
+            })
+        })
+    })
+}
+
+/// Serialises `data` in an envelope of the current format version.
+pub fn encode(data: &Data) -> Result<Vec<u8>, Error> {
+    Envelope::new(data)?.to_bytes()
+}
+
+/// Decodes `Data` from an envelope of any known format version or from unversioned bytes.
+pub fn decode(bytes: &[u8]) -> Result<Data, Error> {
+    Envelope::from_bytes(bytes)?.open()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crypto::sign::{PublicKey, Signature};
+    use data::{Compression, Data, ImmutableData, PlainData, SignaturePolicy, StructuredData};
+    use error::Error;
+    use rand;
+    use serialisation::serialise;
+    use test_utils;
+
+    /// Unversioned structured data as serialised before envelopes were introduced: version 1 of
+    /// ledger data of type 15000 named `[3; 32]`, holding "legacy structured data", owned by a
+    /// single key, which is also the previous owner, with one signature.
+    const LEGACY_STRUCTURED_DATA: &'static [u8] =
+        include_bytes!("vectors/legacy_structured_data.bin");
+    /// Unversioned immutable data holding "legacy immutable data", as serialised before
+    /// envelopes were introduced.
+    const LEGACY_IMMUTABLE_DATA: &'static [u8] =
+        include_bytes!("vectors/legacy_immutable_data.bin");
+
+    #[test]
+    fn versioned() {
+        let data = Data::Immutable(ImmutableData::new(vec![1, 2, 3]));
+        let encoded = unwrap!(encode(&data));
+        assert!(encoded.starts_with(MAGIC));
+        assert_eq!(unwrap!(Envelope::from_bytes(&encoded)).format_version(), FORMAT_VERSION);
+        assert_eq!(unwrap!(decode(&encoded)), data);
+
+        let plain = Data::Plain(PlainData::new(rand::random(), vec![4, 5, 6]));
+        assert_eq!(unwrap!(decode(&unwrap!(encode(&plain)))), plain);
+    }
+
+    #[test]
+    fn legacy_structured_data() {
+        assert_eq!(unwrap!(Envelope::from_bytes(LEGACY_STRUCTURED_DATA)).format_version(),
+                   LEGACY_FORMAT_VERSION);
+        let structured_data = match unwrap!(decode(LEGACY_STRUCTURED_DATA)) {
+            Data::Structured(structured_data) => structured_data,
+            data => panic!("Unexpected data {:?}", data),
+        };
+        assert_eq!(structured_data.get_type_tag(), 15000);
+        assert_eq!(*structured_data.name(), [3; 32]);
+        assert_eq!(structured_data.get_data(), b"legacy structured data");
+        assert_eq!(structured_data.version(), 1);
+        assert_eq!(structured_data.get_owner_keys().len(), 1);
+        assert_eq!(structured_data.get_previous_owner_keys(),
+                   structured_data.get_owner_keys());
+        assert_eq!(structured_data.get_previous_owner_signatures().len(), 1);
+        assert!(structured_data.ledger());
+        assert_eq!(structured_data.signature_policy(), SignaturePolicy::Majority);
+        assert_eq!(structured_data.compression(), None);
+        assert_eq!(structured_data.expires_at(), None);
+        assert_eq!(structured_data.previous_hash(), None);
+        assert!(structured_data.app_metadata().is_empty());
+
+        // Re-encoded, it takes the current format.
+        let encoded = unwrap!(encode(&Data::Structured(structured_data.clone())));
+        assert_eq!(unwrap!(decode(&encoded)), Data::Structured(structured_data));
+
+        // A legacy flag byte is a `bool`.
+        let mut flagged = LEGACY_STRUCTURED_DATA.to_vec();
+        *unwrap!(flagged.last_mut()) = 2;
+        assert!(decode(&flagged).is_err());
+    }
+
+    #[test]
+    fn legacy_immutable_data() {
+        assert_eq!(unwrap!(decode(LEGACY_IMMUTABLE_DATA)),
+                   Data::Immutable(ImmutableData::new(b"legacy immutable data".to_vec())));
+
+        // Nothing may follow the value, and there was no plain data.
+        let mut extended = LEGACY_IMMUTABLE_DATA.to_vec();
+        extended.push(0);
+        assert!(decode(&extended).is_err());
+        let plain = Data::Plain(PlainData::new(rand::random(), vec![4, 5, 6]));
+        assert!(decode(&unwrap!(serialise(&plain))).is_err());
+    }
+
+    #[test]
+    fn earlier_formats() {
+        let keys = test_utils::gen_keypair();
+        let name: [u8; 32] = rand::random();
+        let open = |format_version, payload| {
+            Envelope {
+                    format_version: format_version,
+                    payload: payload,
+                }
+                .open()
+        };
+        // The fields of `Data::Structured` in every format, up to the `ledger` flag.
+        let fields = (0u32,
+                      5u64,
+                      name,
+                      b"earlier".to_vec(),
+                      Vec::<PublicKey>::new(),
+                      0u64,
+                      vec![keys.0],
+                      Vec::<Signature>::new(),
+                      false);
+        let policy = (SignaturePolicy::All, None::<Compression>);
+        let mut expected = unwrap!(StructuredData::new(5,
+                                                       name,
+                                                       0,
+                                                       b"earlier".to_vec(),
+                                                       vec![keys.0],
+                                                       vec![],
+                                                       None,
+                                                       false));
+
+        expected.set_signature_policy(SignaturePolicy::All);
+        let payload = unwrap!(serialise(&(&fields, &policy)));
+        assert_eq!(unwrap!(open(ENVELOPE_FORMAT_VERSION, payload)),
+                   Data::Structured(expected.clone()));
+
+        expected.set_expires_at(Some(1000));
+        let payload = unwrap!(serialise(&(&fields, &policy, Some(1000u64))));
+        assert_eq!(unwrap!(open(EXPIRY_FORMAT_VERSION, payload.clone())),
+                   Data::Structured(expected.clone()));
+        // Each layout is only decoded as its own format.
+        assert!(open(FORMAT_VERSION, payload.clone()).is_err());
+        assert!(open(ENVELOPE_FORMAT_VERSION, payload).is_err());
+
+        expected.set_previous_hash(Some([7; 32]));
+        let payload = unwrap!(serialise(&(&fields, &policy, Some(1000u64), Some([7u8; 32]))));
+        assert_eq!(unwrap!(open(LEDGER_LINK_FORMAT_VERSION, payload)),
+                   Data::Structured(expected.clone()));
+
+        unwrap!(expected.set_app_metadata(b"metadata".to_vec()));
+        let metadata = b"metadata".to_vec();
+        let payload =
+            unwrap!(serialise(&(&fields, &policy, Some(1000u64), Some([7u8; 32]), metadata)));
+        assert_eq!(unwrap!(open(APP_METADATA_FORMAT_VERSION, payload.clone())),
+                   Data::Structured(expected.clone()));
+        // Data which is neither soft-deleted nor frozen and has no writers serialises as before
+        // the flags byte.
+        assert_eq!(unwrap!(open(FORMAT_VERSION, payload.clone())),
+                   Data::Structured(expected.clone()));
+
+        // The soft-deleted flag is not a `bool`.
+        let soft_deleted = unwrap!(expected.soft_delete(&keys.1));
+        let payload = unwrap!(serialise(&Data::Structured(soft_deleted.clone())));
+        assert_eq!(unwrap!(open(SOFT_DELETE_FORMAT_VERSION, payload.clone())),
+                   Data::Structured(soft_deleted.clone()));
+        assert_eq!(unwrap!(open(FORMAT_VERSION, payload.clone())),
+                   Data::Structured(soft_deleted));
+        assert!(open(APP_METADATA_FORMAT_VERSION, payload).is_err());
+
+        // Writers follow the flags.
+        let mut with_writers = expected.clone();
+        with_writers.set_writers(vec![test_utils::gen_keypair().0]);
+        let payload = unwrap!(serialise(&Data::Structured(with_writers.clone())));
+        assert_eq!(unwrap!(open(WRITERS_FORMAT_VERSION, payload.clone())),
+                   Data::Structured(with_writers));
+        assert!(open(SOFT_DELETE_FORMAT_VERSION, payload).is_err());
+
+        // Data can only be frozen since the frozen flag was added.
+        let frozen = unwrap!(StructuredData::new_frozen(5, name, b"earlier".to_vec(), false));
+        let payload = unwrap!(serialise(&Data::Structured(frozen.clone())));
+        assert_eq!(unwrap!(open(FROZEN_FORMAT_VERSION, payload.clone())),
+                   Data::Structured(frozen));
+        assert!(open(WRITERS_FORMAT_VERSION, payload).is_err());
+
+        // Since the first envelopes immutable data carries its compression, and there is plain
+        // data.
+        let payload = unwrap!(serialise(&(1u32, b"earlier".to_vec(), None::<Compression>)));
+        assert_eq!(unwrap!(open(ENVELOPE_FORMAT_VERSION, payload)),
+                   Data::Immutable(ImmutableData::new(b"earlier".to_vec())));
+        let plain = Data::Plain(PlainData::new(rand::random(), vec![4, 5, 6]));
+        assert_eq!(unwrap!(open(ENVELOPE_FORMAT_VERSION, unwrap!(serialise(&plain)))), plain);
+    }
+
+    #[test]
+    fn unknown_version() {
+        let envelope = Envelope {
+            format_version: FORMAT_VERSION + 1,
+            payload: vec![],
+        };
+        match decode(&unwrap!(envelope.to_bytes())) {
+            Err(Error::InvalidVersion) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
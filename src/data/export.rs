@@ -0,0 +1,517 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Export formats
+//! JSON (feature `json`) and CBOR (feature `cbor`) representations of the data types, intended
+//! for tooling and interop rather than the network.
+//!
+//! Both formats share one layout: names, keys and signatures are hex strings and payloads are
+//! standard base64. Imports go through the normal constructors, so `ImmutableData` names are
+//! checked against the content and `StructuredData` is canonicalised and size checked.
+
+#[cfg(feature = "cbor")]
+use cbor::{Decoder as CborDecoder, Encoder as CborEncoder};
+use crypto::ct;
+use crypto::sign::{PublicKey, Signature};
+use data::{Compression, Data, ImmutableData, MutableData, PermissionSet, PlainData,
+           SignaturePolicy, StructuredData, Value};
+use error::Error;
+#[cfg(feature = "json")]
+use rustc_serialize::json;
+use rustc_serialize::{Decodable, Encodable};
+use rustc_serialize::base64::{FromBase64, STANDARD, ToBase64};
+use rustc_serialize::hex::{FromHex, ToHex};
+use std::collections::BTreeMap;
+
+#[derive(RustcEncodable, RustcDecodable)]
+struct ExportedImmutableData {
+    name: String,
+    value: String,
+    compression: Option<Compression>,
+}
+
+#[derive(RustcEncodable, RustcDecodable)]
+struct ExportedPlainData {
+    name: String,
+    value: String,
+}
+
+#[derive(RustcEncodable, RustcDecodable)]
+struct ExportedStructuredData {
+    type_tag: u64,
+    name: String,
+    version: u64,
+    data: String,
+    current_owner_keys: Vec<String>,
+    previous_owner_keys: Vec<String>,
+    previous_owner_signatures: Vec<String>,
+    ledger: bool,
+    policy: SignaturePolicy,
+    compression: Option<Compression>,
+    expires_at: Option<u64>,
+    previous_hash: Option<String>,
+    app_metadata: String,
+    soft_deleted: bool,
+    writers: Vec<String>,
+    frozen: bool,
+}
+
+#[derive(RustcEncodable, RustcDecodable)]
+struct ExportedValue {
+    content: String,
+    entry_version: u64,
+}
+
+#[derive(RustcEncodable, RustcDecodable)]
+struct ExportedMutableData {
+    name: String,
+    type_tag: u64,
+    version: u64,
+    entries: BTreeMap<String, ExportedValue>,
+    permissions: BTreeMap<String, PermissionSet>,
+    owners: Vec<String>,
+}
+
+#[derive(RustcEncodable, RustcDecodable)]
+enum ExportedData {
+    Structured(ExportedStructuredData),
+    Immutable(ExportedImmutableData),
+    Plain(ExportedPlainData),
+    Mutable(ExportedMutableData),
+}
+
+fn encoding_error<E: ::std::fmt::Display>(error: E) -> Error {
+    Error::Encoding(error.to_string())
+}
+
+fn name_from_hex(hex: &str) -> Result<[u8; 32], Error> {
+    let bytes = hex.from_hex().map_err(encoding_error)?;
+    if bytes.len() != 32 {
+        return Err(Error::Encoding(format!("name must be 32 bytes, not {}", bytes.len())));
+    }
+    let mut name = [0u8; 32];
+    name.copy_from_slice(&bytes);
+    Ok(name)
+}
+
+fn key_from_hex(hex: &str) -> Result<PublicKey, Error> {
+    PublicKey::from_slice(&hex.from_hex().map_err(encoding_error)?)
+        .ok_or_else(|| Error::Encoding(format!("invalid public key {}", hex)))
+}
+
+fn signature_from_hex(hex: &str) -> Result<Signature, Error> {
+    Signature::from_slice(&hex.from_hex().map_err(encoding_error)?)
+        .ok_or_else(|| Error::Encoding(format!("invalid signature {}", hex)))
+}
+
+impl<'a> From<&'a ImmutableData> for ExportedImmutableData {
+    fn from(data: &'a ImmutableData) -> ExportedImmutableData {
+        ExportedImmutableData {
+            name: data.name().to_hex(),
+            value: data.value().to_base64(STANDARD),
+            compression: data.compression(),
+        }
+    }
+}
+
+impl ExportedImmutableData {
+    fn import(self) -> Result<ImmutableData, Error> {
+        let name = name_from_hex(&self.name)?;
+        let data = ImmutableData::with_compression(self.value
+                                                       .from_base64()
+                                                       .map_err(encoding_error)?,
+                                                   self.compression)?;
+        if !ct::name_eq(data.name(), &name) {
+            return Err(Error::BadIdentifier);
+        }
+        Ok(data)
+    }
+}
+
+impl<'a> From<&'a PlainData> for ExportedPlainData {
+    fn from(data: &'a PlainData) -> ExportedPlainData {
+        ExportedPlainData {
+            name: data.name().to_hex(),
+            value: data.value().to_base64(STANDARD),
+        }
+    }
+}
+
+impl ExportedPlainData {
+    fn import(self) -> Result<PlainData, Error> {
+        Ok(PlainData::new(name_from_hex(&self.name)?,
+                          self.value.from_base64().map_err(encoding_error)?))
+    }
+}
+
+impl<'a> From<&'a StructuredData> for ExportedStructuredData {
+    fn from(data: &'a StructuredData) -> ExportedStructuredData {
+        ExportedStructuredData {
+            type_tag: data.get_type_tag(),
+            name: data.name().to_hex(),
+            version: data.get_version(),
+            data: data.get_data().to_base64(STANDARD),
+            current_owner_keys: data.get_owner_keys().iter().map(|key| key.0.to_hex()).collect(),
+            previous_owner_keys: data.get_previous_owner_keys()
+                .iter()
+                .map(|key| key.0.to_hex())
+                .collect(),
+            previous_owner_signatures: data.get_previous_owner_signatures()
+                .iter()
+                .map(|signature| signature.0.to_hex())
+                .collect(),
+            ledger: data.ledger(),
+            policy: data.signature_policy(),
+            compression: data.compression(),
+            expires_at: data.expires_at(),
+            previous_hash: data.previous_hash().map(|hash| hash.to_hex()),
+            app_metadata: data.app_metadata().to_base64(STANDARD),
+            soft_deleted: data.is_soft_deleted(),
+            writers: data.writers().iter().map(|key| key.0.to_hex()).collect(),
+            frozen: data.is_frozen(),
+        }
+    }
+}
+
+impl ExportedStructuredData {
+    fn import(self) -> Result<StructuredData, Error> {
+        let current_owner_keys = self.current_owner_keys
+            .iter()
+            .map(|key| key_from_hex(key))
+            .collect::<Result<Vec<_>, _>>()?;
+        let previous_owner_keys = self.previous_owner_keys
+            .iter()
+            .map(|key| key_from_hex(key))
+            .collect::<Result<Vec<_>, _>>()?;
+        let signatures = self.previous_owner_signatures
+            .iter()
+            .map(|signature| signature_from_hex(signature))
+            .collect::<Result<Vec<_>, _>>()?;
+        let writers = self.writers
+            .iter()
+            .map(|key| key_from_hex(key))
+            .collect::<Result<Vec<_>, _>>()?;
+        let name = name_from_hex(&self.name)?;
+        let content = self.data.from_base64().map_err(encoding_error)?;
+        let mut data = if self.frozen {
+            if self.version != 0 || !current_owner_keys.is_empty() ||
+               !previous_owner_keys.is_empty() {
+                return Err(Error::Validation);
+            }
+            StructuredData::new_frozen(self.type_tag, name, content, self.ledger)?
+        } else {
+            StructuredData::new(self.type_tag,
+                                name,
+                                self.version,
+                                content,
+                                current_owner_keys,
+                                previous_owner_keys,
+                                None,
+                                self.ledger)?
+        };
+        data.set_signature_policy(self.policy);
+        data.set_compression(self.compression);
+        data.set_expires_at(self.expires_at);
+        data.set_previous_hash(match self.previous_hash {
+            Some(ref hash) => Some(name_from_hex(hash)?),
+            None => None,
+        });
+        data.set_app_metadata(self.app_metadata.from_base64().map_err(encoding_error)?)?;
+        data.set_soft_deleted(self.soft_deleted);
+        data.set_writers(writers);
+        data.replace_signatures(signatures);
+        data.validate_owners()?;
+        data.validate_size()?;
+        Ok(data)
+    }
+}
+
+impl<'a> From<&'a MutableData> for ExportedMutableData {
+    fn from(data: &'a MutableData) -> ExportedMutableData {
+        ExportedMutableData {
+            name: data.name().to_hex(),
+            type_tag: data.type_tag(),
+            version: data.version(),
+            entries: data.entries()
+                .iter()
+                .map(|(key, value)| {
+                    (key.to_base64(STANDARD),
+                     ExportedValue {
+                        content: value.content.to_base64(STANDARD),
+                        entry_version: value.entry_version,
+                    })
+                })
+                .collect(),
+            permissions: data.permissions()
+                .iter()
+                .map(|(key, permissions)| (key.0.to_hex(), *permissions))
+                .collect(),
+            owners: data.owners().iter().map(|key| key.0.to_hex()).collect(),
+        }
+    }
+}
+
+impl ExportedMutableData {
+    fn import(self) -> Result<MutableData, Error> {
+        let mut entries = BTreeMap::new();
+        for (key, value) in self.entries {
+            let _ = entries.insert(key.from_base64().map_err(encoding_error)?,
+                                   Value {
+                                       content: value.content
+                                           .from_base64()
+                                           .map_err(encoding_error)?,
+                                       entry_version: value.entry_version,
+                                   });
+        }
+        let mut permissions = BTreeMap::new();
+        for (key, permission_set) in self.permissions {
+            let _ = permissions.insert(key_from_hex(&key)?, permission_set);
+        }
+        let owners = self.owners
+            .iter()
+            .map(|key| key_from_hex(key))
+            .collect::<Result<_, _>>()?;
+        let mut data = MutableData::new(name_from_hex(&self.name)?,
+                                        self.type_tag,
+                                        permissions,
+                                        entries,
+                                        owners)?;
+        data.set_version(self.version);
+        Ok(data)
+    }
+}
+
+impl<'a> From<&'a Data> for ExportedData {
+    fn from(data: &'a Data) -> ExportedData {
+        match *data {
+            Data::Structured(ref data) => ExportedData::Structured(data.into()),
+            Data::Immutable(ref data) => ExportedData::Immutable(data.into()),
+            Data::Plain(ref data) => ExportedData::Plain(data.into()),
+            Data::Mutable(ref data) => ExportedData::Mutable(data.into()),
+        }
+    }
+}
+
+impl ExportedData {
+    fn import(self) -> Result<Data, Error> {
+        Ok(match self {
+            ExportedData::Structured(data) => Data::Structured(data.import()?),
+            ExportedData::Immutable(data) => Data::Immutable(data.import()?),
+            ExportedData::Plain(data) => Data::Plain(data.import()?),
+            ExportedData::Mutable(data) => Data::Mutable(data.import()?),
+        })
+    }
+}
+
+#[cfg(feature = "json")]
+fn to_json<T: Encodable>(exported: &T) -> Result<String, Error> {
+    json::encode(exported).map_err(encoding_error)
+}
+
+#[cfg(feature = "json")]
+fn from_json<T: Decodable>(json: &str) -> Result<T, Error> {
+    json::decode(json).map_err(encoding_error)
+}
+
+#[cfg(feature = "cbor")]
+fn to_cbor<T: Encodable>(exported: &T) -> Result<Vec<u8>, Error> {
+    let mut encoder = CborEncoder::from_memory();
+    encoder.encode(&[exported]).map_err(encoding_error)?;
+    Ok(encoder.as_bytes().to_vec())
+}
+
+#[cfg(feature = "cbor")]
+fn from_cbor<T: Decodable>(cbor: &[u8]) -> Result<T, Error> {
+    let mut decoder = CborDecoder::from_bytes(cbor);
+    match decoder.decode().next() {
+        Some(result) => result.map_err(encoding_error),
+        None => Err(Error::Encoding("empty CBOR input".to_owned())),
+    }
+}
+
+macro_rules! impl_export {
+    ($data:ident, $exported:ident) => {
+        impl $data {
+            /// Exports to JSON with hex names, keys and signatures and a base64 payload.
+            #[cfg(feature = "json")]
+            pub fn to_json(&self) -> Result<String, Error> {
+                to_json(&$exported::from(self))
+            }
+
+            /// Imports from the JSON produced by `to_json()`, validating the result.
+            #[cfg(feature = "json")]
+            pub fn from_json(json: &str) -> Result<$data, Error> {
+                from_json::<$exported>(json)?.import()
+            }
+
+            /// Exports to CBOR, using the same layout as `to_json()`.
+            #[cfg(feature = "cbor")]
+            pub fn to_cbor(&self) -> Result<Vec<u8>, Error> {
+                to_cbor(&$exported::from(self))
+            }
+
+            /// Imports from the CBOR produced by `to_cbor()`, validating the result.
+            #[cfg(feature = "cbor")]
+            pub fn from_cbor(cbor: &[u8]) -> Result<$data, Error> {
+                from_cbor::<$exported>(cbor)?.import()
+            }
+        }
+    }
+}
+
+impl_export!(Data, ExportedData);
+impl_export!(ImmutableData, ExportedImmutableData);
+impl_export!(MutableData, ExportedMutableData);
+impl_export!(PlainData, ExportedPlainData);
+impl_export!(StructuredData, ExportedStructuredData);
+
+#[cfg(test)]
+mod tests {
+    use data::{Action, Data, ImmutableData, MutableData, PermissionSet, PlainData,
+               SignaturePolicy, StructuredData, Value};
+    #[cfg(feature = "json")]
+    use error::Error;
+    use rand;
+    #[cfg(feature = "json")]
+    use rustc_serialize::hex::ToHex;
+    use std::collections::BTreeMap;
+    use test_utils;
+
+    fn structured_data() -> StructuredData {
+        let keys: Vec<_> = (0..3).map(|_| test_utils::gen_keypair()).collect();
+        let mut data = unwrap!(StructuredData::new(7,
+                                                   rand::random(),
+                                                   1,
+                                                   b"structured".to_vec(),
+                                                   keys.iter().map(|key| key.0).collect(),
+                                                   vec![],
+                                                   Some(&keys[0].1),
+                                                   false));
+        data.set_signature_policy(SignaturePolicy::Threshold(2));
+        data.set_writers(vec![test_utils::gen_keypair().0]);
+        let _ = unwrap!(data.add_signature(&keys[0].1));
+        let _ = unwrap!(data.add_signature(&keys[1].1));
+        data
+    }
+
+    fn mutable_data() -> MutableData {
+        let owner = test_utils::gen_keypair();
+        let mut entries = BTreeMap::new();
+        let _ = entries.insert(vec![0, 255],
+                               Value {
+                                   content: b"mutable".to_vec(),
+                                   entry_version: 2,
+                               });
+        let mut permissions = BTreeMap::new();
+        let _ = permissions.insert(test_utils::gen_keypair().0,
+                                   PermissionSet::new().allow(Action::Insert));
+        let mut data = unwrap!(MutableData::new(rand::random(),
+                                                5,
+                                                permissions,
+                                                entries,
+                                                vec![owner.0].into_iter().collect()));
+        data.set_version(3);
+        data
+    }
+
+    fn soft_deleted_data() -> StructuredData {
+        let keys = test_utils::gen_keypair();
+        let data = unwrap!(StructuredData::new(7,
+                                               rand::random(),
+                                               0,
+                                               b"structured".to_vec(),
+                                               vec![keys.0],
+                                               vec![],
+                                               Some(&keys.1),
+                                               false));
+        unwrap!(data.soft_delete(&keys.1))
+    }
+
+    fn all_data() -> Vec<Data> {
+        vec![Data::Structured(structured_data()),
+             Data::Structured(soft_deleted_data()),
+             Data::Structured(unwrap!(StructuredData::new_frozen(3,
+                                                                 rand::random(),
+                                                                 b"frozen".to_vec(),
+                                                                 false))),
+             Data::Immutable(ImmutableData::new(b"immutable".to_vec())),
+             Data::Plain(PlainData::new(rand::random(), b"plain".to_vec())),
+             Data::Mutable(mutable_data())]
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_round_trip() {
+        for data in all_data() {
+            let json = unwrap!(data.to_json());
+            assert_eq!(unwrap!(Data::from_json(&json)), data);
+        }
+
+        let structured_data = structured_data();
+        let json = unwrap!(structured_data.to_json());
+        assert!(json.contains(&format!("\"name\":\"{}\"", structured_data.name().to_hex())));
+        assert!(json.contains("\"data\":\"c3RydWN0dXJlZA==\""));
+        assert_eq!(unwrap!(StructuredData::from_json(&json)), structured_data);
+
+        let immutable_data = ImmutableData::new(vec![1, 2, 3]);
+        let json = unwrap!(immutable_data.to_json());
+        assert_eq!(unwrap!(ImmutableData::from_json(&json)), immutable_data);
+
+        let plain_data = PlainData::new(rand::random(), vec![4, 5, 6]);
+        let json = unwrap!(plain_data.to_json());
+        assert_eq!(unwrap!(PlainData::from_json(&json)), plain_data);
+
+        let mutable_data = mutable_data();
+        let json = unwrap!(mutable_data.to_json());
+        assert_eq!(unwrap!(MutableData::from_json(&json)), mutable_data);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_import_is_validated() {
+        let immutable_data = ImmutableData::new(vec![1, 2, 3]);
+        let json = unwrap!(immutable_data.to_json()).replace("AQID", "AQIE");
+        match ImmutableData::from_json(&json) {
+            Err(Error::BadIdentifier) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        let json = unwrap!(structured_data().to_json()).replace("\"name\":\"", "\"name\":\"zz");
+        match StructuredData::from_json(&json) {
+            Err(Error::Encoding(_)) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        match Data::from_json("{}") {
+            Err(Error::Encoding(_)) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn cbor_round_trip() {
+        for data in all_data() {
+            let cbor = unwrap!(data.to_cbor());
+            assert_eq!(unwrap!(Data::from_cbor(&cbor)), data);
+        }
+
+        let structured_data = structured_data();
+        let cbor = unwrap!(structured_data.to_cbor());
+        assert_eq!(unwrap!(StructuredData::from_cbor(&cbor)), structured_data);
+    }
+}
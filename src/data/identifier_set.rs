@@ -0,0 +1,263 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use data::DataIdentifier;
+#[cfg(feature = "serialisation")]
+use rustc_serialize::{Decodable, Decoder, Encodable, Encoder};
+use std::collections::{BTreeMap, BTreeSet};
+use std::collections::btree_map;
+use std::collections::btree_set;
+use xor_name::XorName;
+
+/// A set of data identifiers ordered by the XOR distance of their names to a pivot, e.g. the
+/// name of the node keeping it, as a data manager does to track the data it is responsible for.
+///
+/// Identifiers sharing a name, such as structured data of different type tags, are all kept, in
+/// the order of the identifiers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IdentifierSet {
+    pivot: XorName,
+    identifiers: BTreeMap<XorName, BTreeSet<DataIdentifier>>,
+    len: usize,
+}
+
+impl IdentifierSet {
+    /// Creates an empty set ordered by distance to `pivot`.
+    pub fn new(pivot: XorName) -> IdentifierSet {
+        IdentifierSet {
+            pivot: pivot,
+            identifiers: BTreeMap::new(),
+            len: 0,
+        }
+    }
+
+    /// The name distances are measured from.
+    pub fn pivot(&self) -> &XorName {
+        &self.pivot
+    }
+
+    /// Returns the XOR distance of `identifier`'s name from the pivot.
+    pub fn distance(&self, identifier: &DataIdentifier) -> XorName {
+        self.pivot.distance(&XorName(*identifier.name()))
+    }
+
+    /// Adds `identifier`, returning whether it was not already in the set.
+    pub fn insert(&mut self, identifier: DataIdentifier) -> bool {
+        let distance = self.distance(&identifier);
+        let inserted = self.identifiers
+            .entry(distance)
+            .or_insert_with(BTreeSet::new)
+            .insert(identifier);
+        if inserted {
+            self.len += 1;
+        }
+        inserted
+    }
+
+    /// Removes `identifier`, returning whether it was in the set.
+    pub fn remove(&mut self, identifier: &DataIdentifier) -> bool {
+        let distance = self.distance(identifier);
+        let (removed, now_empty) = match self.identifiers.get_mut(&distance) {
+            Some(identifiers) => (identifiers.remove(identifier), identifiers.is_empty()),
+            None => return false,
+        };
+        if now_empty {
+            let _ = self.identifiers.remove(&distance);
+        }
+        if removed {
+            self.len -= 1;
+        }
+        removed
+    }
+
+    /// Returns whether `identifier` is in the set.
+    pub fn contains(&self, identifier: &DataIdentifier) -> bool {
+        self.identifiers
+            .get(&self.distance(identifier))
+            .map_or(false, |identifiers| identifiers.contains(identifier))
+    }
+
+    /// Returns the number of identifiers in the set.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Iterates over the identifiers, closest to the pivot first.
+    pub fn iter(&self) -> Iter {
+        Iter {
+            groups: self.identifiers.values(),
+            group: None,
+        }
+    }
+
+    /// Returns the (at most) `k` identifiers closest to the pivot, closest first.
+    pub fn closest(&self, k: usize) -> Vec<DataIdentifier> {
+        self.iter().take(k).cloned().collect()
+    }
+
+    /// Splits the set in two at `distance` from the pivot: this set keeps the identifiers closer
+    /// than `distance`, and those at `distance` or further are returned in a set of their own,
+    /// e.g. to hand over the data a node is no longer responsible for once others join close to
+    /// it.
+    pub fn split_at_distance(&mut self, distance: &XorName) -> IdentifierSet {
+        let identifiers = self.identifiers.split_off(distance);
+        let len = identifiers.values().map(BTreeSet::len).sum();
+        self.len -= len;
+        IdentifierSet {
+            pivot: self.pivot,
+            identifiers: identifiers,
+            len: len,
+        }
+    }
+}
+
+impl Extend<DataIdentifier> for IdentifierSet {
+    fn extend<I: IntoIterator<Item = DataIdentifier>>(&mut self, identifiers: I) {
+        for identifier in identifiers {
+            let _ = self.insert(identifier);
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a IdentifierSet {
+    type Item = &'a DataIdentifier;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+/// Iterator over the identifiers of an `IdentifierSet`, closest to the pivot first, returned by
+/// `iter`.
+pub struct Iter<'a> {
+    groups: btree_map::Values<'a, XorName, BTreeSet<DataIdentifier>>,
+    group: Option<btree_set::Iter<'a, DataIdentifier>>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a DataIdentifier;
+
+    fn next(&mut self) -> Option<&'a DataIdentifier> {
+        loop {
+            if let Some(identifier) = self.group.as_mut().and_then(Iterator::next) {
+                return Some(identifier);
+            }
+            match self.groups.next() {
+                Some(group) => self.group = Some(group.iter()),
+                None => return None,
+            }
+        }
+    }
+}
+
+/// Serialised as the pivot followed by the identifiers, closest first.
+#[cfg(feature = "serialisation")]
+impl Encodable for IdentifierSet {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), E::Error> {
+        (self.pivot, self.iter().cloned().collect::<Vec<_>>()).encode(encoder)
+    }
+}
+
+#[cfg(feature = "serialisation")]
+impl Decodable for IdentifierSet {
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<IdentifierSet, D::Error> {
+        let (pivot, identifiers): (XorName, Vec<DataIdentifier>) = Decodable::decode(decoder)?;
+        let mut set = IdentifierSet::new(pivot);
+        set.extend(identifiers);
+        Ok(set)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::DataIdentifier;
+    use rand;
+    use serialisation::{deserialise, serialise};
+    use xor_name::{self, XorName};
+
+    fn random_identifiers(count: usize) -> Vec<DataIdentifier> {
+        (0..count)
+            .map(|index| if index % 2 == 0 {
+                DataIdentifier::Immutable(rand::random())
+            } else {
+                DataIdentifier::Structured(rand::random(), index as u64)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn insert_remove_and_closest() {
+        let pivot: [u8; 32] = rand::random();
+        let mut set = IdentifierSet::new(XorName(pivot));
+        let identifiers = random_identifiers(50);
+        for identifier in &identifiers {
+            assert!(set.insert(*identifier));
+        }
+        assert!(!set.insert(identifiers[0]));
+        assert_eq!(set.len(), 50);
+
+        let names: Vec<_> = identifiers.iter().map(|identifier| *identifier.name()).collect();
+        let expected = xor_name::closest_nodes(&pivot, &names, 10);
+        let closest: Vec<_> = set.closest(10).iter().map(|identifier| *identifier.name()).collect();
+        assert_eq!(closest, expected);
+        assert_eq!(set.closest(100).len(), 50);
+
+        // Another identifier with the same name is kept too.
+        let same_name = DataIdentifier::Plain(*identifiers[0].name());
+        assert!(set.insert(same_name));
+        assert_eq!(set.len(), 51);
+        assert!(set.contains(&same_name));
+        assert_eq!(set.iter().count(), 51);
+
+        assert!(set.remove(&same_name));
+        assert!(!set.remove(&same_name));
+        assert!(set.contains(&identifiers[0]));
+        assert!(set.remove(&identifiers[0]));
+        assert!(!set.contains(&identifiers[0]));
+        assert_eq!(set.len(), 49);
+    }
+
+    #[test]
+    fn split_at_distance() {
+        let mut set = IdentifierSet::new(XorName(rand::random()));
+        set.extend(random_identifiers(50));
+        let ordered: Vec<_> = set.iter().cloned().collect();
+        let boundary = set.distance(&ordered[20]);
+
+        let far = set.split_at_distance(&boundary);
+        assert_eq!(set.len(), 20);
+        assert_eq!(far.len(), 30);
+        assert_eq!(set.iter().cloned().collect::<Vec<_>>(), &ordered[..20]);
+        assert_eq!(far.iter().cloned().collect::<Vec<_>>(), &ordered[20..]);
+        assert_eq!(far.pivot(), set.pivot());
+    }
+
+    #[test]
+    fn serialisation() {
+        let mut set = IdentifierSet::new(XorName(rand::random()));
+        set.extend(random_identifiers(20));
+        let serialised = unwrap!(serialise(&set));
+        assert_eq!(unwrap!(deserialise::<IdentifierSet>(&serialised)), set);
+    }
+}
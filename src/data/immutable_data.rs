@@ -16,60 +16,503 @@
 // relating to use of the SAFE Network Software.
 
 use data::DataIdentifier;
+use data::compression::{self, Compression};
+#[cfg(feature = "compression")]
+use data::compression::MAX_DECOMPRESSED_BYTES;
+#[cfg(feature = "serialisation")]
+use data::envelope;
+use data::immutable_data_value::ImmutableDataValue;
+use error::Error;
+use name_hasher::{NameHasher, Sha3NameHasher};
+#[cfg(feature = "serialisation")]
 use rustc_serialize::{Decodable, Decoder, Encodable, Encoder};
+#[cfg(feature = "serialisation")]
+use serialisation::serialise;
 use sha3::hash;
+use std::borrow::Cow;
 use std::fmt::{self, Debug, Formatter};
+use std::io::{self, Read, Write};
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Size of the buffer used when reading values from a stream.
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+/// Size of the length prefix of a serialised value.
+const LENGTH_PREFIX_SIZE: usize = 8;
+/// Size of the variant index of a serialised enum.
+const VARIANT_INDEX_SIZE: usize = 4;
+/// Serialised variant index of `Data::Immutable`.
+const IMMUTABLE_DATA_VARIANT: &'static [u8] = &[0, 0, 0, 1];
+/// Serialised compression of an uncompressed value.
+const UNCOMPRESSED: &'static [u8] = &[0];
+/// Serialised compression of a value compressed with `Compression::Deflate`.
+const DEFLATE_COMPRESSED: &'static [u8] = &[1, 0, 0, 0, 0];
+
+/// Kind of copy of an `ImmutableData` a name is for.
+///
+/// Replica management stores the backup and sacrificial copies of a chunk under names derived
+/// from its normal name by re-hashing, so they are spread to other parts of the network.
+#[derive(Hash, Eq, PartialEq, PartialOrd, Ord, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serialisation", derive(RustcEncodable, RustcDecodable))]
+pub enum ImmutableDataKind {
+    /// The chunk itself, named by the hash of its value.
+    Normal,
+    /// The backup copy, named by the hash of the normal name.
+    Backup,
+    /// The sacrificial copy, named by the hash of the backup name.
+    Sacrificial,
+}
+
+/// Returns the name the copy of `kind` of the chunk with the given normal name is stored under.
+pub fn name_for_kind(normal_name: &[u8; 32], kind: ImmutableDataKind) -> [u8; 32] {
+    name_for_kind_with::<Sha3NameHasher>(normal_name, kind)
+}
+
+/// Returns the name the copy of `kind` of the chunk with the given normal name is stored under,
+/// re-hashing with `H`.
+pub fn name_for_kind_with<H: NameHasher>(normal_name: &[u8; 32],
+                                         kind: ImmutableDataKind)
+                                         -> [u8; 32] {
+    match kind {
+        ImmutableDataKind::Normal => *normal_name,
+        ImmutableDataKind::Backup => H::hash(normal_name),
+        ImmutableDataKind::Sacrificial => H::hash(&H::hash(normal_name)),
+    }
+}
 
 /// An immutable chunk of data.
+///
+/// The value may be stored compressed, in which case the name is the hash of the compressed bytes
+/// and `decompressed_value` recovers the original content. It is held in memory unless the data
+/// was created `from_value` with a value mapped from a file.
+///
+/// The value is shared between clones rather than copied, so cloning is cheap however large the
+/// value is, e.g. when a chunk is queued for several peers.
 #[derive(Hash, Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub struct ImmutableData {
     name: [u8; 32],
-    value: Vec<u8>,
+    value: Arc<ImmutableDataValue>,
+    compression: Option<Compression>,
 }
 
 impl ImmutableData {
+    /// Maximum allowed size of the value of an `ImmutableData`
+    pub const MAX_BYTES: usize = 1024 * 1024;
+
     /// Creates a new instance of `ImmutableData`
     pub fn new(value: Vec<u8>) -> ImmutableData {
+        ImmutableData::from_value(ImmutableDataValue::Memory(value))
+    }
+
+    /// Creates a new instance of `ImmutableData` holding `value`, e.g. one mapped from a file.
+    pub fn from_value(value: ImmutableDataValue) -> ImmutableData {
         ImmutableData {
             name: hash(&value),
-            value: value,
+            value: Arc::new(value),
+            compression: None,
         }
     }
 
-    /// Returns the value
-    pub fn value(&self) -> &Vec<u8> {
+    /// Creates a new instance of `ImmutableData` holding `value` compressed, refusing values
+    /// which would be too large to decompress again.
+    #[cfg(feature = "compression")]
+    pub fn new_compressed(value: Vec<u8>) -> Result<ImmutableData, Error> {
+        if value.len() > MAX_DECOMPRESSED_BYTES {
+            return Err(Error::DataTooLarge);
+        }
+        let compressed = Compression::Deflate.compress(&value)?;
+        ImmutableData::with_compression(compressed, Some(Compression::Deflate))
+    }
+
+    /// Creates a new instance of `ImmutableData` from a value already compressed according to
+    /// `compression`, refusing values larger than `MAX_BYTES`.
+    pub fn with_compression(value: Vec<u8>,
+                            compression: Option<Compression>)
+                            -> Result<ImmutableData, Error> {
+        let mut data = ImmutableData::try_new(value)?;
+        data.compression = compression;
+        Ok(data)
+    }
+
+    /// Creates a new instance of `ImmutableData`, refusing values larger than `MAX_BYTES`.
+    pub fn try_new(value: Vec<u8>) -> Result<ImmutableData, Error> {
+        if value.len() > ImmutableData::MAX_BYTES {
+            return Err(Error::DataTooLarge);
+        }
+        Ok(ImmutableData::new(value))
+    }
+
+    /// Creates a new instance of `ImmutableData` from the contents of `reader`, hashing the value
+    /// as it is read rather than once it is complete.
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<ImmutableData, Error> {
+        let mut hasher = ImmutableDataHasher::new();
+        let mut value = Vec::new();
+        let mut buffer = [0u8; READ_BUFFER_SIZE];
+        loop {
+            match reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(size) => {
+                    hasher.update(&buffer[..size]);
+                    value.extend_from_slice(&buffer[..size]);
+                }
+                Err(ref error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(From::from(error)),
+            }
+        }
+        Ok(ImmutableData {
+            name: hasher.finalize(),
+            value: Arc::new(ImmutableDataValue::Memory(value)),
+            compression: None,
+        })
+    }
+
+    /// Parses a serialised `ImmutableData` received as the data named `name`, e.g. from a peer,
+    /// failing with `BadIdentifier` if its value does not hash to `name` and with `DataTooLarge`
+    /// if it is larger than `MAX_BYTES`.
+    pub fn deserialise_validated(serialised: &[u8],
+                                 name: &[u8; 32])
+                                 -> Result<ImmutableData, Error> {
+        let data_ref = ImmutableDataRef::from_serialised(serialised)?;
+        if data_ref.payload_size() > ImmutableData::MAX_BYTES {
+            return Err(Error::DataTooLarge);
+        }
+        if data_ref.name() != name {
+            return Err(Error::BadIdentifier);
+        }
+        Ok(data_ref.to_immutable_data())
+    }
+
+    /// Writes this data to `writer` in its serialised form, writing the value straight from where
+    /// it is held rather than first copying it into an encoder's buffer.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        let len = self.value.len() as u64;
+        let mut prefix = [0u8; LENGTH_PREFIX_SIZE];
+        for (index, byte) in prefix.iter_mut().enumerate() {
+            *byte = (len >> (8 * (LENGTH_PREFIX_SIZE - 1 - index))) as u8;
+        }
+        writer.write_all(&prefix)?;
+        writer.write_all(&self.value)?;
+        writer.write_all(match self.compression {
+                None => UNCOMPRESSED,
+                Some(Compression::Deflate) => DEFLATE_COMPRESSED,
+            })?;
+        Ok(())
+    }
+
+    /// Reads data in its serialised form from `reader`, e.g. as written by `write_to`, reading no
+    /// further than its end. The value is read in blocks and hashed as it is read.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<ImmutableData, Error> {
+        let mut prefix = [0u8; LENGTH_PREFIX_SIZE];
+        reader.read_exact(&mut prefix)?;
+        let len = prefix.iter().fold(0u64, |len, &byte| (len << 8) | byte as u64);
+        let mut data = ImmutableData::from_reader(reader.by_ref().take(len))?;
+        if data.value.len() as u64 != len {
+            return Err(Error::Encoding("truncated immutable data".to_owned()));
+        }
+        let mut flag = [0u8; 1];
+        reader.read_exact(&mut flag)?;
+        if &flag[..] != UNCOMPRESSED {
+            let mut compression = [0u8; 5];
+            compression[0] = flag[0];
+            reader.read_exact(&mut compression[1..])?;
+            if &compression[..] != DEFLATE_COMPRESSED {
+                return Err(Error::Encoding("invalid immutable data compression".to_owned()));
+            }
+            data.compression = Some(Compression::Deflate);
+        }
+        Ok(data)
+    }
+
+    /// Returns the value as stored, i.e. compressed if this data is compressed, without copying
+    /// it.
+    pub fn value(&self) -> &[u8] {
+        self.value.as_slice()
+    }
+
+    /// Returns what holds the value.
+    pub fn value_storage(&self) -> &ImmutableDataValue {
         &self.value
     }
 
+    /// Returns the value, decompressing it if necessary.
+    pub fn decompressed_value(&self) -> Result<Cow<[u8]>, Error> {
+        compression::decompressed(&self.value, self.compression)
+    }
+
+    /// Returns the bytes of the value in `range` without copying them, or `None` if the range is
+    /// out of bounds. The range is into the value as stored, i.e. compressed if this data is
+    /// compressed.
+    pub fn slice(&self, range: Range<usize>) -> Option<&[u8]> {
+        if range.start > range.end || range.end > self.value.len() {
+            return None;
+        }
+        Some(&self.value[range])
+    }
+
+    /// Returns the compression the value is stored with, if any.
+    pub fn compression(&self) -> Option<Compression> {
+        self.compression
+    }
+
     /// Returns name ensuring invariant.
     pub fn name(&self) -> &[u8; 32] {
         &self.name
     }
 
-    /// Returns size of contained value.
+    /// Returns the name the copy of `kind` of this data is stored under.
+    pub fn name_for_kind(&self, kind: ImmutableDataKind) -> [u8; 32] {
+        name_for_kind(&self.name, kind)
+    }
+
+    /// Returns the name this data would have if names were derived with `H`.
+    pub fn name_with<H: NameHasher>(&self) -> [u8; 32] {
+        H::hash(&self.value)
+    }
+
+    /// Checks `name` is the name of this data derived with `H`, failing with `BadIdentifier` if
+    /// not.
+    pub fn validate_name_with<H: NameHasher>(&self, name: &[u8; 32]) -> Result<(), Error> {
+        if self.name_with::<H>() == *name {
+            Ok(())
+        } else {
+            Err(Error::BadIdentifier)
+        }
+    }
+
+    /// Checks the name is the hash of the value, failing with `BadIdentifier` if not, e.g.
+    /// because a value mapped from a file has changed since the data was created.
+    pub fn validate_name(&self) -> Result<(), Error> {
+        self.validate_name_with::<Sha3NameHasher>(&self.name)
+    }
+
+    /// Returns size of contained value, as stored.
     pub fn payload_size(&self) -> usize {
         self.value.len()
     }
 
+    /// Returns the exact size of the serialised data.
+    #[cfg(feature = "serialisation")]
+    pub fn serialised_size(&self) -> Result<usize, Error> {
+        Ok(serialise(self)?.len())
+    }
+
+    /// Returns the size of the serialised data, worked out from the fields without serialising.
+    pub fn estimated_size(&self) -> usize {
+        let compression_size = match self.compression {
+            Some(_) => DEFLATE_COMPRESSED.len(),
+            None => UNCOMPRESSED.len(),
+        };
+        LENGTH_PREFIX_SIZE + self.value.len() + compression_size
+    }
+
     /// Returns `DataIdentifier` for this data element.
     pub fn identifier(&self) -> DataIdentifier {
         DataIdentifier::Immutable(self.name)
     }
+
+    /// Returns a view of this data borrowing the value.
+    pub fn as_data_ref(&self) -> ImmutableDataRef {
+        ImmutableDataRef {
+            name: self.name,
+            value: &self.value,
+            compression: self.compression,
+        }
+    }
+}
+
+/// An immutable chunk of data whose value is borrowed rather than owned.
+///
+/// This lets a serialised `ImmutableData` be validated and relayed straight out of the buffer it
+/// was received in, without copying the value out first.
+#[derive(Hash, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+pub struct ImmutableDataRef<'a> {
+    name: [u8; 32],
+    value: &'a [u8],
+    compression: Option<Compression>,
 }
 
+impl<'a> ImmutableDataRef<'a> {
+    /// Creates a view of `value`, computing its name.
+    pub fn new(value: &'a [u8]) -> ImmutableDataRef<'a> {
+        ImmutableDataRef {
+            name: hash(value),
+            value: value,
+            compression: None,
+        }
+    }
+
+    /// Parses a serialised `ImmutableData`, borrowing the value from `serialised`.
+    pub fn from_serialised(serialised: &'a [u8]) -> Result<ImmutableDataRef<'a>, Error> {
+        if serialised.len() < LENGTH_PREFIX_SIZE {
+            return Err(Error::Encoding("truncated immutable data".to_owned()));
+        }
+        let (prefix, rest) = serialised.split_at(LENGTH_PREFIX_SIZE);
+        // The value is serialised as a big-endian `u64` length followed by the bytes, then the
+        // optional compression.
+        let len = prefix.iter().fold(0u64, |len, &byte| (len << 8) | byte as u64);
+        if len > rest.len() as u64 {
+            return Err(Error::Encoding(format!("immutable data length {} exceeds the {} bytes \
+                                                given",
+                                               len,
+                                               rest.len())));
+        }
+        let (value, compression) = rest.split_at(len as usize);
+        let compression = match compression {
+            UNCOMPRESSED => None,
+            DEFLATE_COMPRESSED => Some(Compression::Deflate),
+            _ => return Err(Error::Encoding("invalid immutable data compression".to_owned())),
+        };
+        let mut data_ref = ImmutableDataRef::new(value);
+        data_ref.compression = compression;
+        Ok(data_ref)
+    }
+
+    /// Parses a serialised `Data`, which must hold an `ImmutableData`, borrowing the value from
+    /// `serialised`.
+    pub fn from_serialised_data(serialised: &'a [u8]) -> Result<ImmutableDataRef<'a>, Error> {
+        if serialised.len() < VARIANT_INDEX_SIZE {
+            return Err(Error::Encoding("truncated data".to_owned()));
+        }
+        let (variant, rest) = serialised.split_at(VARIANT_INDEX_SIZE);
+        if variant != IMMUTABLE_DATA_VARIANT {
+            return Err(Error::BadIdentifier);
+        }
+        ImmutableDataRef::from_serialised(rest)
+    }
+
+    /// Returns the value as stored, i.e. compressed if this data is compressed.
+    pub fn value(&self) -> &'a [u8] {
+        self.value
+    }
+
+    /// Returns the value, decompressing it if necessary.
+    pub fn decompressed_value(&self) -> Result<Cow<'a, [u8]>, Error> {
+        compression::decompressed(self.value, self.compression)
+    }
 
+    /// Returns the compression the value is stored with, if any.
+    pub fn compression(&self) -> Option<Compression> {
+        self.compression
+    }
+
+    /// Returns name ensuring invariant.
+    pub fn name(&self) -> &[u8; 32] {
+        &self.name
+    }
+
+    /// Returns the name the copy of `kind` of this data is stored under.
+    pub fn name_for_kind(&self, kind: ImmutableDataKind) -> [u8; 32] {
+        name_for_kind(&self.name, kind)
+    }
+
+    /// Returns size of contained value, as stored.
+    pub fn payload_size(&self) -> usize {
+        self.value.len()
+    }
+
+    /// Returns `DataIdentifier` for this data element.
+    pub fn identifier(&self) -> DataIdentifier {
+        DataIdentifier::Immutable(self.name)
+    }
+
+    /// Copies the value into an owned `ImmutableData`.
+    pub fn to_immutable_data(&self) -> ImmutableData {
+        ImmutableData {
+            name: self.name,
+            value: Arc::new(ImmutableDataValue::Memory(self.value.to_vec())),
+            compression: self.compression,
+        }
+    }
+}
+
+impl<'a> Debug for ImmutableDataRef<'a> {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter, "ImmutableDataRef {:?}", self.name())
+    }
+}
+
+
+/// Incrementally computes the name an `ImmutableData` holding the fed content would have, with
+/// names derived with `H`.
+#[derive(Default)]
+pub struct ImmutableDataHasher<H: NameHasher = Sha3NameHasher> {
+    hasher: H,
+}
+
+impl ImmutableDataHasher {
+    /// Creates a hasher with no content fed yet.
+    pub fn new() -> ImmutableDataHasher {
+        ImmutableDataHasher::default()
+    }
+
+    /// Computes the name of the content of `reader` without holding all of it in memory.
+    pub fn name_of<R: Read>(reader: R) -> io::Result<[u8; 32]> {
+        ImmutableDataHasher::<Sha3NameHasher>::name_of_with(reader)
+    }
+}
+
+impl<H: NameHasher> ImmutableDataHasher<H> {
+    /// Computes the name derived with `H` of the content of `reader` without holding all of it in
+    /// memory.
+    pub fn name_of_with<R: Read>(mut reader: R) -> io::Result<[u8; 32]> {
+        let mut hasher = ImmutableDataHasher::<H>::default();
+        let _ = io::copy(&mut reader, &mut hasher)?;
+        Ok(hasher.finalize())
+    }
+
+    /// Feeds the next part of the content.
+    pub fn update(&mut self, data: &[u8]) {
+        self.hasher.update(data);
+    }
+
+    /// Returns the name of all content fed so far.
+    pub fn finalize(self) -> [u8; 32] {
+        self.hasher.finalize()
+    }
+}
+
+impl<H: NameHasher> Write for ImmutableDataHasher<H> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.update(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serialisation")]
 impl Encodable for ImmutableData {
     fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), E::Error> {
-        self.value.encode(encoder)
+        (&*self.value, self.compression).encode(encoder)
     }
 }
 
+#[cfg(feature = "serialisation")]
 impl Decodable for ImmutableData {
     fn decode<D: Decoder>(decoder: &mut D) -> Result<ImmutableData, D::Error> {
-        let value: Vec<u8> = Decodable::decode(decoder)?;
+        ImmutableData::decode_in_format(decoder, envelope::FORMAT_VERSION)
+    }
+}
+
+#[cfg(feature = "serialisation")]
+impl ImmutableData {
+    /// Decodes data serialised in the layout of the envelope format version `format`. In
+    /// `LEGACY_FORMAT_VERSION` the data is only its value, which is uncompressed.
+    pub(crate) fn decode_in_format<D: Decoder>(decoder: &mut D,
+                                               format: u32)
+                                               -> Result<ImmutableData, D::Error> {
+        let (value, compression): (ImmutableDataValue, Option<Compression>) =
+            if format == envelope::LEGACY_FORMAT_VERSION {
+                (Decodable::decode(decoder)?, None)
+            } else {
+                Decodable::decode(decoder)?
+            };
         Ok(ImmutableData {
             name: hash(&value),
-            value: value,
+            value: Arc::new(value),
+            compression: compression,
         })
     }
 }
@@ -83,7 +526,13 @@ impl Debug for ImmutableData {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use data::{Data, PlainData};
+    use error::Error;
+    use rand;
     use rustc_serialize::hex::ToHex;
+    #[cfg(feature = "compression")]
+    use serialisation::deserialise;
+    use std::io::Cursor;
 
     #[test]
     fn deterministic_test() {
@@ -96,4 +545,195 @@ mod tests {
 
         assert_eq!(&expected_name, &immutable_data_name);
     }
+
+    #[test]
+    fn names_for_kinds() {
+        let immutable_data = ImmutableData::new(b"immutable data value".to_vec());
+        let normal = immutable_data.name_for_kind(ImmutableDataKind::Normal);
+        let backup = immutable_data.name_for_kind(ImmutableDataKind::Backup);
+        let sacrificial = immutable_data.name_for_kind(ImmutableDataKind::Sacrificial);
+        assert_eq!(&normal, immutable_data.name());
+        assert_eq!(backup, hash(&normal));
+        assert_eq!(sacrificial, hash(&backup));
+        assert_eq!(immutable_data.as_data_ref().name_for_kind(ImmutableDataKind::Sacrificial),
+                   sacrificial);
+        assert_eq!(name_for_kind(&normal, ImmutableDataKind::Backup), backup);
+        assert_eq!(name_for_kind_with::<Sha3NameHasher>(&normal, ImmutableDataKind::Sacrificial),
+                   sacrificial);
+    }
+
+    #[test]
+    fn names_with_hasher() {
+        let value = b"immutable data value";
+        let immutable_data = ImmutableData::new(value.to_vec());
+        assert_eq!(&immutable_data.name_with::<Sha3NameHasher>(),
+                   immutable_data.name());
+        unwrap!(immutable_data.validate_name_with::<Sha3NameHasher>(immutable_data.name()));
+        match immutable_data.validate_name_with::<Sha3NameHasher>(&[0; 32]) {
+            Err(Error::BadIdentifier) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        let name = unwrap!(ImmutableDataHasher::<Sha3NameHasher>::name_of_with(&value[..]));
+        assert_eq!(&name, immutable_data.name());
+    }
+
+    #[test]
+    fn streamed_naming() {
+        let value: Vec<u8> = (0..3 * READ_BUFFER_SIZE + 17).map(|i| i as u8).collect();
+        let immutable_data = ImmutableData::new(value.clone());
+
+        let streamed = unwrap!(ImmutableData::from_reader(Cursor::new(value.clone())));
+        assert_eq!(streamed, immutable_data);
+        assert_eq!(streamed.name(), immutable_data.name());
+
+        let mut hasher = ImmutableDataHasher::new();
+        for chunk in value.chunks(1000) {
+            hasher.update(chunk);
+        }
+        assert_eq!(&hasher.finalize(), immutable_data.name());
+        assert_eq!(&unwrap!(ImmutableDataHasher::name_of(Cursor::new(value))),
+                   immutable_data.name());
+        assert_eq!(ImmutableDataHasher::new().finalize(),
+                   *ImmutableData::new(vec![]).name());
+    }
+
+    #[test]
+    fn from_value() {
+        let value: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        let immutable_data = ImmutableData::new(value.clone());
+        let from_value = ImmutableData::from_value(ImmutableDataValue::from(value.clone()));
+        assert_eq!(from_value, immutable_data);
+        assert_eq!(from_value.value(), &value[..]);
+        assert!(!from_value.value_storage().is_mapped());
+        assert_eq!(unwrap!(serialise(&from_value)), unwrap!(serialise(&immutable_data)));
+    }
+
+    #[test]
+    fn clones_share_value() {
+        let immutable_data = ImmutableData::new(vec![7; 100000]);
+        let clone = immutable_data.clone();
+        assert_eq!(clone, immutable_data);
+        assert_eq!(clone.value().as_ptr(), immutable_data.value().as_ptr());
+
+        let data = Data::Immutable(immutable_data.clone());
+        match data.clone() {
+            Data::Immutable(clone) => {
+                assert_eq!(clone.value().as_ptr(), immutable_data.value().as_ptr())
+            }
+            data => panic!("Unexpected data {:?}", data),
+        }
+    }
+
+    #[test]
+    fn borrowed_from_serialised() {
+        let immutable_data = ImmutableData::new((0..1000).map(|i| i as u8).collect());
+        let serialised = unwrap!(serialise(&immutable_data));
+        let data_ref = unwrap!(ImmutableDataRef::from_serialised(&serialised));
+        assert_eq!(data_ref.name(), immutable_data.name());
+        assert_eq!(data_ref.value(), &immutable_data.value()[..]);
+        assert_eq!(data_ref.value().as_ptr(), serialised[8..].as_ptr());
+        assert_eq!(data_ref.to_immutable_data(), immutable_data);
+        assert_eq!(immutable_data.as_data_ref(), data_ref);
+
+        let serialised_data = unwrap!(serialise(&Data::Immutable(immutable_data.clone())));
+        let data_ref = unwrap!(ImmutableDataRef::from_serialised_data(&serialised_data));
+        assert_eq!(data_ref.identifier(), immutable_data.identifier());
+
+        match ImmutableDataRef::from_serialised(&serialised[..serialised.len() - 1]) {
+            Err(Error::Encoding(_)) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        let plain_data = Data::Plain(PlainData::new(rand::random(), vec![1, 2, 3]));
+        match ImmutableDataRef::from_serialised_data(&unwrap!(serialise(&plain_data))) {
+            Err(Error::BadIdentifier) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn validated_deserialisation() {
+        let immutable_data = ImmutableData::new((0..1000).map(|i| i as u8).collect());
+        let serialised = unwrap!(serialise(&immutable_data));
+        assert_eq!(unwrap!(ImmutableData::deserialise_validated(&serialised,
+                                                                immutable_data.name())),
+                   immutable_data);
+        match ImmutableData::deserialise_validated(&serialised, &rand::random()) {
+            Err(Error::BadIdentifier) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        let too_large = ImmutableData::new(vec![0; ImmutableData::MAX_BYTES + 1]);
+        match ImmutableData::deserialise_validated(&unwrap!(serialise(&too_large)),
+                                                   too_large.name()) {
+            Err(Error::DataTooLarge) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        unwrap!(immutable_data.validate_name());
+        unwrap!(Data::Immutable(immutable_data.clone()).validate());
+        let forged = ImmutableData { name: rand::random(), ..immutable_data };
+        match forged.validate_name() {
+            Err(Error::BadIdentifier) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match Data::Immutable(forged).validate() {
+            Err(Error::BadIdentifier) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn slices() {
+        let immutable_data = ImmutableData::new((0..100).collect());
+        assert_eq!(immutable_data.slice(10..13), Some(&[10u8, 11, 12][..]));
+        assert_eq!(immutable_data.slice(0..100).map(|slice| slice.len()), Some(100));
+        assert_eq!(immutable_data.slice(100..100), Some(&[][..]));
+        assert_eq!(immutable_data.slice(90..101), None);
+        assert_eq!(immutable_data.slice(20..10), None);
+    }
+
+    #[test]
+    fn sizes() {
+        for size in &[0, 1, 1000] {
+            let immutable_data = ImmutableData::new(vec![7; *size]);
+            assert_eq!(immutable_data.estimated_size(), unwrap!(immutable_data.serialised_size()));
+            let compressed = unwrap!(ImmutableData::with_compression(vec![7; *size],
+                                                                     Some(Compression::Deflate)));
+            assert_eq!(compressed.estimated_size(), unwrap!(compressed.serialised_size()));
+        }
+    }
+
+    #[test]
+    fn max_size() {
+        assert!(ImmutableData::try_new(vec![0; ImmutableData::MAX_BYTES]).is_ok());
+        match ImmutableData::try_new(vec![0; ImmutableData::MAX_BYTES + 1]) {
+            Err(Error::DataTooLarge) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn compressed() {
+        let value = vec![3u8; 10000];
+        let immutable_data = unwrap!(ImmutableData::new_compressed(value.clone()));
+        assert_eq!(immutable_data.compression(), Some(Compression::Deflate));
+        assert!(immutable_data.payload_size() < value.len());
+        assert_eq!(immutable_data.name(), &hash(immutable_data.value()));
+        assert_eq!(&*unwrap!(immutable_data.decompressed_value()), &value[..]);
+
+        let serialised = unwrap!(serialise(&immutable_data));
+        assert_eq!(unwrap!(deserialise::<ImmutableData>(&serialised)), immutable_data);
+        let data_ref = unwrap!(ImmutableDataRef::from_serialised(&serialised));
+        assert_eq!(data_ref.compression(), Some(Compression::Deflate));
+        assert_eq!(&*unwrap!(data_ref.decompressed_value()), &value[..]);
+        assert_eq!(data_ref.to_immutable_data(), immutable_data);
+
+        let uncompressed = ImmutableData::new(value.clone());
+        assert_eq!(&*unwrap!(uncompressed.decompressed_value()), &value[..]);
+        match ImmutableData::new_compressed(vec![0; MAX_DECOMPRESSED_BYTES + 1]) {
+            Err(Error::DataTooLarge) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
 }
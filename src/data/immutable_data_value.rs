@@ -0,0 +1,210 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Immutable data values
+//! The storage behind the value of an `ImmutableData`. Values are normally held in memory; with
+//! the `mmap` feature a vault can instead map a chunk's file, so even very large values are paged
+//! in by the OS as they are read rather than copied onto the heap. Either way the value is read
+//! through `as_slice`, and serialises exactly as a `Vec<u8>` would.
+
+#[cfg(feature = "mmap")]
+use error::Error;
+#[cfg(feature = "mmap")]
+use memmap::Mmap;
+#[cfg(feature = "serialisation")]
+use rustc_serialize::{Decodable, Decoder, Encodable, Encoder};
+use std::cmp::Ordering;
+use std::fmt::{self, Debug, Formatter};
+#[cfg(feature = "mmap")]
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+#[cfg(feature = "mmap")]
+use std::path::Path;
+#[cfg(feature = "mmap")]
+use std::sync::Arc;
+
+/// The value of an `ImmutableData`, in memory or mapped from a file.
+///
+/// Values compare, hash and serialise by their bytes, whatever holds them. A deserialised value
+/// is always in memory.
+#[derive(Clone)]
+pub enum ImmutableDataValue {
+    /// A value held in memory.
+    Memory(Vec<u8>),
+    /// A value mapped read-only from a file, shared between clones.
+    #[cfg(feature = "mmap")]
+    Mapped(Arc<Mmap>),
+}
+
+impl ImmutableDataValue {
+    /// Maps the contents of the file at `path`.
+    ///
+    /// The name of an `ImmutableData` holding the value is computed once, by
+    /// `ImmutableData::from_value`. If the file changes afterwards the name is stale: the data no
+    /// longer hashes to it, but nothing notices until it is validated.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated, by this process or any other, while the value
+    /// or any clone of it is alive. The mapping would see the changes, so bytes already read,
+    /// e.g. checked against the name, could differ when read again, and reading past the end of
+    /// a truncated file is undefined behaviour.
+    #[cfg(feature = "mmap")]
+    #[allow(unsafe_code)]
+    pub unsafe fn map_file<P: AsRef<Path>>(path: P) -> Result<ImmutableDataValue, Error> {
+        let file = File::open(path)?;
+        // An empty file cannot be mapped.
+        if file.metadata()?.len() == 0 {
+            return Ok(ImmutableDataValue::Memory(vec![]));
+        }
+        Ok(ImmutableDataValue::Mapped(Arc::new(Mmap::map(&file)?)))
+    }
+
+    /// Returns the bytes of the value without copying them.
+    pub fn as_slice(&self) -> &[u8] {
+        match *self {
+            ImmutableDataValue::Memory(ref value) => &value[..],
+            #[cfg(feature = "mmap")]
+            ImmutableDataValue::Mapped(ref mmap) => &mmap[..],
+        }
+    }
+
+    /// Returns whether the value is mapped from a file.
+    pub fn is_mapped(&self) -> bool {
+        match *self {
+            ImmutableDataValue::Memory(_) => false,
+            #[cfg(feature = "mmap")]
+            ImmutableDataValue::Mapped(_) => true,
+        }
+    }
+
+    /// Returns the bytes of the value, copying them if it is mapped.
+    pub fn into_vec(self) -> Vec<u8> {
+        match self {
+            ImmutableDataValue::Memory(value) => value,
+            #[cfg(feature = "mmap")]
+            ImmutableDataValue::Mapped(mmap) => mmap.to_vec(),
+        }
+    }
+}
+
+impl Deref for ImmutableDataValue {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl From<Vec<u8>> for ImmutableDataValue {
+    fn from(value: Vec<u8>) -> ImmutableDataValue {
+        ImmutableDataValue::Memory(value)
+    }
+}
+
+impl PartialEq for ImmutableDataValue {
+    fn eq(&self, other: &ImmutableDataValue) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl Eq for ImmutableDataValue {}
+
+impl PartialOrd for ImmutableDataValue {
+    fn partial_cmp(&self, other: &ImmutableDataValue) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ImmutableDataValue {
+    fn cmp(&self, other: &ImmutableDataValue) -> Ordering {
+        self.as_slice().cmp(other.as_slice())
+    }
+}
+
+impl Hash for ImmutableDataValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_slice().hash(state)
+    }
+}
+
+#[cfg(feature = "serialisation")]
+impl Encodable for ImmutableDataValue {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), E::Error> {
+        self.as_slice().encode(encoder)
+    }
+}
+
+#[cfg(feature = "serialisation")]
+impl Decodable for ImmutableDataValue {
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<ImmutableDataValue, D::Error> {
+        Ok(ImmutableDataValue::Memory(Decodable::decode(decoder)?))
+    }
+}
+
+impl Debug for ImmutableDataValue {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter,
+               "ImmutableDataValue {{ len: {}, mapped: {} }}",
+               self.len(),
+               self.is_mapped())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serialisation::{deserialise, serialise};
+
+    #[test]
+    fn serialises_as_vec() {
+        let bytes: Vec<u8> = (0..100).collect();
+        let value = ImmutableDataValue::from(bytes.clone());
+        assert_eq!(value.as_slice(), &bytes[..]);
+        assert_eq!(unwrap!(serialise(&value)), unwrap!(serialise(&bytes)));
+        let parsed: ImmutableDataValue = unwrap!(deserialise(&unwrap!(serialise(&bytes))));
+        assert_eq!(parsed, value);
+        assert!(!parsed.is_mapped());
+        assert_eq!(parsed.into_vec(), bytes);
+    }
+
+    #[cfg(all(feature = "fs", feature = "mmap"))]
+    #[test]
+    #[allow(unsafe_code)]
+    fn mapped_file() {
+        use std::fs::File;
+        use std::io::Write;
+        use tempdir::TempDir;
+
+        let dir = unwrap!(TempDir::new("immutable_data_value"));
+        let path = dir.path().join("chunk");
+        let bytes: Vec<u8> = (0..10000).map(|i| i as u8).collect();
+        unwrap!(unwrap!(File::create(&path)).write_all(&bytes));
+
+        // Nothing writes to the file until the value is dropped.
+        {
+            let value = unwrap!(unsafe { ImmutableDataValue::map_file(&path) });
+            assert!(value.is_mapped());
+            assert_eq!(value, ImmutableDataValue::from(bytes.clone()));
+            assert_eq!(unwrap!(serialise(&value)), unwrap!(serialise(&bytes)));
+        }
+
+        unwrap!(File::create(&path));
+        assert!(!unwrap!(unsafe { ImmutableDataValue::map_file(&path) }).is_mapped());
+    }
+}
@@ -0,0 +1,154 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Ledgers
+//! The history of ledger `StructuredData`, every version committing to the `ledger_hash` of the
+//! version before it. Holding the whole history lets anyone check that no version was rewritten
+//! or left out, starting from the first version alone.
+
+use data::StructuredData;
+use error::Error;
+
+/// The versions of a ledger `StructuredData`, oldest first.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serialisation", derive(RustcEncodable, RustcDecodable))]
+pub struct Ledger {
+    entries: Vec<StructuredData>,
+}
+
+impl Ledger {
+    /// Starts a ledger with its first version, which must be ledger data at version 0.
+    pub fn new(first: StructuredData) -> Result<Ledger, Error> {
+        if !first.ledger() || first.version() != 0 {
+            return Err(Error::InvalidVersion);
+        }
+        Ok(Ledger { entries: vec![first] })
+    }
+
+    /// Appends `successor` if it is a valid update of the latest version.
+    pub fn push(&mut self, successor: StructuredData) -> Result<(), Error> {
+        self.latest().validate_self_against_successor(&successor)?;
+        self.entries.push(successor);
+        Ok(())
+    }
+
+    /// Returns the latest version.
+    pub fn latest(&self) -> &StructuredData {
+        // A ledger is never empty.
+        &self.entries[self.entries.len() - 1]
+    }
+
+    /// Returns all versions, oldest first.
+    pub fn entries(&self) -> &[StructuredData] {
+        &self.entries
+    }
+
+    /// Checks the whole history, e.g. after receiving it from a peer.
+    pub fn verify_chain(&self) -> Result<(), Error> {
+        verify_chain(&self.entries)
+    }
+}
+
+/// Checks `entries`, oldest first, form the history of ledger data: the first is at version 0
+/// and every other one is a valid update of, and so hash-linked to, the one before it.
+pub fn verify_chain(entries: &[StructuredData]) -> Result<(), Error> {
+    match entries.first() {
+        Some(first) if first.ledger() && first.version() == 0 => (),
+        _ => return Err(Error::InvalidVersion),
+    }
+    for pair in entries.windows(2) {
+        pair[0].validate_self_against_successor(&pair[1])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::StructuredData;
+    use error::Error;
+    use rand;
+    use test_utils;
+
+    #[test]
+    fn hash_linked_history() {
+        let keys: Vec<_> = (0..3).map(|_| test_utils::gen_keypair()).collect();
+        let first = unwrap!(StructuredData::new(0,
+                                                rand::random(),
+                                                0,
+                                                b"genesis".to_vec(),
+                                                vec![keys[0].0],
+                                                vec![],
+                                                Some(&keys[0].1),
+                                                true));
+        let mut ledger = unwrap!(Ledger::new(first));
+        for i in 1..keys.len() {
+            let successor = unwrap!(ledger.latest().transfer_ownership(vec![keys[i].0],
+                                                                       &keys[i - 1].1));
+            assert_eq!(successor.previous_hash(),
+                       Some(unwrap!(ledger.latest().ledger_hash())));
+            unwrap!(ledger.push(successor));
+        }
+        assert_eq!(ledger.entries().len(), 3);
+        unwrap!(ledger.verify_chain());
+
+        // Rewriting an earlier version breaks the link from the one after it, even though the
+        // rewritten version is itself a valid update.
+        let mut entries = ledger.entries().to_vec();
+        let mut rewritten = unwrap!(StructuredData::new(0,
+                                                        *entries[0].name(),
+                                                        1,
+                                                        b"rewritten".to_vec(),
+                                                        vec![keys[1].0],
+                                                        vec![keys[0].0],
+                                                        None,
+                                                        true));
+        rewritten.set_previous_hash(Some(unwrap!(entries[0].ledger_hash())));
+        let _ = unwrap!(rewritten.add_signature(&keys[0].1));
+        unwrap!(entries[0].validate_self_against_successor(&rewritten));
+        entries[1] = rewritten;
+        match verify_chain(&entries) {
+            Err(Error::NoLink) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // Leaving a version out breaks the version sequence.
+        assert!(verify_chain(&[entries[0].clone(), entries[2].clone()]).is_err());
+        assert!(verify_chain(&[]).is_err());
+    }
+
+    #[test]
+    fn unlinked_successor() {
+        let keys = test_utils::gen_keypair();
+        let new_ledger_data = |version| {
+            StructuredData::new(0,
+                                [1; 32],
+                                version,
+                                vec![],
+                                vec![keys.0],
+                                vec![],
+                                Some(&keys.1),
+                                true)
+        };
+        let mut ledger = unwrap!(Ledger::new(unwrap!(new_ledger_data(0))));
+        match ledger.push(unwrap!(new_ledger_data(1))) {
+            Err(Error::NoLink) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert!(Ledger::new(unwrap!(new_ledger_data(1))).is_err());
+    }
+}
@@ -0,0 +1,40 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use data::DataIdentifier;
+
+/// What a cache needs to know about a version of some data without holding its content, e.g. to
+/// answer a conditional request for the data only if it differs from a cached copy.
+#[derive(Hash, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug)]
+#[cfg_attr(feature = "serialisation", derive(RustcEncodable, RustcDecodable))]
+pub struct DataMetadata {
+    /// Identifier of the data.
+    pub identifier: DataIdentifier,
+    /// Version of the data.
+    pub version: u64,
+    /// Hash of the content, which changes whenever the content, owners or version do.
+    pub content_hash: [u8; 32],
+    /// Size of the payload.
+    pub payload_size: u64,
+}
+
+impl DataMetadata {
+    /// Returns whether the data differs from the version whose content hash is `content_hash`.
+    pub fn differs_from(&self, content_hash: &[u8; 32]) -> bool {
+        self.content_hash != *content_hash
+    }
+}
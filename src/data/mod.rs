@@ -17,7 +17,7 @@
 
 //! # Data types
 //!
-//! These data types fall into three categories
+//! These data types fall into four categories
 //!
 //! - Immutable : This data type has fixed content and self validates. The name is derived from the
 //!               hash of the content.
@@ -25,66 +25,327 @@
 //! - Structured : This is `owned` data and reflects a location in the address space that contains
 //!                content that may be altered by the owner(s).
 //!
-//! - Plain : This is data with no fixed content or name. It is currently unused in SAFE
+//! - Mutable : This is `owned` key-value data whose entries are mutated individually by the
+//!             owner(s) or by keys they have granted permissions to.
+//!
+//! - Plain : This is data with no fixed content or name, neither of which is validated.
 //!
 
 
+/// Account packets stored as structured data at a name derived from a keyword and PIN
+#[cfg(all(feature = "serialisation", feature = "sodium"))]
+pub mod account_packet;
+/// Data with its identifier and local name worked out once
+pub mod addressed_data;
+/// Logs of signed entries which can only be appended to
+#[cfg(feature = "serialisation")]
+pub mod append_only_log;
+/// `quickcheck::Arbitrary` instances of the data types
+#[cfg(feature = "arbitrary")]
+mod arbitrary;
+/// Validation of batches of data
+pub mod batch;
+/// Canonical encoding of the bytes owners sign
+pub mod canonical;
+/// Encodings of data to and from bytes
+pub mod codec;
+/// Compact wire format for constrained links
+#[cfg(feature = "serialisation")]
+pub mod compact;
+/// Licensing of payload encryption keys to specific readers
+#[cfg(all(feature = "serialisation", feature = "sodium"))]
+pub mod capabilities;
+/// Reading content split over immutable chunks as one stream
+pub mod chunked_reader;
+/// Content-defined chunking of content into immutable chunks
+pub mod chunker;
+/// Compression of payloads
+pub mod compression;
+/// Competing successors of structured data and the one holders settle on
+pub mod conflict_set;
+/// Costs of storing data
+pub mod cost;
+/// Behaviour common to every kind of data
+pub mod data_like;
+/// Maps of files split into immutable chunks
+pub mod data_map;
+/// Directories of files stored as structured data
+#[cfg(feature = "serialisation")]
+pub mod directory;
+/// Resolution of human-readable domains to the data of their services
+#[cfg(feature = "serialisation")]
+pub mod dns_record;
+/// Versioned serialisation envelopes for stored data
+#[cfg(feature = "serialisation")]
+pub mod envelope;
+/// JSON and CBOR export of the data types
+#[cfg(all(feature = "serialisation", any(feature = "json", feature = "cbor")))]
+mod export;
+/// Sets of data identifiers ordered by distance to a name
+pub mod identifier_set;
 /// Data that will not change it's contents
 pub mod immutable_data;
+/// Values of immutable data, held in memory or mapped from a file
+pub mod immutable_data_value;
+/// Hash-linked history of ledger structured data
+pub mod ledger;
+/// Summaries of data versions for caching
+pub mod metadata;
+/// Key-value data with per-entry versions and per-key permissions
+pub mod mutable_data;
+/// Sorted, deduplicated owner keys of structured data
+pub mod owners;
+/// Proposed multi-owner updates passed between owners while they sign
+pub mod pending_update;
+/// Unnamed, free-form data
+pub mod plain_data;
+/// Validators of structured data content by type tag
+pub mod schema;
+/// Collection of owner signatures for multi-owner updates
+pub mod signing_session;
 /// Data that will retain it's name but allow dynamic content or transfer of ownership
 pub mod structured_data;
+/// Structured data holding an application's own serialisable type
+#[cfg(feature = "serialisation")]
+pub mod typed_structured_data;
+/// Decoding of bytes received from untrusted sources
+#[cfg(feature = "serialisation")]
+pub mod untrusted;
+/// Test vectors of the serialised data types
+#[cfg(all(feature = "serialisation", any(test, feature = "test_utils")))]
+pub mod vectors;
+/// Versions which only move forward
+pub mod version;
+/// Structured data holding the names of the immutable data of each version of a blob
+#[cfg(feature = "serialisation")]
+pub mod versioned_blob;
+/// State machine tests of the structured data update rules
+#[cfg(test)]
+mod model;
 
-pub use data::immutable_data::ImmutableData;
-pub use data::structured_data::{MAX_BYTES, StructuredData};
+#[cfg(all(feature = "serialisation", feature = "sodium"))]
+pub use data::account_packet::AccountPacket;
+pub use data::addressed_data::AddressedData;
+#[cfg(feature = "serialisation")]
+pub use data::append_only_log::{AppendOnlyLog, LogControl, LogEntry};
+pub use data::batch::validate_batch;
+#[cfg(all(feature = "serialisation", feature = "sodium"))]
+pub use data::capabilities::CapabilityList;
+pub use data::chunked_reader::ChunkedReader;
+pub use data::chunker::chunk_cdc;
+#[cfg(feature = "serialisation")]
+pub use data::codec::DefaultCodec;
+pub use data::codec::Codec;
+#[cfg(feature = "serialisation")]
+pub use data::compact::CompactCodec;
+pub use data::compression::Compression;
+pub use data::conflict_set::ConflictSet;
+pub use data::cost::{CostPolicy, DEFAULT_QUOTA_POLICY, DefaultCostPolicy, QuotaPolicy};
+pub use data::data_like::DataLike;
+pub use data::data_map::{ChunkDetails, DataMap};
+#[cfg(feature = "serialisation")]
+pub use data::directory::Directory;
+#[cfg(feature = "serialisation")]
+pub use data::dns_record::DnsRecord;
+pub use data::identifier_set::IdentifierSet;
+pub use data::immutable_data::{ImmutableData, ImmutableDataHasher, ImmutableDataKind,
+                               ImmutableDataRef};
+pub use data::immutable_data_value::ImmutableDataValue;
+pub use data::ledger::Ledger;
+pub use data::metadata::DataMetadata;
+pub use data::mutable_data::{Action, EntryAction, MutableData, PermissionSet, Value};
+pub use data::owners::Owners;
+pub use data::pending_update::PendingUpdate;
+pub use data::plain_data::PlainData;
+pub use data::schema::SchemaRegistry;
+pub use data::signing_session::SigningSession;
+pub use data::structured_data::{MAX_BYTES, MAX_OWNERS, SignaturePolicy, StructuredData,
+                                 StructuredDataPatch, ValidationState, Winner};
+#[cfg(feature = "serialisation")]
+pub use data::typed_structured_data::TypedStructuredData;
+pub use data::version::Version;
+#[cfg(feature = "serialisation")]
+pub use data::versioned_blob::VersionedBlob;
 
 
+use data::canonical::CanonicalWriter;
 use error::Error;
-use maidsafe_utilities::serialisation::serialise;
-use std::fmt::{self, Debug, Formatter};
-use tiny_keccak::Keccak;
+use hex;
+#[cfg(feature = "metrics")]
+use metrics;
+use name_hasher::{NameHasher, Sha3NameHasher};
+#[cfg(any(test, feature = "rand"))]
+use rand::Rng;
+#[cfg(feature = "serialisation")]
+use serialisation::{deserialise_from, serialise, serialise_into};
+use sha3::hash;
+use std::fmt::{self, Debug, Display, Formatter};
+#[cfg(feature = "serialisation")]
+use std::io::{Read, Write};
+use std::str::FromStr;
 
 /// Data types handled in a SAFE
-#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, RustcEncodable, RustcDecodable)]
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[cfg_attr(feature = "serialisation", derive(RustcEncodable, RustcDecodable))]
 pub enum Data {
     /// `StructuredData` data type.
     Structured(StructuredData),
     /// `ImmutableData` data type.
     Immutable(ImmutableData),
+    /// `PlainData` data type.
+    Plain(PlainData),
+    /// `MutableData` data type.
+    Mutable(MutableData),
 }
 
 impl Data {
-    /// Return data name.
-    pub fn name(&self) -> &[u8; 32] {
+    /// Returns the contained data as a `DataLike`.
+    pub fn as_data_like(&self) -> &DataLike {
         match *self {
-            Data::Structured(ref data) => data.name(),
-            Data::Immutable(ref data) => data.name(),
+            Data::Structured(ref data) => data,
+            Data::Immutable(ref data) => data,
+            Data::Plain(ref data) => data,
+            Data::Mutable(ref data) => data,
         }
     }
 
+    /// Return data name.
+    pub fn name(&self) -> &[u8; 32] {
+        self.as_data_like().name()
+    }
+
     /// Return data identifier.
     pub fn identifier(&self) -> DataIdentifier {
-        match *self {
-            Data::Structured(ref data) => data.identifier(),
-            Data::Immutable(ref data) => data.identifier(),
-        }
+        self.as_data_like().identifier()
     }
 
     /// Return data size.
     pub fn payload_size(&self) -> usize {
+        self.as_data_like().payload_size()
+    }
+
+    /// Returns the exact size of the serialised data, including the variant index.
+    #[cfg(feature = "serialisation")]
+    pub fn serialised_size(&self) -> Result<usize, Error> {
+        Ok(serialise(self)?.len())
+    }
+
+    /// Returns the size of the serialised data, worked out from the fields without serialising.
+    pub fn estimated_size(&self) -> usize {
+        // The variant index is serialised as a `u32`.
+        4 +
+        match *self {
+            Data::Structured(ref data) => data.estimated_size(),
+            Data::Immutable(ref data) => data.estimated_size(),
+            Data::Plain(ref data) => data.estimated_size(),
+            Data::Mutable(ref data) => data.estimated_size(),
+        }
+    }
+
+    /// Checks the invariants of the contained data, including that the name of immutable data is
+    /// the hash of its value. Data should be validated before it is accepted into any store.
+    pub fn validate(&self) -> Result<(), Error> {
+        let result = self.as_data_like().validate();
+        #[cfg(feature = "metrics")]
+        metrics::record_validation(&result);
+        result
+    }
+
+    /// Returns the cost, in units, of storing this data under the `DefaultCostPolicy`.
+    pub fn store_cost(&self) -> u64 {
+        self.store_cost_with(&DefaultCostPolicy::default())
+    }
+
+    /// Returns the cost, in units, of storing this data under `policy`.
+    pub fn store_cost_with<P: CostPolicy>(&self, policy: &P) -> u64 {
+        policy.cost(self)
+    }
+
+    /// Returns the units charged for storing this data under the quota `policy` agreed by a
+    /// group of vaults.
+    pub fn charge_units(&self, policy: &QuotaPolicy) -> u64 {
+        policy.cost(self)
+    }
+
+    /// Returns the payload without copying it: the value of immutable and plain data and the data
+    /// field of structured data. Mutable data has no single payload, so this is empty for it.
+    pub fn payload_bytes(&self) -> &[u8] {
         match *self {
-            Data::Structured(ref data) => data.payload_size(),
-            Data::Immutable(ref data) => data.payload_size(),
+            Data::Structured(ref data) => data.get_data(),
+            Data::Immutable(ref data) => data.value(),
+            Data::Plain(ref data) => data.value(),
+            Data::Mutable(_) => &[],
+        }
+    }
+
+    /// Encodes this data in the compact wire format, for constrained links.
+    #[cfg(feature = "serialisation")]
+    pub fn encode_compact(&self) -> Result<Vec<u8>, Error> {
+        compact::serialise(self)
+    }
+
+    /// Decodes data encoded by `encode_compact`.
+    #[cfg(feature = "serialisation")]
+    pub fn decode_compact(bytes: &[u8]) -> Result<Data, Error> {
+        compact::deserialise(bytes)
+    }
+
+    /// Decodes and validates data received from an untrusted source, failing rather than
+    /// panicking on malformed bytes.
+    #[cfg(feature = "serialisation")]
+    pub fn decode_untrusted(bytes: &[u8]) -> Result<Data, Error> {
+        let data: Data = untrusted::deserialise(bytes)?;
+        data.validate()?;
+        Ok(data)
+    }
+
+    /// Writes this data to `writer` in its serialised form. The value of immutable data is
+    /// written straight from where it is held rather than first copied into an encoder's buffer.
+    #[cfg(feature = "serialisation")]
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        match *self {
+            Data::Immutable(ref data) => {
+                // The variant index of `Data::Immutable`, as a big-endian `u32`.
+                writer.write_all(&[0, 0, 0, 1])?;
+                data.write_to(writer)
+            }
+            _ => Ok(serialise_into(self, writer)?),
+        }
+    }
+
+    /// Reads data in its serialised form from `reader`, e.g. as written by `write_to`, reading no
+    /// further than its end. The value of immutable data is read in blocks rather than byte by
+    /// byte; other data is decoded as it is read, so `reader` should be buffered.
+    #[cfg(feature = "serialisation")]
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Data, Error> {
+        let mut variant = [0u8; 4];
+        reader.read_exact(&mut variant)?;
+        match variant.iter().fold(0u32, |index, &byte| (index << 8) | byte as u32) {
+            0 => Ok(Data::Structured(deserialise_from(reader)?)),
+            1 => Ok(Data::Immutable(ImmutableData::read_from(reader)?)),
+            2 => Ok(Data::Plain(deserialise_from(reader)?)),
+            3 => Ok(Data::Mutable(deserialise_from(reader)?)),
+            index => Err(Error::Encoding(format!("invalid data variant {}", index))),
         }
     }
 }
 
-#[derive(Hash, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, RustcEncodable, RustcDecodable)]
+#[derive(Hash, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[cfg_attr(feature = "serialisation", derive(RustcEncodable, RustcDecodable))]
 /// An identifier to address a data chunk.
+///
+/// Its textual form, used by `Display` and `FromStr`, is a URI naming the kind of data, the type
+/// tag where there is one and the name in hex, e.g. `safe://immutable/<name>` or
+/// `safe://structured/<type tag>/<name>`.
 pub enum DataIdentifier {
     /// Data request, (Identifier, TypeTag) pair for name resolution, for StructuredData.
     Structured([u8; 32], u64),
     /// Data request, (Identifier), for `ImmutableData`.
     Immutable([u8; 32]),
+    /// Request for `PlainData`.
+    Plain([u8; 32]),
+    /// Data request, (Identifier, TypeTag) pair for name resolution, for `MutableData`.
+    Mutable([u8; 32], u64),
 }
 
 impl Debug for Data {
@@ -92,46 +353,171 @@ impl Debug for Data {
         match *self {
             Data::Structured(ref data) => data.fmt(formatter),
             Data::Immutable(ref data) => data.fmt(formatter),
+            Data::Plain(ref data) => data.fmt(formatter),
+            Data::Mutable(ref data) => data.fmt(formatter),
         }
     }
 }
 
+/// Scheme of the textual form of a `DataIdentifier`.
+pub const DATA_IDENTIFIER_SCHEME: &'static str = "safe://";
+
+impl Display for DataIdentifier {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        match *self {
+            DataIdentifier::Structured(ref name, tag) => {
+                write!(formatter,
+                       "{}structured/{}/{}",
+                       DATA_IDENTIFIER_SCHEME,
+                       tag,
+                       hex::to_hex(name))
+            }
+            DataIdentifier::Immutable(ref name) => {
+                write!(formatter, "{}immutable/{}", DATA_IDENTIFIER_SCHEME, hex::to_hex(name))
+            }
+            DataIdentifier::Plain(ref name) => {
+                write!(formatter, "{}plain/{}", DATA_IDENTIFIER_SCHEME, hex::to_hex(name))
+            }
+            DataIdentifier::Mutable(ref name, tag) => {
+                write!(formatter,
+                       "{}mutable/{}/{}",
+                       DATA_IDENTIFIER_SCHEME,
+                       tag,
+                       hex::to_hex(name))
+            }
+        }
+    }
+}
+
+impl FromStr for DataIdentifier {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<DataIdentifier, Error> {
+        let invalid = || Error::Encoding(format!("invalid data identifier {:?}", text));
+        if !text.starts_with(DATA_IDENTIFIER_SCHEME) {
+            return Err(invalid());
+        }
+        let parts: Vec<&str> = text[DATA_IDENTIFIER_SCHEME.len()..].split('/').collect();
+        let name = |hex: &str| -> Result<[u8; 32], Error> {
+            let bytes = hex::from_hex(hex).ok_or_else(&invalid)?;
+            if bytes.len() != 32 {
+                return Err(invalid());
+            }
+            let mut name = [0u8; 32];
+            name.copy_from_slice(&bytes);
+            Ok(name)
+        };
+        let tag = |text: &str| text.parse::<u64>().map_err(|_| invalid());
+        match (parts[0], parts.len()) {
+            ("structured", 3) => Ok(DataIdentifier::Structured(name(parts[2])?, tag(parts[1])?)),
+            ("immutable", 2) => Ok(DataIdentifier::Immutable(name(parts[1])?)),
+            ("plain", 2) => Ok(DataIdentifier::Plain(name(parts[1])?)),
+            ("mutable", 3) => Ok(DataIdentifier::Mutable(name(parts[2])?, tag(parts[1])?)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Domain-separation prefix of the names made by `namespaced_name`.
+pub const NAMESPACED_NAME_DOMAIN: &'static [u8] = b"data_chain/namespaced_name/1";
+
+/// Returns the name of `id` in the namespace `prefix`, e.g. a user's inbox address with the user
+/// as the namespace, so applications can carve deterministic sub-namespaces out of the address
+/// space.
+///
+/// The prefix and id are each written with their length, so no two pairs hash the same input and
+/// names in one namespace are unrelated to those in any other.
+pub fn namespaced_name(prefix: &[u8], id: &[u8]) -> [u8; 32] {
+    let mut writer = CanonicalWriter::new(NAMESPACED_NAME_DOMAIN);
+    let _ = writer.write_bytes(prefix).write_bytes(id);
+    hash(&writer.into_bytes())
+}
+
 impl DataIdentifier {
+    /// Returns the identifier of structured data of type `tag` at a name drawn from `rng`.
+    #[cfg(any(test, feature = "rand"))]
+    pub fn random_structured<R: Rng>(tag: u64, rng: &mut R) -> DataIdentifier {
+        DataIdentifier::Structured(rng.gen(), tag)
+    }
+
+    /// Returns the identifier of structured data of type `tag` at `namespaced_name(prefix, id)`.
+    pub fn namespaced_structured(tag: u64, prefix: &[u8], id: &[u8]) -> DataIdentifier {
+        DataIdentifier::Structured(namespaced_name(prefix, id), tag)
+    }
+
+    /// Encodes this identifier in the compact wire format, for constrained links.
+    #[cfg(feature = "serialisation")]
+    pub fn encode_compact(&self) -> Result<Vec<u8>, Error> {
+        compact::serialise(self)
+    }
+
+    /// Decodes an identifier encoded by `encode_compact`.
+    #[cfg(feature = "serialisation")]
+    pub fn decode_compact(bytes: &[u8]) -> Result<DataIdentifier, Error> {
+        compact::deserialise(bytes)
+    }
+
     /// DataIdentifier name.
     pub fn name(&self) -> &[u8; 32] {
         match *self {
             DataIdentifier::Structured(ref name, _) |
-            DataIdentifier::Immutable(ref name) => name,
+            DataIdentifier::Immutable(ref name) |
+            DataIdentifier::Plain(ref name) |
+            DataIdentifier::Mutable(ref name, _) => name,
         }
     }
     /// check for ledger
     /// DataIdentifier local name (for store).
-    pub fn local_name(&self) -> Result<[u8; 32], Error> {
+    pub fn local_name(&self) -> [u8; 32] {
+        self.local_name_with::<Sha3NameHasher>()
+    }
+
+    /// DataIdentifier local name (for store), derived with `H`.
+    ///
+    /// The type tag is hashed as its eight big-endian bytes, as it is serialised, so the names
+    /// are those derived from the serialised tag before, without allocating.
+    pub fn local_name_with<H: NameHasher>(&self) -> [u8; 32] {
         match *self {
-            DataIdentifier::Structured(ref name, ref tag) => {
-                let mut sha3 = Keccak::new_sha3_256();
-                sha3.update(name);
-                sha3.update(&serialise(tag)?);
-                let mut res: [u8; 32] = [0; 32];
-                sha3.finalize(&mut res);
-                Ok(res)
+            DataIdentifier::Structured(ref name, tag) => {
+                let mut hasher = H::default();
+                hasher.update(name);
+                hasher.update(&tag_bytes(tag));
+                hasher.finalize()
             }
-            DataIdentifier::Immutable(name) => Ok(name),
+            DataIdentifier::Mutable(ref name, tag) => {
+                // Distinguished from structured data with the same name and type tag.
+                let mut hasher = H::default();
+                hasher.update(b"mutable");
+                hasher.update(name);
+                hasher.update(&tag_bytes(tag));
+                hasher.finalize()
+            }
+            DataIdentifier::Immutable(name) |
+            DataIdentifier::Plain(name) => name,
         }
     }
 }
 
+/// The big-endian bytes of a type tag.
+fn tag_bytes(tag: u64) -> [u8; 8] {
+    let mut bytes = [0u8; 8];
+    for (index, byte) in bytes.iter_mut().enumerate() {
+        *byte = (tag >> (8 * (7 - index))) as u8;
+    }
+    bytes
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use rand;
-    use rust_sodium::crypto::sign;
+    use rand::{self, SeedableRng, XorShiftRng};
     use sha3::hash;
+    use test_utils;
 
     #[test]
     fn data_name() {
         // name() resolves correctly for StructuredData
-        let keys = sign::gen_keypair();
+        let keys = test_utils::gen_keypair();
         let owner_keys = vec![keys.0];
         match StructuredData::new(0,
                                   rand::random(),
@@ -160,12 +546,17 @@ mod tests {
         assert_eq!(immutable_data.identifier(),
                    DataIdentifier::Immutable(*immutable_data.name()));
 
+        // name() resolves correctly for PlainData
+        let name = hash(&[]);
+        let plain_data = PlainData::new(name, vec![]);
+        assert_eq!(plain_data.name(), Data::Plain(plain_data.clone()).name());
+        assert_eq!(plain_data.identifier(), DataIdentifier::Plain(name));
     }
 
     #[test]
     fn data_payload_size() {
         // payload_size() resolves correctly for StructuredData
-        let keys = ::rust_sodium::crypto::sign::gen_keypair();
+        let keys = test_utils::gen_keypair();
         let owner_keys = vec![keys.0];
         match StructuredData::new(0,
                                   rand::random(),
@@ -188,6 +579,108 @@ mod tests {
         assert_eq!(immutable_data.payload_size(),
                    Data::Immutable(immutable_data).payload_size());
 
+        // payload_size() resolves correctly for PlainData
+        let value = "plain data value".to_owned().into_bytes();
+        let plain_data = PlainData::new(hash(&value), value);
+        assert_eq!(plain_data.payload_size(),
+                   Data::Plain(plain_data).payload_size());
+    }
+
+    #[test]
+    fn data_payload_bytes() {
+        let value = "immutable data value".to_owned().into_bytes();
+        let data = Data::Immutable(ImmutableData::new(value.clone()));
+        assert_eq!(data.payload_bytes(), &value[..]);
+        assert_eq!(data.payload_bytes().len(), data.payload_size());
+
+        let data = Data::Plain(PlainData::new(hash(&value), value.clone()));
+        assert_eq!(data.payload_bytes(), &value[..]);
+    }
+
+    #[test]
+    fn data_validate() {
+        let keys = test_utils::gen_keypair();
+        let structured_data = unwrap!(StructuredData::new(0,
+                                                          rand::random(),
+                                                          0,
+                                                          vec![],
+                                                          vec![keys.0],
+                                                          vec![],
+                                                          Some(&keys.1),
+                                                          false));
+        assert!(Data::Structured(structured_data).validate().is_ok());
+        assert!(Data::Plain(PlainData::new(rand::random(), vec![0; 2 * MAX_BYTES]))
+            .validate()
+            .is_ok());
+
+        let data = Data::Immutable(ImmutableData::new(vec![0; ImmutableData::MAX_BYTES + 1]));
+        assert_eq!(data.as_data_like().name(), data.name());
+        match data.validate() {
+            Err(Error::DataTooLarge) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn data_sizes() {
+        let keys = test_utils::gen_keypair();
+        let structured_data = unwrap!(StructuredData::new(0,
+                                                          rand::random(),
+                                                          0,
+                                                          vec![1; 10],
+                                                          vec![keys.0],
+                                                          vec![],
+                                                          Some(&keys.1),
+                                                          false));
+        for data in vec![Data::Structured(structured_data),
+                         Data::Immutable(ImmutableData::new(vec![2; 20])),
+                         Data::Plain(PlainData::new(rand::random(), vec![3; 30]))] {
+            assert_eq!(data.estimated_size(), unwrap!(data.serialised_size()));
+        }
+    }
+
+    #[test]
+    fn streamed_serialisation() {
+        let keys = test_utils::gen_keypair();
+        let structured_data = unwrap!(StructuredData::new(0,
+                                                          rand::random(),
+                                                          0,
+                                                          vec![1; 10],
+                                                          vec![keys.0],
+                                                          vec![],
+                                                          Some(&keys.1),
+                                                          false));
+        let compressed = unwrap!(ImmutableData::with_compression(vec![3; 30],
+                                                                 Some(Compression::Deflate)));
+        let items = vec![Data::Structured(structured_data),
+                         Data::Immutable(ImmutableData::new(vec![2; 100_000])),
+                         Data::Immutable(compressed),
+                         Data::Plain(PlainData::new(rand::random(), vec![4; 40]))];
+        let mut stream = Vec::new();
+        for data in &items {
+            let mut written = Vec::new();
+            unwrap!(data.write_to(&mut written));
+            assert_eq!(written, unwrap!(serialise(data)));
+            stream.extend_from_slice(&written);
+        }
+
+        let mut reader = &stream[..];
+        for data in &items {
+            let read = unwrap!(Data::read_from(&mut reader));
+            assert_eq!(read, *data);
+            assert_eq!(read.name(), data.name());
+        }
+        assert!(reader.is_empty());
+
+        let truncated = unwrap!(serialise(&items[1]));
+        match Data::read_from(&mut &truncated[..truncated.len() - 2]) {
+            Err(Error::Encoding(_)) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match Data::read_from(&mut &[0, 0, 0, 9][..]) {
+            Err(Error::Encoding(_)) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
     }
 
     #[test]
@@ -201,5 +694,83 @@ mod tests {
         // name() resolves correctly for ImmutableData
         assert_eq!(&name, DataIdentifier::Immutable(name).name());
 
+        // name() resolves correctly for PlainData
+        assert_eq!(&name, DataIdentifier::Plain(name).name());
+
+        // name() resolves correctly for MutableData
+        assert_eq!(&name, DataIdentifier::Mutable(name, tag).name());
+
+        // local_name() differs between structured and mutable data of the same name and tag
+        assert_ne!(DataIdentifier::Structured(name, tag).local_name(),
+                   DataIdentifier::Mutable(name, tag).local_name());
+        assert_eq!(DataIdentifier::Structured(name, tag).local_name(),
+                   DataIdentifier::Structured(name, tag).local_name_with::<Sha3NameHasher>());
+    }
+
+    #[test]
+    fn local_names_unchanged() {
+        // The names must stay those derived from the serialised type tag, as stored before.
+        let name: [u8; 32] = rand::random();
+        let tag: u64 = 0x0102_0304_0506_0708;
+        let mut structured = name.to_vec();
+        structured.extend_from_slice(&unwrap!(serialise(&tag)));
+        assert_eq!(DataIdentifier::Structured(name, tag).local_name(), hash(&structured));
+        let mut mutable = b"mutable".to_vec();
+        mutable.extend_from_slice(&structured);
+        assert_eq!(DataIdentifier::Mutable(name, tag).local_name(), hash(&mutable));
+        assert_eq!(DataIdentifier::Immutable(name).local_name(), name);
+    }
+
+    #[test]
+    fn data_identifier_text() {
+        let name: [u8; 32] = rand::random();
+        let identifiers = [DataIdentifier::Structured(name, 7),
+                           DataIdentifier::Immutable(name),
+                           DataIdentifier::Plain(name),
+                           DataIdentifier::Mutable(name, u64::max_value())];
+        for identifier in &identifiers {
+            assert_eq!(unwrap!(identifier.to_string().parse::<DataIdentifier>()), *identifier);
+        }
+        assert_eq!(DataIdentifier::Structured([0xab; 32], 7).to_string(),
+                   format!("safe://structured/7/{}", "ab".repeat(32)));
+
+        for text in &["immutable/00",
+                      "safe://immutable/00",
+                      "safe://immutable/zz",
+                      "safe://structured/x/00",
+                      "safe://plain",
+                      "safe://unknown/00"] {
+            match text.parse::<DataIdentifier>() {
+                Err(Error::Encoding(_)) => (),
+                result => panic!("Unexpected result {:?} for {}", result, text),
+            }
+        }
+    }
+
+    #[test]
+    fn namespaced_names() {
+        let inbox = namespaced_name(b"alice", b"inbox");
+        assert_eq!(namespaced_name(b"alice", b"inbox"), inbox);
+        assert!(namespaced_name(b"bob", b"inbox") != inbox);
+        assert!(namespaced_name(b"alice", b"outbox") != inbox);
+        // Moving bytes between the prefix and the id gives another name.
+        assert!(namespaced_name(b"alic", b"einbox") != inbox);
+        assert!(namespaced_name(b"", b"aliceinbox") != namespaced_name(b"aliceinbox", b""));
+
+        let mut writer = CanonicalWriter::new(NAMESPACED_NAME_DOMAIN);
+        let _ = writer.write_bytes(b"alice").write_bytes(b"inbox");
+        assert_eq!(inbox, hash(&writer.into_bytes()));
+        assert_eq!(DataIdentifier::namespaced_structured(7, b"alice", b"inbox"),
+                   DataIdentifier::Structured(inbox, 7));
+
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        let identifier = DataIdentifier::random_structured(7, &mut rng);
+        match identifier {
+            DataIdentifier::Structured(_, 7) => (),
+            _ => panic!("Unexpected identifier {:?}", identifier),
+        }
+        assert!(DataIdentifier::random_structured(7, &mut rng) != identifier);
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        assert_eq!(DataIdentifier::random_structured(7, &mut rng), identifier);
     }
 }
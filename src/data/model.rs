@@ -0,0 +1,429 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Model tests
+//! State machine tests for `StructuredData` updates.
+//!
+//! Quickcheck generates sequences of operations (updates, transfers, deletes, soft deletes,
+//! changes of the signature policy or writers, updates by writers and malformed variants of
+//! these), applies them to a real `StructuredData`, which may start out frozen, and to an abstract
+//! model of the update rules, and checks both agree on whether every operation is accepted. After
+//! every operation the version must not have gone back and the type tag and name must be
+//! unchanged. A sequence on which they disagree is shrunk to a minimal one.
+
+use crypto::sign::{PublicKey, SecretKey};
+use data::{SignaturePolicy, StructuredData};
+use error::Error;
+use quickcheck::{QuickCheck, StdGen, TestResult};
+use rand::{self, Rng, SeedableRng, XorShiftRng};
+use std::cmp;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use test_utils;
+
+const KEY_POOL_SIZE: usize = 6;
+/// Number of sequences generated.
+const SEQUENCES: usize = 1000;
+/// Size sequences are generated with, bounding their number of operations.
+const MAX_OPS: usize = 16;
+/// Number of kinds of operation `op_of_kind` builds.
+const OP_KINDS: u8 = 17;
+/// One in this many sequences starts from frozen data.
+const FROZEN_ONE_IN: u8 = 8;
+
+/// Number of operations accepted over all sequences.
+static ACCEPTED: AtomicUsize = AtomicUsize::new(0);
+
+/// Signature policies covered by the model.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Policy {
+    Majority,
+    All,
+    Threshold(u64),
+}
+
+impl Policy {
+    fn required(self, owners: usize) -> usize {
+        match self {
+            Policy::Majority => (owners + 1) / 2,
+            Policy::All => owners,
+            Policy::Threshold(count) => cmp::max(count as usize, 1),
+        }
+    }
+
+    fn signature_policy(self) -> SignaturePolicy {
+        match self {
+            Policy::Majority => SignaturePolicy::Majority,
+            Policy::All => SignaturePolicy::All,
+            Policy::Threshold(count) => SignaturePolicy::Threshold(count),
+        }
+    }
+}
+
+/// Abstract state of a structured data item. Owners and writers are indices into the key pool.
+#[derive(Clone, Debug)]
+struct Model {
+    version: u64,
+    owners: Vec<usize>,
+    deleted: bool,
+    soft_deleted: bool,
+    ledger: bool,
+    policy: Policy,
+    writers: Vec<usize>,
+    frozen: bool,
+}
+
+/// A candidate successor described in terms of the model.
+#[derive(Debug)]
+struct Op {
+    name_changed: bool,
+    tag_changed: bool,
+    version: u64,
+    data: Vec<u8>,
+    current_owners: Vec<usize>,
+    previous_owners: Vec<usize>,
+    signers: Vec<usize>,
+    soft_deleted: bool,
+    policy: Policy,
+    writers: Vec<usize>,
+    frozen: bool,
+}
+
+impl Model {
+    /// The update rules, written independently of the implementation.
+    fn accepts(&self, op: &Op) -> bool {
+        if self.deleted || op.name_changed || op.tag_changed || op.version != self.version + 1 {
+            return false;
+        }
+        // Frozen data has no owners to sign an update, and only its first version is frozen.
+        if self.frozen || op.frozen {
+            return false;
+        }
+        if op.current_owners.is_empty() && (self.ledger || !op.data.is_empty()) {
+            return false;
+        }
+        // Only data emptied but still owned may be marked soft-deleted.
+        if op.soft_deleted && (!op.data.is_empty() || op.current_owners.is_empty()) {
+            return false;
+        }
+        let required_owners = if op.previous_owners.is_empty() {
+            &op.current_owners
+        } else {
+            &op.previous_owners
+        };
+        if *required_owners != self.owners {
+            return false;
+        }
+        // Soft-deleted data keeps its owners unless it is deleted for good.
+        if self.soft_deleted && !op.current_owners.is_empty() && op.current_owners != self.owners {
+            return false;
+        }
+        self.signed_by_owners(op, required_owners) || self.signed_by_writer(op)
+    }
+
+    /// Is `op` signed by enough of `required_owners` for the current policy.
+    fn signed_by_owners(&self, op: &Op, required_owners: &[usize]) -> bool {
+        // Repeated signers produce identical signatures, which invalidate the update.
+        if (1..op.signers.len()).any(|i| op.signers[..i].contains(&op.signers[i])) {
+            return false;
+        }
+        let owners = required_owners.len();
+        // A key rotation replacing one of several owners isn't approved by the replaced key.
+        let rotated_out = if owners > 1 {
+            self.rotated_out(op)
+        } else {
+            None
+        };
+        let required = match rotated_out {
+            Some(_) => cmp::min(self.policy.required(owners), owners - 1),
+            None => self.policy.required(owners),
+        };
+        let valid = op.signers
+            .iter()
+            .filter(|signer| required_owners.contains(signer) && Some(**signer) != rotated_out)
+            .count();
+        op.signers.len() >= required && valid >= required
+    }
+
+    /// Is `op` a change of nothing but the data, to data which is neither deleted nor emptied,
+    /// signed by one of the writers.
+    fn signed_by_writer(&self, op: &Op) -> bool {
+        !self.writers.is_empty() && !self.soft_deleted && !op.data.is_empty() &&
+        op.current_owners == self.owners && op.writers == self.writers &&
+        op.policy == self.policy && op.signers.iter().any(|signer| self.writers.contains(signer))
+    }
+
+    /// The owner `op` replaces if it is a transfer replacing exactly one owner.
+    fn rotated_out(&self, op: &Op) -> Option<usize> {
+        if op.previous_owners.is_empty() || op.current_owners.len() != self.owners.len() {
+            return None;
+        }
+        let removed: Vec<_> =
+            self.owners.iter().filter(|owner| !op.current_owners.contains(owner)).collect();
+        if removed.len() == 1 {
+            Some(*removed[0])
+        } else {
+            None
+        }
+    }
+
+    fn apply(&mut self, op: &Op) {
+        self.version = op.version;
+        self.owners = op.current_owners.clone();
+        self.deleted = op.current_owners.is_empty() && op.data.is_empty();
+        self.soft_deleted = op.soft_deleted;
+        self.policy = op.policy;
+        self.writers = op.writers.clone();
+        self.frozen = op.frozen;
+    }
+}
+
+fn random_owners<R: Rng>(rng: &mut R) -> Vec<usize> {
+    // Single owners, whose one signature is a majority, are drawn more often than the rest.
+    let count = if rng.gen_weighted_bool(3) {
+        1
+    } else {
+        rng.gen_range(1, KEY_POOL_SIZE + 1)
+    };
+    let mut owners = rand::sample(rng, 0..KEY_POOL_SIZE, count);
+    owners.sort();
+    owners
+}
+
+fn random_signers<R: Rng>(rng: &mut R, owners: &[usize]) -> Vec<usize> {
+    let mut signers: Vec<usize> = owners.iter().cloned().filter(|_| rng.gen()).collect();
+    if rng.gen_weighted_bool(4) {
+        signers.push(rng.gen_range(0, KEY_POOL_SIZE));
+    }
+    rng.shuffle(&mut signers);
+    signers
+}
+
+/// Builds an operation of kind `kind`, less than `OP_KINDS`, with its details drawn from `rng`.
+fn op_of_kind<R: Rng>(rng: &mut R, model: &Model, kind: u8) -> Op {
+    let data_len = rng.gen_range(1, 16);
+    let mut op = Op {
+        name_changed: false,
+        tag_changed: false,
+        version: model.version + 1,
+        data: rng.gen_iter().take(data_len).collect(),
+        current_owners: model.owners.clone(),
+        previous_owners: vec![],
+        signers: vec![],
+        soft_deleted: false,
+        policy: model.policy,
+        writers: model.writers.clone(),
+        frozen: false,
+    };
+    let mut signed_by_writers = false;
+    match kind {
+        // Transfer of ownership.
+        0 | 1 => {
+            op.current_owners = random_owners(rng);
+            op.previous_owners = model.owners.clone();
+        }
+        // Tombstone.
+        2 => {
+            op.data.clear();
+            op.current_owners.clear();
+            op.previous_owners = model.owners.clone();
+        }
+        // Ownership change without naming the previous owners.
+        3 => op.current_owners = random_owners(rng),
+        // Key rotation.
+        6 if !model.owners.is_empty() && model.owners.len() < KEY_POOL_SIZE => {
+            let old = rng.gen_range(0, model.owners.len());
+            let new = (0..KEY_POOL_SIZE).find(|key| !model.owners.contains(key));
+            op.current_owners[old] = unwrap!(new);
+            op.current_owners.sort();
+            op.previous_owners = model.owners.clone();
+        }
+        // Wrong version.
+        4 => op.version = model.version + [0, 2][rng.gen_range(0, 2)],
+        // Wrong name.
+        5 => op.name_changed = true,
+        // Soft delete.
+        7 => {
+            op.data.clear();
+            op.soft_deleted = true;
+        }
+        // Data emptied without being soft-deleted, so still transferable.
+        8 => op.data.clear(),
+        // Soft-deleted mark kept with data.
+        9 => op.soft_deleted = true,
+        // Wrong type tag.
+        10 => op.tag_changed = true,
+        // Owners removed without deleting the data.
+        11 => op.current_owners.clear(),
+        // Change of signature policy.
+        12 => {
+            op.policy = match rng.gen_range(0, 3) {
+                0 => Policy::Majority,
+                1 => Policy::All,
+                _ => Policy::Threshold(rng.gen_range(0, 3)),
+            }
+        }
+        // Change of writers.
+        13 => op.writers = if rng.gen() { vec![] } else { random_owners(rng) },
+        // Update by the writers.
+        14 => signed_by_writers = true,
+        // Change of owners signed by the writers.
+        15 => {
+            op.current_owners = random_owners(rng);
+            signed_by_writers = true;
+        }
+        // Frozen successor.
+        16 => op.frozen = true,
+        // Plain update, or undelete if the data is soft-deleted.
+        _ => (),
+    }
+    let required_owners = if signed_by_writers {
+        model.writers.clone()
+    } else if op.previous_owners.is_empty() {
+        op.current_owners.clone()
+    } else {
+        op.previous_owners.clone()
+    };
+    op.signers = random_signers(rng, &required_owners);
+    op
+}
+
+/// Builds the successor `op` describes. Successors of ledger data are linked to `previous`, as
+/// the model does not cover the hash links. Fails as `StructuredData::new` does, e.g. for a
+/// successor without owners which is not a tombstone. A frozen successor is built by
+/// `StructuredData::new_frozen`, so is version 0 without owners whatever `op` says.
+fn build(op: &Op,
+         name: [u8; 32],
+         previous: &StructuredData,
+         keys: &[(PublicKey, SecretKey)])
+         -> Result<StructuredData, Error> {
+    let pub_keys = |indices: &[usize]| indices.iter().map(|&i| keys[i].0).collect();
+    let mut name = name;
+    if op.name_changed {
+        name[0] ^= 1;
+    }
+    let mut structured_data = if op.frozen {
+        StructuredData::new_frozen(op.tag_changed as u64,
+                                   name,
+                                   op.data.clone(),
+                                   previous.ledger())?
+    } else {
+        StructuredData::new(op.tag_changed as u64,
+                            name,
+                            op.version,
+                            op.data.clone(),
+                            pub_keys(&op.current_owners),
+                            pub_keys(&op.previous_owners),
+                            None,
+                            previous.ledger())?
+    };
+    if previous.ledger() {
+        structured_data.set_previous_hash(Some(unwrap!(previous.ledger_hash())));
+    }
+    structured_data.set_soft_deleted(op.soft_deleted);
+    structured_data.set_signature_policy(op.policy.signature_policy());
+    structured_data.set_writers(pub_keys(&op.writers));
+    for &signer in &op.signers {
+        let _ = unwrap!(structured_data.add_signature(&keys[signer].1));
+    }
+    Ok(structured_data)
+}
+
+/// Applies the operations `ops` to data with `owner_count` owners, or to frozen data without
+/// owners if `owner_count` is a multiple of `FROZEN_ONE_IN`, ledger data if `ledger`, and to its
+/// model. Each operation is a kind and a seed its details are drawn from, so the operations left
+/// in a shrunk sequence are unchanged.
+fn sequence(owner_count: u8, ledger: bool, ops: Vec<(u8, u32)>) -> TestResult {
+    let keys: Vec<_> = (0..KEY_POOL_SIZE).map(|_| test_utils::gen_keypair()).collect();
+    let name = [7; 32];
+    let frozen = owner_count % FROZEN_ONE_IN == 0;
+    let mut model = Model {
+        version: 0,
+        owners: if frozen {
+            vec![]
+        } else {
+            (0..(owner_count as usize % KEY_POOL_SIZE) + 1).collect()
+        },
+        deleted: false,
+        soft_deleted: false,
+        ledger: ledger,
+        policy: Policy::Majority,
+        writers: vec![],
+        frozen: frozen,
+    };
+    let mut structured_data = if frozen {
+        unwrap!(StructuredData::new_frozen(0, name, vec![], model.ledger))
+    } else {
+        unwrap!(StructuredData::new(0,
+                                    name,
+                                    0,
+                                    vec![],
+                                    model.owners.iter().map(|&i| keys[i].0).collect(),
+                                    vec![],
+                                    None,
+                                    model.ledger))
+    };
+    for &(kind, seed) in &ops {
+        let mut rng = XorShiftRng::from_seed([seed, 1, 2, 3]);
+        let op = op_of_kind(&mut rng, &model, kind % OP_KINDS);
+        let expected = model.accepts(&op);
+        let version = structured_data.version();
+        let result = match build(&op, name, &structured_data, &keys) {
+            Ok(successor) => structured_data.replace_with_other(successor),
+            Err(error) => Err(error),
+        };
+        if result.is_ok() != expected {
+            return TestResult::error(format!("model {:?} expected {} for {:?}, got {:?}",
+                                             model,
+                                             expected,
+                                             op,
+                                             result));
+        }
+        if expected {
+            model.apply(&op);
+            let _ = ACCEPTED.fetch_add(1, Ordering::SeqCst);
+        }
+        let mut writers: Vec<_> = model.writers.iter().map(|&i| keys[i].0).collect();
+        writers.sort();
+        if structured_data.version() < version {
+            return TestResult::error(format!("version went back from {} for {:?}", version, op));
+        }
+        if structured_data.get_type_tag() != 0 || *structured_data.name() != name ||
+           structured_data.version() != model.version ||
+           structured_data.is_deleted() != model.deleted ||
+           structured_data.is_soft_deleted() != model.soft_deleted ||
+           structured_data.signature_policy() != model.policy.signature_policy() ||
+           structured_data.writers()[..] != writers[..] ||
+           structured_data.is_frozen() != model.frozen {
+            return TestResult::error(format!("model {:?} disagrees with {:?}",
+                                             model,
+                                             structured_data));
+        }
+    }
+    TestResult::passed()
+}
+
+#[test]
+fn update_rules_agree_with_model() {
+    QuickCheck::new()
+        .tests(SEQUENCES)
+        .gen(StdGen::new(rand::thread_rng(), MAX_OPS))
+        .quickcheck(sequence as fn(u8, bool, Vec<(u8, u32)>) -> TestResult);
+
+    // Make sure the generator exercises the accepting paths as well as the rejecting ones.
+    let accepted = ACCEPTED.load(Ordering::SeqCst);
+    assert!(accepted > SEQUENCES / 2, "only {} accepted", accepted);
+}
@@ -0,0 +1,634 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use crypto::sign::{self, PublicKey, SecretKey, Signature};
+use data::{DataIdentifier, MAX_BYTES};
+use data::canonical::{CanonicalWriter, ENTRY_ACTIONS_DOMAIN, USER_PERMISSIONS_DOMAIN};
+use data::version::Version;
+use error::Error;
+#[cfg(feature = "serialisation")]
+use serialisation::serialise;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Debug, Formatter};
+use std::mem;
+
+/// An action on a `MutableData` which may be granted to a user key.
+#[derive(Hash, Eq, PartialEq, PartialOrd, Ord, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serialisation", derive(RustcEncodable, RustcDecodable))]
+pub enum Action {
+    /// Insert new entries.
+    Insert,
+    /// Update existing entries.
+    Update,
+    /// Delete existing entries.
+    Delete,
+    /// Change the permissions of users.
+    ManagePermissions,
+}
+
+/// The actions a user key is allowed to perform. Owners are allowed every action.
+#[derive(Hash, Eq, PartialEq, PartialOrd, Ord, Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serialisation", derive(RustcEncodable, RustcDecodable))]
+pub struct PermissionSet {
+    insert: bool,
+    update: bool,
+    delete: bool,
+    manage_permissions: bool,
+}
+
+impl PermissionSet {
+    /// A permission set allowing nothing.
+    pub fn new() -> PermissionSet {
+        PermissionSet::default()
+    }
+
+    /// Allows `action`.
+    pub fn allow(mut self, action: Action) -> PermissionSet {
+        *self.flag(action) = true;
+        self
+    }
+
+    /// Denies `action`.
+    pub fn deny(mut self, action: Action) -> PermissionSet {
+        *self.flag(action) = false;
+        self
+    }
+
+    /// Is `action` allowed.
+    pub fn is_allowed(&self, action: Action) -> bool {
+        match action {
+            Action::Insert => self.insert,
+            Action::Update => self.update,
+            Action::Delete => self.delete,
+            Action::ManagePermissions => self.manage_permissions,
+        }
+    }
+
+    fn flag(&mut self, action: Action) -> &mut bool {
+        match action {
+            Action::Insert => &mut self.insert,
+            Action::Update => &mut self.update,
+            Action::Delete => &mut self.delete,
+            Action::ManagePermissions => &mut self.manage_permissions,
+        }
+    }
+}
+
+/// The value of an entry together with its version.
+#[derive(Hash, Eq, PartialEq, PartialOrd, Ord, Clone, Debug)]
+#[cfg_attr(feature = "serialisation", derive(RustcEncodable, RustcDecodable))]
+pub struct Value {
+    /// Content of the entry.
+    pub content: Vec<u8>,
+    /// Version of the entry, starting at 0 and incremented by each update or deletion.
+    pub entry_version: u64,
+}
+
+/// A mutation of a single entry.
+#[derive(Hash, Eq, PartialEq, PartialOrd, Ord, Clone, Debug)]
+#[cfg_attr(feature = "serialisation", derive(RustcEncodable, RustcDecodable))]
+pub enum EntryAction {
+    /// Inserts a new entry, which must be at version 0.
+    Ins(Value),
+    /// Updates an existing entry to the next version.
+    Update(Value),
+    /// Deletes an existing entry, giving what would have been its next version.
+    Del(u64),
+}
+
+impl EntryAction {
+    fn action(&self) -> Action {
+        match *self {
+            EntryAction::Ins(_) => Action::Insert,
+            EntryAction::Update(_) => Action::Update,
+            EntryAction::Del(_) => Action::Delete,
+        }
+    }
+}
+
+/// Mutable key-value data.
+///
+/// Unlike `StructuredData`, entries are mutated individually, each carrying its own version.
+/// Every mutation is signed by the requesting key, which must be an owner or hold the
+/// permission for the action in question.
+#[derive(Hash, Eq, PartialEq, PartialOrd, Ord, Clone)]
+#[cfg_attr(feature = "serialisation", derive(RustcEncodable, RustcDecodable))]
+pub struct MutableData {
+    name: [u8; 32],
+    type_tag: u64,
+    entries: BTreeMap<Vec<u8>, Value>,
+    permissions: BTreeMap<PublicKey, PermissionSet>,
+    version: u64,
+    owners: BTreeSet<PublicKey>,
+}
+
+impl MutableData {
+    /// Creates a new `MutableData` at version 0. There must be at least one owner.
+    pub fn new(name: [u8; 32],
+               type_tag: u64,
+               permissions: BTreeMap<PublicKey, PermissionSet>,
+               entries: BTreeMap<Vec<u8>, Value>,
+               owners: BTreeSet<PublicKey>)
+               -> Result<MutableData, Error> {
+        if owners.is_empty() {
+            return Err(Error::Validation);
+        }
+        let mutable_data = MutableData {
+            name: name,
+            type_tag: type_tag,
+            entries: entries,
+            permissions: permissions,
+            version: 0,
+            owners: owners,
+        };
+        mutable_data.validate_size()?;
+        Ok(mutable_data)
+    }
+
+    /// Returns the name.
+    pub fn name(&self) -> &[u8; 32] {
+        &self.name
+    }
+
+    /// Returns the type tag.
+    pub fn type_tag(&self) -> u64 {
+        self.type_tag
+    }
+
+    /// Returns the version, incremented by each permission change.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Restores the version of data imported from an export format.
+    #[cfg(any(feature = "json", feature = "cbor"))]
+    pub(crate) fn set_version(&mut self, version: u64) {
+        self.version = version;
+    }
+
+    /// Returns the owners.
+    pub fn owners(&self) -> &BTreeSet<PublicKey> {
+        &self.owners
+    }
+
+    /// Returns the entries.
+    pub fn entries(&self) -> &BTreeMap<Vec<u8>, Value> {
+        &self.entries
+    }
+
+    /// Returns the value of the entry at `key`.
+    pub fn get(&self, key: &[u8]) -> Option<&Value> {
+        self.entries.get(key)
+    }
+
+    /// Returns the permissions of all users.
+    pub fn permissions(&self) -> &BTreeMap<PublicKey, PermissionSet> {
+        &self.permissions
+    }
+
+    /// Returns the permissions of `user`, if any were set.
+    pub fn user_permissions(&self, user: &PublicKey) -> Option<&PermissionSet> {
+        self.permissions.get(user)
+    }
+
+    /// Returns `DataIdentifier` for this data element.
+    pub fn identifier(&self) -> DataIdentifier {
+        DataIdentifier::Mutable(self.name, self.type_tag)
+    }
+
+    /// Returns the size of the entry keys and contents.
+    pub fn payload_size(&self) -> usize {
+        self.entries.iter().map(|(key, value)| key.len() + value.content.len()).sum()
+    }
+
+    /// Returns the exact size of the serialised data.
+    #[cfg(feature = "serialisation")]
+    pub fn serialised_size(&self) -> Result<usize, Error> {
+        Ok(serialise(self)?.len())
+    }
+
+    /// The size `validate_size` checks: the exact serialised size where the `serialisation`
+    /// feature provides it, otherwise the estimated size.
+    #[cfg(feature = "serialisation")]
+    fn size(&self) -> Result<usize, Error> {
+        self.serialised_size()
+    }
+
+    #[cfg(not(feature = "serialisation"))]
+    fn size(&self) -> Result<usize, Error> {
+        Ok(self.estimated_size())
+    }
+
+    /// Returns the size of the serialised data, worked out from the fields without serialising.
+    pub fn estimated_size(&self) -> usize {
+        // Lengths are serialised as `u64`; a permission set is four `bool`s.
+        let entries: usize = self.entries
+            .iter()
+            .map(|(key, value)| 8 + key.len() + 8 + value.content.len() + 8)
+            .sum();
+        self.name.len() + 8 + 8 + entries + 8 +
+        self.permissions.len() * (sign::PUBLICKEYBYTES + 4) + 8 + 8 +
+        self.owners.len() * sign::PUBLICKEYBYTES
+    }
+
+    /// Returns an error if the serialised data is larger than `MAX_BYTES` plus the
+    /// `sign::size_allowance` for its keys.
+    pub fn validate_size(&self) -> Result<(), Error> {
+        let keys = self.permissions.len() + self.owners.len();
+        if self.size()? > MAX_BYTES + sign::size_allowance(keys, 0) {
+            return Err(Error::DataTooLarge);
+        }
+        Ok(())
+    }
+
+    /// Signs `actions` for use with `mutate_entries`.
+    pub fn sign_entry_actions(&self,
+                              actions: &BTreeMap<Vec<u8>, EntryAction>,
+                              secret_key: &SecretKey)
+                              -> Result<Signature, Error> {
+        Ok(sign::sign_detached(&self.entry_actions_to_sign(actions)?, secret_key))
+    }
+
+    /// Applies all of `actions` or, if any of them is invalid, none.
+    ///
+    /// `signature` must be `requester`'s signature over `actions`, and `requester` must be an owner
+    /// or be allowed every kind of action requested.
+    pub fn mutate_entries(&mut self,
+                          actions: BTreeMap<Vec<u8>, EntryAction>,
+                          requester: &PublicKey,
+                          signature: &Signature)
+                          -> Result<(), Error> {
+        if !sign::verify_detached(signature, &self.entry_actions_to_sign(&actions)?, requester) {
+            return Err(Error::Signature);
+        }
+        for action in actions.values() {
+            self.check_permission(requester, action.action())?;
+        }
+
+        let mut entries = self.entries.clone();
+        for (key, action) in actions {
+            match action {
+                EntryAction::Ins(value) => {
+                    if entries.contains_key(&key) {
+                        return Err(Error::EntryExists);
+                    }
+                    if value.entry_version != 0 {
+                        return Err(Error::InvalidVersion);
+                    }
+                    let _ = entries.insert(key, value);
+                }
+                EntryAction::Update(value) => {
+                    Self::check_next_version(entries.get(&key), value.entry_version)?;
+                    let _ = entries.insert(key, value);
+                }
+                EntryAction::Del(version) => {
+                    Self::check_next_version(entries.get(&key), version)?;
+                    let _ = entries.remove(&key);
+                }
+            }
+        }
+
+        let old_entries = mem::replace(&mut self.entries, entries);
+        if let Err(error) = self.validate_size() {
+            self.entries = old_entries;
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    /// Signs a change of `user`'s permissions for use with `set_user_permissions`.
+    pub fn sign_user_permissions(&self,
+                                 user: &PublicKey,
+                                 permissions: PermissionSet,
+                                 version: u64,
+                                 secret_key: &SecretKey)
+                                 -> Result<Signature, Error> {
+        let data = self.user_permissions_to_sign(user, permissions, version)?;
+        Ok(sign::sign_detached(&data, secret_key))
+    }
+
+    /// Sets `user`'s permissions, replacing any previous ones.
+    ///
+    /// `version` must be the next version of this data, `signature` must be `requester`'s
+    /// signature over the change and `requester` must be an owner or be allowed to manage
+    /// permissions.
+    pub fn set_user_permissions(&mut self,
+                                user: PublicKey,
+                                permissions: PermissionSet,
+                                version: u64,
+                                requester: &PublicKey,
+                                signature: &Signature)
+                                -> Result<(), Error> {
+        let data = self.user_permissions_to_sign(&user, permissions, version)?;
+        if !sign::verify_detached(signature, &data, requester) {
+            return Err(Error::Signature);
+        }
+        self.check_permission(requester, Action::ManagePermissions)?;
+        if !Version(self.version).is_next(Version(version)) {
+            return Err(Error::InvalidVersion);
+        }
+
+        let old_permissions = self.permissions.insert(user, permissions);
+        if let Err(error) = self.validate_size() {
+            let _ = match old_permissions {
+                Some(old_permissions) => self.permissions.insert(user, old_permissions),
+                None => self.permissions.remove(&user),
+            };
+            return Err(error);
+        }
+        self.version = version;
+        Ok(())
+    }
+
+    fn check_permission(&self, requester: &PublicKey, action: Action) -> Result<(), Error> {
+        if self.owners.contains(requester) ||
+           self.permissions.get(requester).map_or(false, |set| set.is_allowed(action)) {
+            Ok(())
+        } else {
+            Err(Error::AccessDenied)
+        }
+    }
+
+    fn check_next_version(current: Option<&Value>, version: u64) -> Result<(), Error> {
+        match current {
+            None => Err(Error::NoSuchEntry),
+            Some(value) if !Version(value.entry_version).is_next(Version(version)) => {
+                Err(Error::InvalidVersion)
+            }
+            Some(_) => Ok(()),
+        }
+    }
+
+    fn entry_actions_to_sign(&self,
+                             actions: &BTreeMap<Vec<u8>, EntryAction>)
+                             -> Result<Vec<u8>, Error> {
+        let mut writer = CanonicalWriter::new(ENTRY_ACTIONS_DOMAIN);
+        let _ = writer.write_fixed(&self.name)
+            .write_u64(self.type_tag)
+            .write_len(actions.len());
+        for (key, action) in actions {
+            let _ = writer.write_bytes(key);
+            let _ = match *action {
+                EntryAction::Ins(ref value) => {
+                    writer.write_u8(0).write_bytes(&value.content).write_u64(value.entry_version)
+                }
+                EntryAction::Update(ref value) => {
+                    writer.write_u8(1).write_bytes(&value.content).write_u64(value.entry_version)
+                }
+                EntryAction::Del(version) => writer.write_u8(2).write_u64(version),
+            };
+        }
+        Ok(writer.into_bytes())
+    }
+
+    fn user_permissions_to_sign(&self,
+                                user: &PublicKey,
+                                permissions: PermissionSet,
+                                version: u64)
+                                -> Result<Vec<u8>, Error> {
+        let mut writer = CanonicalWriter::new(USER_PERMISSIONS_DOMAIN);
+        let _ = writer.write_fixed(&self.name)
+            .write_u64(self.type_tag)
+            .write_fixed(&user.0)
+            .write_bool(permissions.insert)
+            .write_bool(permissions.update)
+            .write_bool(permissions.delete)
+            .write_bool(permissions.manage_permissions)
+            .write_u64(version);
+        Ok(writer.into_bytes())
+    }
+}
+
+impl Debug for MutableData {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter,
+               "MutableData {{ name: {:?}, type_tag: {}, version: {}, entries: {}, permissions: \
+                {}, owners: {} }}",
+               self.name,
+               self.type_tag,
+               self.version,
+               self.entries.len(),
+               self.permissions.len(),
+               self.owners.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crypto::sign::{self, PublicKey};
+    use data::DataIdentifier;
+    use error::Error;
+    use rand;
+    use rustc_serialize::hex::ToHex;
+    use serialisation::{deserialise, serialise};
+    use std::collections::{BTreeMap, BTreeSet};
+    use test_utils;
+
+    fn value(content: &[u8], entry_version: u64) -> Value {
+        Value {
+            content: content.to_vec(),
+            entry_version: entry_version,
+        }
+    }
+
+    fn actions(list: Vec<(&[u8], EntryAction)>) -> BTreeMap<Vec<u8>, EntryAction> {
+        list.into_iter().map(|(key, action)| (key.to_vec(), action)).collect()
+    }
+
+    #[test]
+    fn owner_mutates_entries() {
+        let owner = test_utils::gen_keypair();
+        let mut data = unwrap!(MutableData::new(rand::random(),
+                                                10,
+                                                BTreeMap::new(),
+                                                BTreeMap::new(),
+                                                vec![owner.0].into_iter().collect()));
+        assert_eq!(data.identifier(), DataIdentifier::Mutable(*data.name(), 10));
+
+        let insert = actions(vec![(b"a", EntryAction::Ins(value(b"one", 0))),
+                                  (b"b", EntryAction::Ins(value(b"two", 0)))]);
+        let signature = unwrap!(data.sign_entry_actions(&insert, &owner.1));
+        unwrap!(data.mutate_entries(insert.clone(), &owner.0, &signature));
+        assert_eq!(data.entries().len(), 2);
+        assert_eq!(data.payload_size(), 8);
+
+        // Replaying the insert fails.
+        match data.mutate_entries(insert, &owner.0, &signature) {
+            Err(Error::EntryExists) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        let update = actions(vec![(b"a", EntryAction::Update(value(b"uno", 1))),
+                                  (b"b", EntryAction::Del(1))]);
+        let signature = unwrap!(data.sign_entry_actions(&update, &owner.1));
+        unwrap!(data.mutate_entries(update, &owner.0, &signature));
+        assert_eq!(data.get(b"a"), Some(&value(b"uno", 1)));
+        assert!(data.get(b"b").is_none());
+
+        // Versions must increase by one, and a failing action rolls back the others.
+        let bad = actions(vec![(b"0", EntryAction::Ins(value(b"zero", 0))),
+                               (b"a", EntryAction::Update(value(b"eins", 3)))]);
+        let signature = unwrap!(data.sign_entry_actions(&bad, &owner.1));
+        match data.mutate_entries(bad, &owner.0, &signature) {
+            Err(Error::InvalidVersion) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert!(data.get(b"0").is_none());
+
+        let missing = actions(vec![(b"z", EntryAction::Del(1))]);
+        let signature = unwrap!(data.sign_entry_actions(&missing, &owner.1));
+        match data.mutate_entries(missing, &owner.0, &signature) {
+            Err(Error::NoSuchEntry) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn user_permissions() {
+        let owner = test_utils::gen_keypair();
+        let app = test_utils::gen_keypair();
+        let mut data = unwrap!(MutableData::new(rand::random(),
+                                                0,
+                                                BTreeMap::new(),
+                                                BTreeMap::new(),
+                                                vec![owner.0].into_iter().collect()));
+
+        let insert = actions(vec![(b"key", EntryAction::Ins(value(b"value", 0)))]);
+        let signature = unwrap!(data.sign_entry_actions(&insert, &app.1));
+        match data.mutate_entries(insert.clone(), &app.0, &signature) {
+            Err(Error::AccessDenied) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // Only owners (or managers) may grant permissions.
+        let permissions = PermissionSet::new().allow(Action::Insert);
+        let signature = unwrap!(data.sign_user_permissions(&app.0, permissions, 1, &app.1));
+        match data.set_user_permissions(app.0, permissions, 1, &app.0, &signature) {
+            Err(Error::AccessDenied) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        let signature = unwrap!(data.sign_user_permissions(&app.0, permissions, 1, &owner.1));
+        match data.set_user_permissions(app.0, permissions, 2, &owner.0, &signature) {
+            Err(Error::Signature) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        unwrap!(data.set_user_permissions(app.0, permissions, 1, &owner.0, &signature));
+        assert_eq!(data.version(), 1);
+        assert_eq!(data.user_permissions(&app.0), Some(&permissions));
+
+        // A signature by the owner does not authorise the app.
+        let owner_signature = unwrap!(data.sign_entry_actions(&insert, &owner.1));
+        match data.mutate_entries(insert.clone(), &app.0, &owner_signature) {
+            Err(Error::Signature) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        let signature = unwrap!(data.sign_entry_actions(&insert, &app.1));
+        unwrap!(data.mutate_entries(insert, &app.0, &signature));
+
+        let update = actions(vec![(b"key", EntryAction::Update(value(b"new", 1)))]);
+        let signature = unwrap!(data.sign_entry_actions(&update, &app.1));
+        match data.mutate_entries(update, &app.0, &signature) {
+            Err(Error::AccessDenied) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn new_requires_owner() {
+        match MutableData::new(rand::random(),
+                               0,
+                               BTreeMap::new(),
+                               BTreeMap::new(),
+                               BTreeSet::new()) {
+            Err(Error::Validation) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn permission_set() {
+        let permissions = PermissionSet::new().allow(Action::Update).allow(Action::Delete);
+        assert!(permissions.is_allowed(Action::Update));
+        assert!(permissions.is_allowed(Action::Delete));
+        assert!(!permissions.is_allowed(Action::Insert));
+        assert!(!permissions.deny(Action::Delete).is_allowed(Action::Delete));
+    }
+
+    #[test]
+    fn serialisation() {
+        let owner = test_utils::gen_keypair();
+        let mut entries = BTreeMap::new();
+        let _ = entries.insert(b"key".to_vec(), value(b"value", 0));
+        let mut permissions = BTreeMap::new();
+        let _ = permissions.insert(test_utils::gen_keypair().0,
+                                   PermissionSet::new().allow(Action::Insert));
+        let data = unwrap!(MutableData::new(rand::random(),
+                                            3,
+                                            permissions,
+                                            entries,
+                                            vec![owner.0].into_iter().collect()));
+        let serialised = unwrap!(serialise(&data));
+        assert_eq!(data, unwrap!(deserialise::<MutableData>(&serialised)));
+        assert_eq!(data.estimated_size(), serialised.len());
+        assert_eq!(unwrap!(data.serialised_size()), serialised.len());
+    }
+
+    #[test]
+    fn signed_bytes_golden_vectors() {
+        // The expected bytes hold Ed25519 keys.
+        if cfg!(feature = "dilithium") {
+            return;
+        }
+        let owner = PublicKey([1; sign::PUBLICKEYBYTES]);
+        let data = unwrap!(MutableData::new([2; 32],
+                                            5,
+                                            BTreeMap::new(),
+                                            BTreeMap::new(),
+                                            vec![owner].into_iter().collect()));
+        let actions = actions(vec![(b"a", EntryAction::Ins(value(b"one", 0))),
+                                   (b"b", EntryAction::Del(4))]);
+        // Domain, name, type tag, then each key and action.
+        assert_eq!(unwrap!(data.entry_actions_to_sign(&actions)).to_hex(),
+                   "0000000000000025\
+                    646174615f636861696e2f4d757461626c65446174612f456e747279416374696f6e732f31\
+                    0202020202020202020202020202020202020202020202020202020202020202\
+                    0000000000000005\
+                    0000000000000002\
+                    000000000000000161\
+                    00\
+                    00000000000000036f6e65\
+                    0000000000000000\
+                    000000000000000162\
+                    02\
+                    0000000000000004");
+
+        let permissions = PermissionSet::new()
+            .allow(Action::Insert)
+            .allow(Action::ManagePermissions);
+        // Domain, name, type tag, user, permissions, version.
+        assert_eq!(unwrap!(data.user_permissions_to_sign(&owner, permissions, 1)).to_hex(),
+                   "0000000000000028\
+                    646174615f636861696e2f4d757461626c65446174612f\
+                    557365725065726d697373696f6e732f31\
+                    0202020202020202020202020202020202020202020202020202020202020202\
+                    0000000000000005\
+                    0101010101010101010101010101010101010101010101010101010101010101\
+                    01000001\
+                    0000000000000001");
+    }
+}
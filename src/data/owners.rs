@@ -0,0 +1,156 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use crypto::ct;
+use crypto::sign::PublicKey;
+use data::canonical::CanonicalWriter;
+#[cfg(feature = "serialisation")]
+use rustc_serialize::{Decodable, Decoder, Encodable, Encoder};
+use std::fmt::{self, Debug, Formatter};
+use std::ops::Deref;
+use std::slice;
+
+/// The owner keys of a `StructuredData`, held sorted by key bytes and without duplicates.
+///
+/// Holding keys canonically means the same set of owners always signs the same bytes, whatever
+/// order the keys were supplied in and however often each was listed. Keys are canonicalised on
+/// construction and when decoding, so data from before owners were deduplicated which lists an
+/// owner more than once decodes with that owner listed once, and needs signing afresh if it was
+/// signed over the duplicates.
+///
+/// `Owners` derefs to a slice of the keys and serialises as a sequence of them.
+#[derive(Hash, Eq, PartialEq, PartialOrd, Ord, Clone, Default)]
+pub struct Owners(Vec<PublicKey>);
+
+impl Owners {
+    /// Creates the owners from `keys`, sorting them and removing duplicates.
+    pub fn new(mut keys: Vec<PublicKey>) -> Owners {
+        keys.sort();
+        keys.dedup();
+        Owners(keys)
+    }
+
+    /// Is `key` one of the owners. Compares in constant time.
+    pub fn contains(&self, key: &PublicKey) -> bool {
+        ct::contains_key(&self.0, key)
+    }
+
+    /// Writes the canonical encoding of the owners, as signed: the number of keys, then each key.
+    pub fn write_canonical<'a>(&self, writer: &'a mut CanonicalWriter) -> &'a mut CanonicalWriter {
+        let _ = writer.write_len(self.0.len());
+        for key in &self.0 {
+            let _ = writer.write_fixed(&key.0);
+        }
+        writer
+    }
+
+    /// Returns the keys.
+    pub fn into_vec(self) -> Vec<PublicKey> {
+        self.0
+    }
+}
+
+impl Deref for Owners {
+    type Target = [PublicKey];
+
+    fn deref(&self) -> &[PublicKey] {
+        &self.0
+    }
+}
+
+impl<'a> IntoIterator for &'a Owners {
+    type Item = &'a PublicKey;
+    type IntoIter = slice::Iter<'a, PublicKey>;
+
+    fn into_iter(self) -> slice::Iter<'a, PublicKey> {
+        self.0.iter()
+    }
+}
+
+impl From<Vec<PublicKey>> for Owners {
+    fn from(keys: Vec<PublicKey>) -> Owners {
+        Owners::new(keys)
+    }
+}
+
+impl PartialEq<Vec<PublicKey>> for Owners {
+    fn eq(&self, other: &Vec<PublicKey>) -> bool {
+        self.0 == *other
+    }
+}
+
+impl Debug for Owners {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        self.0.fmt(formatter)
+    }
+}
+
+#[cfg(feature = "serialisation")]
+impl Encodable for Owners {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), E::Error> {
+        self.0.encode(encoder)
+    }
+}
+
+#[cfg(feature = "serialisation")]
+impl Decodable for Owners {
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Owners, D::Error> {
+        Ok(Owners::new(Decodable::decode(decoder)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::canonical::CanonicalWriter;
+    use serialisation::{deserialise, serialise};
+    use test_utils;
+
+    #[test]
+    fn canonical() {
+        let mut keys = vec![test_utils::gen_keypair().0,
+                            test_utils::gen_keypair().0,
+                            test_utils::gen_keypair().0];
+        keys.sort();
+        let owners = Owners::new(vec![keys[2], keys[0], keys[1], keys[0], keys[2]]);
+        assert_eq!(owners, keys);
+        assert_eq!(owners, Owners::from(keys.clone()));
+        assert!(owners.contains(&keys[1]));
+        assert!(!owners.contains(&test_utils::gen_keypair().0));
+
+        let mut writer = CanonicalWriter::new(b"owners");
+        let _ = owners.write_canonical(&mut writer);
+        let mut expected = CanonicalWriter::new(b"owners");
+        let _ = expected.write_len(3).write_fixed(&keys[0].0).write_fixed(&keys[1].0);
+        let _ = expected.write_fixed(&keys[2].0);
+        assert_eq!(writer.into_bytes(), expected.into_bytes());
+    }
+
+    #[test]
+    fn serialisation() {
+        let key0 = test_utils::gen_keypair().0;
+        let key1 = test_utils::gen_keypair().0;
+        let owners = Owners::new(vec![key0, key1]);
+        let serialised = unwrap!(serialise(&owners));
+        assert_eq!(serialised, unwrap!(serialise(&owners.clone().into_vec())));
+        assert_eq!(unwrap!(deserialise::<Owners>(&serialised)), owners);
+
+        // Keys listed out of order or more than once decode canonically.
+        let legacy = unwrap!(serialise(&vec![key1, key0, key1]));
+        assert_eq!(unwrap!(deserialise::<Owners>(&legacy)), owners);
+    }
+}
@@ -0,0 +1,345 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Pending updates
+//! A proposed update of multi-owner `StructuredData` passed between the owners' devices while
+//! they sign it. Unlike a `SigningSession`, which collects signatures in one place, a
+//! `PendingUpdate` is serialised and sent on, and copies signed independently on different
+//! devices can be merged.
+//!
+//! A pending update may carry a deadline, after which it can neither be signed nor finalised, so
+//! a proposal left unsigned does not stay valid indefinitely.
+
+use crypto::ct;
+use crypto::sign::{self, PublicKey, SecretKey, Signature};
+use data::StructuredData;
+#[cfg(feature = "serialisation")]
+use data::untrusted;
+use error::Error;
+#[cfg(feature = "serialisation")]
+use serialisation::serialise;
+use std::collections::BTreeMap;
+
+/// A proposed successor of a `StructuredData`, the owners' signatures of it collected so far and
+/// an optional deadline for collecting the rest.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialisation", derive(RustcEncodable, RustcDecodable))]
+pub struct PendingUpdate {
+    successor: StructuredData,
+    signatures: BTreeMap<PublicKey, Signature>,
+    expires_at: Option<u64>,
+}
+
+impl PendingUpdate {
+    /// Proposes `successor`, to be signed by `expires_at` if given.
+    ///
+    /// Any signatures already on `successor` which are valid for one of the owners are kept;
+    /// invalid ones are discarded.
+    pub fn new(mut successor: StructuredData,
+               expires_at: Option<u64>)
+               -> Result<PendingUpdate, Error> {
+        let data_to_sign = successor.signable_bytes()?;
+        let mut signatures = BTreeMap::new();
+        for signature in successor.get_previous_owner_signatures() {
+            let signer = owner_keys(&successor)
+                .iter()
+                .find(|key| sign::verify_detached(signature, &data_to_sign, key));
+            if let Some(key) = signer {
+                let _ = signatures.insert(*key, *signature);
+            }
+        }
+        successor.replace_signatures(vec![]);
+        Ok(PendingUpdate {
+            successor: successor,
+            signatures: signatures,
+            expires_at: expires_at,
+        })
+    }
+
+    /// The proposed successor, without signatures.
+    pub fn successor(&self) -> &StructuredData {
+        &self.successor
+    }
+
+    /// The bytes each owner has to sign.
+    pub fn data_to_sign(&self) -> Result<Vec<u8>, Error> {
+        self.successor.signable_bytes()
+    }
+
+    /// The keys whose signatures count towards this update.
+    pub fn owner_keys(&self) -> &[PublicKey] {
+        owner_keys(&self.successor)
+    }
+
+    /// The owners who have signed so far.
+    pub fn signers(&self) -> Vec<PublicKey> {
+        self.signatures.keys().cloned().collect()
+    }
+
+    /// Number of signatures still required.
+    pub fn remaining(&self) -> usize {
+        self.successor.signature_policy().remaining_signatures(&self.signers(), self.owner_keys())
+    }
+
+    /// Have enough owners signed.
+    pub fn is_complete(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// The deadline for signing, if any.
+    pub fn expires_at(&self) -> Option<u64> {
+        self.expires_at
+    }
+
+    /// Has the deadline passed at `now`.
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires_at.map_or(false, |expires_at| now >= expires_at)
+    }
+
+    /// Signs the update with `secret_key` at `now` and returns the number of signatures still
+    /// required.
+    ///
+    /// Fails with `Expired` if the deadline has passed and with `AccessDenied` if the key is not
+    /// one of the owner keys.
+    pub fn sign(&mut self, secret_key: &SecretKey, now: u64) -> Result<usize, Error> {
+        let mut public_key = PublicKey([0; sign::PUBLICKEYBYTES]);
+        public_key.0.copy_from_slice(&secret_key.0[sign::SECRETKEYBYTES - sign::PUBLICKEYBYTES..]);
+        let signature = sign::sign_detached(&self.data_to_sign()?, secret_key);
+        self.add_signature(&public_key, signature, now)
+    }
+
+    /// Adds `owner`'s detached signature at `now` and returns the number of signatures still
+    /// required.
+    ///
+    /// Fails with `Expired` if the deadline has passed, with `AccessDenied` if `owner` is not one
+    /// of the owner keys and with `Signature` if the signature is not `owner`'s signature of
+    /// `data_to_sign()`. Adding a signature again is harmless.
+    pub fn add_signature(&mut self,
+                         owner: &PublicKey,
+                         signature: Signature,
+                         now: u64)
+                         -> Result<usize, Error> {
+        if self.is_expired(now) {
+            return Err(Error::Expired);
+        }
+        self.check_signature(owner, &signature, &self.data_to_sign()?)?;
+        let _ = self.signatures.insert(*owner, signature);
+        Ok(self.remaining())
+    }
+
+    /// Adds the signatures collected in `other`, a copy of this update signed independently, and
+    /// returns the number of signatures still required.
+    ///
+    /// Fails with `BadIdentifier` if `other` proposes a different successor, and with `Signature`
+    /// or `AccessDenied`, adding none of its signatures, if any of them is invalid. The earlier of
+    /// the two deadlines applies afterwards.
+    pub fn merge(&mut self, other: &PendingUpdate) -> Result<usize, Error> {
+        if other.successor != self.successor {
+            return Err(Error::BadIdentifier);
+        }
+        let data_to_sign = self.data_to_sign()?;
+        for (owner, signature) in &other.signatures {
+            self.check_signature(owner, signature, &data_to_sign)?;
+        }
+        for (owner, signature) in &other.signatures {
+            let _ = self.signatures.insert(*owner, *signature);
+        }
+        self.expires_at = match (self.expires_at, other.expires_at) {
+            (Some(ours), Some(theirs)) => Some(ours.min(theirs)),
+            (ours, theirs) => ours.or(theirs),
+        };
+        Ok(self.remaining())
+    }
+
+    /// Returns the successor signed by the collected signatures, failing with `Expired` if the
+    /// deadline has passed at `now` or `Majority` if not enough owners have signed yet.
+    pub fn finalise(self, now: u64) -> Result<StructuredData, Error> {
+        if self.is_expired(now) {
+            return Err(Error::Expired);
+        }
+        if !self.is_complete() {
+            return Err(Error::Majority);
+        }
+        let mut successor = self.successor;
+        successor.replace_signatures(self.signatures.into_iter().map(|(_, sig)| sig).collect());
+        Ok(successor)
+    }
+
+    /// Returns the successor carrying the signatures collected so far, whether or not they are
+    /// enough, e.g. to hold on to until more arrive.
+    pub fn signed_successor(&self) -> StructuredData {
+        let mut successor = self.successor.clone();
+        successor.replace_signatures(self.signatures.values().cloned().collect());
+        successor
+    }
+
+    /// Serialises the update for sending to another owner.
+    #[cfg(feature = "serialisation")]
+    pub fn serialise(&self) -> Result<Vec<u8>, Error> {
+        serialise(self).map_err(From::from)
+    }
+
+    /// Parses a received update, checking each signature it carries.
+    #[cfg(feature = "serialisation")]
+    pub fn deserialise(bytes: &[u8]) -> Result<PendingUpdate, Error> {
+        let update: PendingUpdate = untrusted::deserialise(bytes)?;
+        let data_to_sign = update.data_to_sign()?;
+        for (owner, signature) in &update.signatures {
+            update.check_signature(owner, signature, &data_to_sign)?;
+        }
+        Ok(update)
+    }
+
+    fn check_signature(&self,
+                       owner: &PublicKey,
+                       signature: &Signature,
+                       data_to_sign: &[u8])
+                       -> Result<(), Error> {
+        if !ct::contains_key(self.owner_keys(), owner) {
+            return Err(Error::AccessDenied);
+        }
+        if !sign::verify_detached(signature, data_to_sign, owner) {
+            return Err(Error::Signature);
+        }
+        Ok(())
+    }
+}
+
+/// The owners whose signatures `successor` requires: the previous owners on a transfer of
+/// ownership, otherwise the current owners.
+fn owner_keys(successor: &StructuredData) -> &[PublicKey] {
+    if successor.get_previous_owner_keys().is_empty() {
+        successor.get_owner_keys()
+    } else {
+        successor.get_previous_owner_keys()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crypto::sign;
+    use data::StructuredData;
+    use error::Error;
+    use rand;
+    use test_utils;
+
+    fn proposal(keys: &[(sign::PublicKey, sign::SecretKey)]) -> (StructuredData, StructuredData) {
+        let owner_keys: Vec<_> = keys.iter().map(|key| key.0).collect();
+        let name = rand::random();
+        let original = unwrap!(StructuredData::new(0,
+                                                   name,
+                                                   0,
+                                                   vec![],
+                                                   owner_keys.clone(),
+                                                   vec![],
+                                                   None,
+                                                   false));
+        let successor = unwrap!(StructuredData::new(0,
+                                                    name,
+                                                    1,
+                                                    b"update".to_vec(),
+                                                    owner_keys,
+                                                    vec![],
+                                                    Some(&keys[0].1),
+                                                    false));
+        (original, successor)
+    }
+
+    #[test]
+    fn sign_on_several_devices_and_merge() {
+        let keys: Vec<_> = (0..5).map(|_| test_utils::gen_keypair()).collect();
+        let (mut original, successor) = proposal(&keys);
+        let update = unwrap!(PendingUpdate::new(successor, Some(100)));
+        assert_eq!(update.signers(), vec![keys[0].0]);
+        assert_eq!(update.remaining(), 2);
+
+        // Two owners sign copies of the update on their own devices.
+        let mut first = unwrap!(PendingUpdate::deserialise(&unwrap!(update.serialise())));
+        let mut second = unwrap!(PendingUpdate::deserialise(&unwrap!(update.serialise())));
+        assert_eq!(unwrap!(first.sign(&keys[1].1, 10)), 1);
+        assert_eq!(unwrap!(second.sign(&keys[2].1, 10)), 1);
+        match first.clone().finalise(10) {
+            Err(Error::Majority) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        let second = unwrap!(PendingUpdate::deserialise(&unwrap!(second.serialise())));
+        assert_eq!(unwrap!(first.merge(&second)), 0);
+        assert!(first.is_complete());
+        unwrap!(original.replace_with_other(unwrap!(first.finalise(10))));
+        assert_eq!(original.version(), 1);
+    }
+
+    #[test]
+    fn rejects_invalid_signatures_and_other_proposals() {
+        let keys: Vec<_> = (0..3).map(|_| test_utils::gen_keypair()).collect();
+        let (_, successor) = proposal(&keys);
+        let mut update = unwrap!(PendingUpdate::new(successor, None));
+
+        let stranger = test_utils::gen_keypair();
+        match update.sign(&stranger.1, 0) {
+            Err(Error::AccessDenied) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        let forged = sign::sign_detached(b"other", &keys[1].1);
+        match update.add_signature(&keys[1].0, forged, 0) {
+            Err(Error::Signature) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        let mut tampered = update.clone();
+        let _ = tampered.signatures.insert(keys[1].0, forged);
+        match PendingUpdate::deserialise(&unwrap!(tampered.serialise())) {
+            Err(Error::Signature) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match update.merge(&tampered) {
+            Err(Error::Signature) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert_eq!(update.signers(), vec![keys[0].0]);
+
+        let (_, other_successor) = proposal(&keys);
+        let other = unwrap!(PendingUpdate::new(other_successor, None));
+        match update.merge(&other) {
+            Err(Error::BadIdentifier) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn deadline() {
+        let keys: Vec<_> = (0..3).map(|_| test_utils::gen_keypair()).collect();
+        let (_, successor) = proposal(&keys);
+        let mut update = unwrap!(PendingUpdate::new(successor.clone(), Some(100)));
+        let mut later = unwrap!(PendingUpdate::new(successor, None));
+        assert_eq!(unwrap!(later.sign(&keys[1].1, 200)), 0);
+
+        match update.sign(&keys[1].1, 100) {
+            Err(Error::Expired) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert_eq!(unwrap!(update.merge(&later)), 0);
+        assert_eq!(update.expires_at(), Some(100));
+        assert!(update.clone().finalise(99).is_ok());
+        match update.finalise(100) {
+            Err(Error::Expired) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
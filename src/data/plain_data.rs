@@ -0,0 +1,107 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use data::DataIdentifier;
+#[cfg(feature = "serialisation")]
+use error::Error;
+#[cfg(feature = "serialisation")]
+use serialisation::serialise;
+use std::fmt::{self, Debug, Formatter};
+
+/// Plain data with a name and a value, neither of which is validated.
+#[derive(Hash, Clone, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serialisation", derive(RustcEncodable, RustcDecodable))]
+pub struct PlainData {
+    name: [u8; 32],
+    value: Vec<u8>,
+}
+
+impl PlainData {
+    /// Creates a new instance of `PlainData`
+    pub fn new(name: [u8; 32], value: Vec<u8>) -> PlainData {
+        PlainData {
+            name: name,
+            value: value,
+        }
+    }
+
+    /// Returns the value
+    pub fn value(&self) -> &Vec<u8> {
+        &self.value
+    }
+
+    /// Returns name
+    pub fn name(&self) -> &[u8; 32] {
+        &self.name
+    }
+
+    /// Returns size of contained value.
+    pub fn payload_size(&self) -> usize {
+        self.value.len()
+    }
+
+    /// Returns the exact size of the serialised data.
+    #[cfg(feature = "serialisation")]
+    pub fn serialised_size(&self) -> Result<usize, Error> {
+        Ok(serialise(self)?.len())
+    }
+
+    /// Returns the size of the serialised data, worked out from the fields without serialising.
+    pub fn estimated_size(&self) -> usize {
+        self.name.len() + 8 + self.value.len()
+    }
+
+    /// Returns `DataIdentifier` for this data element.
+    pub fn identifier(&self) -> DataIdentifier {
+        DataIdentifier::Plain(self.name)
+    }
+}
+
+impl Debug for PlainData {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter, "PlainData {:?}", self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::DataIdentifier;
+    use rand;
+    use serialisation::deserialise;
+
+    #[test]
+    fn name_is_not_derived_from_value() {
+        let name: [u8; 32] = rand::random();
+        let value = "plain data value".to_owned().into_bytes();
+        let plain_data = PlainData::new(name, value.clone());
+
+        assert_eq!(plain_data.name(), &name);
+        assert_eq!(plain_data.value(), &value);
+        assert_eq!(plain_data.payload_size(), value.len());
+        assert_eq!(plain_data.identifier(), DataIdentifier::Plain(name));
+    }
+
+    #[test]
+    fn serialisation() {
+        let plain_data = PlainData::new(rand::random(), vec![1, 2, 3]);
+        let serialised = unwrap!(serialise(&plain_data));
+        assert_eq!(plain_data, unwrap!(deserialise::<PlainData>(&serialised)));
+        assert_eq!(plain_data.estimated_size(), serialised.len());
+        assert_eq!(unwrap!(plain_data.serialised_size()), serialised.len());
+    }
+}
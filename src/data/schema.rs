@@ -0,0 +1,163 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Schemas
+//! Applications register a validator for each type tag whose `StructuredData` content has a
+//! known format, e.g. DNS records or directory listings. `StructuredData::new_checked` and
+//! `StructuredData::replace_with_other_checked` run it on the content, so malformed items are
+//! refused when they are created or updated rather than found after they were stored.
+//!
+//! Validators see the decompressed content. Content encrypted with `encrypt_data` can only be
+//! checked by its readers, so its type tags should not be registered.
+
+use data::StructuredData;
+use error::Error;
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+
+/// Validators of the content of `StructuredData`, by type tag.
+#[derive(Default)]
+pub struct SchemaRegistry {
+    validators: HashMap<u64, Box<Fn(&[u8]) -> bool + Send + Sync>>,
+}
+
+impl SchemaRegistry {
+    /// Creates a registry with no validators, accepting any content.
+    pub fn new() -> SchemaRegistry {
+        SchemaRegistry::default()
+    }
+
+    /// Registers `validator` for content with `type_tag`, replacing any previous one. It returns
+    /// whether the content is well-formed.
+    pub fn register<F>(&mut self, type_tag: u64, validator: F)
+        where F: Fn(&[u8]) -> bool + Send + Sync + 'static
+    {
+        let _ = self.validators.insert(type_tag, Box::new(validator));
+    }
+
+    /// Removes the validator for `type_tag`, returning whether there was one.
+    pub fn unregister(&mut self, type_tag: u64) -> bool {
+        self.validators.remove(&type_tag).is_some()
+    }
+
+    /// Returns whether a validator is registered for `type_tag`.
+    pub fn is_registered(&self, type_tag: u64) -> bool {
+        self.validators.contains_key(&type_tag)
+    }
+
+    /// Checks `content` with the validator for `type_tag`, failing with `Validation` if it is
+    /// malformed. Content of type tags without a validator is accepted.
+    pub fn validate_content(&self, type_tag: u64, content: &[u8]) -> Result<(), Error> {
+        match self.validators.get(&type_tag) {
+            Some(validator) if !validator(content) => Err(Error::Validation),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks the decompressed content of `structured_data` with the validator for its type tag.
+    pub fn validate(&self, structured_data: &StructuredData) -> Result<(), Error> {
+        if !self.is_registered(structured_data.get_type_tag()) {
+            return Ok(());
+        }
+        self.validate_content(structured_data.get_type_tag(),
+                              &structured_data.decompressed_data()?)
+    }
+}
+
+impl Debug for SchemaRegistry {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        let mut type_tags: Vec<_> = self.validators.keys().collect();
+        type_tags.sort();
+        write!(formatter, "SchemaRegistry {{ type_tags: {:?} }}", type_tags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::StructuredData;
+    use error::Error;
+    use rand;
+    use std::str;
+    use test_utils;
+
+    const DNS_TYPE_TAG: u64 = 5;
+
+    fn schemas() -> SchemaRegistry {
+        let mut schemas = SchemaRegistry::new();
+        schemas.register(DNS_TYPE_TAG, |content| {
+            str::from_utf8(content).map(|record| record.contains('.')).unwrap_or(false)
+        });
+        schemas
+    }
+
+    #[test]
+    fn create_and_replace() {
+        let schemas = schemas();
+        let keys = test_utils::gen_keypair();
+        let name = rand::random();
+        let create = |type_tag, version, content: &[u8]| {
+            StructuredData::new_checked(type_tag,
+                                        name,
+                                        version,
+                                        content.to_vec(),
+                                        vec![keys.0],
+                                        vec![],
+                                        Some(&keys.1),
+                                        false,
+                                        &schemas)
+        };
+
+        let mut structured_data = unwrap!(create(DNS_TYPE_TAG, 0, b"example.net"));
+        match create(DNS_TYPE_TAG, 0, b"malformed") {
+            Err(Error::Validation) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        // Other type tags are not checked.
+        let _ = unwrap!(create(DNS_TYPE_TAG + 1, 0, b"malformed"));
+
+        let malformed = unwrap!(StructuredData::new(DNS_TYPE_TAG,
+                                                    name,
+                                                    1,
+                                                    b"malformed".to_vec(),
+                                                    vec![keys.0],
+                                                    vec![],
+                                                    Some(&keys.1),
+                                                    false));
+        match structured_data.replace_with_other_checked(malformed, &schemas) {
+            Err(Error::Validation) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert_eq!(structured_data.get_version(), 0);
+        unwrap!(structured_data.replace_with_other_checked(unwrap!(create(DNS_TYPE_TAG,
+                                                                          1,
+                                                                          b"example.org")),
+                                                           &schemas));
+        assert_eq!(structured_data.get_version(), 1);
+    }
+
+    #[test]
+    fn unregister() {
+        let mut schemas = schemas();
+        assert!(schemas.is_registered(DNS_TYPE_TAG));
+        assert!(schemas.validate_content(DNS_TYPE_TAG, b"malformed").is_err());
+        assert!(schemas.unregister(DNS_TYPE_TAG));
+        assert!(!schemas.unregister(DNS_TYPE_TAG));
+        assert!(!schemas.is_registered(DNS_TYPE_TAG));
+        unwrap!(schemas.validate_content(DNS_TYPE_TAG, b"malformed"));
+    }
+}
@@ -0,0 +1,217 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use crypto::ct;
+use crypto::sign::{self, PublicKey, Signature};
+use data::{SignaturePolicy, StructuredData};
+use error::Error;
+use std::collections::BTreeMap;
+
+/// Collects the owners' signatures for a multi-owner `StructuredData` update.
+///
+/// The session holds the unsigned data and the bytes to sign, which can be handed to each owner.
+/// Detached signatures returned by the owners are checked against the owner's own key as they
+/// arrive, and once enough have been collected `finalise()` yields the signed data.
+///
+/// The number of signatures required is taken from the data's own signature policy, which must
+/// match the policy of the version it replaces.
+pub struct SigningSession {
+    data: StructuredData,
+    data_to_sign: Vec<u8>,
+    owner_keys: Vec<PublicKey>,
+    policy: SignaturePolicy,
+    signatures: BTreeMap<PublicKey, Signature>,
+}
+
+impl SigningSession {
+    /// Starts a session for `data`.
+    ///
+    /// Any signatures already on `data` which are valid for one of the owners are kept; invalid
+    /// ones are discarded.
+    pub fn new(mut data: StructuredData) -> Result<SigningSession, Error> {
+        let data_to_sign = data.signable_bytes()?;
+        let owner_keys = if data.get_previous_owner_keys().is_empty() {
+            data.get_owner_keys().to_vec()
+        } else {
+            data.get_previous_owner_keys().to_vec()
+        };
+        let policy = data.signature_policy();
+        let mut signatures = BTreeMap::new();
+        for signature in data.get_previous_owner_signatures() {
+            let signer = owner_keys.iter()
+                .find(|key| sign::verify_detached(signature, &data_to_sign, key));
+            if let Some(key) = signer {
+                let _ = signatures.insert(*key, *signature);
+            }
+        }
+        data.replace_signatures(vec![]);
+        Ok(SigningSession {
+            data: data,
+            data_to_sign: data_to_sign,
+            owner_keys: owner_keys,
+            policy: policy,
+            signatures: signatures,
+        })
+    }
+
+    /// The bytes each owner has to sign.
+    pub fn data_to_sign(&self) -> &[u8] {
+        &self.data_to_sign
+    }
+
+    /// The keys whose signatures count towards this update.
+    pub fn owner_keys(&self) -> &[PublicKey] {
+        &self.owner_keys
+    }
+
+    /// Has `owner` signed yet.
+    pub fn has_signed(&self, owner: &PublicKey) -> bool {
+        self.signatures.contains_key(owner)
+    }
+
+    /// The owners who have not signed yet.
+    pub fn pending_owners(&self) -> Vec<PublicKey> {
+        self.owner_keys.iter().filter(|key| !self.has_signed(key)).cloned().collect()
+    }
+
+    /// Number of signatures still required.
+    pub fn remaining(&self) -> usize {
+        let signers: Vec<PublicKey> = self.signatures.keys().cloned().collect();
+        self.policy.remaining_signatures(&signers, &self.owner_keys)
+    }
+
+    /// Have enough owners signed.
+    pub fn is_complete(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// Adds `owner`'s detached signature and returns the number of signatures still required.
+    ///
+    /// Fails with `AccessDenied` if `owner` is not one of the owner keys and with `Signature` if
+    /// the signature is not `owner`'s signature of `data_to_sign()`. Adding a signature again is
+    /// harmless.
+    pub fn add_signature(&mut self,
+                         owner: &PublicKey,
+                         signature: Signature)
+                         -> Result<usize, Error> {
+        if !ct::contains_key(&self.owner_keys, owner) {
+            return Err(Error::AccessDenied);
+        }
+        if !sign::verify_detached(&signature, &self.data_to_sign, owner) {
+            return Err(Error::Signature);
+        }
+        let _ = self.signatures.insert(*owner, signature);
+        Ok(self.remaining())
+    }
+
+    /// Returns the data signed by the collected signatures, or `Majority` if not enough owners
+    /// have signed yet.
+    pub fn finalise(self) -> Result<StructuredData, Error> {
+        if !self.is_complete() {
+            return Err(Error::Majority);
+        }
+        let mut data = self.data;
+        data.replace_signatures(self.signatures.into_iter().map(|(_, sig)| sig).collect());
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crypto::sign;
+    use data::{SignaturePolicy, StructuredData};
+    use error::Error;
+    use rand;
+    use test_utils;
+
+    #[test]
+    fn collects_owner_signatures() {
+        let keys: Vec<_> = (0..5).map(|_| test_utils::gen_keypair()).collect();
+        let owner_keys: Vec<_> = keys.iter().map(|key| key.0).collect();
+        let name = rand::random();
+        let mut original = unwrap!(StructuredData::new(0,
+                                                       name,
+                                                       0,
+                                                       vec![],
+                                                       owner_keys.clone(),
+                                                       vec![],
+                                                       None,
+                                                       false));
+        original.set_signature_policy(SignaturePolicy::Threshold(3));
+        let mut update = unwrap!(StructuredData::new(0,
+                                                     name,
+                                                     1,
+                                                     b"update".to_vec(),
+                                                     owner_keys,
+                                                     vec![],
+                                                     Some(&keys[0].1),
+                                                     false));
+        update.set_signature_policy(SignaturePolicy::Threshold(3));
+        let _ = unwrap!(update.add_signature(&keys[0].1));
+
+        let mut session = unwrap!(SigningSession::new(update));
+        assert!(session.has_signed(&keys[0].0));
+        assert_eq!(session.remaining(), 2);
+        assert_eq!(session.pending_owners().len(), 4);
+
+        let sign = |index: usize| sign::sign_detached(session.data_to_sign(), &keys[index].1);
+        let (first, second) = (sign(1), sign(2));
+
+        // Signatures must come from the claimed owner.
+        match session.add_signature(&keys[3].0, first) {
+            Err(Error::Signature) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        let stranger = test_utils::gen_keypair();
+        let signature = sign::sign_detached(session.data_to_sign(), &stranger.1);
+        match session.add_signature(&stranger.0, signature) {
+            Err(Error::AccessDenied) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        assert_eq!(unwrap!(session.add_signature(&keys[1].0, first)), 1);
+        assert_eq!(unwrap!(session.add_signature(&keys[1].0, first)), 1);
+        assert!(!session.is_complete());
+        assert_eq!(unwrap!(session.add_signature(&keys[2].0, second)), 0);
+        assert!(session.is_complete());
+
+        let signed = unwrap!(session.finalise());
+        assert_eq!(signed.get_previous_owner_signatures().len(), 3);
+        unwrap!(original.replace_with_other(signed));
+        assert_eq!(original.version(), 1);
+    }
+
+    #[test]
+    fn finalise_requires_enough_signatures() {
+        let keys: Vec<_> = (0..3).map(|_| test_utils::gen_keypair()).collect();
+        let data = unwrap!(StructuredData::new(0,
+                                               rand::random(),
+                                               0,
+                                               vec![],
+                                               keys.iter().map(|key| key.0).collect(),
+                                               vec![],
+                                               Some(&keys[0].1),
+                                               false));
+        let session = unwrap!(SigningSession::new(data));
+        assert_eq!(session.remaining(), 1);
+        match session.finalise() {
+            Err(Error::Majority) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
@@ -15,35 +15,234 @@
 // Please review the Licences for the specific language governing permissions and limitations
 // relating to use of the SAFE Network Software.
 
+#[cfg(feature = "audit")]
+use audit::{self, AuditOperation};
+use crypto::ct;
+#[cfg(all(feature = "serialisation", feature = "sodium"))]
+use crypto::KeyRatchet;
+use crypto::ValidationCache;
+use crypto::sign::{self, PublicKey, SecretKey, Signature};
 use data::DataIdentifier;
+use data::canonical::{CanonicalWriter, STRUCTURED_DATA_DOMAIN};
+use data::compression::{self, Compression};
+#[cfg(feature = "serialisation")]
+use data::envelope;
+use data::metadata::DataMetadata;
+use data::owners::Owners;
+#[cfg(feature = "serialisation")]
+use data::pending_update::PendingUpdate;
+use data::schema::SchemaRegistry;
+use data::version::Version;
 use error::Error;
-use maidsafe_utilities::serialisation::serialise;
-use rust_sodium::crypto::sign::{self, PublicKey, SecretKey, Signature};
+#[cfg(feature = "metrics")]
+use metrics;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+#[cfg(feature = "serialisation")]
+use rustc_serialize::{Decodable, Decoder, Encodable, Encoder};
+#[cfg(all(feature = "serialisation", feature = "sodium"))]
+use rust_sodium::crypto::{box_, sealedbox, secretbox};
+#[cfg(all(feature = "serialisation", feature = "sodium"))]
+use serialisation::deserialise;
+#[cfg(feature = "serialisation")]
+use serialisation::serialise;
+use sha3::hash;
+use std::borrow::Cow;
+use std::cmp;
 use std::fmt::{self, Debug, Formatter};
+#[cfg(any(feature = "compression", all(feature = "serialisation", feature = "sodium")))]
+use std::mem;
+#[cfg(feature = "parallel")]
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-/// Maximum allowed size for a Structured Data to grow to
+/// Maximum allowed size for a Structured Data to grow to, measured in serialised bytes, beyond
+/// the `sign::size_allowance` for its keys and signatures
 pub const MAX_BYTES: usize = 102400;
+/// Maximum allowed size of the application-defined metadata of a Structured Data
+pub const MAX_APP_METADATA_BYTES: usize = 1024;
+/// Maximum number of keys in each of the current owners, previous owners and writers of a
+/// Structured Data, bounding the cost of verifying it
+pub const MAX_OWNERS: usize = 512;
+
+/// Version of the encoding of `StructuredData` used for signing.
+///
+/// Since version 1 owner keys are held and signed in canonical (sorted) order, so the validity of
+/// a signature does not depend on the order in which owner keys were supplied. Since version 2
+/// the signed bytes use the canonical encoding of the `canonical` module, prefixed with
+/// `STRUCTURED_DATA_DOMAIN`. Since version 3 owner keys are held as `Owners`, so are also
+/// deduplicated, both on construction and when decoding.
+pub const WIRE_VERSION: u8 = 3;
+
+/// Bit of the serialised flags byte set for ledger data.
+#[cfg(feature = "serialisation")]
+const LEDGER_FLAG: u8 = 1;
+/// Bit of the serialised flags byte set for soft-deleted data.
+#[cfg(feature = "serialisation")]
+const SOFT_DELETED_FLAG: u8 = 2;
+/// Bit of the serialised flags byte set when writers follow it.
+#[cfg(feature = "serialisation")]
+const WRITERS_FLAG: u8 = 4;
+/// Bit of the serialised flags byte set for frozen data.
+#[cfg(feature = "serialisation")]
+const FROZEN_FLAG: u8 = 8;
+
+/// Number of owner signatures required for an update to be valid.
+///
+/// The policy of the current version decides whether a successor is validly signed, so a successor
+/// may change the policy only with the approval required by its predecessor.
+#[derive(Hash, Eq, PartialEq, PartialOrd, Ord, Clone, Debug)]
+#[cfg_attr(feature = "serialisation", derive(RustcEncodable, RustcDecodable))]
+pub enum SignaturePolicy {
+    /// At least half of the owners must sign. `add_signature` only reports no signatures missing
+    /// once a strict majority has signed.
+    Majority,
+    /// Every owner must sign.
+    All,
+    /// At least this many owners must sign (and at least one).
+    Threshold(u64),
+    /// The weights of the owners who sign must add up to at least the threshold (and at least
+    /// one). Owners not listed weigh 1.
+    Weighted(Vec<(PublicKey, u64)>, u64),
+}
+
+impl SignaturePolicy {
+    /// Number of valid signatures needed from `owners` owners.
+    pub fn required_signatures(&self, owners: usize) -> usize {
+        match *self {
+            SignaturePolicy::Majority => owners / 2 + owners % 2,
+            SignaturePolicy::All => owners,
+            SignaturePolicy::Threshold(count) => cmp::max(count as usize, 1),
+            // However heavy, at least one owner must sign; see `remaining_signatures`.
+            SignaturePolicy::Weighted(..) => 1,
+        }
+    }
+
+    /// The weight of `owner`'s signature: as listed for `Weighted`, otherwise 1.
+    pub fn weight_of(&self, owner: &PublicKey) -> u64 {
+        match *self {
+            SignaturePolicy::Weighted(ref weights, _) => {
+                weights.iter()
+                    .find(|&&(ref key, _)| ct::key_eq(key, owner))
+                    .map_or(1, |&(_, weight)| weight)
+            }
+            _ => 1,
+        }
+    }
+
+    /// Number of further signatures needed from `owners` once `signers` have signed, taking the
+    /// heaviest owners first for `Weighted`. If the owners yet to sign cannot satisfy the policy,
+    /// this is more than their number.
+    pub fn remaining_signatures(&self, signers: &[PublicKey], owners: &[PublicKey]) -> usize {
+        let (signed, unsigned): (Vec<&PublicKey>, Vec<&PublicKey>) =
+            owners.iter().partition(|owner| ct::contains_key(signers, owner));
+        match *self {
+            SignaturePolicy::Weighted(_, threshold) => {
+                let threshold = cmp::max(threshold, 1);
+                let mut weight = signed.iter()
+                    .fold(0u64, |sum, key| sum.saturating_add(self.weight_of(key)));
+                let mut weights: Vec<u64> =
+                    unsigned.iter().map(|key| self.weight_of(key)).collect();
+                weights.sort_by(|lhs, rhs| rhs.cmp(lhs));
+                for (count, extra) in weights.iter().enumerate() {
+                    if weight >= threshold {
+                        return count;
+                    }
+                    weight = weight.saturating_add(*extra);
+                }
+                if weight >= threshold {
+                    weights.len()
+                } else {
+                    weights.len() + 1
+                }
+            }
+            _ => self.required_signatures(owners.len()).saturating_sub(signed.len()),
+        }
+    }
+}
+
+impl Default for SignaturePolicy {
+    fn default() -> SignaturePolicy {
+        SignaturePolicy::Majority
+    }
+}
+
+/// How far a `StructuredData` is through being signed, as reported by `validation_state`.
+#[derive(Hash, Eq, PartialEq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub enum ValidationState {
+    /// None of the owners whose signatures are required has signed.
+    Unsigned,
+    /// Some owners have signed but this many more signatures are needed.
+    PartiallySigned(usize),
+    /// Enough owners have signed to satisfy the signature policy.
+    Valid,
+}
+
+/// Which of two competing versions wins, as decided by `StructuredData::resolve_conflict`.
+#[derive(Hash, Eq, PartialEq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub enum Winner {
+    /// The first version given wins.
+    First,
+    /// The second version given wins.
+    Second,
+}
 
 /// Mutable structured data.
 ///
 /// The name is computed from the type tag and identifier, so these two fields are immutable.
 ///
 /// These types may be stored unsigned with previous and current owner keys
-/// set to the same keys. Updates require a signature to validate.
-#[derive(Hash, Eq, PartialEq, PartialOrd, Ord, Clone, RustcDecodable, RustcEncodable)]
+/// set to the same keys. Updates require a signature to validate. `validation_state` tells
+/// unsigned and partially signed data from valid data.
+///
+/// Owner keys are held as `Owners`, sorted by key bytes and without duplicates.
+///
+/// Updates need signatures from the owners as set out by the `SignaturePolicy`, a majority by
+/// default.
+///
+/// The data may carry a compression hint, in which case it is stored compressed and
+/// `decompressed_data` recovers the original content.
+///
+/// Ephemeral data, such as presence records, may carry an expiry time after which caches and
+/// vaults are free to drop it.
+///
+/// Small application-defined metadata, such as a MIME type or schema id, may be kept apart from
+/// the data so it can be read without decoding the data.
+///
+/// Every version of ledger data after the first commits to the `ledger_hash` of the version
+/// before it, so the versions form a hash-linked history which can be checked with
+/// `ledger::verify_chain`.
+///
+/// The owners may name writers, e.g. an app server, who can publish versions changing nothing
+/// but the data without holding an owner key (see `set_writers`).
+///
+/// Every version needs at least one current owner, except a tombstone and frozen data, which is
+/// created without owners by `new_frozen` and can never be updated. No list of keys may be longer
+/// than `MAX_OWNERS`.
+#[derive(Hash, Eq, PartialEq, PartialOrd, Ord, Clone)]
 pub struct StructuredData {
     type_tag: u64,
     name: [u8; 32],
     data: Vec<u8>,
-    previous_owner_keys: Vec<PublicKey>,
+    previous_owner_keys: Owners,
     version: u64,
-    current_owner_keys: Vec<PublicKey>,
+    current_owner_keys: Owners,
     previous_owner_signatures: Vec<Signature>,
     ledger: bool,
+    policy: SignaturePolicy,
+    compression: Option<Compression>,
+    expires_at: Option<u64>,
+    previous_hash: Option<[u8; 32]>,
+    app_metadata: Vec<u8>,
+    soft_deleted: bool,
+    writers: Owners,
+    frozen: bool,
 }
 
 impl StructuredData {
     /// Creates a new `StructuredData` signed with `signing_key`.
+    ///
+    /// Fails with `NoOwners` if there are no current owners, unless this is a tombstone, and with
+    /// `TooManyOwners` if there are more than `MAX_OWNERS` current or previous owners.
     #[cfg_attr(feature = "cargo-clippy", allow(too_many_arguments))]
     pub fn new(type_tag: u64,
                name: [u8; 32],
@@ -54,24 +253,91 @@ impl StructuredData {
                signing_key: Option<&SecretKey>,
                ledger: bool)
                -> Result<StructuredData, Error> {
+        let mut structured_data = StructuredData::unsigned(type_tag,
+                                                           name,
+                                                           version,
+                                                           data,
+                                                           current_owner_keys,
+                                                           previous_owner_keys,
+                                                           ledger,
+                                                           false)?;
+        if let Some(key) = signing_key {
+            let _ = structured_data.add_signature(key)?;
+        }
+        structured_data.validate_size()?;
+        Ok(structured_data)
+    }
+
+    /// Creates version 0 of frozen `StructuredData`, which has no owners, so can never be updated
+    /// or deleted, e.g. for published content nobody should control.
+    pub fn new_frozen(type_tag: u64,
+                      name: [u8; 32],
+                      data: Vec<u8>,
+                      ledger: bool)
+                      -> Result<StructuredData, Error> {
+        let structured_data =
+            StructuredData::unsigned(type_tag, name, 0, data, vec![], vec![], ledger, true)?;
+        structured_data.validate_size()?;
+        Ok(structured_data)
+    }
 
+    #[cfg_attr(feature = "cargo-clippy", allow(too_many_arguments))]
+    fn unsigned(type_tag: u64,
+                name: [u8; 32],
+                version: u64,
+                data: Vec<u8>,
+                current_owner_keys: Vec<PublicKey>,
+                previous_owner_keys: Vec<PublicKey>,
+                ledger: bool,
+                frozen: bool)
+                -> Result<StructuredData, Error> {
         let mut structured_data = StructuredData {
             type_tag: type_tag,
             name: name,
             data: data,
-            previous_owner_keys: previous_owner_keys,
+            previous_owner_keys: Owners::new(previous_owner_keys),
             version: version,
-            current_owner_keys: current_owner_keys,
+            current_owner_keys: Owners::new(current_owner_keys),
             previous_owner_signatures: vec![],
             ledger: ledger,
+            policy: SignaturePolicy::default(),
+            compression: None,
+            expires_at: None,
+            previous_hash: None,
+            app_metadata: Vec::new(),
+            soft_deleted: false,
+            writers: Owners::default(),
+            frozen: frozen,
         };
-
-        if let Some(key) = signing_key {
-            let _ = structured_data.add_signature(key)?;
-        }
+        structured_data.canonicalise();
+        structured_data.validate_owners()?;
         Ok(structured_data)
     }
 
+    /// Creates a new `StructuredData` as `new` does, first checking `data` with the validator
+    /// registered in `schemas` for `type_tag`.
+    #[cfg_attr(feature = "cargo-clippy", allow(too_many_arguments))]
+    pub fn new_checked(type_tag: u64,
+                       name: [u8; 32],
+                       version: u64,
+                       data: Vec<u8>,
+                       current_owner_keys: Vec<PublicKey>,
+                       previous_owner_keys: Vec<PublicKey>,
+                       signing_key: Option<&SecretKey>,
+                       ledger: bool,
+                       schemas: &SchemaRegistry)
+                       -> Result<StructuredData, Error> {
+        schemas.validate_content(type_tag, &data)?;
+        StructuredData::new(type_tag,
+                            name,
+                            version,
+                            data,
+                            current_owner_keys,
+                            previous_owner_keys,
+                            signing_key,
+                            ledger)
+    }
+
     /// Replaces this data item with the given updated version if the update is valid, otherwise
     /// returns an error.
     ///
@@ -79,6 +345,21 @@ impl StructuredData {
     /// To transfer ownership, the current owner signs over the data; the previous owners field
     /// must have the previous owners of `version - 1` as the current owners of that last version.
     pub fn replace_with_other(&mut self, other: StructuredData) -> Result<(), Error> {
+        #[cfg(feature = "audit")]
+        let version = other.version;
+        let result = self.do_replace_with_other(other);
+        #[cfg(feature = "audit")]
+        audit::record(AuditOperation::ReplaceWithOther,
+                      self.identifier(),
+                      Some(version),
+                      &result);
+        #[cfg(feature = "metrics")]
+        metrics::record_validation(&result);
+        result
+    }
+
+    fn do_replace_with_other(&mut self, other: StructuredData) -> Result<(), Error> {
+        other.validate_size()?;
         self.validate_self_against_successor(&other)?;
 
         self.type_tag = other.type_tag;
@@ -88,9 +369,60 @@ impl StructuredData {
         self.version = other.version;
         self.current_owner_keys = other.current_owner_keys;
         self.previous_owner_signatures = other.previous_owner_signatures;
+        self.policy = other.policy;
+        self.compression = other.compression;
+        self.expires_at = other.expires_at;
+        self.previous_hash = other.previous_hash;
+        self.app_metadata = other.app_metadata;
+        self.soft_deleted = other.soft_deleted;
+        self.writers = other.writers;
+        self.frozen = other.frozen;
         Ok(())
     }
 
+    /// Replaces this data item with `other` as `replace_with_other` does, first checking the
+    /// content of `other` with the validator registered in `schemas` for its type tag.
+    pub fn replace_with_other_checked(&mut self,
+                                      other: StructuredData,
+                                      schemas: &SchemaRegistry)
+                                      -> Result<(), Error> {
+        schemas.validate(&other)?;
+        self.replace_with_other(other)
+    }
+
+    /// Returns an error if the serialised data is larger than `max_size` or the
+    /// application-defined metadata larger than `MAX_APP_METADATA_BYTES`.
+    pub fn validate_size(&self) -> Result<(), Error> {
+        if self.app_metadata.len() > MAX_APP_METADATA_BYTES || self.size()? > self.max_size() {
+            return Err(Error::DataTooLarge);
+        }
+        Ok(())
+    }
+
+    /// Returns the largest serialised size allowed: `MAX_BYTES` plus the `sign::size_allowance`
+    /// for the keys and signatures held, which is zero with Ed25519 keys.
+    pub fn max_size(&self) -> usize {
+        let weighted_keys = match self.policy {
+            SignaturePolicy::Weighted(ref weights, _) => weights.len(),
+            _ => 0,
+        };
+        let keys = self.previous_owner_keys.len() + self.current_owner_keys.len() +
+                   self.writers.len() + weighted_keys;
+        MAX_BYTES + sign::size_allowance(keys, self.previous_owner_signatures.len())
+    }
+
+    /// Are the owner keys in canonical (sorted) order. Always true, as they are held as `Owners`.
+    pub fn is_canonical(&self) -> bool {
+        let is_sorted = |keys: &[PublicKey]| keys.windows(2).all(|pair| pair[0] < pair[1]);
+        is_sorted(&self.previous_owner_keys[..]) && is_sorted(&self.current_owner_keys[..])
+    }
+
+    fn canonicalise(&mut self) {
+        if let SignaturePolicy::Weighted(ref mut weights, _) = self.policy {
+            weights.sort();
+        }
+    }
+
     /// Returns the name.
     pub fn name(&self) -> &[u8; 32] {
         &self.name
@@ -109,6 +441,261 @@ impl StructuredData {
         DataIdentifier::Structured(self.name, self.type_tag)
     }
 
+    /// Creates the successor of this data owned by `new_owners`, signed with `signing_key`.
+    ///
+    /// The successor keeps the type tag, name and data, increments the version and records the
+    /// current owners as previous owners. Further owners may need to `add_signature` before it
+    /// becomes a valid update.
+    pub fn transfer_ownership(&self,
+                              new_owners: Vec<PublicKey>,
+                              signing_key: &SecretKey)
+                              -> Result<StructuredData, Error> {
+        if self.is_deleted() || self.is_soft_deleted() {
+            return Err(Error::DataDeleted);
+        }
+        if new_owners.is_empty() {
+            return Err(Error::Validation);
+        }
+        self.successor(self.data.clone(), self.compression, new_owners, false, signing_key)
+    }
+
+    /// Creates the successor of this data in which owner key `old` is replaced by `new`, signed
+    /// with `signing_key`.
+    ///
+    /// Unlike other transfers of ownership, a rotation is not approved by the key it replaces
+    /// unless that is the only owner: `old` may be compromised, so the remaining owners must sign
+    /// as the signature policy requires (capped at the number of remaining owners).
+    pub fn rotate_owner_key(&self,
+                            old: &PublicKey,
+                            new: PublicKey,
+                            signing_key: &SecretKey)
+                            -> Result<StructuredData, Error> {
+        if !self.current_owner_keys.contains(old) {
+            return Err(Error::NoSuchEntry);
+        }
+        if self.current_owner_keys.contains(&new) {
+            return Err(Error::EntryExists);
+        }
+        let mut new_owners: Vec<_> = self.current_owner_keys
+            .iter()
+            .filter(|key| !ct::key_eq(key, old))
+            .cloned()
+            .collect();
+        new_owners.push(new);
+        self.transfer_ownership(new_owners, signing_key)
+    }
+
+    /// Returns the owner key `other` rotates out if it is a key rotation of `self`: a transfer of
+    /// ownership replacing exactly one of the current owner keys.
+    fn rotated_out_key(&self, other: &StructuredData) -> Option<PublicKey> {
+        if other.previous_owner_keys.is_empty() ||
+           other.current_owner_keys.len() != self.current_owner_keys.len() {
+            return None;
+        }
+        let mut removed = self.current_owner_keys
+            .iter()
+            .filter(|key| !other.current_owner_keys.contains(key));
+        match (removed.next(), removed.next()) {
+            (Some(key), None) => Some(*key),
+            _ => None,
+        }
+    }
+
+    /// Creates the tombstone successor of this data, signed with `signing_key`.
+    ///
+    /// The tombstone keeps the type tag and name, increments the version and has empty data and
+    /// no current owners; the current owners become the previous owners who must sign it. Ledger
+    /// data is never deleted.
+    pub fn delete(&self, signing_key: &SecretKey) -> Result<StructuredData, Error> {
+        if self.ledger {
+            return Err(Error::Validation);
+        }
+        if self.is_deleted() {
+            return Err(Error::DataDeleted);
+        }
+        self.successor(vec![], None, vec![], false, signing_key)
+    }
+
+    /// Creates the successor of this data with the data removed but the owners kept, marked as
+    /// soft-deleted and signed with `signing_key`.
+    ///
+    /// Unlike a tombstone made by `delete`, soft-deleted data can be restored with `undelete`,
+    /// and only by the same owners: until then its owners can't change, though it can still be
+    /// deleted for good. Fails with `DataDeleted` if the data is already deleted either way.
+    pub fn soft_delete(&self, signing_key: &SecretKey) -> Result<StructuredData, Error> {
+        if self.is_deleted() || self.is_soft_deleted() {
+            return Err(Error::DataDeleted);
+        }
+        self.successor(vec![], None, self.current_owner_keys.to_vec(), true, signing_key)
+    }
+
+    /// Creates the successor of soft-deleted data holding `data` again, owned by the same owners
+    /// and signed with `signing_key`.
+    ///
+    /// Fails with `DataDeleted` if the data has been deleted for good, and with `Validation` if
+    /// it is not soft-deleted or `data` is empty.
+    pub fn undelete(&self,
+                    data: Vec<u8>,
+                    signing_key: &SecretKey)
+                    -> Result<StructuredData, Error> {
+        if self.is_deleted() {
+            return Err(Error::DataDeleted);
+        }
+        if !self.is_soft_deleted() || data.is_empty() {
+            return Err(Error::Validation);
+        }
+        self.successor(data, None, self.current_owner_keys.to_vec(), false, signing_key)
+    }
+
+    /// Creates the successor of this data holding `data`, signed with `writer_key`, the secret key
+    /// of one of the writers. Everything but the data and version stays as it is.
+    ///
+    /// Fails with `DataDeleted` if the data is deleted either way, and with `Validation` if `data`
+    /// is empty: writers cannot delete the data.
+    pub fn writer_update(&self,
+                         data: Vec<u8>,
+                         writer_key: &SecretKey)
+                         -> Result<StructuredData, Error> {
+        if self.is_deleted() || self.is_soft_deleted() {
+            return Err(Error::DataDeleted);
+        }
+        if data.is_empty() {
+            return Err(Error::Validation);
+        }
+        self.successor(data,
+                       self.compression,
+                       self.current_owner_keys.to_vec(),
+                       false,
+                       writer_key)
+    }
+
+    /// Creates the next version with the given data and owners, keeping the policy and recording
+    /// the current owners as previous owners.
+    fn successor(&self,
+                 data: Vec<u8>,
+                 compression: Option<Compression>,
+                 new_owners: Vec<PublicKey>,
+                 soft_deleted: bool,
+                 signing_key: &SecretKey)
+                 -> Result<StructuredData, Error> {
+        let mut successor = StructuredData::new(self.type_tag,
+                                                self.name,
+                                                Version(self.version).next()?.0,
+                                                data,
+                                                new_owners,
+                                                self.current_owner_keys.to_vec(),
+                                                None,
+                                                self.ledger)?;
+        successor.policy = self.policy.clone();
+        successor.compression = compression;
+        successor.expires_at = self.expires_at;
+        successor.app_metadata = self.app_metadata.clone();
+        successor.soft_deleted = soft_deleted;
+        successor.writers = self.writers.clone();
+        if self.ledger {
+            successor.previous_hash = Some(self.ledger_hash()?);
+        }
+        let _ = successor.add_signature(signing_key)?;
+        successor.validate_size()?;
+        Ok(successor)
+    }
+
+    /// Returns a patch turning this data into `other`, to send in place of `other` when this
+    /// version is known to be held at the other end.
+    ///
+    /// The patch holds `other` without its data, plus the part of the data which differs from
+    /// this data's: whatever lies between the longest common prefix and suffix.
+    pub fn diff(&self, other: &StructuredData) -> Result<StructuredDataPatch, Error> {
+        let prefix = self.data.iter().zip(&other.data).take_while(|&(a, b)| a == b).count();
+        let suffix = self.data[prefix..]
+            .iter()
+            .rev()
+            .zip(other.data[prefix..].iter().rev())
+            .take_while(|&(a, b)| a == b)
+            .count();
+        let mut successor = other.clone();
+        successor.data = Vec::new();
+        Ok(StructuredDataPatch {
+            base_hash: self.content_hash()?,
+            prefix: prefix as u64,
+            suffix: suffix as u64,
+            inserted: other.data[prefix..other.data.len() - suffix].to_vec(),
+            successor: successor,
+        })
+    }
+
+    /// Reconstructs the data `patch` was made for from this data and checks it is a valid
+    /// successor of this data, as `validate_self_against_successor` does.
+    ///
+    /// Fails with `Validation` if the patch was made against another version.
+    pub fn apply_patch(&self, patch: StructuredDataPatch) -> Result<StructuredData, Error> {
+        if patch.base_hash != self.content_hash()? {
+            return Err(Error::Validation);
+        }
+        let (prefix, suffix) = (patch.prefix as usize, patch.suffix as usize);
+        if prefix.checked_add(suffix).map_or(true, |len| len > self.data.len()) {
+            return Err(Error::Validation);
+        }
+        let mut data = Vec::with_capacity(prefix + patch.inserted.len() + suffix);
+        data.extend_from_slice(&self.data[..prefix]);
+        data.extend_from_slice(&patch.inserted);
+        data.extend_from_slice(&self.data[self.data.len() - suffix..]);
+
+        let mut successor = patch.successor;
+        successor.data = data;
+        successor.validate_size()?;
+        self.validate_self_against_successor(&successor)?;
+        Ok(successor)
+    }
+
+    /// Is this a tombstone, i.e. a version with no data and no owners left.
+    pub fn is_deleted(&self) -> bool {
+        self.version > 0 && self.data.is_empty() && self.current_owner_keys.is_empty()
+    }
+
+    /// Is this soft-deleted, i.e. a version marked by `soft_delete`, with no data but its owners
+    /// kept until `undelete` restores it.
+    pub fn is_soft_deleted(&self) -> bool {
+        self.soft_deleted
+    }
+
+    /// Restores the soft-deleted mark of data imported from an export format, or sets it on the
+    /// successors built by the model tests.
+    #[cfg(any(test, feature = "json", all(feature = "serialisation", feature = "cbor")))]
+    pub(crate) fn set_soft_deleted(&mut self, soft_deleted: bool) {
+        self.soft_deleted = soft_deleted;
+    }
+
+    /// Is this frozen, i.e. created without owners by `new_frozen`, so never to be updated.
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    /// Returns `TooManyOwners` if there are more than `MAX_OWNERS` current owners, previous
+    /// owners or writers, and `NoOwners` if there are no current owners, unless this is a
+    /// tombstone or frozen. Frozen data must be version 0 and have no owners at all, and
+    /// soft-deleted data must be a later version with owners but no data.
+    pub fn validate_owners(&self) -> Result<(), Error> {
+        if self.current_owner_keys.len() > MAX_OWNERS ||
+           self.previous_owner_keys.len() > MAX_OWNERS || self.writers.len() > MAX_OWNERS {
+            return Err(Error::TooManyOwners);
+        }
+        if self.soft_deleted &&
+           (self.frozen || self.version == 0 || !self.data.is_empty() ||
+            self.current_owner_keys.is_empty()) {
+            return Err(Error::Validation);
+        }
+        if self.frozen {
+            if self.version != 0 || !self.current_owner_keys.is_empty() ||
+               !self.previous_owner_keys.is_empty() {
+                return Err(Error::Validation);
+            }
+        } else if self.current_owner_keys.is_empty() && !self.is_deleted() {
+            return Err(Error::NoOwners);
+        }
+        Ok(())
+    }
+
     /// Verifies that `other` is a valid update for `self`; returns an error otherwise.
     ///
     /// An update is valid if it doesn't change type tag or identifier (these are immutable),
@@ -116,7 +703,45 @@ impl StructuredData {
     ///
     /// In case of an ownership transfer, the `previous_owner_keys` in `other` must match the
     /// `current_owner_keys` in `self`.
+    ///
+    /// Deleted data can never be updated again, ledger data can't be deleted and an update
+    /// leaving no owners is only valid as a tombstone (see `delete`). The owners of soft-deleted
+    /// data can't change (see `soft_delete`). An update of ledger data
+    /// must commit to the `ledger_hash` of `self`. Signatures by the key a key rotation replaces
+    /// don't count unless it is the only owner (see `rotate_owner_key`).
+    ///
+    /// An update changing nothing but the data, which must not be empty, is also valid if signed
+    /// by one of the writers of `self` (see `set_writers`). Such an update carries no owner
+    /// signatures, so only validates against its predecessor: `verify_own_signatures` fails on it.
     pub fn validate_self_against_successor(&self, other: &StructuredData) -> Result<(), Error> {
+        self.validate_successor(other, None)
+    }
+
+    /// Verifies `other` is a valid update for `self` as `validate_self_against_successor` does,
+    /// reusing the results of verifying signatures cached in `cache`.
+    pub fn validate_self_against_successor_cached(&self,
+                                                  other: &StructuredData,
+                                                  cache: &mut ValidationCache)
+                                                  -> Result<(), Error> {
+        self.validate_successor(other, Some(cache))
+    }
+
+    fn validate_successor(&self,
+                          other: &StructuredData,
+                          cache: Option<&mut ValidationCache>)
+                          -> Result<(), Error> {
+        if self.is_deleted() {
+            return Err(Error::DataDeleted);
+        }
+        // Frozen data has no owners who could sign an update.
+        if self.frozen {
+            return Err(Error::NoOwners);
+        }
+        if other.current_owner_keys.is_empty() && (!other.is_deleted() || self.ledger) {
+            return Err(Error::Validation);
+        }
+        other.validate_owners()?;
+
         let owner_keys_to_match = if other.previous_owner_keys.is_empty() {
             &other.current_owner_keys
         } else {
@@ -124,76 +749,591 @@ impl StructuredData {
         };
 
         // TODO(dirvine) Increase error types to be more descriptive  :07/07/2015
-        if other.type_tag != self.type_tag || other.name != self.name ||
-           other.version != self.version + 1 ||
-           *owner_keys_to_match != self.current_owner_keys {
+        if other.type_tag != self.type_tag || !ct::name_eq(&other.name, &self.name) ||
+           !Version(self.version).is_next(Version(other.version)) ||
+           !ct::keys_eq(owner_keys_to_match, &self.current_owner_keys) {
             return Err(Error::Signature);
         }
-        other.verify_previous_owner_signatures(owner_keys_to_match)
+        // Soft-deleted data keeps its owners until it is undeleted or deleted for good.
+        if self.is_soft_deleted() && !other.current_owner_keys.is_empty() &&
+           !ct::keys_eq(&other.current_owner_keys, &self.current_owner_keys) {
+            return Err(Error::DataDeleted);
+        }
+        if self.ledger {
+            let hash = self.ledger_hash()?;
+            if !other.previous_hash.map_or(false, |previous| ct::name_eq(&previous, &hash)) {
+                return Err(Error::NoLink);
+            }
+        }
+        let result = match self.rotated_out_key(other) {
+            Some(old) if owner_keys_to_match.len() > 1 => {
+                let remaining: Vec<_> = owner_keys_to_match.iter()
+                    .filter(|key| !ct::key_eq(key, &old))
+                    .cloned()
+                    .collect();
+                match self.policy {
+                    SignaturePolicy::Weighted(ref weights, threshold) => {
+                        let available = remaining.iter()
+                            .fold(0u64, |sum, key| sum.saturating_add(self.policy.weight_of(key)));
+                        let policy = SignaturePolicy::Weighted(weights.clone(),
+                                                               cmp::min(threshold, available));
+                        other.verify_policy(&remaining, &policy, cache)
+                    }
+                    ref policy => {
+                        let required = policy.required_signatures(owner_keys_to_match.len());
+                        let required = cmp::min(required, remaining.len());
+                        other.verify_required_signatures_with(&remaining, required, cache)
+                    }
+                }
+            }
+            _ => other.verify_policy(owner_keys_to_match, &self.policy, cache),
+        };
+        if result.is_err() && self.is_writer_update(other) &&
+           other.signed_by_writer(&self.writers)? {
+            return Ok(());
+        }
+        result
+    }
+
+    /// Does `other` change nothing but the data of `self`, which has writers, so may be signed by
+    /// a writer instead of the owners. Writers can neither delete nor undelete the data.
+    fn is_writer_update(&self, other: &StructuredData) -> bool {
+        !self.writers.is_empty() && !self.is_soft_deleted() && !other.data.is_empty() &&
+        ct::keys_eq(&other.current_owner_keys, &self.current_owner_keys) &&
+        ct::keys_eq(&other.writers, &self.writers) && other.ledger == self.ledger &&
+        other.policy == self.policy && other.compression == self.compression &&
+        other.expires_at == self.expires_at && other.app_metadata == self.app_metadata
+    }
+
+    /// Is one of the signatures added to this data valid for one of `writers`.
+    fn signed_by_writer(&self, writers: &[PublicKey]) -> Result<bool, Error> {
+        let data = self.data_to_sign()?;
+        Ok(self.previous_owner_signatures
+            .iter()
+            .any(|sig| Self::signed_by_any(sig, &data, writers)))
+    }
+
+    /// Decides which of two competing versions of the same data, e.g. two successors of one
+    /// version signed by different owners, wins, by a rule every holder applies alike so they all
+    /// settle on the same version whichever they received first: the version with the smaller
+    /// `content_hash` wins, and of two with the same content, the one whose sorted signatures are
+    /// smaller. Identical versions resolve to `First`.
+    ///
+    /// Fails with `BadIdentifier` if `a` and `b` are different data and with `InvalidVersion` if
+    /// they are different versions. Neither is checked to be validly signed.
+    pub fn resolve_conflict(a: &StructuredData, b: &StructuredData) -> Result<Winner, Error> {
+        if a.identifier() != b.identifier() {
+            return Err(Error::BadIdentifier);
+        }
+        if a.version != b.version {
+            return Err(Error::InvalidVersion);
+        }
+        if a.conflict_key()? <= b.conflict_key()? {
+            Ok(Winner::First)
+        } else {
+            Ok(Winner::Second)
+        }
+    }
+
+    /// What `resolve_conflict` orders competing versions by.
+    fn conflict_key(&self) -> Result<([u8; 32], Vec<&[u8]>), Error> {
+        let mut signatures: Vec<_> = self.previous_owner_signatures
+            .iter()
+            .map(|sig| &sig.0[..])
+            .collect();
+        signatures.sort();
+        Ok((self.content_hash()?, signatures))
+    }
+
+    /// Checks `newer`, a later version of this data, neither goes back to an earlier version nor
+    /// skips more than `max_skip` versions, failing with `InvalidVersion` if it does, or
+    /// `BadIdentifier` if it is other data.
+    ///
+    /// Unlike `validate_self_against_successor` this accepts versions more than one apart, for
+    /// holders catching up on updates they missed, but does not check `newer` is signed by the
+    /// owners of this version.
+    pub fn validate_version_update(&self,
+                                   newer: &StructuredData,
+                                   max_skip: u64)
+                                   -> Result<(), Error> {
+        if newer.type_tag != self.type_tag || !ct::name_eq(&newer.name, &self.name) {
+            return Err(Error::BadIdentifier);
+        }
+        Version(self.version).check_update(Version(newer.version), max_skip)
     }
 
-    /// Confirms *unique and valid* owner_signatures are more than 50% of total owners.
+    /// Confirms *unique and valid* owner_signatures satisfy this data's own signature policy.
+    #[cfg(test)]
     fn verify_previous_owner_signatures(&self, owner_keys: &[PublicKey]) -> Result<(), Error> {
+        self.verify_signatures(owner_keys, &self.policy)
+    }
+
+    /// Confirms this data is signed as its own signature policy requires by the owners whose
+    /// signatures it needs: the previous owners on a transfer of ownership, otherwise the current
+    /// owners.
+    ///
+    /// Unlike `validate_self_against_successor` this does not need the previous version, so it
+    /// cannot tell whether the previous owners recorded are the real ones.
+    pub fn verify_own_signatures(&self) -> Result<(), Error> {
+        self.verify_signatures(self.signing_keys(), &self.policy)
+    }
+
+    /// Confirms this data is signed as `verify_own_signatures` does, reusing the results of
+    /// verifying signatures cached in `cache`.
+    pub fn verify_own_signatures_cached(&self, cache: &mut ValidationCache) -> Result<(), Error> {
+        self.verify_policy(self.signing_keys(), &self.policy, Some(cache))
+    }
+
+    /// Returns whether `signature` is `pub_key`'s valid signature over this data. It need not be
+    /// one of the signatures added to this data, nor `pub_key` one of its owners.
+    pub fn verify_signature(&self, signature: &Signature, pub_key: &PublicKey) -> bool {
+        match self.data_to_sign() {
+            Ok(data) => sign::verify_detached(signature, &data, pub_key),
+            Err(_) => false,
+        }
+    }
+
+    /// Returns each owner whose signature this data requires, with whether a valid signature of
+    /// theirs has been added, e.g. to show which owners have still to sign a pending update.
+    pub fn signature_status(&self) -> Vec<(PublicKey, bool)> {
+        let data = match self.data_to_sign() {
+            Ok(data) => data,
+            Err(_) => return self.signing_keys().iter().map(|key| (*key, false)).collect(),
+        };
+        self.signing_keys()
+            .iter()
+            .map(|key| {
+                let signed = self.previous_owner_signatures
+                    .iter()
+                    .any(|sig| sign::verify_detached(sig, &data, key));
+                (*key, signed)
+            })
+            .collect()
+    }
+
+    /// Confirms every signature added to this data is valid for one of the owners whose signatures
+    /// it needs, failing with `Signature` if not, e.g. because the data or a signature has been
+    /// corrupted since it was signed. Unlike `verify_own_signatures` this accepts data not yet
+    /// signed as its signature policy requires.
+    pub fn verify_added_signatures(&self) -> Result<(), Error> {
+        let data = self.data_to_sign()?;
+        let keys = self.signing_keys();
+        if self.previous_owner_signatures.iter().all(|sig| Self::signed_by_any(sig, &data, keys)) {
+            Ok(())
+        } else {
+            Err(Error::Signature)
+        }
+    }
+
+    /// Returns whether this data is unsigned, partially signed or signed as its own signature
+    /// policy requires by the owners whose signatures it needs.
+    ///
+    /// Each owner counts once however many of their signatures have been added.
+    pub fn validation_state(&self) -> ValidationState {
+        let signers = self.signers();
+        let remaining = self.policy.remaining_signatures(&signers, self.signing_keys());
+        if remaining == 0 {
+            ValidationState::Valid
+        } else if signers.is_empty() {
+            ValidationState::Unsigned
+        } else {
+            ValidationState::PartiallySigned(remaining)
+        }
+    }
+
+    /// Drops the signatures beyond the fewest valid ones this data's signature policy requires,
+    /// returning how many were dropped, e.g. when more owners signed than needed.
+    ///
+    /// The signatures kept are those of the first signing owners, in the order of their keys, so
+    /// every minimised copy of the same data carries the same signatures. Invalid and duplicate
+    /// signatures are dropped too. Fails with `Validation`, leaving the signatures unchanged, if
+    /// too few are valid.
+    pub fn minimise_signatures(&mut self) -> Result<usize, Error> {
+        let minimal = self.minimal_signatures()?;
+        let dropped = self.previous_owner_signatures.len() - minimal.len();
+        self.previous_owner_signatures = minimal;
+        Ok(dropped)
+    }
+
+    /// Returns how many signatures `minimise_signatures` would drop.
+    pub fn superfluous_signatures(&self) -> Result<usize, Error> {
+        Ok(self.previous_owner_signatures.len() - self.minimal_signatures()?.len())
+    }
+
+    /// Confirms this data is signed as `verify_own_signatures` does, logging a warning if it
+    /// carries more signatures than its signature policy requires.
+    pub fn verify_own_signatures_minimal(&self) -> Result<(), Error> {
+        self.verify_own_signatures()?;
+        let superfluous = self.superfluous_signatures()?;
+        if superfluous > 0 {
+            warn!("{:?} carries {} superfluous signature(s)", self.identifier(), superfluous);
+        }
+        Ok(())
+    }
+
+    /// Returns the first valid signatures in the order of the signing keys which satisfy the
+    /// signature policy.
+    fn minimal_signatures(&self) -> Result<Vec<Signature>, Error> {
+        let data = self.data_to_sign()?;
+        let keys = self.signing_keys();
+        let mut signers = Vec::new();
+        let mut minimal = Vec::new();
+        for key in keys {
+            if self.policy.remaining_signatures(&signers, keys) == 0 {
+                break;
+            }
+            if let Some(signature) = self.previous_owner_signatures
+                .iter()
+                .find(|sig| sign::verify_detached(sig, &data, key)) {
+                if !ct::contains_signature(&minimal, signature) {
+                    minimal.push(*signature);
+                    signers.push(*key);
+                }
+            }
+        }
+        if self.policy.remaining_signatures(&signers, keys) > 0 {
+            return Err(Error::Validation);
+        }
+        Ok(minimal)
+    }
+
+    /// Returns the owners whose signatures this data requires and who have validly signed it.
+    fn signers(&self) -> Vec<PublicKey> {
+        self.signature_status()
+            .into_iter()
+            .filter(|&(_, signed)| signed)
+            .map(|(key, _)| key)
+            .collect()
+    }
+
+    /// Confirms *unique and valid* owner_signatures satisfy `policy`.
+    fn verify_signatures(&self,
+                         owner_keys: &[PublicKey],
+                         policy: &SignaturePolicy)
+                         -> Result<(), Error> {
+        self.verify_policy(owner_keys, policy, None)
+    }
+
+    /// Confirms *unique and valid* owner_signatures satisfy `policy`, with `cache` if given.
+    fn verify_policy(&self,
+                     owner_keys: &[PublicKey],
+                     policy: &SignaturePolicy,
+                     cache: Option<&mut ValidationCache>)
+                     -> Result<(), Error> {
+        match *policy {
+            SignaturePolicy::Weighted(..) => {
+                self.verify_weighted_signatures(owner_keys, policy, cache)
+            }
+            _ => {
+                self.verify_required_signatures_with(owner_keys,
+                                                     policy.required_signatures(owner_keys.len()),
+                                                     cache)
+            }
+        }
+    }
+
+    /// Confirms the owners with *unique and valid* owner_signatures weigh enough for `policy`,
+    /// with `cache` if given.
+    fn verify_weighted_signatures(&self,
+                                  owner_keys: &[PublicKey],
+                                  policy: &SignaturePolicy,
+                                  mut cache: Option<&mut ValidationCache>)
+                                  -> Result<(), Error> {
+        self.check_signature_count(1)?;
+        let data = self.data_to_sign()?;
+        let data_hash = hash(&data);
+        let mut signers = Vec::new();
+        for key in owner_keys {
+            let signed = self.previous_owner_signatures.iter().any(|sig| match cache {
+                Some(ref mut cache) => cache.verify(&data_hash, &data, sig, key),
+                None => sign::verify_detached(sig, &data, key),
+            });
+            if signed {
+                signers.push(*key);
+                if policy.remaining_signatures(&signers, owner_keys) == 0 {
+                    return Ok(());
+                }
+            }
+        }
+        Err(Error::Validation)
+    }
+
+    /// Confirms at least `required` *unique and valid* owner_signatures, with `cache` if given.
+    fn verify_required_signatures_with(&self,
+                                       owner_keys: &[PublicKey],
+                                       required: usize,
+                                       cache: Option<&mut ValidationCache>)
+                                       -> Result<(), Error> {
+        match cache {
+            None => self.verify_required_signatures(owner_keys, required),
+            Some(cache) => {
+                self.check_signature_count(required)?;
+                let data = self.data_to_sign()?;
+                let data_hash = hash(&data);
+                let valid = self.previous_owner_signatures
+                    .iter()
+                    .filter(|sig| {
+                        owner_keys.iter().any(|key| cache.verify(&data_hash, &data, sig, key))
+                    })
+                    .take(required)
+                    .count();
+                if valid < required {
+                    return Err(Error::Validation);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Confirms at least `required` *unique and valid* owner_signatures.
+    fn verify_required_signatures(&self,
+                                  owner_keys: &[PublicKey],
+                                  required: usize)
+                                  -> Result<(), Error> {
+        self.check_signature_count(required)?;
+        let data = self.data_to_sign()?;
+        // Count valid previous_owner_signatures and refuse if quantity is not enough
+        if !self.has_valid_signatures(&data, owner_keys, required) {
+            return Err(Error::Validation);
+        }
+        Ok(())
+    }
+
+    /// Refuses duplicate signatures, and fewer than `required` signatures.
+    fn check_signature_count(&self, required: usize) -> Result<(), Error> {
         // Refuse any duplicate previous_owner_signatures (people can have many owner keys)
         // Any duplicates invalidates this type.
         for (i, sig) in self.previous_owner_signatures.iter().enumerate() {
             for sig_check in &self.previous_owner_signatures[..i] {
-                if sig == sig_check {
+                if ct::signature_eq(sig, sig_check) {
                     return Err(Error::Validation);
                 }
             }
         }
 
         // Refuse when not enough previous_owner_signatures found
-        if self.previous_owner_signatures.len() < (owner_keys.len() + 1) / 2 {
+        if self.previous_owner_signatures.len() < required {
             return Err(Error::Validation);
         }
+        Ok(())
+    }
 
-        let data = self.data_to_sign()?;
-        // Count valid previous_owner_signatures and refuse if quantity is not enough
-
-        let check_all_keys = |&sig| {
-            owner_keys.iter()
-                .any(|pub_key| sign::verify_detached(&sig, &data, pub_key))
-        };
-
-        if self.previous_owner_signatures
+    /// Checks at least `required` signatures are valid for one of `owner_keys`, stopping as soon
+    /// as enough have been found.
+    #[cfg(not(feature = "parallel"))]
+    fn has_valid_signatures(&self, data: &[u8], owner_keys: &[PublicKey], required: usize) -> bool {
+        self.previous_owner_signatures
             .iter()
-            .filter(|&sig| check_all_keys(sig))
-            .count() < (owner_keys.len() / 2 + owner_keys.len() % 2) {
-            return Err(Error::Validation);
+            .filter(|sig| Self::signed_by_any(sig, data, owner_keys))
+            .take(required)
+            .count() == required
+    }
+
+    /// Checks at least `required` signatures are valid for one of `owner_keys`, verifying them in
+    /// parallel and stopping as soon as enough have been found.
+    #[cfg(feature = "parallel")]
+    fn has_valid_signatures(&self, data: &[u8], owner_keys: &[PublicKey], required: usize) -> bool {
+        if required == 0 {
+            return true;
         }
-        Ok(())
+        let valid = AtomicUsize::new(0);
+        self.previous_owner_signatures
+            .par_iter()
+            .any(|sig| {
+                Self::signed_by_any(sig, data, owner_keys) &&
+                valid.fetch_add(1, Ordering::SeqCst) + 1 >= required
+            })
+    }
+
+    fn signed_by_any(sig: &Signature, data: &[u8], owner_keys: &[PublicKey]) -> bool {
+        owner_keys.iter().any(|pub_key| sign::verify_detached(sig, data, pub_key))
     }
 
     fn data_to_sign(&self) -> Result<Vec<u8>, Error> {
-        // Seems overkill to use serialisation here, but done to ensure cross platform signature
-        // handling is OK
-        let sd = SerialisableStructuredData {
-            type_tag: self.type_tag.to_string().as_bytes().to_vec(),
-            name: self.name,
-            data: &self.data,
-            previous_owner_keys: &self.previous_owner_keys,
-            current_owner_keys: &self.current_owner_keys,
-            version: self.version.to_string().as_bytes().to_vec(),
+        let mut writer = CanonicalWriter::new(STRUCTURED_DATA_DOMAIN);
+        let _ = writer.write_u64(self.type_tag)
+            .write_fixed(&self.name)
+            .write_bytes(&self.data);
+        let _ = self.previous_owner_keys.write_canonical(&mut writer);
+        let _ = self.current_owner_keys.write_canonical(&mut writer);
+        let _ = writer.write_u64(self.version);
+        let _ = match self.policy {
+            SignaturePolicy::Majority => writer.write_u8(0),
+            SignaturePolicy::All => writer.write_u8(1),
+            SignaturePolicy::Threshold(count) => writer.write_u8(2).write_u64(count),
+            SignaturePolicy::Weighted(ref weights, threshold) => {
+                let _ = writer.write_u8(3).write_len(weights.len());
+                for &(ref key, weight) in weights {
+                    let _ = writer.write_fixed(&key.0).write_u64(weight);
+                }
+                writer.write_u64(threshold)
+            }
         };
+        // The optional fields are only appended when at least one is present, as a byte flagging
+        // which are followed by those present, so data without them signs the same bytes as
+        // before they were introduced.
+        let flags = self.compression.map_or(0, |_| 1) | self.expires_at.map_or(0, |_| 2) |
+                    self.previous_hash.map_or(0, |_| 4) |
+                    if self.app_metadata.is_empty() { 0 } else { 8 } |
+                    if self.soft_deleted { 16 } else { 0 } |
+                    if self.writers.is_empty() { 0 } else { 32 } |
+                    if self.frozen { 64 } else { 0 };
+        if flags != 0 {
+            let _ = writer.write_u8(flags);
+        }
+        if let Some(compression) = self.compression {
+            let _ = writer.write_u8(compression.index());
+        }
+        if let Some(expires_at) = self.expires_at {
+            let _ = writer.write_u64(expires_at);
+        }
+        if let Some(ref previous_hash) = self.previous_hash {
+            let _ = writer.write_fixed(previous_hash);
+        }
+        if !self.app_metadata.is_empty() {
+            let _ = writer.write_bytes(&self.app_metadata);
+        }
+        if !self.writers.is_empty() {
+            let _ = self.writers.write_canonical(&mut writer);
+        }
+        Ok(writer.into_bytes())
+    }
 
-        serialise(&sd).map_err(From::from)
+    /// Returns the bytes owners sign, for signing outside this crate, e.g. on a hardware wallet
+    /// or a remote signer. The resulting signature can be added with `add_external_signature`.
+    pub fn signable_bytes(&self) -> Result<Vec<u8>, Error> {
+        self.data_to_sign()
     }
 
     /// Adds a signature with the given `secret_key` to the `previous_owner_signatures` and returns
-    /// the number of signatures that are still required. If more than 50% of the previous owners
-    /// have signed, 0 is returned and validation is complete.
+    /// the number of signatures that are still required. Once the signature policy is satisfied
+    /// (for the default policy, more than 50% of the previous owners have signed), 0 is returned
+    /// and validation is complete.
     pub fn add_signature(&mut self, secret_key: &SecretKey) -> Result<usize, Error> {
+        let result = self.do_add_signature(secret_key);
+        #[cfg(feature = "audit")]
+        self.record_signature(&result);
+        result
+    }
+
+    fn do_add_signature(&mut self, secret_key: &SecretKey) -> Result<usize, Error> {
         let data = self.data_to_sign()?;
         let sig = sign::sign_detached(&data, secret_key);
         self.previous_owner_signatures.push(sig);
-        let owner_keys = if self.previous_owner_keys.is_empty() {
+        Ok(self.remaining_signatures())
+    }
+
+    /// Adds a signature of `signable_bytes()` produced externally by `pub_key` and returns the
+    /// number of signatures that are still required, as `add_signature` does.
+    ///
+    /// The signature is only accepted if `pub_key` is one of the owners whose signatures this
+    /// data requires, the signature is valid for `pub_key` and it has not been added already.
+    pub fn add_external_signature(&mut self,
+                                  pub_key: &PublicKey,
+                                  signature: Signature)
+                                  -> Result<usize, Error> {
+        let result = self.do_add_external_signature(pub_key, signature);
+        #[cfg(feature = "audit")]
+        self.record_signature(&result);
+        result
+    }
+
+    fn do_add_external_signature(&mut self,
+                                 pub_key: &PublicKey,
+                                 signature: Signature)
+                                 -> Result<usize, Error> {
+        if !self.signing_keys().contains(pub_key) {
+            return Err(Error::AccessDenied);
+        }
+        if !sign::verify_detached(&signature, &self.data_to_sign()?, pub_key) {
+            return Err(Error::Signature);
+        }
+        if ct::contains_signature(&self.previous_owner_signatures, &signature) {
+            return Err(Error::Validation);
+        }
+        self.previous_owner_signatures.push(signature);
+        Ok(self.remaining_signatures())
+    }
+
+    /// Returns this data and the valid signatures collected on it so far as portable bytes, a
+    /// serialised `PendingUpdate`, so an owner can pass an update on to their other devices to
+    /// sign, e.g. as a file or QR code.
+    #[cfg(feature = "serialisation")]
+    pub fn export_unsigned(&self) -> Result<Vec<u8>, Error> {
+        PendingUpdate::new(self.clone(), None)?.serialise()
+    }
+
+    /// Parses bytes from `export_unsigned`, checking the signatures they carry, signs the data
+    /// with `secret_key` and returns it with all the signatures collected so far. It can be
+    /// exported again to pass on, or merged back into the original with `merge_exported`.
+    ///
+    /// Fails with `AccessDenied` if `secret_key` is not that of one of the owners whose
+    /// signatures the data requires.
+    #[cfg(feature = "serialisation")]
+    pub fn import_and_sign(bytes: &[u8], secret_key: &SecretKey) -> Result<StructuredData, Error> {
+        let mut update = PendingUpdate::deserialise(bytes)?;
+        let _ = update.sign(secret_key, 0)?;
+        Ok(update.signed_successor())
+    }
+
+    /// Adds the signatures carried by bytes from `export_unsigned`, exported from a copy of this
+    /// data signed on another device, and returns the number of signatures still required.
+    ///
+    /// Fails with `BadIdentifier` if the bytes carry different data, and with `Signature` or
+    /// `AccessDenied`, adding none of their signatures, if any of them is invalid. Invalid
+    /// signatures already on this data are discarded.
+    #[cfg(feature = "serialisation")]
+    pub fn merge_exported(&mut self, bytes: &[u8]) -> Result<usize, Error> {
+        let mut update = PendingUpdate::new(self.clone(), None)?;
+        let _ = update.merge(&PendingUpdate::deserialise(bytes)?)?;
+        *self = update.signed_successor();
+        Ok(self.remaining_signatures())
+    }
+
+    #[cfg(feature = "audit")]
+    fn record_signature(&self, result: &Result<usize, Error>) {
+        audit::record(AuditOperation::AddSignature,
+                      self.identifier(),
+                      Some(self.version),
+                      result)
+    }
+
+    /// The owners whose signatures this data requires: the previous owners on a transfer of
+    /// ownership, otherwise the current owners.
+    fn signing_keys(&self) -> &Owners {
+        if self.previous_owner_keys.is_empty() {
             &self.current_owner_keys
         } else {
             &self.previous_owner_keys
-        };
-        Ok(((owner_keys.len() / 2) + 1).saturating_sub(self.previous_owner_signatures.len()))
+        }
+    }
+
+    fn remaining_signatures(&self) -> usize {
+        let owners = self.signing_keys().len();
+        match self.policy {
+            SignaturePolicy::Majority => {
+                ((owners / 2) + 1).saturating_sub(self.previous_owner_signatures.len())
+            }
+            SignaturePolicy::Weighted(..) => {
+                self.policy.remaining_signatures(&self.signers(), self.signing_keys())
+            }
+            ref policy => {
+                policy.required_signatures(owners)
+                    .saturating_sub(self.previous_owner_signatures.len())
+            }
+        }
+    }
+
+    /// Sets the signature policy. Existing signatures no longer cover the data and are cleared.
+    pub fn set_signature_policy(&mut self, policy: SignaturePolicy) {
+        self.policy = policy;
+        self.previous_owner_signatures.clear();
+        self.canonicalise();
+    }
+
+    /// Get the signature policy
+    pub fn signature_policy(&self) -> SignaturePolicy {
+        self.policy.clone()
     }
 
     /// Overwrite any existing signatures with the new signatures provided.
@@ -212,7 +1352,7 @@ impl StructuredData {
     }
 
     /// Get the previous owner keys
-    pub fn get_previous_owner_keys(&self) -> &Vec<PublicKey> {
+    pub fn get_previous_owner_keys(&self) -> &Owners {
         &self.previous_owner_keys
     }
 
@@ -222,7 +1362,7 @@ impl StructuredData {
     }
 
     /// Get the current owner keys
-    pub fn get_owner_keys(&self) -> &Vec<PublicKey> {
+    pub fn get_owner_keys(&self) -> &Owners {
         &self.current_owner_keys
     }
 
@@ -231,44 +1371,527 @@ impl StructuredData {
         &self.previous_owner_signatures
     }
 
-    /// Return data size.
+    /// Return data size, as stored.
     pub fn payload_size(&self) -> usize {
         self.data.len()
     }
+
+    /// Returns the exact size of the serialised data, including keys, signatures and metadata.
+    #[cfg(feature = "serialisation")]
+    pub fn serialised_size(&self) -> Result<usize, Error> {
+        Ok(serialise(self)?.len())
+    }
+
+    /// The size `validate_size` checks: the exact serialised size where the `serialisation`
+    /// feature provides it, otherwise the estimated size.
+    #[cfg(feature = "serialisation")]
+    fn size(&self) -> Result<usize, Error> {
+        self.serialised_size()
+    }
+
+    #[cfg(not(feature = "serialisation"))]
+    fn size(&self) -> Result<usize, Error> {
+        Ok(self.estimated_size())
+    }
+
+    /// Returns the size of the serialised data, worked out from the fields without serialising.
+    pub fn estimated_size(&self) -> usize {
+        // Lengths and enum variant indices are serialised as `u64` and `u32`; an `Option` takes a
+        // byte plus its value when present.
+        let keys = |keys: &[PublicKey]| 8 + keys.len() * sign::PUBLICKEYBYTES;
+        let policy = match self.policy {
+            SignaturePolicy::Threshold(_) => 4 + 8,
+            SignaturePolicy::Majority | SignaturePolicy::All => 4,
+            SignaturePolicy::Weighted(ref weights, _) => {
+                4 + 8 + weights.len() * (sign::PUBLICKEYBYTES + 8) + 8
+            }
+        };
+        8 + self.name.len() + 8 + self.data.len() + keys(&self.previous_owner_keys[..]) + 8 +
+        keys(&self.current_owner_keys[..]) +
+        8 + self.previous_owner_signatures.len() * sign::SIGNATUREBYTES + 1 + policy +
+        self.compression.map_or(1, |_| 1 + 4) + self.expires_at.map_or(1, |_| 1 + 8) +
+        self.previous_hash.map_or(1, |hash| 1 + hash.len()) + 8 + self.app_metadata.len() +
+        if self.writers.is_empty() { 0 } else { keys(&self.writers[..]) }
+    }
+
+    /// Get the compression hint of the data
+    pub fn compression(&self) -> Option<Compression> {
+        self.compression
+    }
+
+    /// Sets the compression hint, declaring the data to be stored compressed accordingly.
+    /// Existing signatures no longer cover the data and are cleared.
+    pub fn set_compression(&mut self, compression: Option<Compression>) {
+        self.compression = compression;
+        self.previous_owner_signatures.clear();
+    }
+
+    /// Get the expiry time, in seconds since the Unix epoch
+    pub fn expires_at(&self) -> Option<u64> {
+        self.expires_at
+    }
+
+    /// Sets the expiry time, in seconds since the Unix epoch. Existing signatures no longer cover
+    /// the data and are cleared.
+    pub fn set_expires_at(&mut self, expires_at: Option<u64>) {
+        self.expires_at = expires_at;
+        self.previous_owner_signatures.clear();
+    }
+
+    /// Has this data expired at `now`, in seconds since the Unix epoch. Data without an expiry
+    /// time never expires.
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires_at.map_or(false, |expires_at| now >= expires_at)
+    }
+
+    /// Returns the hash of this version as signed, i.e. of its serialised form including the
+    /// signatures. The next version of ledger data commits to it.
+    #[cfg(feature = "serialisation")]
+    pub fn ledger_hash(&self) -> Result<[u8; 32], Error> {
+        Ok(hash(&serialise(self)?))
+    }
+
+    /// Without the `serialisation` feature there is no serialised form to hash, so this fails
+    /// with `Encoding`, and ledger data cannot be updated.
+    #[cfg(not(feature = "serialisation"))]
+    pub fn ledger_hash(&self) -> Result<[u8; 32], Error> {
+        Err(Error::Encoding("ledger hashes need the `serialisation` feature".to_owned()))
+    }
+
+    /// Returns a hash of the signed payload: the content, owners, version and metadata but not the
+    /// signatures, so it identifies a version however many owners have signed it so far.
+    pub fn content_hash(&self) -> Result<[u8; 32], Error> {
+        Ok(hash(&self.data_to_sign()?))
+    }
+
+    /// Returns the metadata of this version, for caching.
+    pub fn metadata(&self) -> Result<DataMetadata, Error> {
+        Ok(DataMetadata {
+            identifier: self.identifier(),
+            version: self.version,
+            content_hash: self.content_hash()?,
+            payload_size: self.data.len() as u64,
+        })
+    }
+
+    /// Get the `ledger_hash` of the previous version this version commits to
+    pub fn previous_hash(&self) -> Option<[u8; 32]> {
+        self.previous_hash
+    }
+
+    /// Sets the `ledger_hash` of the previous version this version commits to. Existing
+    /// signatures no longer cover the data and are cleared.
+    pub fn set_previous_hash(&mut self, previous_hash: Option<[u8; 32]>) {
+        self.previous_hash = previous_hash;
+        self.previous_owner_signatures.clear();
+    }
+
+    /// Get the writers, who may publish new data without the owners (see `set_writers`)
+    pub fn writers(&self) -> &Owners {
+        &self.writers
+    }
+
+    /// Sets the writers: keys which may sign the next version instead of the owners as long as it
+    /// changes nothing but the data, e.g. held by an app server updating content on the owners'
+    /// behalf. The owners sign the writers like the rest of the data, so existing signatures no
+    /// longer cover it and are cleared.
+    pub fn set_writers(&mut self, writers: Vec<PublicKey>) {
+        self.writers = Owners::new(writers);
+        self.previous_owner_signatures.clear();
+    }
+
+    /// Get the application-defined metadata, e.g. a MIME type or schema id, which can be read
+    /// without the data. Empty if there is none.
+    pub fn app_metadata(&self) -> &[u8] {
+        &self.app_metadata
+    }
+
+    /// Sets the application-defined metadata, refusing metadata larger than
+    /// `MAX_APP_METADATA_BYTES`. Existing signatures no longer cover the data and are cleared.
+    pub fn set_app_metadata(&mut self, app_metadata: Vec<u8>) -> Result<(), Error> {
+        if app_metadata.len() > MAX_APP_METADATA_BYTES {
+            return Err(Error::DataTooLarge);
+        }
+        self.app_metadata = app_metadata;
+        self.previous_owner_signatures.clear();
+        Ok(())
+    }
+
+    /// Returns the data, decompressing it if the compression hint is set.
+    pub fn decompressed_data(&self) -> Result<Cow<[u8]>, Error> {
+        compression::decompressed(&self.data, self.compression)
+    }
+
+    /// Compresses `data` with `compression` and stores the result as the data of this item.
+    ///
+    /// Existing signatures no longer cover the new data and are cleared.
+    #[cfg(feature = "compression")]
+    pub fn compress_data(&mut self, data: &[u8], compression: Compression) -> Result<(), Error> {
+        let compressed = compression.compress(data)?;
+        let uncompressed = mem::replace(&mut self.data, compressed);
+        let hint = mem::replace(&mut self.compression, Some(compression));
+        if let Err(error) = self.validate_size() {
+            self.data = uncompressed;
+            self.compression = hint;
+            return Err(error);
+        }
+        self.previous_owner_signatures.clear();
+        Ok(())
+    }
+
+    /// Encrypts `plain_text` so that only `readers` can recover it and stores the result as the
+    /// data of this item.
+    ///
+    /// A fresh symmetric key encrypts the content and is then sealed to each reader's encryption
+    /// key. Owner signing keys cannot be used for encryption, so owners wishing to read the data
+    /// must supply an encryption key as well. Existing signatures no longer cover the new data and
+    /// are cleared.
+    #[cfg(all(feature = "serialisation", feature = "sodium"))]
+    pub fn encrypt_data(&mut self,
+                        plain_text: &[u8],
+                        readers: &[box_::PublicKey])
+                        -> Result<(), Error> {
+        if readers.is_empty() {
+            return Err(Error::Crypto);
+        }
+        let key = secretbox::gen_key();
+        let nonce = secretbox::gen_nonce();
+        let payload = SealedPayload {
+            sealed_keys: readers.iter()
+                .map(|reader| (*reader, sealedbox::seal(&key.0, reader)))
+                .collect(),
+            nonce: nonce,
+            cipher_text: secretbox::seal(plain_text, &nonce, &key),
+        };
+        let plain_data = mem::replace(&mut self.data, serialise(&payload)?);
+        if let Err(error) = self.validate_size() {
+            self.data = plain_data;
+            return Err(error);
+        }
+        self.previous_owner_signatures.clear();
+        Ok(())
+    }
+
+    /// Decrypts data previously stored by `encrypt_data` using a reader's encryption keypair.
+    #[cfg(all(feature = "serialisation", feature = "sodium"))]
+    pub fn decrypt_data(&self,
+                        public_key: &box_::PublicKey,
+                        secret_key: &box_::SecretKey)
+                        -> Result<Vec<u8>, Error> {
+        let payload: SealedPayload = deserialise(&self.data)?;
+        let sealed_key = match payload.sealed_keys.iter().find(|&&(ref key, _)| key == public_key) {
+            Some(&(_, ref sealed_key)) => sealed_key,
+            None => return Err(Error::Crypto),
+        };
+        let key_bytes = sealedbox::open(sealed_key, public_key, secret_key)?;
+        let key = secretbox::Key::from_slice(&key_bytes).ok_or(Error::Crypto)?;
+        Ok(secretbox::open(&payload.cipher_text, &payload.nonce, &key)?)
+    }
+
+    /// Encrypts `plain_text` with a fresh key, wraps that under the key of this version in the
+    /// `KeyRatchet` of `root_secret` and stores both as the data of this item.
+    ///
+    /// Each version is encrypted under a key of its own, so the key of one version exposes no
+    /// other, and a chain key given out at one version exposes none of the versions before it.
+    /// Existing signatures no longer cover the new data and are cleared.
+    #[cfg(all(feature = "serialisation", feature = "sodium"))]
+    pub fn encrypt_data_ratcheted(&mut self,
+                                  plain_text: &[u8],
+                                  root_secret: &[u8])
+                                  -> Result<(), Error> {
+        let version_key = KeyRatchet::at_version(root_secret, self.version).version_key();
+        let key = secretbox::gen_key();
+        let key_nonce = secretbox::gen_nonce();
+        let nonce = secretbox::gen_nonce();
+        let payload = RatchetedPayload {
+            version: self.version,
+            key_nonce: key_nonce,
+            wrapped_key: secretbox::seal(&key.0, &key_nonce, &version_key),
+            nonce: nonce,
+            cipher_text: secretbox::seal(plain_text, &nonce, &key),
+        };
+        let plain_data = mem::replace(&mut self.data, serialise(&payload)?);
+        if let Err(error) = self.validate_size() {
+            self.data = plain_data;
+            return Err(error);
+        }
+        self.previous_owner_signatures.clear();
+        Ok(())
+    }
+
+    /// Decrypts data stored by `encrypt_data_ratcheted` at version `n`, with the root secret.
+    ///
+    /// Fails with `InvalidVersion` if the data was encrypted at another version, e.g. because it
+    /// was carried over unchanged from an earlier version.
+    #[cfg(all(feature = "serialisation", feature = "sodium"))]
+    pub fn decrypt_version(&self, n: u64, root_secret: &[u8]) -> Result<Vec<u8>, Error> {
+        self.open_ratcheted(|version| if version == n {
+            Ok(KeyRatchet::at_version(root_secret, n).version_key())
+        } else {
+            Err(Error::InvalidVersion)
+        })
+    }
+
+    /// Decrypts data stored by `encrypt_data_ratcheted` with a chain key of the same ratchet,
+    /// failing with `Crypto` if the data was encrypted at a version before that of the chain key.
+    #[cfg(all(feature = "serialisation", feature = "sodium"))]
+    pub fn decrypt_with_ratchet(&self, ratchet: &KeyRatchet) -> Result<Vec<u8>, Error> {
+        self.open_ratcheted(|version| {
+            let mut ratchet = ratchet.clone();
+            if ratchet.advance_to(version) {
+                Ok(ratchet.version_key())
+            } else {
+                Err(Error::Crypto)
+            }
+        })
+    }
+
+    /// Decrypts data stored by `encrypt_data_ratcheted`, with the key `version_key` returns for
+    /// the version it was encrypted at.
+    #[cfg(all(feature = "serialisation", feature = "sodium"))]
+    fn open_ratcheted<F>(&self, version_key: F) -> Result<Vec<u8>, Error>
+        where F: FnOnce(u64) -> Result<secretbox::Key, Error>
+    {
+        let payload: RatchetedPayload = deserialise(&self.data)?;
+        let version_key = version_key(payload.version)?;
+        let key_bytes = secretbox::open(&payload.wrapped_key, &payload.key_nonce, &version_key)?;
+        let key = secretbox::Key::from_slice(&key_bytes).ok_or(Error::Crypto)?;
+        Ok(secretbox::open(&payload.cipher_text, &payload.nonce, &key)?)
+    }
+}
+
+/// The difference between two versions of a `StructuredData`, made by `diff` and applied by
+/// `apply_patch`.
+#[derive(Hash, Eq, PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "serialisation", derive(RustcEncodable, RustcDecodable))]
+pub struct StructuredDataPatch {
+    base_hash: [u8; 32],
+    prefix: u64,
+    suffix: u64,
+    inserted: Vec<u8>,
+    successor: StructuredData,
+}
+
+impl StructuredDataPatch {
+    /// Returns the `content_hash` of the version the patch applies to.
+    pub fn base_hash(&self) -> &[u8; 32] {
+        &self.base_hash
+    }
+
+    /// Returns the version the patch produces.
+    pub fn version(&self) -> u64 {
+        self.successor.version
+    }
 }
 
 impl Debug for StructuredData {
     fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
         write!(formatter,
                "StructuredData {{ type_tag: {}, name: {:?}, previous_owner_keys: {:?}, \
-                version: {}, current_owner_keys: {:?}, previous_owner_signatures: {:?} }}",
+                version: {}, current_owner_keys: {:?}, previous_owner_signatures: {:?}, \
+                policy: {:?}, compression: {:?}, expires_at: {:?}, previous_hash: {:?}, \
+                app_metadata: {} bytes, soft_deleted: {}, writers: {:?}, frozen: {}, \
+                content_hash: {:?} }}",
                self.type_tag,
                self.name(),
                self.previous_owner_keys,
                self.version,
                self.current_owner_keys,
-               self.previous_owner_signatures)
+               self.previous_owner_signatures,
+               self.policy,
+               self.compression,
+               self.expires_at,
+               self.previous_hash,
+               self.app_metadata.len(),
+               self.soft_deleted,
+               self.writers,
+               self.frozen,
+               self.content_hash().ok())
     }
 }
 
-#[derive(RustcEncodable)]
-struct SerialisableStructuredData<'a> {
-    type_tag: Vec<u8>,
-    name: [u8; 32],
-    data: &'a [u8],
-    previous_owner_keys: &'a [PublicKey],
-    current_owner_keys: &'a [PublicKey],
-    version: Vec<u8>,
+/// Serialised field by field, except that `ledger` is serialised as a byte of flags: `LEDGER_FLAG`
+/// for ledger data, `SOFT_DELETED_FLAG` for soft-deleted data, `WRITERS_FLAG` when the writers
+/// follow it and `FROZEN_FLAG` for frozen data. Data which is neither soft-deleted nor frozen and
+/// has no writers thus serialises exactly as it did while the flag was a `bool`.
+#[cfg(feature = "serialisation")]
+impl Encodable for StructuredData {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), E::Error> {
+        let flags = if self.ledger { LEDGER_FLAG } else { 0 } |
+                    if self.soft_deleted { SOFT_DELETED_FLAG } else { 0 } |
+                    if self.writers.is_empty() { 0 } else { WRITERS_FLAG } |
+                    if self.frozen { FROZEN_FLAG } else { 0 };
+        encoder.emit_struct("StructuredData", 14, |encoder| {
+            encoder.emit_struct_field("type_tag", 0, |encoder| self.type_tag.encode(encoder))?;
+            encoder.emit_struct_field("name", 1, |encoder| self.name.encode(encoder))?;
+            encoder.emit_struct_field("data", 2, |encoder| self.data.encode(encoder))?;
+            encoder.emit_struct_field("previous_owner_keys",
+                                   3,
+                                   |encoder| self.previous_owner_keys.encode(encoder))?;
+            encoder.emit_struct_field("version", 4, |encoder| self.version.encode(encoder))?;
+            encoder.emit_struct_field("current_owner_keys",
+                                   5,
+                                   |encoder| self.current_owner_keys.encode(encoder))?;
+            encoder.emit_struct_field("previous_owner_signatures",
+                                   6,
+                                   |encoder| self.previous_owner_signatures.encode(encoder))?;
+            encoder.emit_struct_field("ledger", 7, |encoder| flags.encode(encoder))?;
+            if !self.writers.is_empty() {
+                encoder.emit_struct_field("writers", 13, |encoder| self.writers.encode(encoder))?;
+            }
+            encoder.emit_struct_field("policy", 8, |encoder| self.policy.encode(encoder))?;
+            encoder.emit_struct_field("compression",
+                                   9,
+                                   |encoder| self.compression.encode(encoder))?;
+            encoder.emit_struct_field("expires_at", 10, |encoder| self.expires_at.encode(encoder))?;
+            encoder.emit_struct_field("previous_hash",
+                                   11,
+                                   |encoder| self.previous_hash.encode(encoder))?;
+            encoder.emit_struct_field("app_metadata",
+                                   12,
+                                   |encoder| self.app_metadata.encode(encoder))
+        })
+    }
+}
+
+#[cfg(feature = "serialisation")]
+impl Decodable for StructuredData {
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<StructuredData, D::Error> {
+        StructuredData::decode_in_format(decoder, envelope::FORMAT_VERSION)
+    }
+}
+
+#[cfg(feature = "serialisation")]
+impl StructuredData {
+    /// Decodes data serialised in the layout of the envelope format version `format`, giving the
+    /// fields it lacks their defaults.
+    ///
+    /// In `LEGACY_FORMAT_VERSION` the data ends with `ledger`, which is a plain `bool` before
+    /// `SOFT_DELETE_FORMAT_VERSION` and can only be followed by writers since
+    /// `WRITERS_FORMAT_VERSION`. Data can only be frozen since `FROZEN_FORMAT_VERSION`.
+    pub(crate) fn decode_in_format<D: Decoder>(decoder: &mut D,
+                                               format: u32)
+                                               -> Result<StructuredData, D::Error> {
+        decoder.read_struct("StructuredData", 14, |decoder| {
+            let type_tag = decoder.read_struct_field("type_tag", 0, Decodable::decode)?;
+            let name = decoder.read_struct_field("name", 1, Decodable::decode)?;
+            let data = decoder.read_struct_field("data", 2, Decodable::decode)?;
+            let previous_owner_keys =
+                decoder.read_struct_field("previous_owner_keys", 3, Decodable::decode)?;
+            let version = decoder.read_struct_field("version", 4, Decodable::decode)?;
+            let current_owner_keys =
+                decoder.read_struct_field("current_owner_keys", 5, Decodable::decode)?;
+            let previous_owner_signatures =
+                decoder.read_struct_field("previous_owner_signatures", 6, Decodable::decode)?;
+            let flags: u8 = if format < envelope::SOFT_DELETE_FORMAT_VERSION {
+                let ledger: bool = decoder.read_struct_field("ledger", 7, Decodable::decode)?;
+                if ledger { LEDGER_FLAG } else { 0 }
+            } else {
+                decoder.read_struct_field("ledger", 7, Decodable::decode)?
+            };
+            let known_flags = if format < envelope::WRITERS_FORMAT_VERSION {
+                LEDGER_FLAG | SOFT_DELETED_FLAG
+            } else if format < envelope::FROZEN_FORMAT_VERSION {
+                LEDGER_FLAG | SOFT_DELETED_FLAG | WRITERS_FLAG
+            } else {
+                LEDGER_FLAG | SOFT_DELETED_FLAG | WRITERS_FLAG | FROZEN_FLAG
+            };
+            if flags & !known_flags != 0 {
+                return Err(decoder.error("invalid StructuredData flags"));
+            }
+            let writers: Owners = if flags & WRITERS_FLAG == 0 {
+                Owners::default()
+            } else {
+                decoder.read_struct_field("writers", 13, Decodable::decode)?
+            };
+            // Writers are only serialised when there are some.
+            if flags & WRITERS_FLAG != 0 && writers.is_empty() {
+                return Err(decoder.error("empty StructuredData writers"));
+            }
+            let mut structured_data = StructuredData {
+                type_tag: type_tag,
+                name: name,
+                data: data,
+                previous_owner_keys: previous_owner_keys,
+                version: version,
+                current_owner_keys: current_owner_keys,
+                previous_owner_signatures: previous_owner_signatures,
+                ledger: flags & LEDGER_FLAG != 0,
+                policy: SignaturePolicy::default(),
+                compression: None,
+                expires_at: None,
+                previous_hash: None,
+                app_metadata: Vec::new(),
+                soft_deleted: flags & SOFT_DELETED_FLAG != 0,
+                writers: writers,
+                frozen: flags & FROZEN_FLAG != 0,
+            };
+            if format >= envelope::ENVELOPE_FORMAT_VERSION {
+                structured_data.policy =
+                    decoder.read_struct_field("policy", 8, Decodable::decode)?;
+                structured_data.compression =
+                    decoder.read_struct_field("compression", 9, Decodable::decode)?;
+            }
+            if format >= envelope::EXPIRY_FORMAT_VERSION {
+                structured_data.expires_at =
+                    decoder.read_struct_field("expires_at", 10, Decodable::decode)?;
+            }
+            if format >= envelope::LEDGER_LINK_FORMAT_VERSION {
+                structured_data.previous_hash =
+                    decoder.read_struct_field("previous_hash", 11, Decodable::decode)?;
+            }
+            if format >= envelope::APP_METADATA_FORMAT_VERSION {
+                structured_data.app_metadata =
+                    decoder.read_struct_field("app_metadata", 12, Decodable::decode)?;
+            }
+            structured_data.canonicalise();
+            Ok(structured_data)
+        })
+    }
+}
+
+/// Encrypted form of the data as written by `StructuredData::encrypt_data`.
+#[cfg(all(feature = "serialisation", feature = "sodium"))]
+#[derive(RustcEncodable, RustcDecodable)]
+struct SealedPayload {
+    sealed_keys: Vec<(box_::PublicKey, Vec<u8>)>,
+    nonce: secretbox::Nonce,
+    cipher_text: Vec<u8>,
+}
+
+/// Encrypted form of the data as written by `StructuredData::encrypt_data_ratcheted`: the content
+/// key, wrapped under the key of `version`, and the content encrypted with it.
+#[cfg(all(feature = "serialisation", feature = "sodium"))]
+#[derive(RustcEncodable, RustcDecodable)]
+struct RatchetedPayload {
+    version: u64,
+    key_nonce: secretbox::Nonce,
+    wrapped_key: Vec<u8>,
+    nonce: secretbox::Nonce,
+    cipher_text: Vec<u8>,
 }
 
 #[cfg(test)]
 mod tests {
+    use super::{MAX_BYTES, MAX_OWNERS, SignaturePolicy, ValidationState};
+    #[cfg(feature = "sodium")]
+    use crypto::KeyRatchet;
+    use crypto::ValidationCache;
+    use crypto::sign::{self, PublicKey};
+    use data::{Compression, Data};
+    use data::owners::Owners;
+    use error::Error;
     use rand;
-    use rust_sodium::crypto::sign;
+    #[cfg(feature = "sodium")]
+    use rust_sodium::crypto::box_;
+    use rustc_serialize::hex::ToHex;
+    use serialisation::{deserialise, serialise};
+    use std::u64;
+    use test_utils;
 
     #[test]
     fn single_owner() {
-        let keys = sign::gen_keypair();
+        let keys = test_utils::gen_keypair();
         let owner_keys = vec![keys.0];
 
         assert!(super::StructuredData::new(0,
@@ -284,7 +1907,7 @@ mod tests {
 
     #[test]
     fn single_owner_unsigned() {
-        let keys = sign::gen_keypair();
+        let keys = test_utils::gen_keypair();
         let owner_keys = vec![keys.0];
 
         let structured_data = super::StructuredData::new(0,
@@ -302,9 +1925,9 @@ mod tests {
 
     #[test]
     fn single_owner_other_signing_key() {
-        let keys = sign::gen_keypair();
+        let keys = test_utils::gen_keypair();
         let owner_keys = vec![keys.0];
-        let other_keys = sign::gen_keypair();
+        let other_keys = test_utils::gen_keypair();
 
         let structured_data = super::StructuredData::new(0,
                                                          rand::random(),
@@ -322,9 +1945,9 @@ mod tests {
 
     #[test]
     fn single_owner_other_signature() {
-        let keys = sign::gen_keypair();
+        let keys = test_utils::gen_keypair();
         let owner_keys = vec![keys.0];
-        let other_keys = sign::gen_keypair();
+        let other_keys = test_utils::gen_keypair();
 
         if let Ok(ref mut structured_data) =
             super::StructuredData::new(0,
@@ -346,9 +1969,9 @@ mod tests {
 
     #[test]
     fn three_owners() {
-        let keys1 = sign::gen_keypair();
-        let keys2 = sign::gen_keypair();
-        let keys3 = sign::gen_keypair();
+        let keys1 = test_utils::gen_keypair();
+        let keys2 = test_utils::gen_keypair();
+        let keys3 = test_utils::gen_keypair();
 
         let owner_keys = vec![keys1.0, keys2.0, keys3.0];
 
@@ -377,10 +2000,10 @@ mod tests {
 
     #[test]
     fn four_owners() {
-        let keys1 = sign::gen_keypair();
-        let keys2 = sign::gen_keypair();
-        let keys3 = sign::gen_keypair();
-        let keys4 = sign::gen_keypair();
+        let keys1 = test_utils::gen_keypair();
+        let keys2 = test_utils::gen_keypair();
+        let keys3 = test_utils::gen_keypair();
+        let keys4 = test_utils::gen_keypair();
 
         let owner_keys = vec![keys1.0, keys2.0, keys3.0, keys4.0];
 
@@ -409,10 +2032,10 @@ mod tests {
 
     #[test]
     fn transfer_owners() {
-        let keys1 = sign::gen_keypair();
-        let keys2 = sign::gen_keypair();
-        let keys3 = sign::gen_keypair();
-        let new_owner = sign::gen_keypair();
+        let keys1 = test_utils::gen_keypair();
+        let keys2 = test_utils::gen_keypair();
+        let keys3 = test_utils::gen_keypair();
+        let new_owner = test_utils::gen_keypair();
 
         let identifier: [u8; 32] = rand::random();
 
@@ -434,9 +2057,12 @@ mod tests {
                                                  vec![],
                                                  vec![new_owner.0],
                                                  vec![keys1.0, keys2.0, keys3.0],
-                                                 Some(&keys1.1),
+                                                 None,
                                                  true) {
                     Ok(mut new_structured_data) => {
+                        new_structured_data.set_previous_hash(orig_structured_data.ledger_hash()
+                            .ok());
+                        assert_eq!(new_structured_data.add_signature(&keys1.1).ok(), Some(1));
                         assert_eq!(new_structured_data.add_signature(&keys2.1).ok(), Some(0));
                         match orig_structured_data.replace_with_other(new_structured_data) {
                             Ok(()) => (),
@@ -449,9 +2075,15 @@ mod tests {
                                                          vec![],
                                                          vec![keys1.0],
                                                          vec![new_owner.0],
-                                                         Some(&new_owner.1),
+                                                         None,
                                                          true) {
-                            Ok(another_new_structured_data) => {
+                            Ok(mut another_new_structured_data) => {
+                                another_new_structured_data.set_previous_hash(
+                                    orig_structured_data.ledger_hash().ok());
+                                assert_eq!(another_new_structured_data.add_signature(
+                                                   &new_owner.1)
+                                               .ok(),
+                                           Some(0));
                                 match orig_structured_data.replace_with_other(
                                         another_new_structured_data) {
                                     Ok(()) => (),
@@ -467,4 +2099,1308 @@ mod tests {
             Err(error) => panic!("Error: {:?}", error),
         }
     }
+
+    #[cfg(feature = "sodium")]
+    #[test]
+    fn encrypted_data() {
+        let keys = test_utils::gen_keypair();
+        let owner_box_keys = box_::gen_keypair();
+        let reader_box_keys = box_::gen_keypair();
+        let other_box_keys = box_::gen_keypair();
+        let plain_text = b"private content".to_vec();
+
+        let mut structured_data = unwrap!(super::StructuredData::new(0,
+                                                                     rand::random(),
+                                                                     0,
+                                                                     vec![],
+                                                                     vec![keys.0],
+                                                                     vec![],
+                                                                     Some(&keys.1),
+                                                                     false));
+        assert!(structured_data.encrypt_data(&plain_text, &[]).is_err());
+        unwrap!(structured_data.encrypt_data(&plain_text,
+                                             &[owner_box_keys.0, reader_box_keys.0]));
+        assert!(structured_data.get_data() != &plain_text);
+        // Changing the data invalidates any existing signatures.
+        assert!(structured_data.get_previous_owner_signatures().is_empty());
+        assert_eq!(unwrap!(structured_data.add_signature(&keys.1)), 0);
+        assert!(structured_data.verify_previous_owner_signatures(&[keys.0]).is_ok());
+
+        assert_eq!(unwrap!(structured_data.decrypt_data(&owner_box_keys.0, &owner_box_keys.1)),
+                   plain_text);
+        assert_eq!(unwrap!(structured_data.decrypt_data(&reader_box_keys.0,
+                                                        &reader_box_keys.1)),
+                   plain_text);
+        assert!(structured_data.decrypt_data(&other_box_keys.0, &other_box_keys.1).is_err());
+        assert!(structured_data.decrypt_data(&reader_box_keys.0, &other_box_keys.1).is_err());
+    }
+
+    #[cfg(feature = "sodium")]
+    #[test]
+    fn ratcheted_encryption() {
+        let keys = test_utils::gen_keypair();
+        let root_secret = b"root secret";
+        let mut first = unwrap!(super::StructuredData::new(0,
+                                                           rand::random(),
+                                                           0,
+                                                           vec![],
+                                                           vec![keys.0],
+                                                           vec![],
+                                                           Some(&keys.1),
+                                                           false));
+        unwrap!(first.encrypt_data_ratcheted(b"first", root_secret));
+        assert!(first.get_previous_owner_signatures().is_empty());
+        assert_eq!(unwrap!(first.decrypt_version(0, root_secret)), b"first".to_vec());
+        assert!(first.decrypt_version(0, b"wrong secret").is_err());
+
+        let mut second = unwrap!(first.transfer_ownership(vec![keys.0], &keys.1));
+        // The data carried over is still that of version 0.
+        match second.decrypt_version(1, root_secret) {
+            Err(Error::InvalidVersion) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        unwrap!(second.encrypt_data_ratcheted(b"second", root_secret));
+        assert_eq!(unwrap!(second.decrypt_version(1, root_secret)), b"second".to_vec());
+
+        // A chain key from version 1 on opens version 1 but not version 0.
+        let ratchet = KeyRatchet::at_version(root_secret, 1);
+        assert_eq!(unwrap!(second.decrypt_with_ratchet(&ratchet)), b"second".to_vec());
+        match first.decrypt_with_ratchet(&ratchet) {
+            Err(Error::Crypto) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert_eq!(unwrap!(first.decrypt_with_ratchet(&KeyRatchet::new(root_secret))),
+                   b"first".to_vec());
+    }
+
+    #[test]
+    fn delete() {
+        let keys1 = test_utils::gen_keypair();
+        let keys2 = test_utils::gen_keypair();
+        let identifier: [u8; 32] = rand::random();
+
+        let mut structured_data = unwrap!(super::StructuredData::new(0,
+                                                                     identifier,
+                                                                     0,
+                                                                     vec![1, 2, 3],
+                                                                     vec![keys1.0],
+                                                                     vec![],
+                                                                     Some(&keys1.1),
+                                                                     false));
+        assert!(!structured_data.is_deleted());
+
+        // Only the owners can sign a valid tombstone.
+        let bad_tombstone = unwrap!(structured_data.delete(&keys2.1));
+        assert!(structured_data.validate_self_against_successor(&bad_tombstone).is_err());
+
+        let tombstone = unwrap!(structured_data.delete(&keys1.1));
+        assert!(tombstone.is_deleted());
+        assert_eq!(tombstone.version(), 1);
+        assert!(tombstone.get_data().is_empty());
+        assert!(tombstone.get_owner_keys().is_empty());
+        assert_eq!(tombstone.get_previous_owner_keys(), &vec![keys1.0]);
+        unwrap!(structured_data.replace_with_other(tombstone));
+        assert!(structured_data.is_deleted());
+        assert!(structured_data.delete(&keys1.1).is_err());
+
+        // Resurrection attempts are rejected, even when signed by the previous owners.
+        let resurrected = unwrap!(super::StructuredData::new(0,
+                                                             identifier,
+                                                             2,
+                                                             vec![4, 5, 6],
+                                                             vec![keys1.0],
+                                                             vec![],
+                                                             Some(&keys1.1),
+                                                             false));
+        match structured_data.validate_self_against_successor(&resurrected) {
+            Err(Error::DataDeleted) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // Stripping all owners without clearing the data is not a valid tombstone.
+        let original = unwrap!(super::StructuredData::new(0,
+                                                          identifier,
+                                                          0,
+                                                          vec![1, 2, 3],
+                                                          vec![keys1.0],
+                                                          vec![],
+                                                          Some(&keys1.1),
+                                                          false));
+        match super::StructuredData::new(0,
+                                         identifier,
+                                         1,
+                                         vec![1, 2, 3],
+                                         vec![],
+                                         vec![keys1.0],
+                                         Some(&keys1.1),
+                                         false) {
+            Err(Error::NoOwners) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        let mut ownerless = original.clone();
+        ownerless.version = 1;
+        ownerless.previous_owner_keys = ownerless.current_owner_keys.clone();
+        ownerless.current_owner_keys = Owners::default();
+        let _ = unwrap!(ownerless.add_signature(&keys1.1));
+        assert!(original.validate_self_against_successor(&ownerless).is_err());
+    }
+
+    #[test]
+    fn ledger_cannot_be_deleted() {
+        let keys = test_utils::gen_keypair();
+        let structured_data = unwrap!(super::StructuredData::new(0,
+                                                                 rand::random(),
+                                                                 0,
+                                                                 vec![],
+                                                                 vec![keys.0],
+                                                                 vec![],
+                                                                 Some(&keys.1),
+                                                                 true));
+        assert!(structured_data.delete(&keys.1).is_err());
+    }
+
+    #[test]
+    fn many_owners() {
+        let keys = (0..21).map(|_| test_utils::gen_keypair()).collect::<Vec<_>>();
+        let owner_keys = keys.iter().map(|&(ref public_key, _)| *public_key).collect::<Vec<_>>();
+        let other_keys = test_utils::gen_keypair();
+
+        let mut structured_data = unwrap!(super::StructuredData::new(0,
+                                                                     rand::random(),
+                                                                     0,
+                                                                     vec![],
+                                                                     owner_keys.clone(),
+                                                                     vec![],
+                                                                     Some(&other_keys.1),
+                                                                     true));
+        for &(_, ref secret_key) in keys.iter().take(10) {
+            let _ = unwrap!(structured_data.add_signature(secret_key));
+        }
+        // Ten owners plus a stranger is not a majority of 21.
+        assert!(structured_data.verify_previous_owner_signatures(&owner_keys).is_err());
+        let _ = unwrap!(structured_data.add_signature(&keys[20].1));
+        assert!(structured_data.verify_previous_owner_signatures(&owner_keys).is_ok());
+    }
+
+    #[test]
+    fn canonical_owner_order() {
+        let keys1 = test_utils::gen_keypair();
+        let keys2 = test_utils::gen_keypair();
+        let keys3 = test_utils::gen_keypair();
+        let identifier: [u8; 32] = rand::random();
+
+        let mut forward = unwrap!(super::StructuredData::new(0,
+                                                             identifier,
+                                                             0,
+                                                             vec![],
+                                                             vec![keys1.0, keys2.0, keys3.0],
+                                                             vec![],
+                                                             Some(&keys1.1),
+                                                             false));
+        let backward = unwrap!(super::StructuredData::new(0,
+                                                          identifier,
+                                                          0,
+                                                          vec![],
+                                                          vec![keys3.0, keys2.0, keys1.0],
+                                                          vec![],
+                                                          Some(&keys1.1),
+                                                          false));
+        let repeated = unwrap!(super::StructuredData::new(0,
+                                                          identifier,
+                                                          0,
+                                                          vec![],
+                                                          vec![keys2.0, keys1.0, keys3.0, keys1.0],
+                                                          vec![],
+                                                          Some(&keys1.1),
+                                                          false));
+        assert!(forward.is_canonical());
+        assert_eq!(forward, backward);
+        // Owners listed more than once are only listed, and only sign, once.
+        assert_eq!(forward, repeated);
+        assert_eq!(repeated.get_owner_keys().len(), 3);
+        // Signatures are independent of the order owners were supplied in.
+        let _ = unwrap!(forward.add_signature(&keys2.1));
+        let mut reordered = backward.clone();
+        reordered.replace_signatures(forward.get_previous_owner_signatures().clone());
+        assert!(reordered.verify_previous_owner_signatures(&[keys2.0, keys1.0, keys3.0]).is_ok());
+    }
+
+    #[test]
+    fn canonicalised_on_decode() {
+        #[derive(RustcEncodable)]
+        struct Unsorted {
+            type_tag: u64,
+            name: [u8; 32],
+            data: Vec<u8>,
+            previous_owner_keys: Vec<PublicKey>,
+            version: u64,
+            current_owner_keys: Vec<PublicKey>,
+            previous_owner_signatures: Vec<sign::Signature>,
+            ledger: bool,
+            policy: SignaturePolicy,
+            compression: Option<Compression>,
+            expires_at: Option<u64>,
+            previous_hash: Option<[u8; 32]>,
+            app_metadata: Vec<u8>,
+        }
+
+        let mut owner_keys = vec![test_utils::gen_keypair().0, test_utils::gen_keypair().0];
+        owner_keys.sort();
+        owner_keys.reverse();
+        let unsorted = Unsorted {
+            type_tag: 0,
+            name: rand::random(),
+            data: vec![],
+            previous_owner_keys: vec![],
+            version: 0,
+            current_owner_keys: vec![owner_keys[0], owner_keys[1], owner_keys[0]],
+            previous_owner_signatures: vec![],
+            ledger: false,
+            policy: SignaturePolicy::Majority,
+            compression: None,
+            expires_at: None,
+            previous_hash: None,
+            app_metadata: vec![],
+        };
+        let decoded: super::StructuredData = unwrap!(deserialise(&unwrap!(serialise(&unsorted))));
+        assert!(decoded.is_canonical());
+        owner_keys.reverse();
+        assert_eq!(decoded.get_owner_keys(), &owner_keys);
+    }
+
+    #[test]
+    fn max_size() {
+        let keys = test_utils::gen_keypair();
+        let identifier: [u8; 32] = rand::random();
+        let new_structured_data = |version, data| {
+            super::StructuredData::new(0,
+                                       identifier,
+                                       version,
+                                       data,
+                                       vec![keys.0],
+                                       vec![],
+                                       Some(&keys.1),
+                                       false)
+        };
+
+        match new_structured_data(0, vec![0; MAX_BYTES]) {
+            Err(Error::DataTooLarge) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        let mut structured_data = unwrap!(new_structured_data(0, vec![0; MAX_BYTES / 2]));
+        assert!(structured_data.validate_size().is_ok());
+        assert_eq!(structured_data.max_size(), MAX_BYTES + sign::size_allowance(1, 1));
+        #[cfg(feature = "sodium")]
+        assert!(structured_data.encrypt_data(&vec![0; MAX_BYTES], &[box_::gen_keypair().0])
+            .is_err());
+        assert_eq!(structured_data.get_data().len(), MAX_BYTES / 2);
+
+        // An oversized successor is rejected even if otherwise valid.
+        let mut successor = unwrap!(new_structured_data(1, vec![]));
+        successor.data = vec![0; MAX_BYTES];
+        successor.previous_owner_signatures.clear();
+        let _ = unwrap!(successor.add_signature(&keys.1));
+        match structured_data.replace_with_other(successor) {
+            Err(Error::DataTooLarge) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn transfer_ownership() {
+        let keys1 = test_utils::gen_keypair();
+        let keys2 = test_utils::gen_keypair();
+        let keys3 = test_utils::gen_keypair();
+        let new_owner = test_utils::gen_keypair();
+
+        let mut structured_data = unwrap!(super::StructuredData::new(0,
+                                                                     rand::random(),
+                                                                     0,
+                                                                     vec![1, 2, 3],
+                                                                     vec![keys1.0,
+                                                                          keys2.0,
+                                                                          keys3.0],
+                                                                     vec![],
+                                                                     Some(&keys1.1),
+                                                                     true));
+        assert!(structured_data.transfer_ownership(vec![], &keys1.1).is_err());
+
+        let mut successor = unwrap!(structured_data.transfer_ownership(vec![new_owner.0],
+                                                                        &keys1.1));
+        assert_eq!(successor.version(), 1);
+        assert_eq!(successor.get_data(), structured_data.get_data());
+        assert_eq!(successor.get_owner_keys(), &vec![new_owner.0]);
+        assert_eq!(successor.get_previous_owner_keys(),
+                   structured_data.get_owner_keys());
+        // One of three previous owners is not enough.
+        assert!(structured_data.validate_self_against_successor(&successor).is_err());
+        assert_eq!(unwrap!(successor.add_signature(&keys2.1)), 0);
+        unwrap!(structured_data.replace_with_other(successor));
+        assert_eq!(structured_data.get_owner_keys(), &vec![new_owner.0]);
+
+        // And back again, signed by the sole new owner.
+        let successor = unwrap!(structured_data.transfer_ownership(vec![keys1.0], &new_owner.1));
+        unwrap!(structured_data.replace_with_other(successor));
+        assert_eq!(structured_data.version(), 2);
+        assert_eq!(structured_data.get_owner_keys(), &vec![keys1.0]);
+    }
+
+    #[test]
+    fn sizes() {
+        let keys: Vec<_> = (0..3).map(|_| test_utils::gen_keypair()).collect();
+        let mut structured_data = unwrap!(super::StructuredData::new(0,
+                                                                     rand::random(),
+                                                                     0,
+                                                                     vec![1; 100],
+                                                                     vec![keys[0].0, keys[1].0],
+                                                                     vec![keys[2].0],
+                                                                     Some(&keys[2].1),
+                                                                     true));
+        assert_eq!(structured_data.estimated_size(),
+                   unwrap!(structured_data.serialised_size()));
+        assert!(structured_data.estimated_size() > structured_data.payload_size());
+
+        structured_data.set_signature_policy(SignaturePolicy::Threshold(2));
+        structured_data.set_compression(Some(Compression::Deflate));
+        structured_data.set_expires_at(Some(1));
+        structured_data.set_previous_hash(Some([3; 32]));
+        assert_eq!(structured_data.estimated_size(),
+                   unwrap!(structured_data.serialised_size()));
+    }
+
+    #[test]
+    fn content_hash() {
+        let keys = test_utils::gen_keypair();
+        let other_keys = test_utils::gen_keypair();
+        let mut structured_data = unwrap!(super::StructuredData::new(0,
+                                                                     rand::random(),
+                                                                     0,
+                                                                     vec![1; 10],
+                                                                     vec![keys.0, other_keys.0],
+                                                                     vec![],
+                                                                     None,
+                                                                     false));
+        let content_hash = unwrap!(structured_data.content_hash());
+        let metadata = unwrap!(structured_data.metadata());
+        assert_eq!(metadata.identifier, structured_data.identifier());
+        assert_eq!(metadata.version, 0);
+        assert_eq!(metadata.content_hash, content_hash);
+        assert_eq!(metadata.payload_size, 10);
+        assert!(!metadata.differs_from(&content_hash));
+
+        // Signing doesn't change the content hash.
+        let _ = unwrap!(structured_data.add_signature(&keys.1));
+        assert_eq!(unwrap!(structured_data.content_hash()), content_hash);
+
+        // Changing the content does.
+        let structured_data = unwrap!(super::StructuredData::new(0,
+                                                                 *structured_data.name(),
+                                                                 0,
+                                                                 vec![2; 10],
+                                                                 vec![keys.0, other_keys.0],
+                                                                 vec![],
+                                                                 None,
+                                                                 false));
+        assert!(metadata.differs_from(&unwrap!(structured_data.content_hash())));
+    }
+
+    #[test]
+    fn signature_status() {
+        let keys: Vec<_> = (0..3).map(|_| test_utils::gen_keypair()).collect();
+        let mut structured_data =
+            unwrap!(super::StructuredData::new(0,
+                                               rand::random(),
+                                               0,
+                                               vec![],
+                                               keys.iter().map(|keys| keys.0).collect(),
+                                               vec![],
+                                               Some(&keys[1].1),
+                                               false));
+        assert_eq!(structured_data.signature_status(),
+                   vec![(keys[0].0, false), (keys[1].0, true), (keys[2].0, false)]);
+
+        let signature = sign::sign_detached(&unwrap!(structured_data.signable_bytes()),
+                                            &keys[2].1);
+        assert!(structured_data.verify_signature(&signature, &keys[2].0));
+        assert!(!structured_data.verify_signature(&signature, &keys[0].0));
+        let _ = unwrap!(structured_data.add_external_signature(&keys[2].0, signature));
+        assert_eq!(structured_data.signature_status(),
+                   vec![(keys[0].0, false), (keys[1].0, true), (keys[2].0, true)]);
+    }
+
+    #[test]
+    fn validation_states() {
+        let keys: Vec<_> = (0..4).map(|_| test_utils::gen_keypair()).collect();
+        let owners: Vec<_> = keys.iter().map(|keys| keys.0).collect();
+        let name = rand::random();
+        let unsigned = || {
+            let mut structured_data = unwrap!(super::StructuredData::new(0,
+                                                                         name,
+                                                                         0,
+                                                                         vec![],
+                                                                         owners.clone(),
+                                                                         vec![],
+                                                                         None,
+                                                                         false));
+            structured_data.set_signature_policy(SignaturePolicy::All);
+            structured_data
+        };
+
+        let mut structured_data = unsigned();
+        assert_eq!(structured_data.validation_state(), ValidationState::Unsigned);
+        let _ = unwrap!(structured_data.add_signature(&keys[0].1));
+        assert_eq!(structured_data.validation_state(),
+                   ValidationState::PartiallySigned(3));
+        // A duplicate signature doesn't count twice.
+        let _ = unwrap!(structured_data.add_signature(&keys[0].1));
+        assert_eq!(structured_data.validation_state(),
+                   ValidationState::PartiallySigned(3));
+
+        let mut structured_data = unsigned();
+        for keys in &keys {
+            let _ = unwrap!(structured_data.add_signature(&keys.1));
+        }
+        assert_eq!(structured_data.validation_state(), ValidationState::Valid);
+        unwrap!(structured_data.verify_own_signatures());
+    }
+
+    #[test]
+    fn version_updates() {
+        let keys = test_utils::gen_keypair();
+        let name = rand::random();
+        let structured_data = |version| {
+            unwrap!(super::StructuredData::new(0,
+                                               name,
+                                               version,
+                                               vec![],
+                                               vec![keys.0],
+                                               vec![],
+                                               Some(&keys.1),
+                                               false))
+        };
+        let current = structured_data(5);
+        unwrap!(current.validate_version_update(&structured_data(6), 0));
+        unwrap!(current.validate_version_update(&structured_data(8), 2));
+        for newer in &[structured_data(5), structured_data(4), structured_data(9)] {
+            match current.validate_version_update(newer, 2) {
+                Err(Error::InvalidVersion) => (),
+                result => panic!("Unexpected result {:?}", result),
+            }
+        }
+
+        // The last version has no successor rather than wrapping round to version 0.
+        let last = structured_data(u64::MAX);
+        match last.delete(&keys.1) {
+            Err(Error::InvalidVersion) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match last.validate_self_against_successor(&structured_data(0)) {
+            Err(Error::Signature) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn minimise_signatures() {
+        let keys: Vec<_> = (0..5).map(|_| test_utils::gen_keypair()).collect();
+        let owners: Vec<_> = keys.iter().map(|keys| keys.0).collect();
+        let unsigned = unwrap!(super::StructuredData::new(0,
+                                                          rand::random(),
+                                                          0,
+                                                          vec![1, 2, 3],
+                                                          owners.clone(),
+                                                          vec![],
+                                                          None,
+                                                          false));
+
+        // All owners sign, in two different orders.
+        let mut forwards = unsigned.clone();
+        let mut backwards = unsigned.clone();
+        for keys in &keys {
+            let _ = unwrap!(forwards.add_signature(&keys.1));
+        }
+        for keys in keys.iter().rev() {
+            let _ = unwrap!(backwards.add_signature(&keys.1));
+        }
+        assert_eq!(unwrap!(forwards.superfluous_signatures()), 2);
+        unwrap!(forwards.verify_own_signatures_minimal());
+        let size = unwrap!(forwards.serialised_size());
+
+        assert_eq!(unwrap!(forwards.minimise_signatures()), 2);
+        assert_eq!(unwrap!(backwards.minimise_signatures()), 2);
+        assert_eq!(forwards, backwards);
+        assert_eq!(forwards.get_previous_owner_signatures().len(), 3);
+        assert!(unwrap!(forwards.serialised_size()) < size);
+        unwrap!(forwards.verify_own_signatures());
+        assert_eq!(unwrap!(forwards.superfluous_signatures()), 0);
+        assert_eq!(unwrap!(forwards.minimise_signatures()), 0);
+
+        // Too few valid signatures are left alone.
+        let mut partial = unsigned.clone();
+        let _ = unwrap!(partial.add_signature(&keys[0].1));
+        let _ = unwrap!(partial.add_signature(&test_utils::gen_keypair().1));
+        match partial.minimise_signatures() {
+            Err(Error::Validation) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert_eq!(partial.get_previous_owner_signatures().len(), 2);
+    }
+
+    #[test]
+    fn diff_and_patch() {
+        let keys = test_utils::gen_keypair();
+        let name = rand::random();
+        let structured_data = |version, data| {
+            unwrap!(super::StructuredData::new(0,
+                                               name,
+                                               version,
+                                               data,
+                                               vec![keys.0],
+                                               vec![],
+                                               Some(&keys.1),
+                                               false))
+        };
+        let content: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        let mut edited = content.clone();
+        edited[500] = 0;
+        edited.extend_from_slice(b"appended");
+        let (first, second) = (structured_data(0, content.clone()), structured_data(1, edited));
+
+        let patch = unwrap!(first.diff(&second));
+        assert_eq!(patch.base_hash(), &unwrap!(first.content_hash()));
+        assert_eq!(patch.version(), 1);
+        let serialised = unwrap!(serialise(&patch));
+        assert!(serialised.len() < unwrap!(serialise(&second)).len() - 900);
+        assert_eq!(unwrap!(first.apply_patch(unwrap!(deserialise(&serialised)))), second);
+
+        // Identical data gives an empty patch.
+        let unchanged = structured_data(1, content.clone());
+        assert_eq!(unwrap!(first.apply_patch(unwrap!(first.diff(&unchanged)))), unchanged);
+
+        // A patch only applies to the version it was made against.
+        let other = structured_data(0, vec![1; 10]);
+        match other.apply_patch(patch.clone()) {
+            Err(Error::Validation) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // The reconstructed successor is validated.
+        let unsigned = unwrap!(super::StructuredData::new(0,
+                                                          name,
+                                                          1,
+                                                          vec![],
+                                                          vec![keys.0],
+                                                          vec![],
+                                                          None,
+                                                          false));
+        assert!(first.apply_patch(unwrap!(first.diff(&unsigned))).is_err());
+    }
+
+    #[test]
+    fn owner_key_rotation() {
+        let keys: Vec<_> = (0..4).map(|_| test_utils::gen_keypair()).collect();
+        let structured_data = unwrap!(super::StructuredData::new(0,
+                                                                 rand::random(),
+                                                                 0,
+                                                                 vec![1, 2, 3],
+                                                                 vec![keys[0].0,
+                                                                      keys[1].0,
+                                                                      keys[2].0],
+                                                                 vec![],
+                                                                 Some(&keys[0].1),
+                                                                 false));
+        match structured_data.rotate_owner_key(&keys[3].0, keys[3].0, &keys[0].1) {
+            Err(Error::NoSuchEntry) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match structured_data.rotate_owner_key(&keys[2].0, keys[1].0, &keys[0].1) {
+            Err(Error::EntryExists) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // Replace the compromised keys[2] with keys[3]: its own signature doesn't count, so two of
+        // the remaining owners must sign.
+        let mut rotated = unwrap!(structured_data.rotate_owner_key(&keys[2].0,
+                                                                   keys[3].0,
+                                                                   &keys[2].1));
+        assert_eq!(rotated.get_data(), structured_data.get_data());
+        let mut new_owners = vec![keys[0].0, keys[1].0, keys[3].0];
+        new_owners.sort();
+        assert_eq!(rotated.get_owner_keys(), &new_owners);
+        let _ = unwrap!(rotated.add_signature(&keys[0].1));
+        assert!(structured_data.validate_self_against_successor(&rotated).is_err());
+        let _ = unwrap!(rotated.add_signature(&keys[1].1));
+        assert!(structured_data.validate_self_against_successor(&rotated).is_ok());
+
+        // A transfer replacing more than one key is not a rotation, so every previous owner's
+        // signature counts.
+        let mut transferred = unwrap!(structured_data.transfer_ownership(vec![keys[3].0],
+                                                                          &keys[2].1));
+        let _ = unwrap!(transferred.add_signature(&keys[0].1));
+        assert!(structured_data.validate_self_against_successor(&transferred).is_ok());
+
+        // The sole owner rotates its own key.
+        let single = unwrap!(super::StructuredData::new(0,
+                                                        rand::random(),
+                                                        0,
+                                                        vec![],
+                                                        vec![keys[0].0],
+                                                        vec![],
+                                                        Some(&keys[0].1),
+                                                        false));
+        let rotated = unwrap!(single.rotate_owner_key(&keys[0].0, keys[1].0, &keys[0].1));
+        assert!(single.validate_self_against_successor(&rotated).is_ok());
+    }
+
+    #[test]
+    fn signature_policies() {
+        let keys = (0..4).map(|_| test_utils::gen_keypair()).collect::<Vec<_>>();
+        let owner_keys = keys.iter().map(|&(ref public_key, _)| *public_key).collect::<Vec<_>>();
+        let identifier: [u8; 32] = rand::random();
+        let unsigned = |version, policy| {
+            let mut structured_data = unwrap!(super::StructuredData::new(0,
+                                                                         identifier,
+                                                                         version,
+                                                                         vec![],
+                                                                         owner_keys.clone(),
+                                                                         vec![],
+                                                                         Some(&keys[0].1),
+                                                                         false));
+            structured_data.set_signature_policy(policy);
+            assert!(structured_data.get_previous_owner_signatures().is_empty());
+            structured_data
+        };
+
+        // All owners must sign.
+        let mut all = unsigned(0, SignaturePolicy::All);
+        assert_eq!(all.signature_policy(), SignaturePolicy::All);
+        for (i, &(_, ref secret_key)) in keys.iter().enumerate() {
+            assert!(all.verify_previous_owner_signatures(&owner_keys).is_err());
+            assert_eq!(unwrap!(all.add_signature(secret_key)), keys.len() - i - 1);
+        }
+        assert!(all.verify_previous_owner_signatures(&owner_keys).is_ok());
+
+        // One of four is enough.
+        let mut one_of_four = unsigned(0, SignaturePolicy::Threshold(1));
+        assert!(one_of_four.verify_previous_owner_signatures(&owner_keys).is_err());
+        assert_eq!(unwrap!(one_of_four.add_signature(&keys[3].1)), 0);
+        assert!(one_of_four.verify_previous_owner_signatures(&owner_keys).is_ok());
+
+        // The predecessor's policy governs its successor, so relaxing the policy needs the
+        // approval of the stricter one.
+        let mut relaxed = unsigned(1, SignaturePolicy::Threshold(1));
+        let _ = unwrap!(relaxed.add_signature(&keys[0].1));
+        assert!(all.validate_self_against_successor(&relaxed).is_err());
+        for &(_, ref secret_key) in keys.iter().skip(1) {
+            let _ = unwrap!(relaxed.add_signature(secret_key));
+        }
+        unwrap!(all.replace_with_other(relaxed));
+        assert_eq!(all.signature_policy(), SignaturePolicy::Threshold(1));
+
+        // The policy is part of the signed data.
+        let mut tampered = unsigned(0, SignaturePolicy::All);
+        for &(_, ref secret_key) in &keys {
+            let _ = unwrap!(tampered.add_signature(secret_key));
+        }
+        let signatures = tampered.get_previous_owner_signatures().clone();
+        tampered.set_signature_policy(SignaturePolicy::Threshold(2));
+        tampered.replace_signatures(signatures);
+        assert!(tampered.verify_previous_owner_signatures(&owner_keys).is_err());
+    }
+
+    #[test]
+    fn weighted_signatures() {
+        let keys = (0..3).map(|_| test_utils::gen_keypair()).collect::<Vec<_>>();
+        let owner_keys = keys.iter().map(|&(ref public_key, _)| *public_key).collect::<Vec<_>>();
+        let identifier: [u8; 32] = rand::random();
+        // The company's key weighs as much as both employees' keys, the second left unlisted.
+        let policy = SignaturePolicy::Weighted(vec![(keys[1].0, 1), (keys[0].0, 2)], 2);
+        assert_eq!(policy.weight_of(&keys[0].0), 2);
+        assert_eq!(policy.weight_of(&keys[2].0), 1);
+        assert_eq!(policy.remaining_signatures(&[], &owner_keys), 1);
+        assert_eq!(policy.remaining_signatures(&[keys[1].0], &owner_keys), 1);
+        assert_eq!(policy.remaining_signatures(&[keys[1].0], &owner_keys[1..]), 1);
+        assert_eq!(policy.remaining_signatures(&[], &owner_keys[2..]), 2);
+        let unsigned = |version| {
+            let mut structured_data = unwrap!(super::StructuredData::new(0,
+                                                                         identifier,
+                                                                         version,
+                                                                         vec![],
+                                                                         owner_keys.clone(),
+                                                                         vec![],
+                                                                         None,
+                                                                         false));
+            structured_data.set_signature_policy(policy.clone());
+            structured_data
+        };
+
+        // The weights are held in canonical order.
+        let mut company = unsigned(0);
+        let mut weights = vec![(keys[0].0, 2), (keys[1].0, 1)];
+        weights.sort();
+        assert_eq!(company.signature_policy(), SignaturePolicy::Weighted(weights, 2));
+        let serialised = unwrap!(serialise(&company));
+        assert_eq!(unwrap!(deserialise::<super::StructuredData>(&serialised)), company);
+        assert_eq!(company.estimated_size(), serialised.len());
+
+        // The company alone is enough.
+        assert_eq!(company.validation_state(), ValidationState::Unsigned);
+        assert_eq!(unwrap!(company.add_signature(&keys[0].1)), 0);
+        assert_eq!(company.validation_state(), ValidationState::Valid);
+        assert!(company.verify_own_signatures().is_ok());
+
+        // So are both employees, but not either of them alone.
+        let mut employees = unsigned(1);
+        assert_eq!(unwrap!(employees.add_signature(&keys[1].1)), 1);
+        assert_eq!(employees.validation_state(), ValidationState::PartiallySigned(1));
+        assert!(employees.verify_own_signatures().is_err());
+        assert!(company.validate_self_against_successor(&employees).is_err());
+        assert_eq!(unwrap!(employees.add_signature(&keys[2].1)), 0);
+        assert!(employees.verify_own_signatures().is_ok());
+        let mut cache = ValidationCache::new(16);
+        assert!(employees.verify_own_signatures_cached(&mut cache).is_ok());
+        assert!(company.validate_self_against_successor(&employees).is_ok());
+
+        // The weights are part of the signed data.
+        let mut tampered = unsigned(0);
+        let _ = unwrap!(tampered.add_signature(&keys[1].1));
+        let signatures = tampered.get_previous_owner_signatures().clone();
+        tampered.set_signature_policy(SignaturePolicy::Weighted(vec![(keys[1].0, 2)], 2));
+        tampered.replace_signatures(signatures);
+        assert!(tampered.verify_own_signatures().is_err());
+    }
+
+    #[test]
+    fn external_signatures() {
+        let keys = (0..3).map(|_| test_utils::gen_keypair()).collect::<Vec<_>>();
+        let owner_keys = keys.iter().map(|&(ref public_key, _)| *public_key).collect::<Vec<_>>();
+        let mut structured_data = unwrap!(super::StructuredData::new(0,
+                                                                     rand::random(),
+                                                                     0,
+                                                                     vec![],
+                                                                     owner_keys.clone(),
+                                                                     vec![],
+                                                                     None,
+                                                                     false));
+        let signable_bytes = unwrap!(structured_data.signable_bytes());
+
+        // Signed elsewhere, e.g. on a hardware wallet.
+        let signature = sign::sign_detached(&signable_bytes, &keys[0].1);
+        match structured_data.add_external_signature(&keys[1].0, signature) {
+            Err(Error::Signature) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        let stranger = test_utils::gen_keypair();
+        let stranger_signature = sign::sign_detached(&signable_bytes, &stranger.1);
+        match structured_data.add_external_signature(&stranger.0, stranger_signature) {
+            Err(Error::AccessDenied) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert_eq!(unwrap!(structured_data.add_external_signature(&keys[0].0, signature)), 1);
+        match structured_data.add_external_signature(&keys[0].0, signature) {
+            Err(Error::Validation) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert!(structured_data.verify_previous_owner_signatures(&owner_keys).is_err());
+
+        let signature = sign::sign_detached(&signable_bytes, &keys[2].1);
+        assert_eq!(unwrap!(structured_data.add_external_signature(&keys[2].0, signature)), 0);
+        assert!(structured_data.verify_previous_owner_signatures(&owner_keys).is_ok());
+    }
+
+    #[test]
+    fn signatures_collected_across_devices() {
+        let keys = (0..5).map(|_| test_utils::gen_keypair()).collect::<Vec<_>>();
+        let owner_keys = keys.iter().map(|&(ref public_key, _)| *public_key).collect::<Vec<_>>();
+        let name = rand::random();
+        let mut current = unwrap!(super::StructuredData::new(0,
+                                                             name,
+                                                             0,
+                                                             vec![],
+                                                             owner_keys.clone(),
+                                                             vec![],
+                                                             None,
+                                                             false));
+        let mut successor = unwrap!(super::StructuredData::new(0,
+                                                               name,
+                                                               1,
+                                                               b"update".to_vec(),
+                                                               owner_keys,
+                                                               vec![],
+                                                               Some(&keys[0].1),
+                                                               false));
+        let exported = unwrap!(successor.export_unsigned());
+
+        // Signed on two other devices, each from the bytes exported by the first.
+        let first = unwrap!(super::StructuredData::import_and_sign(&exported, &keys[1].1));
+        assert_eq!(first.get_previous_owner_signatures().len(), 2);
+        let second = unwrap!(super::StructuredData::import_and_sign(&exported, &keys[2].1));
+        let stranger = test_utils::gen_keypair();
+        match super::StructuredData::import_and_sign(&exported, &stranger.1) {
+            Err(Error::AccessDenied) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        assert_eq!(unwrap!(successor.merge_exported(&unwrap!(first.export_unsigned()))), 1);
+        assert!(current.replace_with_other(successor.clone()).is_err());
+        assert_eq!(unwrap!(successor.merge_exported(&unwrap!(second.export_unsigned()))), 0);
+        unwrap!(current.replace_with_other(successor));
+        assert_eq!(current.version(), 1);
+
+        let other = unwrap!(super::StructuredData::new(0,
+                                                       name,
+                                                       1,
+                                                       b"other".to_vec(),
+                                                       vec![keys[0].0],
+                                                       vec![],
+                                                       None,
+                                                       false));
+        match current.merge_exported(&unwrap!(other.export_unsigned())) {
+            Err(Error::BadIdentifier) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn expiry() {
+        let keys = test_utils::gen_keypair();
+        let mut structured_data = unwrap!(super::StructuredData::new(0,
+                                                                     rand::random(),
+                                                                     0,
+                                                                     vec![],
+                                                                     vec![keys.0],
+                                                                     vec![],
+                                                                     Some(&keys.1),
+                                                                     false));
+        assert_eq!(structured_data.expires_at(), None);
+        assert!(!structured_data.is_expired(u64::max_value()));
+
+        structured_data.set_expires_at(Some(1000));
+        assert!(structured_data.get_previous_owner_signatures().is_empty());
+        assert_eq!(unwrap!(structured_data.add_signature(&keys.1)), 0);
+        assert!(!structured_data.is_expired(999));
+        assert!(structured_data.is_expired(1000));
+
+        // The expiry time is part of the signed data.
+        let mut tampered = structured_data.clone();
+        tampered.expires_at = Some(2000);
+        assert!(tampered.verify_previous_owner_signatures(&[keys.0]).is_err());
+        assert!(structured_data.verify_previous_owner_signatures(&[keys.0]).is_ok());
+
+        let decoded: super::StructuredData =
+            unwrap!(deserialise(&unwrap!(serialise(&structured_data))));
+        assert_eq!(decoded.expires_at(), Some(1000));
+        let successor = unwrap!(structured_data.transfer_ownership(vec![keys.0], &keys.1));
+        assert_eq!(successor.expires_at(), Some(1000));
+    }
+
+    #[test]
+    fn app_metadata() {
+        let keys = test_utils::gen_keypair();
+        let mut structured_data = unwrap!(super::StructuredData::new(0,
+                                                                     rand::random(),
+                                                                     0,
+                                                                     vec![1; 100],
+                                                                     vec![keys.0],
+                                                                     vec![],
+                                                                     Some(&keys.1),
+                                                                     false));
+        assert!(structured_data.app_metadata().is_empty());
+        match structured_data.set_app_metadata(vec![0; super::MAX_APP_METADATA_BYTES + 1]) {
+            Err(Error::DataTooLarge) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        unwrap!(structured_data.set_app_metadata(b"text/plain".to_vec()));
+        assert!(structured_data.get_previous_owner_signatures().is_empty());
+        assert_eq!(unwrap!(structured_data.add_signature(&keys.1)), 0);
+        assert_eq!(structured_data.estimated_size(),
+                   unwrap!(structured_data.serialised_size()));
+
+        // The metadata is part of the signed data.
+        let mut tampered = structured_data.clone();
+        tampered.app_metadata = b"text/html".to_vec();
+        assert!(tampered.verify_previous_owner_signatures(&[keys.0]).is_err());
+        assert!(structured_data.verify_previous_owner_signatures(&[keys.0]).is_ok());
+
+        let decoded: super::StructuredData =
+            unwrap!(deserialise(&unwrap!(serialise(&structured_data))));
+        assert_eq!(decoded.app_metadata(), b"text/plain");
+        let successor = unwrap!(structured_data.transfer_ownership(vec![keys.0], &keys.1));
+        assert_eq!(successor.app_metadata(), b"text/plain");
+
+        // Oversized metadata is refused however it was set.
+        tampered.app_metadata = vec![0; super::MAX_APP_METADATA_BYTES + 1];
+        match tampered.validate_size() {
+            Err(Error::DataTooLarge) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn signable_bytes_golden_vector() {
+        // The expected bytes hold Ed25519 keys.
+        if cfg!(feature = "dilithium") {
+            return;
+        }
+        let owner = PublicKey([1; sign::PUBLICKEYBYTES]);
+        let mut structured_data = unwrap!(super::StructuredData::new(5,
+                                                                     [2; 32],
+                                                                     3,
+                                                                     b"data".to_vec(),
+                                                                     vec![owner],
+                                                                     vec![],
+                                                                     None,
+                                                                     false));
+        structured_data.set_signature_policy(SignaturePolicy::Threshold(2));
+        // Domain, type tag, name, data, previous owners, current owners, version, policy.
+        assert_eq!(unwrap!(structured_data.signable_bytes()).to_hex(),
+                   "000000000000001b\
+                    646174615f636861696e2f53747275637475726564446174612f32\
+                    0000000000000005\
+                    0202020202020202020202020202020202020202020202020202020202020202\
+                    0000000000000004\
+                    64617461\
+                    0000000000000000\
+                    0000000000000001\
+                    0101010101010101010101010101010101010101010101010101010101010101\
+                    0000000000000003\
+                    02\
+                    0000000000000002");
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn compressed_data() {
+        let keys = test_utils::gen_keypair();
+        let content = vec![9u8; MAX_BYTES];
+        let mut structured_data = unwrap!(super::StructuredData::new(0,
+                                                                     rand::random(),
+                                                                     0,
+                                                                     vec![],
+                                                                     vec![keys.0],
+                                                                     vec![],
+                                                                     None,
+                                                                     false));
+        // Too large uncompressed, but fits once compressed.
+        unwrap!(structured_data.compress_data(&content, Compression::Deflate));
+        assert_eq!(structured_data.compression(), Some(Compression::Deflate));
+        assert!(structured_data.payload_size() < content.len());
+        assert_eq!(&*unwrap!(structured_data.decompressed_data()), &content[..]);
+        assert_eq!(unwrap!(structured_data.add_signature(&keys.1)), 0);
+        assert!(structured_data.verify_previous_owner_signatures(&[keys.0]).is_ok());
+
+        let decoded: super::StructuredData =
+            unwrap!(deserialise(&unwrap!(serialise(&structured_data))));
+        assert_eq!(decoded, structured_data);
+
+        // The hint is part of the signed data.
+        let mut tampered = structured_data.clone();
+        tampered.compression = None;
+        assert!(tampered.verify_previous_owner_signatures(&[keys.0]).is_err());
+
+        let successor = unwrap!(structured_data.transfer_ownership(vec![keys.0], &keys.1));
+        assert_eq!(successor.compression(), Some(Compression::Deflate));
+        assert!(structured_data.validate_self_against_successor(&successor).is_ok());
+        assert_eq!(unwrap!(structured_data.delete(&keys.1)).compression(), None);
+    }
+
+    #[test]
+    fn cached_validation() {
+        let owners: Vec<_> = (0..3).map(|_| test_utils::gen_keypair()).collect();
+        let owner_keys: Vec<_> = owners.iter().map(|keys| keys.0).collect();
+        let structured_data = unwrap!(super::StructuredData::new(0,
+                                                                 rand::random(),
+                                                                 0,
+                                                                 vec![],
+                                                                 owner_keys.clone(),
+                                                                 vec![],
+                                                                 Some(&owners[0].1),
+                                                                 false));
+        let mut successor = unwrap!(super::StructuredData::new(0,
+                                                               *structured_data.name(),
+                                                               1,
+                                                               vec![1],
+                                                               owner_keys,
+                                                               vec![],
+                                                               Some(&owners[0].1),
+                                                               false));
+        let mut cache = ValidationCache::new(10);
+        match structured_data.validate_self_against_successor_cached(&successor, &mut cache) {
+            Err(Error::Validation) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        let _ = unwrap!(successor.add_signature(&owners[1].1));
+        unwrap!(structured_data.validate_self_against_successor_cached(&successor, &mut cache));
+        let misses = cache.metrics().misses;
+        assert!(misses > 0);
+        // Later validations find every result cached.
+        for _ in 0..3 {
+            unwrap!(structured_data.validate_self_against_successor_cached(&successor, &mut cache));
+            unwrap!(successor.verify_own_signatures_cached(&mut cache));
+        }
+        assert_eq!(cache.metrics().misses, misses);
+        assert!(cache.metrics().hits >= 6);
+
+        let mut forged = successor.clone();
+        forged.replace_signatures(vec![sign::sign_detached(b"forged", &owners[1].1),
+                                       sign::sign_detached(b"forged", &owners[2].1)]);
+        assert!(structured_data.validate_self_against_successor_cached(&forged, &mut cache)
+            .is_err());
+    }
+
+    #[test]
+    fn soft_delete_and_undelete() {
+        let keys = test_utils::gen_keypair();
+        let other_keys = test_utils::gen_keypair();
+        let structured_data = unwrap!(super::StructuredData::new(0,
+                                                                 rand::random(),
+                                                                 0,
+                                                                 vec![1, 2, 3],
+                                                                 vec![keys.0],
+                                                                 vec![],
+                                                                 Some(&keys.1),
+                                                                 false));
+        match structured_data.undelete(vec![4], &keys.1) {
+            Err(Error::Validation) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        let soft_deleted = unwrap!(structured_data.soft_delete(&keys.1));
+        assert_eq!(soft_deleted.version(), 1);
+        assert!(soft_deleted.get_data().is_empty());
+        assert_eq!(*soft_deleted.get_owner_keys(), vec![keys.0]);
+        assert!(soft_deleted.is_soft_deleted() && !soft_deleted.is_deleted());
+        unwrap!(structured_data.validate_self_against_successor(&soft_deleted));
+        match soft_deleted.soft_delete(&keys.1) {
+            Err(Error::DataDeleted) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match soft_deleted.transfer_ownership(vec![other_keys.0], &keys.1) {
+            Err(Error::DataDeleted) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // Only the same owners may undelete, and they can't hand the data over while deleted.
+        let undeleted = unwrap!(soft_deleted.undelete(vec![4], &keys.1));
+        assert_eq!(undeleted.version(), 2);
+        assert_eq!(*undeleted.get_data(), vec![4]);
+        unwrap!(soft_deleted.validate_self_against_successor(&undeleted));
+        let forged = unwrap!(soft_deleted.undelete(vec![4], &other_keys.1));
+        assert!(soft_deleted.validate_self_against_successor(&forged).is_err());
+        let mut handed_over = unwrap!(super::StructuredData::new(0,
+                                                                 *structured_data.name(),
+                                                                 2,
+                                                                 vec![4],
+                                                                 vec![other_keys.0],
+                                                                 vec![keys.0],
+                                                                 None,
+                                                                 false));
+        let _ = unwrap!(handed_over.add_signature(&keys.1));
+        match soft_deleted.validate_self_against_successor(&handed_over) {
+            Err(Error::DataDeleted) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match undeleted.undelete(vec![5], &keys.1) {
+            Err(Error::Validation) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // Soft-deleted data can still be deleted for good, after which it can't be undeleted.
+        let deleted = unwrap!(soft_deleted.delete(&keys.1));
+        unwrap!(soft_deleted.validate_self_against_successor(&deleted));
+        match deleted.undelete(vec![4], &keys.1) {
+            Err(Error::DataDeleted) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // The mark is serialised and signed, so can't be stripped to hand the data over.
+        let serialised = unwrap!(serialise(&soft_deleted));
+        let decoded: super::StructuredData = unwrap!(deserialise(&serialised));
+        assert!(decoded.is_soft_deleted());
+        unwrap!(decoded.verify_own_signatures());
+        let mut unmarked = soft_deleted.clone();
+        unmarked.soft_deleted = false;
+        assert!(unmarked.verify_own_signatures().is_err());
+
+        // Data emptied by an ordinary update is not soft-deleted, so can still be handed over.
+        let emptied = unwrap!(super::StructuredData::new(0,
+                                                         *structured_data.name(),
+                                                         1,
+                                                         vec![],
+                                                         vec![keys.0],
+                                                         vec![],
+                                                         Some(&keys.1),
+                                                         false));
+        unwrap!(structured_data.validate_self_against_successor(&emptied));
+        assert!(!emptied.is_soft_deleted());
+        let handed_over = unwrap!(emptied.transfer_ownership(vec![other_keys.0], &keys.1));
+        unwrap!(emptied.validate_self_against_successor(&handed_over));
+        match emptied.undelete(vec![4], &keys.1) {
+            Err(Error::Validation) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // Only data emptied but still owned may be marked.
+        let mut marked = unwrap!(super::StructuredData::new(0,
+                                                            *structured_data.name(),
+                                                            1,
+                                                            vec![4],
+                                                            vec![keys.0],
+                                                            vec![],
+                                                            None,
+                                                            false));
+        marked.soft_deleted = true;
+        let _ = unwrap!(marked.add_signature(&keys.1));
+        match structured_data.validate_self_against_successor(&marked) {
+            Err(Error::Validation) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn writers() {
+        let owner = test_utils::gen_keypair();
+        let writer = test_utils::gen_keypair();
+        let other = test_utils::gen_keypair();
+        let mut structured_data = unwrap!(super::StructuredData::new(0,
+                                                                     rand::random(),
+                                                                     0,
+                                                                     vec![1],
+                                                                     vec![owner.0],
+                                                                     vec![],
+                                                                     None,
+                                                                     false));
+        structured_data.set_writers(vec![writer.0, writer.0]);
+        assert_eq!(structured_data.writers(), &vec![writer.0]);
+        let _ = unwrap!(structured_data.add_signature(&owner.1));
+        unwrap!(structured_data.verify_own_signatures());
+
+        // The writers are only serialised when there are some.
+        let serialised = unwrap!(serialise(&structured_data));
+        assert_eq!(structured_data.estimated_size(), serialised.len());
+        assert_eq!(unwrap!(deserialise::<super::StructuredData>(&serialised)), structured_data);
+        let mut without_writers = structured_data.clone();
+        without_writers.set_writers(vec![]);
+        assert_eq!(unwrap!(serialise(&without_writers)).len() + 8 + sign::PUBLICKEYBYTES,
+                   serialised.len());
+
+        // A writer may publish new data, as may the owners.
+        let updated = unwrap!(structured_data.writer_update(vec![2], &writer.1));
+        unwrap!(structured_data.validate_self_against_successor(&updated));
+        let by_owner = unwrap!(structured_data.writer_update(vec![3], &owner.1));
+        unwrap!(structured_data.validate_self_against_successor(&by_owner));
+
+        // But a writer may change nothing else, nor delete the data, and others may not publish.
+        let mut with_metadata = updated.clone();
+        unwrap!(with_metadata.set_app_metadata(b"text/plain".to_vec()));
+        let _ = unwrap!(with_metadata.add_signature(&writer.1));
+        assert!(structured_data.validate_self_against_successor(&with_metadata).is_err());
+        let transferred = unwrap!(structured_data.transfer_ownership(vec![writer.0], &writer.1));
+        assert!(structured_data.validate_self_against_successor(&transferred).is_err());
+        let soft_deleted = unwrap!(structured_data.soft_delete(&writer.1));
+        assert!(structured_data.validate_self_against_successor(&soft_deleted).is_err());
+        match structured_data.writer_update(vec![], &writer.1) {
+            Err(Error::Validation) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        let forged = unwrap!(structured_data.writer_update(vec![2], &other.1));
+        assert!(structured_data.validate_self_against_successor(&forged).is_err());
+
+        unwrap!(structured_data.replace_with_other(updated));
+        assert_eq!(*structured_data.get_data(), vec![2]);
+        assert_eq!(structured_data.writers(), &vec![writer.0]);
+    }
+
+    #[test]
+    fn owner_limits() {
+        let keys = test_utils::gen_keypair();
+        let name = rand::random();
+        match super::StructuredData::new(0, name, 0, vec![], vec![], vec![], None, false) {
+            Err(Error::NoOwners) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        let owner_keys = |count: usize| {
+            (0..count)
+                .map(|index| {
+                    let mut key = PublicKey([0; sign::PUBLICKEYBYTES]);
+                    key.0[0] = index as u8;
+                    key.0[1] = (index >> 8) as u8;
+                    key
+                })
+                .collect::<Vec<_>>()
+        };
+        let most = unwrap!(super::StructuredData::new(0,
+                                                      name,
+                                                      0,
+                                                      vec![1],
+                                                      owner_keys(MAX_OWNERS),
+                                                      vec![],
+                                                      None,
+                                                      false));
+        assert_eq!(most.get_owner_keys().len(), MAX_OWNERS);
+        match super::StructuredData::new(0,
+                                         name,
+                                         0,
+                                         vec![1],
+                                         owner_keys(MAX_OWNERS + 1),
+                                         vec![],
+                                         None,
+                                         false) {
+            Err(Error::TooManyOwners) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        let mut too_many_writers = most.clone();
+        too_many_writers.set_writers(owner_keys(MAX_OWNERS + 1));
+        match Data::Structured(too_many_writers).validate() {
+            Err(Error::TooManyOwners) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // Frozen data has no owners, so nobody can update or delete it.
+        let frozen = unwrap!(super::StructuredData::new_frozen(0, name, vec![1], false));
+        assert!(frozen.is_frozen());
+        assert!(frozen.get_owner_keys().is_empty());
+        unwrap!(Data::Structured(frozen.clone()).validate());
+        let parsed: super::StructuredData = unwrap!(deserialise(&unwrap!(serialise(&frozen))));
+        assert_eq!(parsed, frozen);
+        assert!(parsed.is_frozen());
+
+        let successor = unwrap!(super::StructuredData::new(0,
+                                                           name,
+                                                           1,
+                                                           vec![2],
+                                                           vec![keys.0],
+                                                           vec![],
+                                                           Some(&keys.1),
+                                                           false));
+        match frozen.validate_self_against_successor(&successor) {
+            Err(Error::NoOwners) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        let mut tombstone =
+            unwrap!(super::StructuredData::new(0, name, 1, vec![], vec![], vec![], None, false));
+        assert!(tombstone.is_deleted());
+        match frozen.validate_self_against_successor(&tombstone) {
+            Err(Error::NoOwners) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // Frozen data may not have owners, nor be soft-deleted.
+        let mut soft_deleted = frozen.clone();
+        soft_deleted.data = vec![];
+        soft_deleted.soft_deleted = true;
+        match Data::Structured(soft_deleted).validate() {
+            Err(Error::Validation) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        tombstone.frozen = true;
+        tombstone.version = 0;
+        tombstone.current_owner_keys = Owners::new(vec![keys.0]);
+        match Data::Structured(tombstone).validate() {
+            Err(Error::Validation) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
 }
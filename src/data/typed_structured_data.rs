@@ -0,0 +1,189 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Typed structured data
+//! `StructuredData` whose data is the serialised form of an application type, so applications
+//! work with their own structs rather than raw bytes. The content is decoded once, when the data
+//! is created or received, and must fit in `MAX_BYTES` once encoded.
+
+use crypto::sign::{PublicKey, SecretKey};
+use data::{MAX_BYTES, StructuredData};
+use data::version::Version;
+use error::Error;
+use rustc_serialize::{Decodable, Encodable};
+use serialisation::{deserialise, serialise};
+
+/// A `StructuredData` with its content decoded as a `T`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypedStructuredData<T> {
+    structured_data: StructuredData,
+    content: T,
+}
+
+impl<T: Decodable + Encodable> TypedStructuredData<T> {
+    /// Creates the first version of the data called `name` holding `content`, owned by `owners`
+    /// and signed with `signing_key`.
+    ///
+    /// Fails with `DataTooLarge` if `content` is larger than `MAX_BYTES` once encoded.
+    pub fn new(type_tag: u64,
+               name: [u8; 32],
+               content: T,
+               owners: Vec<PublicKey>,
+               signing_key: &SecretKey)
+               -> Result<TypedStructuredData<T>, Error> {
+        let structured_data = StructuredData::new(type_tag,
+                                                  name,
+                                                  0,
+                                                  encode(&content)?,
+                                                  owners,
+                                                  vec![],
+                                                  Some(signing_key),
+                                                  false)?;
+        Ok(TypedStructuredData {
+            structured_data: structured_data,
+            content: content,
+        })
+    }
+
+    /// Wraps `structured_data` received from the network, decoding its content.
+    pub fn from_structured_data(structured_data: StructuredData)
+                                -> Result<TypedStructuredData<T>, Error> {
+        let content = deserialise(&structured_data.decompressed_data()?)?;
+        Ok(TypedStructuredData {
+            structured_data: structured_data,
+            content: content,
+        })
+    }
+
+    /// The decoded content.
+    pub fn content(&self) -> &T {
+        &self.content
+    }
+
+    /// Creates the next version holding `content`, signed with `signing_key`.
+    ///
+    /// The owners and signature policy are kept, and a ledger's next version links to this one,
+    /// so once signed as the policy requires the result is a valid successor of this data.
+    pub fn update_content(&self,
+                          content: T,
+                          signing_key: &SecretKey)
+                          -> Result<TypedStructuredData<T>, Error> {
+        let current = &self.structured_data;
+        let mut structured_data = StructuredData::new(current.get_type_tag(),
+                                                      *current.name(),
+                                                      Version(current.version()).next()?.0,
+                                                      encode(&content)?,
+                                                      current.get_owner_keys().to_vec(),
+                                                      vec![],
+                                                      None,
+                                                      current.ledger())?;
+        structured_data.set_signature_policy(current.signature_policy());
+        if current.ledger() {
+            structured_data.set_previous_hash(Some(current.ledger_hash()?));
+        }
+        let _ = structured_data.add_signature(signing_key)?;
+        Ok(TypedStructuredData {
+            structured_data: structured_data,
+            content: content,
+        })
+    }
+
+    /// Returns the underlying `StructuredData`.
+    pub fn structured_data(&self) -> &StructuredData {
+        &self.structured_data
+    }
+
+    /// Unwraps the underlying `StructuredData`, e.g. to store it.
+    pub fn into_structured_data(self) -> StructuredData {
+        self.structured_data
+    }
+}
+
+fn encode<T: Encodable>(content: &T) -> Result<Vec<u8>, Error> {
+    let encoded = serialise(content)?;
+    if encoded.len() > MAX_BYTES {
+        return Err(Error::DataTooLarge);
+    }
+    Ok(encoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{MAX_BYTES, StructuredData};
+    use error::Error;
+    use rand;
+    use test_utils;
+
+    #[derive(Clone, Debug, PartialEq, Eq, RustcEncodable, RustcDecodable)]
+    struct DnsRecord {
+        domain: String,
+        addresses: Vec<String>,
+    }
+
+    fn record(address: &str) -> DnsRecord {
+        DnsRecord {
+            domain: "example".to_owned(),
+            addresses: vec![address.to_owned()],
+        }
+    }
+
+    #[test]
+    fn create_update_and_parse() {
+        let keys = test_utils::gen_keypair();
+        let typed = unwrap!(TypedStructuredData::new(5,
+                                                     rand::random(),
+                                                     record("1.2.3.4"),
+                                                     vec![keys.0],
+                                                     &keys.1));
+        assert_eq!(*typed.content(), record("1.2.3.4"));
+        unwrap!(typed.structured_data().verify_own_signatures());
+
+        let updated = unwrap!(typed.update_content(record("5.6.7.8"), &keys.1));
+        assert_eq!(*updated.content(), record("5.6.7.8"));
+        assert_eq!(updated.structured_data().version(), 1);
+        unwrap!(typed.structured_data().validate_self_against_successor(updated.structured_data()));
+
+        let parsed: TypedStructuredData<DnsRecord> =
+            unwrap!(TypedStructuredData::from_structured_data(updated.clone()
+                .into_structured_data()));
+        assert_eq!(parsed, updated);
+
+        let other = unwrap!(StructuredData::new(5,
+                                                rand::random(),
+                                                0,
+                                                vec![1, 2, 3],
+                                                vec![keys.0],
+                                                vec![],
+                                                Some(&keys.1),
+                                                false));
+        match TypedStructuredData::<DnsRecord>::from_structured_data(other) {
+            Err(Error::Serialisation(_)) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn too_large() {
+        let keys = test_utils::gen_keypair();
+        let content = vec![0u8; MAX_BYTES];
+        match TypedStructuredData::new(5, rand::random(), content, vec![keys.0], &keys.1) {
+            Err(Error::DataTooLarge) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
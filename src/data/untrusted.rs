@@ -0,0 +1,358 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Decoding untrusted input
+//! A decoder of the crate's own serialisation for bytes received from peers, which fails with
+//! `Encoding` on any malformed input rather than panicking or exhausting memory.
+//!
+//! The general-purpose decoder trusts the length prefixes it reads and allocates for them up
+//! front, and derived decoders panic on unknown enum variants. This one refuses lengths beyond
+//! the bytes left, since every element of the data types takes at least one byte, variant indices
+//! beyond the variants of the enum, invalid booleans, option tags, chars and UTF-8, and trailing
+//! bytes. It decodes exactly what the general-purpose decoder does from well-formed input.
+
+#![cfg_attr(feature = "cargo-clippy", deny(option_unwrap_used, result_unwrap_used))]
+
+use error::Error;
+use rustc_serialize::{Decodable, Decoder};
+use std::char;
+use std::str;
+
+/// Decodes a `T` serialised in the crate's own format from `bytes` received from an untrusted
+/// source, failing with `Encoding` if they are malformed or there are bytes left over.
+pub fn deserialise<T: Decodable>(bytes: &[u8]) -> Result<T, Error> {
+    let mut decoder = UntrustedDecoder { bytes: bytes };
+    let value = T::decode(&mut decoder)?;
+    if !decoder.bytes.is_empty() {
+        return Err(Error::Encoding(format!("{} bytes left over", decoder.bytes.len())));
+    }
+    Ok(value)
+}
+
+fn encoding_error<T>(message: &str) -> Result<T, Error> {
+    Err(Error::Encoding(message.to_owned()))
+}
+
+struct UntrustedDecoder<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> UntrustedDecoder<'a> {
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if len > self.bytes.len() {
+            return encoding_error("truncated input");
+        }
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(bytes)
+    }
+
+    /// Reads a big-endian unsigned integer of `len` bytes.
+    fn read_be(&mut self, len: usize) -> Result<u64, Error> {
+        let bytes = self.read_bytes(len)?;
+        Ok(bytes.iter().fold(0, |value, &byte| (value << 8) | byte as u64))
+    }
+
+    /// Reads the length of a string, sequence or map, refusing one beyond the bytes left before
+    /// anything is allocated for it.
+    fn read_len(&mut self) -> Result<usize, Error> {
+        let len = self.read_be(8)?;
+        if len > self.bytes.len() as u64 {
+            return Err(Error::Encoding(format!("length {} exceeds the {} bytes left",
+                                               len,
+                                               self.bytes.len())));
+        }
+        Ok(len as usize)
+    }
+}
+
+impl<'a> Decoder for UntrustedDecoder<'a> {
+    type Error = Error;
+
+    fn read_nil(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn read_usize(&mut self) -> Result<usize, Error> {
+        let value = self.read_be(8)?;
+        if value > usize::max_value() as u64 {
+            return encoding_error("usize out of range");
+        }
+        Ok(value as usize)
+    }
+
+    fn read_u64(&mut self) -> Result<u64, Error> {
+        self.read_be(8)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, Error> {
+        Ok(self.read_be(4)? as u32)
+    }
+
+    fn read_u16(&mut self) -> Result<u16, Error> {
+        Ok(self.read_be(2)? as u16)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        Ok(self.read_be(1)? as u8)
+    }
+
+    fn read_isize(&mut self) -> Result<isize, Error> {
+        let value = self.read_i64()?;
+        if value < isize::min_value() as i64 || value > isize::max_value() as i64 {
+            return encoding_error("isize out of range");
+        }
+        Ok(value as isize)
+    }
+
+    fn read_i64(&mut self) -> Result<i64, Error> {
+        Ok(self.read_be(8)? as i64)
+    }
+
+    fn read_i32(&mut self) -> Result<i32, Error> {
+        Ok(self.read_be(4)? as u32 as i32)
+    }
+
+    fn read_i16(&mut self) -> Result<i16, Error> {
+        Ok(self.read_be(2)? as u16 as i16)
+    }
+
+    fn read_i8(&mut self) -> Result<i8, Error> {
+        Ok(self.read_be(1)? as u8 as i8)
+    }
+
+    fn read_bool(&mut self) -> Result<bool, Error> {
+        match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => encoding_error("invalid bool"),
+        }
+    }
+
+    fn read_f64(&mut self) -> Result<f64, Error> {
+        Ok(f64::from_bits(self.read_be(8)?))
+    }
+
+    fn read_f32(&mut self) -> Result<f32, Error> {
+        Ok(f32::from_bits(self.read_be(4)? as u32))
+    }
+
+    fn read_char(&mut self) -> Result<char, Error> {
+        // A char is written as its UTF-8 encoding, whose first byte gives its width.
+        let width = match self.bytes.first() {
+            Some(&byte) if byte < 0x80 => 1,
+            Some(&byte) if byte & 0xe0 == 0xc0 => 2,
+            Some(&byte) if byte & 0xf0 == 0xe0 => 3,
+            Some(&byte) if byte & 0xf8 == 0xf0 => 4,
+            Some(_) => return encoding_error("invalid char"),
+            None => return encoding_error("truncated input"),
+        };
+        let bytes = self.read_bytes(width)?;
+        match str::from_utf8(bytes).ok().and_then(|text| text.chars().next()) {
+            Some(value) => Ok(value),
+            None => encoding_error("invalid char"),
+        }
+    }
+
+    fn read_str(&mut self) -> Result<String, Error> {
+        let len = self.read_len()?;
+        match str::from_utf8(self.read_bytes(len)?) {
+            Ok(text) => Ok(text.to_owned()),
+            Err(_) => encoding_error("invalid UTF-8"),
+        }
+    }
+
+    fn read_enum<T, F>(&mut self, _name: &str, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self) -> Result<T, Error>
+    {
+        f(self)
+    }
+
+    fn read_enum_variant<T, F>(&mut self, names: &[&str], mut f: F) -> Result<T, Error>
+        where F: FnMut(&mut Self, usize) -> Result<T, Error>
+    {
+        let index = self.read_u32()? as usize;
+        if index >= names.len() {
+            return Err(Error::Encoding(format!("invalid variant {}", index)));
+        }
+        f(self, index)
+    }
+
+    fn read_enum_variant_arg<T, F>(&mut self, _index: usize, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self) -> Result<T, Error>
+    {
+        f(self)
+    }
+
+    fn read_enum_struct_variant<T, F>(&mut self, names: &[&str], f: F) -> Result<T, Error>
+        where F: FnMut(&mut Self, usize) -> Result<T, Error>
+    {
+        self.read_enum_variant(names, f)
+    }
+
+    fn read_enum_struct_variant_field<T, F>(&mut self,
+                                            _name: &str,
+                                            _index: usize,
+                                            f: F)
+                                            -> Result<T, Error>
+        where F: FnOnce(&mut Self) -> Result<T, Error>
+    {
+        f(self)
+    }
+
+    fn read_struct<T, F>(&mut self, _name: &str, _len: usize, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self) -> Result<T, Error>
+    {
+        f(self)
+    }
+
+    fn read_struct_field<T, F>(&mut self, _name: &str, _index: usize, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self) -> Result<T, Error>
+    {
+        f(self)
+    }
+
+    fn read_tuple<T, F>(&mut self, _len: usize, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self) -> Result<T, Error>
+    {
+        f(self)
+    }
+
+    fn read_tuple_arg<T, F>(&mut self, _index: usize, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self) -> Result<T, Error>
+    {
+        f(self)
+    }
+
+    fn read_tuple_struct<T, F>(&mut self, _name: &str, _len: usize, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self) -> Result<T, Error>
+    {
+        f(self)
+    }
+
+    fn read_tuple_struct_arg<T, F>(&mut self, _index: usize, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self) -> Result<T, Error>
+    {
+        f(self)
+    }
+
+    fn read_option<T, F>(&mut self, mut f: F) -> Result<T, Error>
+        where F: FnMut(&mut Self, bool) -> Result<T, Error>
+    {
+        match self.read_u8()? {
+            0 => f(self, false),
+            1 => f(self, true),
+            _ => encoding_error("invalid option tag"),
+        }
+    }
+
+    fn read_seq<T, F>(&mut self, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self, usize) -> Result<T, Error>
+    {
+        let len = self.read_len()?;
+        f(self, len)
+    }
+
+    fn read_seq_elt<T, F>(&mut self, _index: usize, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self) -> Result<T, Error>
+    {
+        f(self)
+    }
+
+    fn read_map<T, F>(&mut self, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self, usize) -> Result<T, Error>
+    {
+        let len = self.read_len()?;
+        f(self, len)
+    }
+
+    fn read_map_elt_key<T, F>(&mut self, _index: usize, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self) -> Result<T, Error>
+    {
+        f(self)
+    }
+
+    fn read_map_elt_val<T, F>(&mut self, _index: usize, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self) -> Result<T, Error>
+    {
+        f(self)
+    }
+
+    fn error(&mut self, error: &str) -> Error {
+        Error::Encoding(error.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{Data, DataIdentifier, ImmutableData, StructuredData};
+    use data::vectors;
+    use error::Error;
+    use serialisation::{self, serialise};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn matches_default_decoder() {
+        let vector_sets: &[&[u8]] = &[vectors::STRUCTURED_DATA_SIGNED,
+                                       vectors::STRUCTURED_DATA_MULTI_OWNER,
+                                       vectors::STRUCTURED_DATA_TRANSFERRED];
+        for vector in vector_sets {
+            assert_eq!(unwrap!(deserialise::<StructuredData>(vector)),
+                       unwrap!(serialisation::deserialise::<StructuredData>(vector)));
+        }
+        assert_eq!(unwrap!(deserialise::<ImmutableData>(vectors::IMMUTABLE_DATA)),
+                   vectors::immutable_data());
+        assert_eq!(unwrap!(deserialise::<Vec<DataIdentifier>>(vectors::DATA_IDENTIFIERS)),
+                   vectors::data_identifiers());
+
+        let mut map = BTreeMap::new();
+        let _ = map.insert("é".to_owned(), (-3i32, Some('€'), 1.5f64, true));
+        assert_eq!(unwrap!(deserialise::<BTreeMap<String, (i32, Option<char>, f64, bool)>>(
+                       &unwrap!(serialise(&map)))),
+                   map);
+    }
+
+    #[test]
+    fn malformed_input_refused() {
+        let serialised = unwrap!(serialise(&Data::Structured(vectors::structured_data_signed())));
+        for len in 0..serialised.len() {
+            match deserialise::<Data>(&serialised[..len]) {
+                Err(Error::Encoding(_)) => (),
+                result => panic!("Unexpected result {:?} for {} bytes", result, len),
+            }
+        }
+        let mut trailing = serialised.clone();
+        trailing.push(0);
+        assert!(deserialise::<Data>(&trailing).is_err());
+
+        // An unknown variant, which would panic in a derived decoder.
+        let mut unknown_variant = serialised.clone();
+        unknown_variant[3] = 9;
+        assert!(deserialise::<Data>(&unknown_variant).is_err());
+
+        // `Data::Immutable` with a value claiming 2^62 bytes.
+        let huge = [0, 0, 0, 1, 0x40, 0, 0, 0, 0, 0, 0, 0];
+        match deserialise::<Data>(&huge) {
+            Err(Error::Encoding(_)) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert!(deserialise::<bool>(&[2]).is_err());
+        assert!(deserialise::<Option<u8>>(&[2, 0]).is_err());
+        assert!(deserialise::<char>(&[0xff]).is_err());
+    }
+}
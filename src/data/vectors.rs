@@ -0,0 +1,149 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Test vectors
+//! Values of the data types built from fixed keys and content, with their serialised forms
+//! checked in under `src/data/vectors/`. The tests assert the values encode to, and decode from,
+//! exactly those bytes, so a change breaking compatibility with data already stored on the
+//! network fails them. Other implementations may use the same files to check their encoding.
+//!
+//! The checked-in files must never be changed to make a failing test pass; a deliberate change of
+//! encoding needs new vectors alongside the old.
+
+use crypto::sign::{self, PublicKey, SecretKey, Seed};
+use data::{DataIdentifier, ImmutableData, StructuredData};
+use error::Error;
+
+/// Serialised form of `immutable_data()`.
+pub const IMMUTABLE_DATA: &'static [u8] = include_bytes!("vectors/immutable_data.bin");
+/// Serialised form of `structured_data_signed()`.
+pub const STRUCTURED_DATA_SIGNED: &'static [u8] =
+    include_bytes!("vectors/structured_data_signed.bin");
+/// Serialised form of `structured_data_multi_owner()`.
+pub const STRUCTURED_DATA_MULTI_OWNER: &'static [u8] =
+    include_bytes!("vectors/structured_data_multi_owner.bin");
+/// Serialised form of `structured_data_transferred()`.
+pub const STRUCTURED_DATA_TRANSFERRED: &'static [u8] =
+    include_bytes!("vectors/structured_data_transferred.bin");
+/// Serialised form of `data_identifiers()`.
+pub const DATA_IDENTIFIERS: &'static [u8] = include_bytes!("vectors/data_identifiers.bin");
+
+/// Returns the signing keypair derived from the seed of 32 bytes of value `index`.
+pub fn keypair(index: u8) -> (PublicKey, SecretKey) {
+    sign::keypair_from_seed(&Seed([index; 32]))
+}
+
+/// `ImmutableData` holding a fixed value.
+pub fn immutable_data() -> ImmutableData {
+    ImmutableData::new(b"data_chain immutable data test vector".to_vec())
+}
+
+/// Version 0 of `StructuredData` owned and signed by `keypair(1)`.
+pub fn structured_data_signed() -> StructuredData {
+    let keys = keypair(1);
+    built(StructuredData::new(1000,
+                              [1; 32],
+                              0,
+                              b"signed structured data".to_vec(),
+                              vec![keys.0],
+                              vec![],
+                              Some(&keys.1),
+                              false))
+}
+
+/// Version 0 of `StructuredData` owned by `keypair(1)`, `keypair(2)` and `keypair(3)`, signed by
+/// the first two.
+pub fn structured_data_multi_owner() -> StructuredData {
+    let owners = [keypair(1), keypair(2), keypair(3)];
+    let mut structured_data =
+        built(StructuredData::new(1001,
+                                  [2; 32],
+                                  0,
+                                  b"multi-owner structured data".to_vec(),
+                                  owners.iter().map(|keys| keys.0).collect(),
+                                  vec![],
+                                  Some(&owners[0].1),
+                                  false));
+    let _ = built(structured_data.add_signature(&owners[1].1));
+    structured_data
+}
+
+/// Version 1 of `structured_data_signed()`, transferred to `keypair(2)`.
+pub fn structured_data_transferred() -> StructuredData {
+    built(structured_data_signed().transfer_ownership(vec![keypair(2).0], &keypair(1).1))
+}
+
+/// One `DataIdentifier` of each kind.
+pub fn data_identifiers() -> Vec<DataIdentifier> {
+    vec![DataIdentifier::Structured([1; 32], 1000),
+         DataIdentifier::Immutable([3; 32]),
+         DataIdentifier::Plain([4; 32]),
+         DataIdentifier::Mutable([5; 32], 1002)]
+}
+
+fn built<T>(result: Result<T, Error>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(error) => panic!("Failed to build test vector: {:?}", error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{DataIdentifier, ImmutableData, StructuredData};
+    use rustc_serialize::hex::ToHex;
+    use serialisation::{deserialise, serialise};
+
+    fn check_structured_data(structured_data: &StructuredData, vector: &[u8]) {
+        assert_eq!(unwrap!(serialise(structured_data)).to_hex(), vector.to_hex());
+        let decoded: StructuredData = unwrap!(deserialise(vector));
+        assert_eq!(decoded, *structured_data);
+        unwrap!(decoded.verify_own_signatures());
+    }
+
+    #[test]
+    fn immutable_data_vector() {
+        let data = immutable_data();
+        assert_eq!(unwrap!(serialise(&data)).to_hex(), IMMUTABLE_DATA.to_hex());
+        let decoded: ImmutableData = unwrap!(deserialise(IMMUTABLE_DATA));
+        assert_eq!(decoded, data);
+        assert_eq!(decoded.name(), data.name());
+    }
+
+    #[test]
+    fn structured_data_vectors() {
+        // The vectors hold Ed25519 keys and signatures.
+        if cfg!(feature = "dilithium") {
+            return;
+        }
+        check_structured_data(&structured_data_signed(), STRUCTURED_DATA_SIGNED);
+        check_structured_data(&structured_data_multi_owner(), STRUCTURED_DATA_MULTI_OWNER);
+        check_structured_data(&structured_data_transferred(), STRUCTURED_DATA_TRANSFERRED);
+
+        let transferred: StructuredData = unwrap!(deserialise(STRUCTURED_DATA_TRANSFERRED));
+        unwrap!(structured_data_signed().validate_self_against_successor(&transferred));
+    }
+
+    #[test]
+    fn data_identifier_vector() {
+        let identifiers = data_identifiers();
+        assert_eq!(unwrap!(serialise(&identifiers)).to_hex(), DATA_IDENTIFIERS.to_hex());
+        let decoded: Vec<DataIdentifier> = unwrap!(deserialise(DATA_IDENTIFIERS));
+        assert_eq!(decoded, identifiers);
+    }
+}
@@ -0,0 +1,99 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Versions
+//! Versions of structured and mutable data only ever move forward, one at a time. Checking
+//! updates through `Version` rejects replays of old versions and rollbacks uniformly, and makes
+//! reaching the last `u64` version an error rather than an overflow that would wrap round to
+//! version 0 and let the history be replayed.
+
+use error::Error;
+use std::fmt::{self, Display, Formatter};
+
+/// A data version.
+#[derive(Hash, Eq, PartialEq, Ord, PartialOrd, Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serialisation", derive(RustcEncodable, RustcDecodable))]
+pub struct Version(pub u64);
+
+impl Version {
+    /// The version after this one, or `InvalidVersion` if this is the last.
+    pub fn next(self) -> Result<Version, Error> {
+        self.0.checked_add(1).map(Version).ok_or(Error::InvalidVersion)
+    }
+
+    /// Returns whether `other` is the version after this one.
+    pub fn is_next(self, other: Version) -> bool {
+        self.next().ok() == Some(other)
+    }
+
+    /// Checks an update from this version to `new` neither goes back (a replay or rollback) nor
+    /// skips more than `max_skip` versions, failing with `InvalidVersion` if it does.
+    ///
+    /// A `max_skip` of 0 only accepts the next version; holders catching up on updates they
+    /// missed can allow more.
+    pub fn check_update(self, new: Version, max_skip: u64) -> Result<(), Error> {
+        if new <= self || new.0 - self.0 - 1 > max_skip {
+            return Err(Error::InvalidVersion);
+        }
+        Ok(())
+    }
+}
+
+impl From<u64> for Version {
+    fn from(version: u64) -> Version {
+        Version(version)
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use error::Error;
+    use std::u64;
+
+    #[test]
+    fn next() {
+        assert_eq!(unwrap!(Version(0).next()), Version(1));
+        assert!(Version(5).is_next(Version(6)));
+        assert!(!Version(5).is_next(Version(7)));
+        assert!(!Version(5).is_next(Version(5)));
+        match Version(u64::MAX).next() {
+            Err(Error::InvalidVersion) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert!(!Version(u64::MAX).is_next(Version(0)));
+    }
+
+    #[test]
+    fn updates() {
+        unwrap!(Version(3).check_update(Version(4), 0));
+        unwrap!(Version(3).check_update(Version(6), 2));
+        unwrap!(Version(0).check_update(Version(u64::MAX), u64::MAX));
+        for &(new, max_skip) in &[(3, 10), (2, 10), (5, 0), (7, 2)] {
+            match Version(3).check_update(Version(new), max_skip) {
+                Err(Error::InvalidVersion) => (),
+                result => panic!("Unexpected result {:?}", result),
+            }
+        }
+    }
+}
@@ -0,0 +1,265 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Versioned blobs
+//! `StructuredData` with the versioned blob type tag whose content is the names of the
+//! `ImmutableData` holding each version of a blob, oldest first, so the history of immutable
+//! content can be followed from one fixed name.
+//!
+//! At most `max_versions` names are held in the structured data itself. Appending to a full blob
+//! first archives the names it holds into an overflow `ImmutableData`, which links to the
+//! previous overflow chunk, so the structured data stays bounded however long the history grows.
+
+use crypto::sign::{PublicKey, SecretKey};
+use data::{ImmutableData, StructuredData};
+use error::Error;
+use serialisation::{deserialise, serialise};
+
+/// Type tag of the `StructuredData` holding a versioned blob.
+pub const VERSIONED_BLOB_TYPE_TAG: u64 = 0x5afe_b10b;
+
+/// Names of versions archived out of a `VersionedBlob`, stored as the value of an
+/// `ImmutableData`.
+#[derive(Hash, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, RustcEncodable, RustcDecodable)]
+pub struct ArchivedVersions {
+    /// The archived names, oldest first.
+    pub versions: Vec<[u8; 32]>,
+    /// The name of the overflow chunk holding the versions before these, if any.
+    pub previous: Option<[u8; 32]>,
+}
+
+impl ArchivedVersions {
+    /// Reads the archived versions held by `data`.
+    pub fn from_immutable_data(data: &ImmutableData) -> Result<ArchivedVersions, Error> {
+        Ok(deserialise(data.value())?)
+    }
+}
+
+/// The names of the versions of a blob, the newest held inline and the rest archived.
+#[derive(Hash, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, RustcEncodable, RustcDecodable)]
+pub struct VersionedBlob {
+    versions: Vec<[u8; 32]>,
+    archive: Option<[u8; 32]>,
+    archived_count: u64,
+    max_versions: u64,
+}
+
+impl VersionedBlob {
+    /// Creates a blob with no versions which holds at most `max_versions` names inline. Fails with
+    /// `Validation` if `max_versions` is 0.
+    pub fn new(max_versions: u64) -> Result<VersionedBlob, Error> {
+        if max_versions == 0 {
+            return Err(Error::Validation);
+        }
+        Ok(VersionedBlob {
+            versions: Vec::new(),
+            archive: None,
+            archived_count: 0,
+            max_versions: max_versions,
+        })
+    }
+
+    /// Appends `name` as the newest version.
+    ///
+    /// If the blob is full, the names it holds are first archived and the overflow chunk holding
+    /// them is returned. It must be stored for the archived history to remain readable.
+    pub fn append_version(&mut self, name: [u8; 32]) -> Result<Option<ImmutableData>, Error> {
+        let overflow = if self.versions.len() as u64 >= self.max_versions {
+            let archived = ArchivedVersions {
+                versions: self.versions.clone(),
+                previous: self.archive,
+            };
+            let data = ImmutableData::try_new(serialise(&archived)?)?;
+            self.archived_count += self.versions.len() as u64;
+            self.archive = Some(*data.name());
+            self.versions.clear();
+            Some(data)
+        } else {
+            None
+        };
+        self.versions.push(name);
+        Ok(overflow)
+    }
+
+    /// Returns the name of the newest version, if any.
+    pub fn latest(&self) -> Option<&[u8; 32]> {
+        self.versions.last()
+    }
+
+    /// Returns the names of the versions held inline, oldest first.
+    pub fn history(&self) -> &[[u8; 32]] {
+        &self.versions
+    }
+
+    /// Returns the names of every version, oldest first, fetching the overflow chunks with
+    /// `fetch`.
+    ///
+    /// Fails with `BadIdentifier` if a fetched chunk is not the one asked for, and with
+    /// `Validation` if the chunks do not hold as many versions as were archived.
+    pub fn full_history<F>(&self, mut fetch: F) -> Result<Vec<[u8; 32]>, Error>
+        where F: FnMut(&[u8; 32]) -> Result<ImmutableData, Error>
+    {
+        let mut chunks = Vec::new();
+        let mut count = 0;
+        let mut next = self.archive;
+        while let Some(name) = next {
+            let data = fetch(&name)?;
+            if *data.name() != name {
+                return Err(Error::BadIdentifier);
+            }
+            data.validate_name()?;
+            let archived = ArchivedVersions::from_immutable_data(&data)?;
+            count += archived.versions.len() as u64;
+            if count > self.archived_count {
+                return Err(Error::Validation);
+            }
+            next = archived.previous;
+            chunks.push(archived.versions);
+        }
+        if count != self.archived_count {
+            return Err(Error::Validation);
+        }
+        let mut history: Vec<_> = chunks.into_iter().rev().flat_map(|versions| versions).collect();
+        history.extend_from_slice(&self.versions);
+        Ok(history)
+    }
+
+    /// Returns the name of the newest overflow chunk, if any versions have been archived.
+    pub fn archive(&self) -> Option<&[u8; 32]> {
+        self.archive.as_ref()
+    }
+
+    /// Returns the number of versions archived into overflow chunks.
+    pub fn archived_count(&self) -> u64 {
+        self.archived_count
+    }
+
+    /// Returns the number of versions, archived or not.
+    pub fn version_count(&self) -> u64 {
+        self.archived_count + self.versions.len() as u64
+    }
+
+    /// Returns the most names held inline.
+    pub fn max_versions(&self) -> u64 {
+        self.max_versions
+    }
+
+    /// Stores the blob as version `version` of the structured data called `name`, owned by
+    /// `owners` and signed with `signing_key`.
+    ///
+    /// Fails with `DataTooLarge` if the names held inline do not fit in one `StructuredData`.
+    pub fn to_structured_data(&self,
+                              name: [u8; 32],
+                              version: u64,
+                              owners: Vec<PublicKey>,
+                              signing_key: &SecretKey)
+                              -> Result<StructuredData, Error> {
+        StructuredData::new(VERSIONED_BLOB_TYPE_TAG,
+                            name,
+                            version,
+                            serialise(self)?,
+                            owners,
+                            vec![],
+                            Some(signing_key),
+                            false)
+    }
+
+    /// Reads a blob stored by `to_structured_data`. Fails with `BadIdentifier` if the data does
+    /// not have the versioned blob type tag.
+    pub fn from_structured_data(structured_data: &StructuredData)
+                                -> Result<VersionedBlob, Error> {
+        if structured_data.get_type_tag() != VERSIONED_BLOB_TYPE_TAG {
+            return Err(Error::BadIdentifier);
+        }
+        let blob: VersionedBlob = deserialise(&structured_data.decompressed_data()?)?;
+        // Versions are only ever archived into a chunk, and never beyond the bound.
+        if blob.max_versions == 0 || blob.versions.len() as u64 > blob.max_versions ||
+           blob.archive.is_some() != (blob.archived_count > 0) {
+            return Err(Error::Validation);
+        }
+        Ok(blob)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::ImmutableData;
+    use error::Error;
+    use rand;
+    use std::collections::HashMap;
+    use test_utils;
+
+    #[test]
+    fn append_and_archive() {
+        let mut blob = unwrap!(VersionedBlob::new(3));
+        assert!(blob.latest().is_none());
+        let names: Vec<[u8; 32]> = (0..8).map(|_| rand::random()).collect();
+        let mut chunks = HashMap::new();
+        for name in &names {
+            if let Some(overflow) = unwrap!(blob.append_version(*name)) {
+                let _ = chunks.insert(*overflow.name(), overflow);
+            }
+            assert_eq!(blob.latest(), Some(name));
+        }
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(blob.history(), &names[6..]);
+        assert_eq!(blob.archived_count(), 6);
+        assert_eq!(blob.version_count(), 8);
+
+        let fetch = |name: &[u8; 32]| chunks.get(name).cloned().ok_or(Error::NoFile);
+        assert_eq!(unwrap!(blob.full_history(&fetch)), names);
+
+        let newest = &chunks[unwrap!(blob.archive())];
+        let archived = unwrap!(ArchivedVersions::from_immutable_data(newest));
+        assert_eq!(archived.versions, &names[3..6]);
+        match blob.full_history(|_| Ok(ImmutableData::new(vec![1, 2, 3]))) {
+            Err(Error::BadIdentifier) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match VersionedBlob::new(0) {
+            Err(Error::Validation) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn structured_data_round_trip() {
+        let keys = test_utils::gen_keypair();
+        let mut blob = unwrap!(VersionedBlob::new(2));
+        for _ in 0..3 {
+            let _ = unwrap!(blob.append_version(rand::random()));
+        }
+        let structured_data =
+            unwrap!(blob.to_structured_data(rand::random(), 0, vec![keys.0], &keys.1));
+        assert_eq!(structured_data.get_type_tag(), VERSIONED_BLOB_TYPE_TAG);
+        assert_eq!(unwrap!(VersionedBlob::from_structured_data(&structured_data)), blob);
+
+        let overfull = VersionedBlob {
+            versions: vec![rand::random(); 3],
+            archive: None,
+            archived_count: 0,
+            max_versions: 2,
+        };
+        let structured_data =
+            unwrap!(overfull.to_structured_data(rand::random(), 0, vec![keys.0], &keys.1));
+        match VersionedBlob::from_structured_data(&structured_data) {
+            Err(Error::Validation) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
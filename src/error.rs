@@ -15,7 +15,8 @@
 // Please review the Licences for the specific language governing permissions and limitations
 // relating to use of the SAFE Network Software.
 
-use maidsafe_utilities::serialisation;
+#[cfg(feature = "serialisation")]
+use serialisation;
 use std::{error, fmt, io};
 
 /// Error types.
@@ -24,6 +25,7 @@ use std::{error, fmt, io};
 #[allow(missing_docs)]
 #[derive(Debug)]
 pub enum Error {
+    #[cfg(feature = "serialisation")]
     Serialisation(serialisation::SerialisationError),
     Io(io::Error),
     Crypto,
@@ -34,11 +36,105 @@ pub enum Error {
     NoSpace,
     NoFile,
     BadIdentifier,
+    DataDeleted,
+    DataTooLarge,
+    Encoding(String),
+    AccessDenied,
+    EntryExists,
+    NoSuchEntry,
+    InvalidVersion,
+    Expired,
+    InvalidRange,
+    NoOwners,
+    TooManyOwners,
+}
+
+impl Error {
+    /// Returns the code of this error, for vault responses and callers outside Rust which can
+    /// carry a number but not the error itself.
+    ///
+    /// The codes are frozen: a code is never reused or renumbered, and new errors get new codes.
+    /// 0 is never used.
+    ///
+    /// | Code | Error            | Code | Error            |
+    /// |------|------------------|------|------------------|
+    /// | 1    | `Serialisation`  | 11   | `DataDeleted`    |
+    /// | 2    | `Io`             | 12   | `DataTooLarge`   |
+    /// | 3    | `Crypto`         | 13   | `Encoding`       |
+    /// | 4    | `Validation`     | 14   | `AccessDenied`   |
+    /// | 5    | `Signature`      | 15   | `EntryExists`    |
+    /// | 6    | `Majority`       | 16   | `NoSuchEntry`    |
+    /// | 7    | `NoLink`         | 17   | `InvalidVersion` |
+    /// | 8    | `NoSpace`        | 18   | `Expired`        |
+    /// | 9    | `NoFile`         | 19   | `InvalidRange`   |
+    /// | 10   | `BadIdentifier`  | 20   | `NoOwners`       |
+    /// |      |                  | 21   | `TooManyOwners`  |
+    pub fn code(&self) -> u16 {
+        match *self {
+            #[cfg(feature = "serialisation")]
+            Error::Serialisation(_) => 1,
+            Error::Io(_) => 2,
+            Error::Crypto => 3,
+            Error::Validation => 4,
+            Error::Signature => 5,
+            Error::Majority => 6,
+            Error::NoLink => 7,
+            Error::NoSpace => 8,
+            Error::NoFile => 9,
+            Error::BadIdentifier => 10,
+            Error::DataDeleted => 11,
+            Error::DataTooLarge => 12,
+            Error::Encoding(_) => 13,
+            Error::AccessDenied => 14,
+            Error::EntryExists => 15,
+            Error::NoSuchEntry => 16,
+            Error::InvalidVersion => 17,
+            Error::Expired => 18,
+            Error::InvalidRange => 19,
+            Error::NoOwners => 20,
+            Error::TooManyOwners => 21,
+        }
+    }
+
+    /// Returns the error with the code `code`, as given by `code()`, or `None` for an unknown
+    /// code, e.g. one from a later version.
+    ///
+    /// The details of `Io` and `Encoding` errors are not carried by the code, so they are replaced
+    /// by a message saying so. A serialisation error cannot be made without its cause, so code 1
+    /// gives an `Encoding` error instead.
+    pub fn from_code(code: u16) -> Option<Error> {
+        let error = match code {
+            1 => Error::Encoding("Serialisation failure.".to_owned()),
+            2 => Error::Io(io::Error::new(io::ErrorKind::Other, "I/O failure.")),
+            3 => Error::Crypto,
+            4 => Error::Validation,
+            5 => Error::Signature,
+            6 => Error::Majority,
+            7 => Error::NoLink,
+            8 => Error::NoSpace,
+            9 => Error::NoFile,
+            10 => Error::BadIdentifier,
+            11 => Error::DataDeleted,
+            12 => Error::DataTooLarge,
+            13 => Error::Encoding("Encoding failure.".to_owned()),
+            14 => Error::AccessDenied,
+            15 => Error::EntryExists,
+            16 => Error::NoSuchEntry,
+            17 => Error::InvalidVersion,
+            18 => Error::Expired,
+            19 => Error::InvalidRange,
+            20 => Error::NoOwners,
+            21 => Error::TooManyOwners,
+            _ => return None,
+        };
+        Some(error)
+    }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
+            #[cfg(feature = "serialisation")]
             Error::Serialisation(ref err) => err.fmt(f),
             Error::Io(ref err) => err.fmt(f),
             Error::Crypto => write!(f, "Crypto failure."),
@@ -49,6 +145,17 @@ impl fmt::Display for Error {
             Error::NoSpace => write!(f, "Not enough space."),
             Error::NoFile => write!(f, "No file."),
             Error::BadIdentifier => write!(f, "Invalid identifier type."),
+            Error::DataDeleted => write!(f, "Data has been deleted."),
+            Error::DataTooLarge => write!(f, "Data exceeds the maximum size."),
+            Error::Encoding(ref err) => write!(f, "Encoding error: {}", err),
+            Error::AccessDenied => write!(f, "Access denied."),
+            Error::EntryExists => write!(f, "Entry already exists."),
+            Error::NoSuchEntry => write!(f, "No such entry."),
+            Error::InvalidVersion => write!(f, "Invalid version."),
+            Error::Expired => write!(f, "Expired."),
+            Error::InvalidRange => write!(f, "Range not within the data."),
+            Error::NoOwners => write!(f, "No owners."),
+            Error::TooManyOwners => write!(f, "Too many owners."),
         }
     }
 }
@@ -56,6 +163,7 @@ impl fmt::Display for Error {
 impl error::Error for Error {
     fn description(&self) -> &str {
         match *self {
+            #[cfg(feature = "serialisation")]
             Error::Serialisation(ref err) => err.description(),
             Error::Io(ref err) => err.description(),
             Error::Crypto => "Crypto failure.",
@@ -66,6 +174,26 @@ impl error::Error for Error {
             Error::NoSpace => "No space.",
             Error::NoFile => "No file.",
             Error::BadIdentifier => "Invalid identifier type.",
+            Error::DataDeleted => "Data has been deleted.",
+            Error::DataTooLarge => "Data exceeds the maximum size.",
+            Error::Encoding(_) => "Encoding error.",
+            Error::AccessDenied => "Access denied.",
+            Error::EntryExists => "Entry already exists.",
+            Error::NoSuchEntry => "No such entry.",
+            Error::InvalidVersion => "Invalid version.",
+            Error::Expired => "Expired.",
+            Error::InvalidRange => "Range not within the data.",
+            Error::NoOwners => "No owners.",
+            Error::TooManyOwners => "Too many owners.",
+        }
+    }
+
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            #[cfg(feature = "serialisation")]
+            Error::Serialisation(ref err) => Some(err),
+            Error::Io(ref err) => Some(err),
+            _ => None,
         }
     }
 }
@@ -76,6 +204,7 @@ impl From<io::Error> for Error {
     }
 }
 
+#[cfg(feature = "serialisation")]
 impl From<serialisation::SerialisationError> for Error {
     fn from(orig_error: serialisation::SerialisationError) -> Self {
         Error::Serialisation(orig_error)
@@ -87,3 +216,46 @@ impl From<()> for Error {
         Error::Crypto
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serialisation::deserialise;
+    use std::error::Error as StdError;
+
+    fn assert_send_sync<T: Send + Sync + 'static>() {}
+
+    #[test]
+    fn source_chaining() {
+        assert_send_sync::<Error>();
+
+        let io_error = Error::from(io::Error::new(io::ErrorKind::Other, "disk on fire"));
+        assert_eq!(unwrap!(io_error.source()).to_string(), "disk on fire");
+        assert_eq!(io_error.to_string(), "disk on fire");
+
+        let serialisation_error = Error::from(unwrap!(deserialise::<u64>(&[1]).err()));
+        assert!(serialisation_error.source().is_some());
+
+        assert!(Error::Signature.source().is_none());
+        assert_eq!(Error::Signature.to_string(), "Invalid signature.");
+    }
+
+    #[test]
+    fn codes() {
+        // The codes are frozen, so these must never change.
+        assert_eq!(Error::Io(io::Error::new(io::ErrorKind::Other, "")).code(), 2);
+        assert_eq!(Error::Validation.code(), 4);
+        assert_eq!(Error::Encoding(String::new()).code(), 13);
+        assert_eq!(Error::InvalidRange.code(), 19);
+        let serialisation_error = Error::from(unwrap!(deserialise::<u64>(&[1]).err()));
+        assert_eq!(serialisation_error.code(), 1);
+
+        for code in 2..22 {
+            assert_eq!(unwrap!(Error::from_code(code)).code(), code);
+        }
+        assert_eq!(unwrap!(Error::from_code(1)).code(), 13);
+        assert!(Error::from_code(0).is_none());
+        assert_eq!(Error::TooManyOwners.code(), 21);
+        assert!(Error::from_code(22).is_none());
+    }
+}
@@ -0,0 +1,337 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+//! # C bindings
+//! With the `ffi` feature, `extern "C"` functions build immutable and structured data, name, sign
+//! and validate it, and serialise and parse it, so client bindings in C, Java or Swift can use
+//! this crate directly.
+//!
+//! Data is passed as an opaque handle, a pointer to a `Data` owned by the caller until freed with
+//! `data_chain_data_free`. Byte buffers returned to the caller are freed with
+//! `data_chain_bytes_free`. Each function returns 0 on success, an error code as given by
+//! `Error::code` on failure, `FFI_INVALID_ARGUMENT` if an argument is null or of the wrong type
+//! and `FFI_PANIC` if the call panicked. Outputs are only written on success.
+//!
+//! The crate only builds as a Rust library, so the C library type is asked for on the command
+//! line: `cargo rustc --release --features ffi --crate-type cdylib` builds a shared library and
+//! `--crate-type staticlib` a static one, either declared for C in `include/data_chain.h`.
+
+// Dereferencing the caller's pointers is unavoidably unsafe; each function documents what it
+// requires of them.
+#![allow(unsafe_code)]
+
+use crypto::sign::{self, PublicKey, SecretKey};
+use data::{Data, ImmutableData, StructuredData};
+use error::Error;
+use serialisation::serialise;
+use std::panic::{self, AssertUnwindSafe};
+use std::{ptr, slice};
+
+/// Returned if an argument is null, of the wrong length or a handle of the wrong type of data.
+pub const FFI_INVALID_ARGUMENT: i32 = -1;
+/// Returned if the call panicked.
+pub const FFI_PANIC: i32 = -2;
+
+/// Creates immutable data holding the `len` bytes at `value` and writes its handle to `out`.
+///
+/// `value` may only be null if `len` is 0.
+#[no_mangle]
+pub unsafe extern "C" fn data_chain_immutable_data_new(value: *const u8,
+                                                       len: usize,
+                                                       out: *mut *mut Data)
+                                                       -> i32 {
+    catch(|| {
+        let value = bytes(value, len)?.to_vec();
+        write(out, into_handle(Data::Immutable(ImmutableData::new(value))))
+    })
+}
+
+/// Creates version `version` of the structured data of type `type_tag` named by the 32 bytes at
+/// `name`, holding the `data_len` bytes at `data` and owned by the `owner_count` public keys
+/// packed at `owner_keys`, and writes its handle to `out`. The data is not a ledger and is not
+/// signed; sign it with `data_chain_structured_data_sign`.
+///
+/// `data` may only be null if `data_len` is 0.
+#[no_mangle]
+pub unsafe extern "C" fn data_chain_structured_data_new(type_tag: u64,
+                                                        name: *const u8,
+                                                        version: u64,
+                                                        data: *const u8,
+                                                        data_len: usize,
+                                                        owner_keys: *const u8,
+                                                        owner_count: usize,
+                                                        out: *mut *mut Data)
+                                                        -> i32 {
+    catch(|| {
+        let name = name_from(bytes(name, 32)?);
+        let data = bytes(data, data_len)?.to_vec();
+        let key_bytes = bytes(owner_keys, owner_count.saturating_mul(sign::PUBLICKEYBYTES))?;
+        let owner_keys = key_bytes.chunks(sign::PUBLICKEYBYTES)
+            .map(|chunk| {
+                let mut key = PublicKey([0; sign::PUBLICKEYBYTES]);
+                key.0.copy_from_slice(chunk);
+                key
+            })
+            .collect();
+        let structured_data =
+            StructuredData::new(type_tag, name, version, data, owner_keys, vec![], None, false)?;
+        write(out, into_handle(Data::Structured(structured_data)))
+    })
+}
+
+/// Signs the structured data `data` with the secret key of `sign::SECRETKEYBYTES` bytes at
+/// `secret_key` and writes the number of signatures still required to `remaining`.
+#[no_mangle]
+pub unsafe extern "C" fn data_chain_structured_data_sign(data: *mut Data,
+                                                         secret_key: *const u8,
+                                                         remaining: *mut usize)
+                                                         -> i32 {
+    catch(|| {
+        let mut key = SecretKey([0; sign::SECRETKEYBYTES]);
+        key.0.copy_from_slice(bytes(secret_key, sign::SECRETKEYBYTES)?);
+        match data.as_mut() {
+            Some(&mut Data::Structured(ref mut structured_data)) => {
+                let count = structured_data.add_signature(&key)?;
+                write(remaining, count)
+            }
+            _ => Err(Invalid::Argument),
+        }
+    })
+}
+
+/// Replaces the structured data `current` with its successor `successor`, if `successor` is a
+/// valid update of it. `successor` is left as it was.
+#[no_mangle]
+pub unsafe extern "C" fn data_chain_structured_data_replace(current: *mut Data,
+                                                            successor: *const Data)
+                                                            -> i32 {
+    catch(|| match (current.as_mut(), successor.as_ref()) {
+        (Some(&mut Data::Structured(ref mut current)),
+         Some(&Data::Structured(ref successor))) => {
+            Ok(current.replace_with_other(successor.clone())?)
+        }
+        _ => Err(Invalid::Argument),
+    })
+}
+
+/// Writes the 32-byte name of `data` to `name`.
+#[no_mangle]
+pub unsafe extern "C" fn data_chain_data_name(data: *const Data, name: *mut u8) -> i32 {
+    catch(|| {
+        let data = data.as_ref().ok_or(Invalid::Argument)?;
+        if name.is_null() {
+            return Err(Invalid::Argument);
+        }
+        ptr::copy_nonoverlapping(data.name().as_ptr(), name, 32);
+        Ok(())
+    })
+}
+
+/// Checks the invariants of `data`, as `Data::validate` does.
+#[no_mangle]
+pub unsafe extern "C" fn data_chain_data_validate(data: *const Data) -> i32 {
+    catch(|| Ok(data.as_ref().ok_or(Invalid::Argument)?.validate()?))
+}
+
+/// Serialises `data`, writing a buffer of the bytes to `out` and its length to `out_len`. Free
+/// the buffer with `data_chain_bytes_free`.
+#[no_mangle]
+pub unsafe extern "C" fn data_chain_data_serialise(data: *const Data,
+                                                   out: *mut *mut u8,
+                                                   out_len: *mut usize)
+                                                   -> i32 {
+    catch(|| {
+        let data = data.as_ref().ok_or(Invalid::Argument)?;
+        if out.is_null() || out_len.is_null() {
+            return Err(Invalid::Argument);
+        }
+        let serialised = serialise(data).map_err(Error::from)?.into_boxed_slice();
+        *out_len = serialised.len();
+        *out = Box::into_raw(serialised) as *mut u8;
+        Ok(())
+    })
+}
+
+/// Parses and validates the `len` serialised bytes at `bytes`, received from an untrusted
+/// source, and writes the handle of the data to `out`.
+#[no_mangle]
+pub unsafe extern "C" fn data_chain_data_deserialise(bytes: *const u8,
+                                                     len: usize,
+                                                     out: *mut *mut Data)
+                                                     -> i32 {
+    catch(|| {
+        let data = Data::decode_untrusted(self::bytes(bytes, len)?)?;
+        write(out, into_handle(data))
+    })
+}
+
+/// Frees the data `data`, which may be null.
+#[no_mangle]
+pub unsafe extern "C" fn data_chain_data_free(data: *mut Data) {
+    if !data.is_null() {
+        let _ = Box::from_raw(data);
+    }
+}
+
+/// Frees the buffer of `len` bytes at `bytes`, as returned by `data_chain_data_serialise`.
+/// `bytes` may be null.
+#[no_mangle]
+pub unsafe extern "C" fn data_chain_bytes_free(bytes: *mut u8, len: usize) {
+    if !bytes.is_null() {
+        let _ = Box::from_raw(slice::from_raw_parts_mut(bytes, len) as *mut [u8]);
+    }
+}
+
+/// Why a call failed.
+enum Invalid {
+    Argument,
+    Data(Error),
+}
+
+impl From<Error> for Invalid {
+    fn from(error: Error) -> Invalid {
+        Invalid::Data(error)
+    }
+}
+
+fn catch<F: FnOnce() -> Result<(), Invalid>>(f: F) -> i32 {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => 0,
+        Ok(Err(Invalid::Argument)) => FFI_INVALID_ARGUMENT,
+        Ok(Err(Invalid::Data(error))) => error.code() as i32,
+        Err(_) => FFI_PANIC,
+    }
+}
+
+unsafe fn bytes<'a>(pointer: *const u8, len: usize) -> Result<&'a [u8], Invalid> {
+    if len == 0 {
+        Ok(&[])
+    } else if pointer.is_null() {
+        Err(Invalid::Argument)
+    } else {
+        Ok(slice::from_raw_parts(pointer, len))
+    }
+}
+
+unsafe fn write<T>(out: *mut T, value: T) -> Result<(), Invalid> {
+    if out.is_null() {
+        return Err(Invalid::Argument);
+    }
+    *out = value;
+    Ok(())
+}
+
+fn into_handle(data: Data) -> *mut Data {
+    Box::into_raw(Box::new(data))
+}
+
+fn name_from(bytes: &[u8]) -> [u8; 32] {
+    let mut name = [0; 32];
+    name.copy_from_slice(bytes);
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_utils;
+
+    #[test]
+    fn immutable_data() {
+        unsafe {
+            let value = b"value";
+            let mut data = ptr::null_mut();
+            assert_eq!(data_chain_immutable_data_new(value.as_ptr(), value.len(), &mut data), 0);
+            assert_eq!(data_chain_data_validate(data), 0);
+            let mut name = [0; 32];
+            assert_eq!(data_chain_data_name(data, name.as_mut_ptr()), 0);
+            assert_eq!(&name, (*data).name());
+
+            let mut serialised = ptr::null_mut();
+            let mut len = 0;
+            assert_eq!(data_chain_data_serialise(data, &mut serialised, &mut len), 0);
+            let mut parsed = ptr::null_mut();
+            assert_eq!(data_chain_data_deserialise(serialised, len, &mut parsed), 0);
+            assert_eq!(*parsed, *data);
+            let mut truncated = ptr::null_mut();
+            assert!(data_chain_data_deserialise(serialised, len - 1, &mut truncated) > 0);
+            assert!(truncated.is_null());
+
+            data_chain_bytes_free(serialised, len);
+            data_chain_data_free(parsed);
+            data_chain_data_free(data);
+
+            assert_eq!(data_chain_immutable_data_new(ptr::null(), 1, &mut data),
+                       FFI_INVALID_ARGUMENT);
+            assert_eq!(data_chain_data_validate(ptr::null()), FFI_INVALID_ARGUMENT);
+        }
+    }
+
+    #[test]
+    fn structured_data() {
+        unsafe {
+            let keys = test_utils::gen_keypair();
+            let name = [7; 32];
+            let new = |version, value: &[u8], out: &mut *mut Data| {
+                data_chain_structured_data_new(5,
+                                               name.as_ptr(),
+                                               version,
+                                               value.as_ptr(),
+                                               value.len(),
+                                               (keys.0).0.as_ptr(),
+                                               1,
+                                               out)
+            };
+            let mut current = ptr::null_mut();
+            assert_eq!(new(0, b"first", &mut current), 0);
+            let mut remaining = 1;
+            assert_eq!(data_chain_structured_data_sign(current,
+                                                       (keys.1).0.as_ptr(),
+                                                       &mut remaining),
+                       0);
+            assert_eq!(remaining, 0);
+            assert_eq!(data_chain_data_validate(current), 0);
+
+            let mut successor = ptr::null_mut();
+            assert_eq!(new(1, b"second", &mut successor), 0);
+            assert!(data_chain_structured_data_replace(current, successor) > 0);
+            assert_eq!(data_chain_structured_data_sign(successor,
+                                                       (keys.1).0.as_ptr(),
+                                                       &mut remaining),
+                       0);
+            assert_eq!(data_chain_structured_data_replace(current, successor), 0);
+            match *current {
+                Data::Structured(ref structured_data) => {
+                    assert_eq!(structured_data.version(), 1)
+                }
+                ref data => panic!("Unexpected data {:?}", data),
+            }
+
+            let mut immutable = ptr::null_mut();
+            assert_eq!(data_chain_immutable_data_new(ptr::null(), 0, &mut immutable), 0);
+            assert_eq!(data_chain_structured_data_sign(immutable,
+                                                       (keys.1).0.as_ptr(),
+                                                       &mut remaining),
+                       FFI_INVALID_ARGUMENT);
+            assert_eq!(data_chain_structured_data_replace(current, immutable),
+                       FFI_INVALID_ARGUMENT);
+
+            data_chain_data_free(immutable);
+            data_chain_data_free(successor);
+            data_chain_data_free(current);
+        }
+    }
+}
@@ -0,0 +1,61 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Lower-case hex encoding of names and keys, as rustc-serialize's, without depending on it.
+
+const DIGITS: &'static [u8; 16] = b"0123456789abcdef";
+
+/// Returns `bytes` as lower-case hex.
+pub fn to_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        hex.push(DIGITS[(byte >> 4) as usize] as char);
+        hex.push(DIGITS[(byte & 0xf) as usize] as char);
+    }
+    hex
+}
+
+/// Parses `hex`, in either case, or returns `None` if it is not an even number of hex digits.
+pub fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    let digits = hex.as_bytes();
+    if digits.len() % 2 != 0 {
+        return None;
+    }
+    let digit = |digit: u8| (digit as char).to_digit(16).map(|value| value as u8);
+    digits.chunks(2)
+        .map(|pair| match (digit(pair[0]), digit(pair[1])) {
+            (Some(high), Some(low)) => Some((high << 4) | low),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let bytes = [0, 1, 0x7f, 0x80, 0xab, 0xff];
+        assert_eq!(to_hex(&bytes), "00017f80abff");
+        assert_eq!(from_hex("00017f80abff"), Some(bytes.to_vec()));
+        assert_eq!(from_hex("00017F80ABFF"), Some(bytes.to_vec()));
+        assert_eq!(from_hex(""), Some(vec![]));
+        assert_eq!(from_hex("012"), None);
+        assert_eq!(from_hex("0g"), None);
+    }
+}
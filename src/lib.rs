@@ -30,6 +30,35 @@
 //!
 //! TBD
 //!
+//! # Audit
+//!
+//! The `audit` feature records updates of structured data and mutations of stores, with their
+//! outcomes, to a sink the application installs; see the `audit` module.
+//!
+//! # Serialisation
+//!
+//! The `serialisation` feature, on by default, provides the rustc-serialize encoding of the data
+//! types and everything built on it: chains, messages, accounts and stores on disk. Without it the
+//! data types can still be built, named and validated, and encoded with the caller's own
+//! `data::Codec`.
+//!
+//! # WebAssembly
+//!
+//! The `sodium` feature, on by default, takes signing, constant-time comparison and random numbers
+//! from rust_sodium, and enables what needs its encryption: encrypted structured data, account
+//! packets, capability lists, key ratchets and private addresses. Without it and the `fs` feature,
+//! and with signing from the `dalek` feature, the crate and its dependencies are pure Rust and
+//! build for `wasm32-unknown-unknown`:
+//!
+//! ```text
+//! cargo build --target wasm32-unknown-unknown --no-default-features \
+//!     --features "dalek serialisation"
+//! ```
+//!
+//! That target has no OS random number generator, so there `crypto::sign::gen_keypair` and
+//! `StorageChallenge::new` panic; create keys with `crypto::sign::keypair_from_seed` from a seed
+//! the application gets elsewhere, e.g. from the browser.
+//!
 //! [Github repository](https://github.com/dirvine/data_chain)
 
 
@@ -55,46 +84,125 @@
 
 #[macro_use]
 extern crate log;
+#[cfg(feature = "serialisation")]
 extern crate bincode;
+#[cfg(feature = "blake3")]
+extern crate blake3;
+#[cfg(feature = "cbor")]
+extern crate cbor;
+#[cfg(feature = "dilithium")]
+extern crate crystals_dilithium;
+#[cfg(feature = "dalek")]
+extern crate ed25519_dalek;
+#[cfg(feature = "compression")]
+extern crate flate2;
+#[cfg(feature = "fs")]
 extern crate fs2;
+#[cfg(feature = "async")]
+extern crate futures;
 extern crate itertools;
-extern crate maidsafe_utilities;
-#[cfg(test)]
+#[cfg(any(feature = "audit", feature = "metrics"))]
+#[macro_use]
+extern crate lazy_static;
+#[cfg(feature = "mmap")]
+extern crate memmap;
+#[cfg(any(test, feature = "arbitrary"))]
+extern crate quickcheck;
+#[cfg(any(test, feature = "rand"))]
 extern crate rand;
+#[cfg(feature = "parallel")]
+extern crate rayon;
+#[cfg(feature = "sodium")]
 extern crate rust_sodium;
+#[cfg(feature = "serialisation")]
 extern crate rustc_serialize;
-#[cfg(test)]
+#[cfg(all(test, feature = "fs"))]
 extern crate tempdir;
 extern crate tiny_keccak;
 #[cfg(test)]
 #[macro_use]
 extern crate unwrap;
 
+#[cfg(not(any(feature = "sodium", feature = "dalek", feature = "dilithium")))]
+compile_error!("a signing backend is needed: enable the `sodium`, `dalek` or `dilithium` feature");
+
 /// Error types for this crate
 pub mod error;
 
+/// Storage accounts of clients
+#[cfg(feature = "serialisation")]
+pub mod account;
+
+/// Time-stamped records of mutations, written to a pluggable sink
+#[cfg(feature = "audit")]
+pub mod audit;
+
+/// Detached proofs of ownership authorising requests
+#[cfg(feature = "serialisation")]
+pub mod auth;
+
 /// A block is a type that contains a `BlockIdentifier` and a `Proof`. These can be data blocks or
 /// links. When enough blocks (`Vote`s) are received from other nodes a block
 /// becomes valid. This is a cetnral type to the security of republishable data
 /// on the network.
+#[cfg(feature = "serialisation")]
 pub mod chain;
 
+/// Constant-time comparison helpers and the signing backend
+pub mod crypto;
+
 /// Data types
-/// Immutable data (name == hash of content) and
+/// Immutable data (name == hash of content),
 /// Structured data (constant name with editable contents and ownership changes)
-/// this type is signed by at least one owner and can be a ledger type (never deleted)
+/// this type is signed by at least one owner and can be a ledger type (never deleted),
+/// Mutable data (key-value entries mutated under per-key permissions) and
+/// Plain data (free-form name and contents)
 pub mod data;
+/// C bindings building, naming, signing, validating and serialising data
+#[cfg(feature = "ffi")]
+pub mod ffi;
+/// Hex encoding of names and keys
+mod hex;
+/// Messages exchanged between nodes about the data they hold
+#[cfg(feature = "serialisation")]
+pub mod messages;
+/// Merkle trees over chunk names with inclusion proofs
+pub mod merkle;
+/// Counts of puts, gets, validation failures and cache hits, passed to a pluggable recorder
+#[cfg(feature = "metrics")]
+pub mod metrics;
+/// Pluggable hash algorithms names are derived with
+pub mod name_hasher;
+/// Binary serialisation with bincode and rustc-serialize
+#[cfg(feature = "serialisation")]
+pub mod serialisation;
 /// sha3 (keccak)
 pub mod sha3;
+/// Challenges and proofs that a holder still has a chunk
+#[cfg(feature = "serialisation")]
+pub mod storage_proof;
+/// In-memory and disk-based stores of data
+pub mod store;
+/// Deterministic generators of keys and data for tests
+#[cfg(any(test, feature = "test_utils"))]
+pub mod test_utils;
+/// Names in the network address space, XOR distance between them and closest-name helpers
+pub mod xor_name;
 
 /// API
 /// This is the entry point to this crate and allows the crate to be
 /// used as a secured data store for all data types mentioned above.
+#[cfg(feature = "fs")]
 pub mod secured_data;
 
 /// Persistant store on disk of the data itself as well as the `DataChain`.
+#[cfg(feature = "fs")]
 mod chunk_store;
 
+#[cfg(feature = "serialisation")]
 pub use chain::{Block, BlockIdentifier, DataChain, Proof, Vote};
 
-pub use data::{Data, DataIdentifier, ImmutableData, MAX_BYTES, StructuredData};
+pub use data::{Compression, Data, DataIdentifier, ImmutableData, MAX_BYTES, MutableData,
+               PlainData, SignaturePolicy, StructuredData};
+pub use store::DataCache;
+pub use xor_name::XorName;
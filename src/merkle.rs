@@ -0,0 +1,224 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Merkle trees
+//! A Merkle tree over the names of the chunks a file is split into. The root commits to every
+//! chunk name and its position, so a client holding only the root can check that a single chunk
+//! belongs to the file using an inclusion proof, without fetching the other chunks.
+//!
+//! Leaves are `sha3(0x00 || name)` and inner nodes `sha3(0x01 || left || right)`, so a leaf can
+//! never be passed off as an inner node. A node without a sibling, the last one on a level with an
+//! odd number of nodes, is carried up to the next level unchanged.
+
+use crypto::ct;
+use sha3::hash;
+use tiny_keccak::Keccak;
+
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+fn leaf_hash(name: &[u8; 32]) -> [u8; 32] {
+    let mut sha3 = Keccak::new_sha3_256();
+    sha3.update(&[LEAF_PREFIX]);
+    sha3.update(name);
+    let mut res = [0u8; 32];
+    sha3.finalize(&mut res);
+    res
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut sha3 = Keccak::new_sha3_256();
+    sha3.update(&[NODE_PREFIX]);
+    sha3.update(left);
+    sha3.update(right);
+    let mut res = [0u8; 32];
+    sha3.finalize(&mut res);
+    res
+}
+
+/// A Merkle tree over an ordered list of chunk names.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerkleTree {
+    // Level 0 holds the leaves, the last level the root.
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+impl MerkleTree {
+    /// Builds the tree over `names`, in order.
+    pub fn new(names: &[[u8; 32]]) -> MerkleTree {
+        let mut levels = vec![names.iter().map(leaf_hash).collect::<Vec<_>>()];
+        while levels[levels.len() - 1].len() > 1 {
+            let next = levels[levels.len() - 1]
+                .chunks(2)
+                .map(|pair| if pair.len() == 2 {
+                    node_hash(&pair[0], &pair[1])
+                } else {
+                    pair[0]
+                })
+                .collect();
+            levels.push(next);
+        }
+        MerkleTree { levels: levels }
+    }
+
+    /// The root committing to all the names. For an empty tree this is the hash of nothing.
+    pub fn root(&self) -> [u8; 32] {
+        match self.levels[self.levels.len() - 1].first() {
+            Some(root) => *root,
+            None => hash(&[]),
+        }
+    }
+
+    /// Number of names in the tree.
+    pub fn leaf_count(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    /// Returns a proof that the name at `index` is part of the tree, or `None` if `index` is out
+    /// of range.
+    pub fn proof(&self, index: usize) -> Option<MerkleProof> {
+        if index >= self.leaf_count() {
+            return None;
+        }
+        let mut path = Vec::new();
+        let mut position = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling = position ^ 1;
+            if sibling < level.len() {
+                path.push(level[sibling]);
+            }
+            position /= 2;
+        }
+        Some(MerkleProof {
+            index: index as u64,
+            leaf_count: self.leaf_count() as u64,
+            path: path,
+        })
+    }
+}
+
+/// Proof that a chunk name is at a given position in a `MerkleTree`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialisation", derive(RustcEncodable, RustcDecodable))]
+pub struct MerkleProof {
+    index: u64,
+    leaf_count: u64,
+    path: Vec<[u8; 32]>,
+}
+
+impl MerkleProof {
+    /// Position of the proven name.
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    /// Number of names in the tree the proof was taken from.
+    pub fn leaf_count(&self) -> u64 {
+        self.leaf_count
+    }
+
+    /// Returns `true` if `name` is at `index()` in the tree with the given `root`.
+    pub fn verify(&self, root: &[u8; 32], name: &[u8; 32]) -> bool {
+        if self.index >= self.leaf_count {
+            return false;
+        }
+        let mut path = self.path.iter();
+        let mut node = leaf_hash(name);
+        let mut position = self.index;
+        let mut count = self.leaf_count;
+        while count > 1 {
+            let sibling = position ^ 1;
+            if sibling < count {
+                let sibling_hash = match path.next() {
+                    Some(sibling_hash) => sibling_hash,
+                    None => return false,
+                };
+                node = if position % 2 == 0 {
+                    node_hash(&node, sibling_hash)
+                } else {
+                    node_hash(sibling_hash, &node)
+                };
+            }
+            position /= 2;
+            count = (count + 1) / 2;
+        }
+        path.next().is_none() && ct::name_eq(&node, root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand;
+
+    fn names(count: usize) -> Vec<[u8; 32]> {
+        (0..count).map(|_| rand::random()).collect()
+    }
+
+    #[test]
+    fn proofs_verify() {
+        for count in 1..18 {
+            let names = names(count);
+            let tree = MerkleTree::new(&names);
+            let root = tree.root();
+            assert_eq!(tree.leaf_count(), count);
+            assert!(tree.proof(count).is_none());
+            for (index, name) in names.iter().enumerate() {
+                let proof = unwrap!(tree.proof(index));
+                assert_eq!(proof.index(), index as u64);
+                assert!(proof.verify(&root, name));
+                // A proof is only valid for its own name and position.
+                assert!(!proof.verify(&root, &rand::random()));
+                if count > 1 {
+                    let other = (index + 1) % count;
+                    assert!(!proof.verify(&root, &names[other]));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn root_commits_to_names_and_order() {
+        let names = names(5);
+        let root = MerkleTree::new(&names).root();
+        let mut swapped = names.clone();
+        swapped.swap(1, 2);
+        assert!(MerkleTree::new(&swapped).root() != root);
+        assert!(MerkleTree::new(&names[..4]).root() != root);
+        assert_eq!(MerkleTree::new(&names).root(), root);
+        assert_eq!(MerkleTree::new(&[]).root(), hash(&[]));
+        // A single leaf is not its own root, so a name cannot pose as a tree.
+        assert!(MerkleTree::new(&names[..1]).root() != names[0]);
+    }
+
+    #[test]
+    fn tampered_proof() {
+        let names = names(7);
+        let tree = MerkleTree::new(&names);
+        let mut proof = unwrap!(tree.proof(3));
+        proof.path[0][0] ^= 1;
+        assert!(!proof.verify(&tree.root(), &names[3]));
+
+        let mut proof = unwrap!(tree.proof(6));
+        proof.leaf_count = 8;
+        assert!(!proof.verify(&tree.root(), &names[6]));
+
+        let mut proof = unwrap!(tree.proof(6));
+        proof.path.push([0; 32]);
+        assert!(!proof.verify(&tree.root(), &names[6]));
+    }
+}
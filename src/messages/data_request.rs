@@ -0,0 +1,188 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use data::{DataIdentifier, ImmutableData};
+use error::Error;
+use rustc_serialize::{Decodable, Decoder, Encodable, Encoder};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::ops::Range;
+
+/// A request for the data with an identifier, or just a range of the bytes of its value, e.g. to
+/// seek within a video or to resume an interrupted download.
+///
+/// The range is of the value as it was created, i.e. decompressed. Its end may be past the end
+/// of the value, in which case it runs to the end, so `start..u64::max_value()` asks for
+/// everything from `start` on.
+#[derive(Hash, Clone, Debug, Eq, PartialEq)]
+pub struct DataRequest {
+    /// The identifier of the data.
+    pub id: DataIdentifier,
+    /// The range of bytes of the value requested, or `None` for the whole data.
+    pub range: Option<Range<u64>>,
+}
+
+impl DataRequest {
+    /// Requests the whole data with the identifier.
+    pub fn new(id: DataIdentifier) -> DataRequest {
+        DataRequest {
+            id: id,
+            range: None,
+        }
+    }
+
+    /// Requests the bytes in `range` of the value of the data with the identifier.
+    pub fn with_range(id: DataIdentifier, range: Range<u64>) -> DataRequest {
+        DataRequest {
+            id: id,
+            range: Some(range),
+        }
+    }
+
+    /// Returns whether only part of the data is requested.
+    pub fn is_partial(&self) -> bool {
+        self.range.is_some()
+    }
+
+    /// Returns the range of a value of `len` bytes requested, with its end no further than `len`.
+    /// Fails with `InvalidRange` if the range starts after it ends or after the end of the value.
+    pub fn resolve(&self, len: u64) -> Result<Range<u64>, Error> {
+        match self.range {
+            None => Ok(0..len),
+            Some(ref range) if range.start <= range.end && range.start <= len => {
+                Ok(range.start..range.end.min(len))
+            }
+            Some(_) => Err(Error::InvalidRange),
+        }
+    }
+
+    /// Returns this request with its range resolved against `data`, as sent back with the bytes.
+    pub fn resolved(&self, data: &ImmutableData) -> Result<DataRequest, Error> {
+        let len = data.decompressed_value()?.len() as u64;
+        Ok(DataRequest::with_range(self.id, self.resolve(len)?))
+    }
+
+    /// Returns the bytes of the value of `data` requested, without copying them unless the value
+    /// is compressed. Fails with `BadIdentifier` if `data` is not the data requested, and with
+    /// `InvalidRange` if the range is not within its value.
+    pub fn slice<'a>(&self, data: &'a ImmutableData) -> Result<Cow<'a, [u8]>, Error> {
+        if self.id != DataIdentifier::Immutable(*data.name()) {
+            return Err(Error::BadIdentifier);
+        }
+        let value = data.decompressed_value()?;
+        let range = self.resolve(value.len() as u64)?;
+        let range = range.start as usize..range.end as usize;
+        Ok(match value {
+            Cow::Borrowed(value) => Cow::Borrowed(&value[range]),
+            Cow::Owned(value) => Cow::Owned(value[range].to_vec()),
+        })
+    }
+
+    fn sort_key(&self) -> (DataIdentifier, Option<(u64, u64)>) {
+        (self.id, self.range.as_ref().map(|range| (range.start, range.end)))
+    }
+}
+
+impl PartialOrd for DataRequest {
+    fn partial_cmp(&self, other: &DataRequest) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DataRequest {
+    fn cmp(&self, other: &DataRequest) -> Ordering {
+        self.sort_key().cmp(&other.sort_key())
+    }
+}
+
+impl Encodable for DataRequest {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), E::Error> {
+        self.sort_key().encode(encoder)
+    }
+}
+
+impl Decodable for DataRequest {
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<DataRequest, D::Error> {
+        let (id, range): (DataIdentifier, Option<(u64, u64)>) = Decodable::decode(decoder)?;
+        match range {
+            None => Ok(DataRequest::new(id)),
+            Some((start, end)) if start <= end => Ok(DataRequest::with_range(id, start..end)),
+            Some(_) => Err(decoder.error("range starts after it ends")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{DataIdentifier, ImmutableData};
+    use error::Error;
+    use serialisation::{deserialise, serialise};
+    use std::borrow::Cow;
+
+    #[test]
+    fn slices() {
+        let value: Vec<u8> = (0..100).collect();
+        let data = ImmutableData::new(value.clone());
+        let id = DataIdentifier::Immutable(*data.name());
+
+        match unwrap!(DataRequest::with_range(id, 10..20).slice(&data)) {
+            Cow::Borrowed(slice) => assert_eq!(slice, &value[10..20]),
+            Cow::Owned(_) => panic!("Uncompressed value copied"),
+        }
+        assert_eq!(&*unwrap!(DataRequest::new(id).slice(&data)), &value[..]);
+        let rest = DataRequest::with_range(id, 90..u64::max_value());
+        assert_eq!(&*unwrap!(rest.slice(&data)), &value[90..]);
+        assert_eq!(unwrap!(rest.resolved(&data)), DataRequest::with_range(id, 90..100));
+        assert!(unwrap!(DataRequest::with_range(id, 100..200).slice(&data)).is_empty());
+
+        match DataRequest::with_range(id, 101..200).slice(&data) {
+            Err(Error::InvalidRange) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match DataRequest::with_range(id, 20..10).slice(&data) {
+            Err(Error::InvalidRange) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        let other = DataIdentifier::Immutable([0; 32]);
+        match DataRequest::with_range(other, 10..20).slice(&data) {
+            Err(Error::BadIdentifier) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn slices_decompressed_value() {
+        let value = vec![7u8; 10_000];
+        let data = unwrap!(ImmutableData::new_compressed(value.clone()));
+        let id = DataIdentifier::Immutable(*data.name());
+        let request = DataRequest::with_range(id, 5_000..5_010);
+        assert_eq!(&*unwrap!(request.slice(&data)), &value[5_000..5_010]);
+    }
+
+    #[test]
+    fn serialisation() {
+        let id = DataIdentifier::Structured([1; 32], 5);
+        for request in vec![DataRequest::new(id), DataRequest::with_range(id, 3..7)] {
+            let serialised = unwrap!(serialise(&request));
+            assert_eq!(unwrap!(deserialise::<DataRequest>(&serialised)), request);
+        }
+        let reversed = unwrap!(serialise(&(id, Some((7u64, 3u64)))));
+        assert!(deserialise::<DataRequest>(&reversed).is_err());
+    }
+}
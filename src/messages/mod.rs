@@ -0,0 +1,36 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Messages
+//!
+//! - `DataRequest` : the identifier of data requested, with the range of bytes of its value
+//!                   requested if only part of it is wanted.
+//! - `Refresh` : sent between nodes during churn to relocate data, naming a data item, the nodes
+//!               holding it and the hash of its content.
+//! - `Request`, `Response` : operations on data and their results, with `SignedRequest` carrying
+//!                           a request signed by the requester.
+
+/// Requests for data or a range of its bytes
+pub mod data_request;
+/// Payload exchanged on churn to relocate data
+pub mod refresh;
+/// Requests for operations on data and their responses
+pub mod request;
+
+pub use messages::data_request::DataRequest;
+pub use messages::refresh::Refresh;
+pub use messages::request::{Request, Response, ResponseError, SignedRequest};
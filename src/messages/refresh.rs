@@ -0,0 +1,148 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use data::{Data, DataIdentifier};
+use data::untrusted;
+use error::Error;
+use serialisation::serialise;
+use sha3::hash;
+use xor_name::XorName;
+
+/// Tells the new holders of a data item which item to fetch, from whom, and what to expect.
+///
+/// Sent during churn when responsibility for data moves between nodes. The receiver fetches the
+/// data from one of `holders()` and checks it with `matches()` before storing it.
+#[derive(Hash, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, RustcEncodable, RustcDecodable)]
+pub struct Refresh {
+    identifier: DataIdentifier,
+    holders: Vec<XorName>,
+    data_hash: [u8; 32],
+}
+
+impl Refresh {
+    /// Creates a refresh for `data`, currently held by `holders`.
+    ///
+    /// The holders are sorted and must be unique and non-empty.
+    pub fn new(data: &Data, holders: Vec<XorName>) -> Result<Refresh, Error> {
+        let mut refresh = Refresh {
+            identifier: data.identifier(),
+            holders: holders,
+            data_hash: Refresh::hash_of(data)?,
+        };
+        refresh.holders.sort();
+        refresh.validate()?;
+        Ok(refresh)
+    }
+
+    /// Identifier of the data to relocate.
+    pub fn identifier(&self) -> &DataIdentifier {
+        &self.identifier
+    }
+
+    /// Nodes holding the data, in sorted order.
+    pub fn holders(&self) -> &[XorName] {
+        &self.holders
+    }
+
+    /// Hash of the serialised data.
+    pub fn data_hash(&self) -> &[u8; 32] {
+        &self.data_hash
+    }
+
+    /// Returns `true` if `data` is the data this refresh describes.
+    pub fn matches(&self, data: &Data) -> bool {
+        data.identifier() == self.identifier &&
+        Refresh::hash_of(data).map(|hash| hash == self.data_hash).unwrap_or(false)
+    }
+
+    /// Checks the holders are non-empty, sorted and unique.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.holders.is_empty() || self.holders.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(Error::Validation);
+        }
+        Ok(())
+    }
+
+    /// Serialises the refresh for sending.
+    pub fn serialise(&self) -> Result<Vec<u8>, Error> {
+        serialise(self).map_err(From::from)
+    }
+
+    /// Parses and validates a received refresh.
+    pub fn deserialise(bytes: &[u8]) -> Result<Refresh, Error> {
+        let refresh: Refresh = untrusted::deserialise(bytes)?;
+        refresh.validate()?;
+        Ok(refresh)
+    }
+
+    fn hash_of(data: &Data) -> Result<[u8; 32], Error> {
+        Ok(hash(&serialise(data)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{Data, ImmutableData};
+    use error::Error;
+    use rand;
+    use serialisation::serialise;
+    use xor_name::XorName;
+
+    fn holders(count: usize) -> Vec<XorName> {
+        (0..count).map(|_| XorName(rand::random())).collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let data = Data::Immutable(ImmutableData::new(b"chunk".to_vec()));
+        let refresh = unwrap!(Refresh::new(&data, holders(4)));
+        assert_eq!(refresh.identifier(), &data.identifier());
+        assert!(refresh.holders().windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(refresh.matches(&data));
+        assert!(!refresh.matches(&Data::Immutable(ImmutableData::new(b"other".to_vec()))));
+
+        let serialised = unwrap!(refresh.serialise());
+        assert_eq!(unwrap!(Refresh::deserialise(&serialised)), refresh);
+    }
+
+    #[test]
+    fn invalid_holders() {
+        let data = Data::Immutable(ImmutableData::new(b"chunk".to_vec()));
+        match Refresh::new(&data, vec![]) {
+            Err(Error::Validation) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        let mut duplicated = holders(2);
+        duplicated.push(duplicated[0]);
+        match Refresh::new(&data, duplicated) {
+            Err(Error::Validation) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // Received refreshes are validated too.
+        let unsorted = Refresh {
+            identifier: data.identifier(),
+            holders: vec![XorName([1; 32]), XorName([0; 32])],
+            data_hash: [0; 32],
+        };
+        match Refresh::deserialise(&unwrap!(serialise(&unsorted))) {
+            Err(Error::Validation) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,286 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use crypto::sign::{self, PublicKey, SecretKey, Signature};
+use data::{Data, DataIdentifier};
+use data::canonical::{CanonicalWriter, SIGNED_REQUEST_DOMAIN};
+use data::untrusted;
+use error::Error;
+use messages::DataRequest;
+use serialisation::serialise;
+
+/// A request for an operation on data.
+#[derive(Hash, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, RustcEncodable, RustcDecodable)]
+pub enum Request {
+    /// Stores new data.
+    Put(Data),
+    /// Fetches the data with the identifier.
+    Get(DataIdentifier),
+    /// Updates existing data, e.g. with the next version of a `StructuredData`.
+    Post(Data),
+    /// Deletes the data with the identifier.
+    Delete(DataIdentifier),
+    /// Fetches the data, or a range of the bytes of the value of immutable data.
+    GetRange(DataRequest),
+}
+
+impl Request {
+    /// Returns the identifier of the data the request is for.
+    pub fn identifier(&self) -> DataIdentifier {
+        match *self {
+            Request::Put(ref data) |
+            Request::Post(ref data) => data.identifier(),
+            Request::Get(identifier) |
+            Request::Delete(identifier) => identifier,
+            Request::GetRange(ref request) => request.id,
+        }
+    }
+
+    /// Returns the response to this request failing with `error`.
+    pub fn failure(&self, error: ResponseError) -> Response {
+        let identifier = self.identifier();
+        match *self {
+            Request::Put(_) => Response::PutFailure(identifier, error),
+            Request::Get(_) |
+            Request::GetRange(_) => Response::GetFailure(identifier, error),
+            Request::Post(_) => Response::PostFailure(identifier, error),
+            Request::Delete(_) => Response::DeleteFailure(identifier, error),
+        }
+    }
+}
+
+/// Why a request failed, in a form which can be sent back to the requester.
+#[derive(Hash, Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, RustcEncodable,
+         RustcDecodable)]
+pub enum ResponseError {
+    /// There is no data with the identifier.
+    NoSuchData,
+    /// Data with the identifier is already stored.
+    DataExists,
+    /// The data is invalid, e.g. not a valid successor of the stored version.
+    InvalidData,
+    /// A signature is invalid, or there are not enough of them.
+    InvalidSignature,
+    /// The requester may not perform the operation.
+    AccessDenied,
+    /// The data is too large.
+    DataTooLarge,
+    /// The data has been deleted and can't be stored again.
+    DataDeleted,
+    /// There is not enough space to store the data.
+    NoSpace,
+    /// The request could not be handled for another reason.
+    Failed,
+    /// The range requested is not within the data.
+    InvalidRange,
+}
+
+impl<'a> From<&'a Error> for ResponseError {
+    fn from(error: &'a Error) -> ResponseError {
+        match *error {
+            Error::NoFile | Error::NoSuchEntry => ResponseError::NoSuchData,
+            Error::EntryExists => ResponseError::DataExists,
+            Error::Validation |
+            Error::NoLink |
+            Error::InvalidVersion |
+            Error::BadIdentifier |
+            Error::NoOwners |
+            Error::TooManyOwners => ResponseError::InvalidData,
+            Error::Signature | Error::Majority | Error::Crypto => ResponseError::InvalidSignature,
+            Error::AccessDenied => ResponseError::AccessDenied,
+            Error::DataTooLarge => ResponseError::DataTooLarge,
+            Error::DataDeleted => ResponseError::DataDeleted,
+            Error::NoSpace => ResponseError::NoSpace,
+            Error::InvalidRange => ResponseError::InvalidRange,
+            Error::Serialisation(_) | Error::Io(_) | Error::Encoding(_) | Error::Expired => {
+                ResponseError::Failed
+            }
+        }
+    }
+}
+
+/// The response to a `Request`.
+#[derive(Hash, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, RustcEncodable, RustcDecodable)]
+pub enum Response {
+    /// The data was stored.
+    PutSuccess(DataIdentifier),
+    /// The data was not stored.
+    PutFailure(DataIdentifier, ResponseError),
+    /// The data requested.
+    GetSuccess(Data),
+    /// The data could not be fetched.
+    GetFailure(DataIdentifier, ResponseError),
+    /// The data was updated.
+    PostSuccess(DataIdentifier),
+    /// The data was not updated.
+    PostFailure(DataIdentifier, ResponseError),
+    /// The data was deleted.
+    DeleteSuccess(DataIdentifier),
+    /// The data was not deleted.
+    DeleteFailure(DataIdentifier, ResponseError),
+    /// The bytes requested, with the request's range resolved against the data, i.e. ending no
+    /// further than its end.
+    GetRangeSuccess(DataRequest, Vec<u8>),
+}
+
+impl Response {
+    /// Returns the identifier of the data the response is about.
+    pub fn identifier(&self) -> DataIdentifier {
+        match *self {
+            Response::GetSuccess(ref data) => data.identifier(),
+            Response::GetRangeSuccess(ref request, _) => request.id,
+            Response::PutSuccess(identifier) |
+            Response::PutFailure(identifier, _) |
+            Response::GetFailure(identifier, _) |
+            Response::PostSuccess(identifier) |
+            Response::PostFailure(identifier, _) |
+            Response::DeleteSuccess(identifier) |
+            Response::DeleteFailure(identifier, _) => identifier,
+        }
+    }
+
+    /// Returns the error if the request failed.
+    pub fn error(&self) -> Option<ResponseError> {
+        match *self {
+            Response::PutFailure(_, error) |
+            Response::GetFailure(_, error) |
+            Response::PostFailure(_, error) |
+            Response::DeleteFailure(_, error) => Some(error),
+            Response::PutSuccess(_) |
+            Response::GetSuccess(_) |
+            Response::GetRangeSuccess(..) |
+            Response::PostSuccess(_) |
+            Response::DeleteSuccess(_) => None,
+        }
+    }
+}
+
+/// A `Request` signed by the requester.
+#[derive(Hash, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, RustcEncodable, RustcDecodable)]
+pub struct SignedRequest {
+    request: Request,
+    requester: PublicKey,
+    signature: Signature,
+}
+
+impl SignedRequest {
+    /// Signs `request` as the owner of `requester`'s secret key.
+    pub fn new(request: Request,
+               requester: PublicKey,
+               secret_key: &SecretKey)
+               -> Result<SignedRequest, Error> {
+        let signature = sign::sign_detached(&Self::data_to_sign(&request)?, secret_key);
+        Ok(SignedRequest {
+            request: request,
+            requester: requester,
+            signature: signature,
+        })
+    }
+
+    /// The request.
+    pub fn request(&self) -> &Request {
+        &self.request
+    }
+
+    /// The requester's public key.
+    pub fn requester(&self) -> &PublicKey {
+        &self.requester
+    }
+
+    /// Checks the signature is the requester's over the request.
+    pub fn validate(&self) -> Result<(), Error> {
+        if sign::verify_detached(&self.signature,
+                                 &Self::data_to_sign(&self.request)?,
+                                 &self.requester) {
+            Ok(())
+        } else {
+            Err(Error::Signature)
+        }
+    }
+
+    /// Serialises the request for sending.
+    pub fn serialise(&self) -> Result<Vec<u8>, Error> {
+        serialise(self).map_err(From::from)
+    }
+
+    /// Parses and validates a received request.
+    pub fn deserialise(bytes: &[u8]) -> Result<SignedRequest, Error> {
+        let request: SignedRequest = untrusted::deserialise(bytes)?;
+        request.validate()?;
+        Ok(request)
+    }
+
+    /// Returns the serialised request, domain-separated so the signature can't be replayed as one
+    /// over anything else.
+    fn data_to_sign(request: &Request) -> Result<Vec<u8>, Error> {
+        let mut writer = CanonicalWriter::new(SIGNED_REQUEST_DOMAIN);
+        let _ = writer.write_bytes(&serialise(request)?);
+        Ok(writer.into_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{Data, ImmutableData};
+    use error::Error;
+    use serialisation::{deserialise, serialise};
+    use test_utils;
+
+    #[test]
+    fn identifiers_and_failures() {
+        let data = Data::Immutable(ImmutableData::new(b"chunk".to_vec()));
+        let identifier = data.identifier();
+        let requests = vec![Request::Put(data.clone()),
+                            Request::Get(identifier),
+                            Request::Post(data),
+                            Request::Delete(identifier),
+                            Request::GetRange(DataRequest::with_range(identifier, 1..3))];
+        for request in requests {
+            assert_eq!(request.identifier(), identifier);
+            let response = request.failure(ResponseError::from(&Error::NoSpace));
+            assert_eq!(response.identifier(), identifier);
+            assert_eq!(response.error(), Some(ResponseError::NoSpace));
+
+            let serialised = unwrap!(serialise(&response));
+            assert_eq!(unwrap!(deserialise::<Response>(&serialised)), response);
+        }
+        assert_eq!(Response::PutSuccess(identifier).error(), None);
+    }
+
+    #[test]
+    fn signed_request() {
+        let keys = test_utils::gen_keypair();
+        let data = Data::Immutable(ImmutableData::new(b"chunk".to_vec()));
+        let request = unwrap!(SignedRequest::new(Request::Put(data.clone()), keys.0, &keys.1));
+        assert_eq!(request.requester(), &keys.0);
+        unwrap!(request.validate());
+        let serialised = unwrap!(request.serialise());
+        assert_eq!(unwrap!(SignedRequest::deserialise(&serialised)), request);
+
+        // The signature doesn't cover another request.
+        let tampered = SignedRequest {
+            request: Request::Post(data),
+            requester: request.requester,
+            signature: request.signature,
+        };
+        match SignedRequest::deserialise(&unwrap!(tampered.serialise())) {
+            Err(Error::Signature) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
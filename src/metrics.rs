@@ -0,0 +1,228 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+//! # Metrics
+//! With the `metrics` feature, the crate counts puts, gets, validation failures, bytes stored and
+//! cache hits and misses as they happen, passing each count to a recorder installed with
+//! `set_recorder`. Operators can then monitor the health of the data layer.
+//!
+//! `CounterRecorder` keeps running totals and, with the `prometheus` feature, renders them in the
+//! Prometheus text exposition format for a metrics endpoint to serve; other recorders implement
+//! `MetricsRecorder`. Nothing is counted until a recorder is set.
+
+use error::Error;
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+
+lazy_static! {
+    static ref RECORDER: RwLock<Option<Box<MetricsRecorder>>> = RwLock::new(None);
+}
+
+/// A counted event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Counter {
+    /// Data stored, e.g. by `SecuredData::put_data` or `DataStore::put`.
+    Put,
+    /// Data fetched, e.g. by `SecuredData::get` or `DataStore::get`.
+    Get,
+    /// Data or an update of it rejected as invalid, by `Data::validate` or
+    /// `StructuredData::replace_with_other`.
+    ValidationFailure,
+    /// Bytes of payload stored, counted with each `Put`.
+    BytesStored,
+    /// A `DataCache` lookup which found the item.
+    CacheHit,
+    /// A `DataCache` lookup which did not find the item.
+    CacheMiss,
+}
+
+impl Counter {
+    /// All counters, in the order exporters list them.
+    pub const ALL: [Counter; 6] = [Counter::Put,
+                                   Counter::Get,
+                                   Counter::ValidationFailure,
+                                   Counter::BytesStored,
+                                   Counter::CacheHit,
+                                   Counter::CacheMiss];
+
+    /// Returns the name exporters give the counter.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Counter::Put => "data_chain_puts_total",
+            Counter::Get => "data_chain_gets_total",
+            Counter::ValidationFailure => "data_chain_validation_failures_total",
+            Counter::BytesStored => "data_chain_stored_bytes_total",
+            Counter::CacheHit => "data_chain_cache_hits_total",
+            Counter::CacheMiss => "data_chain_cache_misses_total",
+        }
+    }
+
+    /// Returns a one-line description of the counter.
+    pub fn description(&self) -> &'static str {
+        match *self {
+            Counter::Put => "Data stored.",
+            Counter::Get => "Data fetched.",
+            Counter::ValidationFailure => "Data or updates rejected as invalid.",
+            Counter::BytesStored => "Bytes of payload stored.",
+            Counter::CacheHit => "Cache lookups which found the item.",
+            Counter::CacheMiss => "Cache lookups which did not find the item.",
+        }
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// A destination of counted events.
+pub trait MetricsRecorder: Send + Sync {
+    /// Adds `value` to `counter`. Failures to record must not fail the operation counted, so are
+    /// not reported.
+    fn increment(&self, counter: Counter, value: u64);
+}
+
+/// A recorder keeping a running total of each counter.
+///
+/// Clones share the totals, so a clone kept by the caller sees what the installed recorder counts.
+#[derive(Clone, Default)]
+pub struct CounterRecorder {
+    totals: Arc<Mutex<[u64; 6]>>,
+}
+
+impl CounterRecorder {
+    /// Creates a recorder with all totals zero.
+    pub fn new() -> CounterRecorder {
+        CounterRecorder::default()
+    }
+
+    /// Returns the total of `counter`.
+    pub fn total(&self, counter: Counter) -> u64 {
+        self.lock()[counter.index()]
+    }
+
+    /// Renders the totals in the Prometheus text exposition format.
+    #[cfg(feature = "prometheus")]
+    pub fn render_prometheus(&self) -> String {
+        let totals = *self.lock();
+        let mut text = String::new();
+        for counter in &Counter::ALL {
+            text.push_str(&format!("# HELP {} {}\n# TYPE {} counter\n{} {}\n",
+                                   counter.name(),
+                                   counter.description(),
+                                   counter.name(),
+                                   counter.name(),
+                                   totals[counter.index()]));
+        }
+        text
+    }
+
+    fn lock(&self) -> MutexGuard<[u64; 6]> {
+        self.totals.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl MetricsRecorder for CounterRecorder {
+    fn increment(&self, counter: Counter, value: u64) {
+        let mut totals = self.lock();
+        let total = &mut totals[counter.index()];
+        *total = total.saturating_add(value);
+    }
+}
+
+/// Installs `recorder` as the destination of all counts, replacing any recorder set before.
+pub fn set_recorder(recorder: Box<MetricsRecorder>) {
+    *RECORDER.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(recorder);
+}
+
+/// Removes the installed recorder, so nothing more is counted.
+pub fn clear_recorder() {
+    *RECORDER.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+}
+
+/// Adds `value` to `counter`. The crate counts its own operations; applications may count theirs
+/// too.
+pub fn increment(counter: Counter, value: u64) {
+    let recorder = RECORDER.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(ref recorder) = *recorder {
+        recorder.increment(counter, value);
+    }
+}
+
+/// Counts a put of `payload_size` bytes if it succeeded, as `result` shows.
+pub fn record_put<T>(payload_size: usize, result: &Result<T, Error>) {
+    if result.is_ok() {
+        increment(Counter::Put, 1);
+        increment(Counter::BytesStored, payload_size as u64);
+    }
+}
+
+/// Counts a get if it succeeded, as `result` shows.
+pub fn record_get<T>(result: &Result<T, Error>) {
+    if result.is_ok() {
+        increment(Counter::Get, 1);
+    }
+}
+
+/// Counts a validation failure if `result` is an error.
+pub fn record_validation<T>(result: &Result<T, Error>) {
+    if result.is_err() {
+        increment(Counter::ValidationFailure, 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{Data, DataIdentifier, ImmutableData};
+    use rand;
+    use store::DataCache;
+
+    #[test]
+    fn operations_counted() {
+        let recorder = CounterRecorder::new();
+        set_recorder(Box::new(recorder.clone()));
+
+        // Other tests may be counting at the same time, so only lower bounds are checked.
+        let data = Data::Immutable(ImmutableData::new(vec![1, 2, 3]));
+        unwrap!(data.validate());
+        record_put(data.payload_size(), &Ok::<(), Error>(()));
+        record_put(data.payload_size(), &Err::<(), Error>(Error::Validation));
+        record_validation(&Err::<(), Error>(Error::Validation));
+
+        let mut cache = DataCache::new(10, 1024);
+        let _ = cache.insert(data.clone());
+        assert!(cache.get(&data.identifier()).is_some());
+        assert!(cache.get(&DataIdentifier::Immutable(rand::random())).is_none());
+
+        assert!(recorder.total(Counter::Put) >= 1);
+        assert!(recorder.total(Counter::BytesStored) >= 3);
+        assert!(recorder.total(Counter::ValidationFailure) >= 1);
+        assert!(recorder.total(Counter::CacheHit) >= 1);
+        assert!(recorder.total(Counter::CacheMiss) >= 1);
+    }
+
+    #[cfg(feature = "prometheus")]
+    #[test]
+    fn prometheus_format() {
+        let recorder = CounterRecorder::new();
+        recorder.increment(Counter::Put, 2);
+        recorder.increment(Counter::BytesStored, 1024);
+        let text = recorder.render_prometheus();
+        assert!(text.contains("# TYPE data_chain_puts_total counter\ndata_chain_puts_total 2\n"));
+        assert!(text.contains("\ndata_chain_stored_bytes_total 1024\n"));
+        assert!(text.contains("\ndata_chain_cache_misses_total 0\n"));
+        assert_eq!(text.lines().count(), 3 * Counter::ALL.len());
+    }
+}
@@ -0,0 +1,111 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Name hashers
+//!
+//! The hash algorithm names are derived with, e.g. the names of immutable data and the local
+//! names of structured and mutable data. `Sha3NameHasher` is the algorithm in use and the one the
+//! plain naming functions use; the `_with` variants of those functions take the algorithm as a
+//! type parameter, so a network can move to another one, such as `Blake3NameHasher` with the
+//! `blake3` feature, without forking the crate.
+
+#[cfg(feature = "blake3")]
+use blake3;
+use tiny_keccak::Keccak;
+
+/// An algorithm hashing content incrementally into a 32-byte name.
+pub trait NameHasher: Default {
+    /// Feeds the next part of the content.
+    fn update(&mut self, data: &[u8]);
+
+    /// Returns the name of all content fed so far.
+    fn finalize(self) -> [u8; 32];
+
+    /// Returns the name of `data`.
+    fn hash(data: &[u8]) -> [u8; 32] {
+        let mut hasher = Self::default();
+        hasher.update(data);
+        hasher.finalize()
+    }
+}
+
+/// SHA3-256, the algorithm names are currently derived with.
+pub struct Sha3NameHasher {
+    sha3: Keccak,
+}
+
+impl Default for Sha3NameHasher {
+    fn default() -> Sha3NameHasher {
+        Sha3NameHasher { sha3: Keccak::new_sha3_256() }
+    }
+}
+
+impl NameHasher for Sha3NameHasher {
+    fn update(&mut self, data: &[u8]) {
+        self.sha3.update(data);
+    }
+
+    fn finalize(self) -> [u8; 32] {
+        let mut name = [0u8; 32];
+        self.sha3.finalize(&mut name);
+        name
+    }
+}
+
+/// BLAKE3, a faster alternative for future networks.
+#[cfg(feature = "blake3")]
+#[derive(Default)]
+pub struct Blake3NameHasher {
+    blake3: blake3::Hasher,
+}
+
+#[cfg(feature = "blake3")]
+impl NameHasher for Blake3NameHasher {
+    fn update(&mut self, data: &[u8]) {
+        let _ = self.blake3.update(data);
+    }
+
+    fn finalize(self) -> [u8; 32] {
+        *self.blake3.finalize().as_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha3::hash;
+
+    #[test]
+    fn sha3_matches_hash() {
+        let mut hasher = Sha3NameHasher::default();
+        hasher.update(b"hel");
+        hasher.update(b"lo");
+        assert_eq!(hasher.finalize(), hash(b"hello"));
+        assert_eq!(Sha3NameHasher::hash(b"hello"), hash(b"hello"));
+    }
+
+    #[cfg(feature = "blake3")]
+    #[test]
+    fn blake3_differs() {
+        let mut hasher = Blake3NameHasher::default();
+        hasher.update(b"hel");
+        hasher.update(b"lo");
+        let name = hasher.finalize();
+        assert_eq!(name, Blake3NameHasher::hash(b"hello"));
+        assert!(name != hash(b"hello"));
+    }
+}
@@ -15,23 +15,30 @@
 // Please review the Licences for the specific language governing permissions and limitations
 // relating to use of the SAFE Network Software.
 
+#[cfg(feature = "audit")]
+use audit::{self, AuditOperation};
 use chain::{BlockIdentifier, DataChain, Vote};
 use chunk_store::ChunkStore;
+pub use chunk_store::RestoreReport;
+use crypto::ct;
+use crypto::sign::{PublicKey, Signature};
 use data::{Data, DataIdentifier};
 use error::Error;
 use itertools::Itertools;
-use maidsafe_utilities::serialisation;
-use rust_sodium::crypto::sign::{PublicKey, Signature};
+#[cfg(feature = "metrics")]
+use metrics;
+use serialisation;
 use sha3::hash;
 use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, MutexGuard};
 
 /// API for data based operations.
 pub struct SecuredData {
     cs: ChunkStore<[u8; 32], Data>,
     dc: Arc<Mutex<DataChain>>,
+    accept_unsigned: bool,
 }
 
 impl SecuredData {
@@ -42,7 +49,11 @@ impl SecuredData {
                           -> Result<SecuredData, Error> {
         let cs = ChunkStore::new(path.clone(), max_disk_space)?;
         let dc = Arc::new(Mutex::new(DataChain::create_in_path(path, group_size)?));
-        Ok(SecuredData { cs: cs, dc: dc })
+        Ok(SecuredData {
+            cs: cs,
+            dc: dc,
+            accept_unsigned: false,
+        })
     }
 
     /// Open an existing container from path
@@ -52,12 +63,57 @@ impl SecuredData {
                      -> Result<SecuredData, Error> {
         let cs = ChunkStore::from_path(path.clone(), max_disk_space)?;
         let dc = Arc::new(Mutex::new(DataChain::from_path(path, group_size)?));
-        Ok(SecuredData { cs: cs, dc: dc })
+        Ok(SecuredData {
+            cs: cs,
+            dc: dc,
+            accept_unsigned: false,
+        })
+    }
+
+    /// Reopens a container left in `path` by an earlier run, e.g. after a crash, checking every
+    /// chunk before serving it.
+    ///
+    /// A chunk is kept if it deserialises, its name is still the hash of its content and, for
+    /// structured data, it is validly signed. Version 0 of non-ledger structured data is kept
+    /// unsigned too, since it may have been accepted as provisional (see `set_accept_unsigned`).
+    /// Other chunks are moved to the `lost+found` directory and listed in the report.
+    pub fn restore(path: PathBuf,
+                   max_disk_space: u64,
+                   group_size: usize)
+                   -> Result<(SecuredData, RestoreReport), Error> {
+        let (cs, report) = ChunkStore::open_validated(path.clone(), max_disk_space, is_intact)?;
+        Ok((SecuredData::with_stores(cs, DataChain::from_path(path, group_size)?), report))
+    }
+
+    /// Reopens a container left in `path` as `restore` does, but without reading the chunks, so
+    /// nothing is quarantined and a corrupt chunk is only noticed when fetching it fails.
+    pub fn restore_lazily(path: PathBuf,
+                          max_disk_space: u64,
+                          group_size: usize)
+                          -> Result<(SecuredData, RestoreReport), Error> {
+        let (cs, report) = ChunkStore::open(path.clone(), max_disk_space)?;
+        Ok((SecuredData::with_stores(cs, DataChain::from_path(path, group_size)?), report))
+    }
+
+    fn with_stores(cs: ChunkStore<[u8; 32], Data>, dc: DataChain) -> SecuredData {
+        SecuredData {
+            cs: cs,
+            dc: Arc::new(Mutex::new(dc)),
+            accept_unsigned: false,
+        }
+    }
+
+    /// Sets whether version 0 of a non-ledger `StructuredData` may be put before its owners have
+    /// signed it as its signature policy requires, i.e. while its `validation_state` is
+    /// `Unsigned` or `PartiallySigned`. Such data is provisional: any later version must still be
+    /// validly signed. Off by default, so all structured data put must be valid.
+    pub fn set_accept_unsigned(&mut self, accept_unsigned: bool) {
+        self.accept_unsigned = accept_unsigned;
     }
 
     /// remove all disk based data
     pub fn clear_disk(&self, path: &Path) -> Result<(), Error> {
-        self.dc.lock().unwrap().unlock();
+        self.lock_chain().unlock();
         Ok(fs::remove_dir_all(&path)?)
     }
 
@@ -69,15 +125,12 @@ impl SecuredData {
     /// Add a Vote from another node
     /// If block is valid will return BlockIdentifier
     pub fn add_vote(&mut self, nb: Vote) -> Option<BlockIdentifier> {
-        self.dc.lock().unwrap().add_vote(nb)
+        self.lock_chain().add_vote(nb)
     }
 
     /// Do we have the data on disk.
     pub fn has_data(&self, data_id: &DataIdentifier) -> bool {
-        if let Some(id) = self.dc
-            .lock()
-            .unwrap()
-            .find_name(data_id.name()) {
+        if let Some(id) = self.lock_chain().find_name(data_id.name()) {
             if let Some(name) = id.identifier().name() {
 
                 return self.cs.has(name);
@@ -89,10 +142,14 @@ impl SecuredData {
 
     /// Retrieve data we have on disk, that is also marked valid in the data chain.
     pub fn get(&self, data_id: &DataIdentifier) -> Result<Data, Error> {
-        if let Some(block_id) = self.dc
-            .lock()
-            .unwrap()
-            .find_name(data_id.name()) {
+        let result = self.do_get(data_id);
+        #[cfg(feature = "metrics")]
+        metrics::record_get(&result);
+        result
+    }
+
+    fn do_get(&self, data_id: &DataIdentifier) -> Result<Data, Error> {
+        if let Some(block_id) = self.lock_chain().find_name(data_id.name()) {
             if block_id.valid {
                 if let Some(name) = block_id.identifier().name() {
                     return Ok(self.cs.get(name)?);
@@ -114,7 +171,9 @@ impl SecuredData {
                         let _ = self.cs.delete(hash);
                     }
                 }
-                Data::Immutable(ref _id) => {
+                Data::Immutable(_) |
+                Data::Plain(_) |
+                Data::Mutable(_) => {
                     let _ = self.cs.delete(hash);
                 }
             }
@@ -128,11 +187,29 @@ impl SecuredData {
     /// prevent coupling with keypairs etc.
     ///
     /// **Versioned ledger structured data will be Put and paid for**
+    ///
+    /// Structured data must be signed as its signature policy requires, unless it is accepted as
+    /// provisional under `set_accept_unsigned`.
     pub fn put_data(&mut self, data: &Data) -> Result<BlockIdentifier, Error> {
+        let result = self.do_put_data(data);
+        #[cfg(feature = "audit")]
+        audit::record_data(AuditOperation::Put, data, &result);
+        #[cfg(feature = "metrics")]
+        metrics::record_put(data.payload_size(), &result);
+        result
+    }
+
+    fn do_put_data(&mut self, data: &Data) -> Result<BlockIdentifier, Error> {
         let hash = hash(&serialisation::serialise(&data)?);
         let id = match *data {
-            Data::Immutable(ref im) if *im.name() == hash => BlockIdentifier::ImmutableData(hash),
+            Data::Immutable(ref im) if ct::name_eq(im.name(), &hash) => {
+                BlockIdentifier::ImmutableData(hash)
+            }
             Data::Structured(ref sd) if sd.version() == 0 || sd.ledger() => {
+                let provisional = self.accept_unsigned && sd.version() == 0 && !sd.ledger();
+                if !provisional {
+                    sd.verify_own_signatures()?;
+                }
                 BlockIdentifier::StructuredData(hash, sd.identifier())
             }
             _ => return Err(Error::BadIdentifier),
@@ -147,6 +224,15 @@ impl SecuredData {
     ///
     /// **Will not accept versioned ledger based structuredData !**
     pub fn post_data(&mut self, data: &Data) -> Result<BlockIdentifier, Error> {
+        let result = self.do_post_data(data);
+        #[cfg(feature = "audit")]
+        audit::record_data(AuditOperation::Post, data, &result);
+        #[cfg(feature = "metrics")]
+        metrics::record_put(data.payload_size(), &result);
+        result
+    }
+
+    fn do_post_data(&mut self, data: &Data) -> Result<BlockIdentifier, Error> {
         let hash = hash(&serialisation::serialise(&data)?);
         let id = match *data {
             Data::Structured(ref sd) if !sd.ledger() => {
@@ -174,18 +260,25 @@ impl SecuredData {
     /// Handle Delete data Unless ledger bit is set
     pub fn delete_data(&mut self,
                        data_id: &DataIdentifier,
-                       _sigs: &[Signature])
+                       sigs: &[Signature])
                        -> Result<BlockIdentifier, Error> {
-        if let Some(block_id) = self.dc
-            .lock()
-            .unwrap()
-            .find_name(data_id.name()) {
+        let result = self.do_delete_data(data_id, sigs);
+        #[cfg(feature = "audit")]
+        audit::record(AuditOperation::Delete, *data_id, None, &result);
+        result
+    }
+
+    fn do_delete_data(&mut self,
+                      data_id: &DataIdentifier,
+                      _sigs: &[Signature])
+                      -> Result<BlockIdentifier, Error> {
+        if let Some(block_id) = self.lock_chain().find_name(data_id.name()) {
             // if !block_id.identifier().is_ledger() {
             if let Some(name) = block_id.identifier().name() {
                 let _ = self.cs.delete(name);
             }
 
-            self.dc.lock().unwrap().remove(block_id.identifier());
+            self.lock_chain().remove(block_id.identifier());
             return Ok(block_id.identifier().clone());
             // }
         }
@@ -196,9 +289,7 @@ impl SecuredData {
     /// Restricted to data that has a corresponding valid `Block`.
     pub fn provable_chain(&self, group_size: usize) -> DataChain {
         let keys = self.cs.keys();
-        DataChain::from_blocks(self.dc
-                                   .lock()
-                                   .unwrap()
+        DataChain::from_blocks(self.lock_chain()
                                    .chain()
                                    .iter()
                                    .cloned()
@@ -219,9 +310,7 @@ impl SecuredData {
     /// Remove any data on disk that we do not have a valid Block for
     pub fn purge_disk(&mut self) -> Result<(), Error> {
         let mut invalid_names: HashSet<_> = self.cs.keys().into_iter().collect();
-        for valid_name in self.dc
-            .lock()
-            .unwrap()
+        for valid_name in self.lock_chain()
             .chain()
             .iter()
             .filter(|x| !x.identifier().is_link() && x.valid)
@@ -240,16 +329,14 @@ impl SecuredData {
     /// This will trim (purge invalid) exsiting entries then merge valid entries.
     /// May be used to create a new chain from given chains on node startup.
     pub fn merge_chain(&mut self, chain: &mut DataChain) {
-        self.dc.lock().unwrap().merge_chain(chain);
+        self.lock_chain().merge_chain(chain);
     }
 
     /// How many network events a given proover has been involved in (proover == node)
     /// First missed event stops the count
     // TODO this is very basic and requires some further discussion
     pub fn trust_level(&self, node: &PublicKey) -> usize {
-        self.dc
-            .lock()
-            .unwrap()
+        self.lock_chain()
             .chain()
             .iter()
             .rev()
@@ -263,9 +350,7 @@ impl SecuredData {
     /// match.
     pub fn required_data(&self) -> Vec<BlockIdentifier> {
         let keys = self.cs.keys();
-        self.dc
-            .lock()
-            .unwrap()
+        self.lock_chain()
             .chain()
             .iter()
             .filter(|x| !x.identifier().is_link() && x.valid)
@@ -278,6 +363,18 @@ impl SecuredData {
             .collect_vec()
     }
 
+    /// Names the data on disk is stored under from `start` to `end` inclusive, in order, e.g. to
+    /// find what to relocate when the close group changes.
+    pub fn stored_names_in_range(&self, start: &[u8; 32], end: &[u8; 32]) -> Vec<[u8; 32]> {
+        self.cs.names_in_range(start, end)
+    }
+
+    /// The (at most) `k` names the data on disk is stored under closest to `target`, closest
+    /// first.
+    pub fn stored_names_close_to(&self, target: &[u8; 32], k: usize) -> Vec<[u8; 32]> {
+        self.cs.names_close_to(target, k)
+    }
+
     /// Max space avilable for disk storage (as set by user)
     pub fn max_space(&self) -> u64 {
         self.cs.max_space()
@@ -287,12 +384,40 @@ impl SecuredData {
     pub fn used_space(&self) -> u64 {
         self.cs.used_space()
     }
+
+    /// Locks the data chain, recovering it rather than panicking if another thread panicked while
+    /// holding the lock.
+    fn lock_chain(&self) -> MutexGuard<DataChain> {
+        self.dc.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Checks a chunk read back from disk is still named after its content and, if it is structured
+/// data, validly signed or possibly provisional.
+fn is_intact(name: &[u8; 32], data: &Data) -> bool {
+    let named = serialisation::serialise(data)
+        .map_or(false, |bytes| ct::name_eq(&hash(&bytes), name));
+    named &&
+    match *data {
+        Data::Structured(ref sd) => {
+            (sd.version() == 0 && !sd.ledger()) || sd.verify_own_signatures().is_ok()
+        }
+        Data::Immutable(_) |
+        Data::Plain(_) |
+        Data::Mutable(_) => true,
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use data::StructuredData;
+    use rand;
+    use rustc_serialize::hex::ToHex;
+    use std::fs::File;
+    use std::io::Write;
     use tempdir::TempDir;
+    use test_utils;
 
     #[test]
     fn disk_create_cleanup() {
@@ -308,4 +433,42 @@ mod tests {
         assert!(!storedir.exists());
     }
 
+    #[test]
+    fn restore_after_restart() {
+        let tempdir = unwrap!(TempDir::new("test"));
+        let storedir = tempdir.path().join("test");
+        let keys = test_utils::gen_keypair();
+        let structured_data = |version| {
+            Data::Structured(unwrap!(StructuredData::new(0,
+                                                         rand::random(),
+                                                         version,
+                                                         vec![version as u8],
+                                                         vec![keys.0],
+                                                         vec![],
+                                                         Some(&keys.1),
+                                                         false)))
+        };
+        let (intact, corrupt) = (structured_data(0), structured_data(0));
+        let corrupt_path = {
+            let mut store = unwrap!(SecuredData::create_in_path(storedir.clone(), 10000, 999));
+            let _ = unwrap!(store.put_data(&intact));
+            let _ = unwrap!(store.put_data(&corrupt));
+            let name = hash(&unwrap!(serialisation::serialise(&corrupt)));
+            storedir.join(unwrap!(serialisation::serialise(&name)).to_hex())
+        };
+        unwrap!(unwrap!(File::create(&corrupt_path)).write_all(b"corrupt"));
+
+        let (store, report) = unwrap!(SecuredData::restore_lazily(storedir.clone(), 10000, 999));
+        assert_eq!(report.restored, 2);
+        assert!(report.quarantined.is_empty());
+        drop(store);
+
+        let (store, report) = unwrap!(SecuredData::restore(storedir.clone(), 10000, 999));
+        assert_eq!(report.restored, 1);
+        assert_eq!(report.quarantined.len(), 1);
+        assert!(report.quarantined[0].exists());
+        assert!(!corrupt_path.exists());
+        assert!(store.used_space() > 0);
+        assert_eq!(store.stored_names_in_range(&[0; 32], &[255; 32]).len(), 1);
+    }
 }
@@ -0,0 +1,128 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! Binary serialisation of the crate's types: bincode's encoding of their rustc-serialize
+//! implementations, without a size limit.
+//!
+//! It writes the same bytes as `serialisation`, which the crate used before,
+//! but needs neither that crate's networking and logging dependencies nor anything else which
+//! doesn't build for `wasm32-unknown-unknown`.
+
+use bincode::SizeLimit;
+use bincode::rustc_serialize::{DecodingError, EncodingError, decode_from, encode, encode_into};
+use rustc_serialize::{Decodable, Encodable};
+use std::{error, fmt};
+use std::io::{Cursor, Read, Write};
+
+/// Serialisation error.
+#[derive(Debug)]
+pub enum SerialisationError {
+    /// Error during serialisation (encoding).
+    Serialise(EncodingError),
+    /// Error during deserialisation (decoding).
+    Deserialise(DecodingError),
+}
+
+impl fmt::Display for SerialisationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SerialisationError::Serialise(ref err) => write!(f, "Serialise error: {}", err),
+            SerialisationError::Deserialise(ref err) => write!(f, "Deserialise error: {}", err),
+        }
+    }
+}
+
+impl error::Error for SerialisationError {
+    fn description(&self) -> &str {
+        match *self {
+            SerialisationError::Serialise(_) => "Serialise error",
+            SerialisationError::Deserialise(_) => "Deserialise error",
+        }
+    }
+
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            SerialisationError::Serialise(ref err) => Some(err),
+            SerialisationError::Deserialise(ref err) => Some(err),
+        }
+    }
+}
+
+/// Serialises `data`.
+pub fn serialise<T: Encodable>(data: &T) -> Result<Vec<u8>, SerialisationError> {
+    encode(data, SizeLimit::Infinite).map_err(SerialisationError::Serialise)
+}
+
+/// Deserialises a `T` from the start of `data`. Any bytes following it are ignored.
+pub fn deserialise<T: Decodable>(data: &[u8]) -> Result<T, SerialisationError> {
+    deserialise_from(&mut Cursor::new(data))
+}
+
+/// Serialises `data` into `write`.
+pub fn serialise_into<T: Encodable, W: Write>(data: &T,
+                                              write: &mut W)
+                                              -> Result<(), SerialisationError> {
+    encode_into(data, write, SizeLimit::Infinite).map_err(SerialisationError::Serialise)
+}
+
+/// Deserialises a `T` from `read`, reading no further than its end.
+pub fn deserialise_from<R: Read, T: Decodable>(read: &mut R) -> Result<T, SerialisationError> {
+    decode_from(read, SizeLimit::Infinite).map_err(SerialisationError::Deserialise)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+    use std::io::Cursor;
+
+    #[test]
+    fn serialise_deserialise() {
+        let original = (vec![0u8, 1, 3, 9], vec![-1i64, 888, -8765], "Some-String".to_owned());
+        let serialised = unwrap!(serialise(&original));
+        let deserialised: (Vec<u8>, Vec<i64>, String) = unwrap!(deserialise(&serialised));
+        assert_eq!(deserialised, original);
+
+        // The encoding is bincode's: big-endian, with u64 lengths.
+        assert_eq!(unwrap!(serialise(&(1u16, vec![2u8]))), [0, 1, 0, 0, 0, 0, 0, 0, 0, 1, 2]);
+    }
+
+    #[test]
+    fn serialise_into_deserialise_from() {
+        let original = (vec![0u8, 1, 3, 9], "Some-String".to_owned());
+        let mut serialised = vec![];
+        unwrap!(serialise_into(&original, &mut serialised));
+        unwrap!(serialise_into(&7u32, &mut serialised));
+
+        let mut read = Cursor::new(serialised);
+        let deserialised: (Vec<u8>, String) = unwrap!(deserialise_from(&mut read));
+        assert_eq!(deserialised, original);
+        assert_eq!(unwrap!(deserialise_from::<_, u32>(&mut read)), 7);
+    }
+
+    #[test]
+    fn truncated() {
+        let serialised = unwrap!(serialise(&u64::max_value()));
+        match deserialise::<u64>(&serialised[..4]) {
+            Err(error @ SerialisationError::Deserialise(_)) => {
+                assert!(error.to_string().starts_with("Deserialise error: "));
+                assert!(error.source().is_some());
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
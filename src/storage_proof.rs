@@ -0,0 +1,146 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Proofs of storage
+//! Lets group members audit that a holder still has a chunk without it being transferred: the
+//! auditor sends a fresh random nonce, the holder answers with the hash of the nonce followed by
+//! the content and the auditor checks the answer against its own copy. A holder which has
+//! dropped the content cannot answer, since the nonce is new each time.
+
+use crypto::ct;
+use data::{Data, DataIdentifier};
+use error::Error;
+#[cfg(not(feature = "sodium"))]
+use rand::{OsRng, Rng};
+#[cfg(feature = "sodium")]
+use rust_sodium::randombytes;
+use serialisation::serialise;
+use tiny_keccak::Keccak;
+
+/// Returns the proof that `content` is held, for the challenge `nonce`: the hash of the nonce
+/// followed by the content.
+pub fn prove(nonce: &[u8; 32], content: &[u8]) -> [u8; 32] {
+    let mut sha3 = Keccak::new_sha3_256();
+    sha3.update(nonce);
+    sha3.update(content);
+    let mut proof = [0u8; 32];
+    sha3.finalize(&mut proof);
+    proof
+}
+
+/// Checks `proof` is the proof that `expected` is held, for the challenge `nonce`.
+pub fn verify(nonce: &[u8; 32], proof: &[u8; 32], expected: &[u8]) -> bool {
+    ct::name_eq(&prove(nonce, expected), proof)
+}
+
+/// A challenge to prove the data with an identifier is held.
+#[derive(Hash, Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, RustcEncodable,
+         RustcDecodable)]
+pub struct StorageChallenge {
+    identifier: DataIdentifier,
+    nonce: [u8; 32],
+}
+
+impl StorageChallenge {
+    /// Creates a challenge for the data with `identifier`, with a fresh random nonce.
+    pub fn new(identifier: DataIdentifier) -> StorageChallenge {
+        StorageChallenge {
+            identifier: identifier,
+            nonce: random_nonce(),
+        }
+    }
+
+    /// Identifier of the data to prove is held.
+    pub fn identifier(&self) -> &DataIdentifier {
+        &self.identifier
+    }
+
+    /// The nonce the proof must include.
+    pub fn nonce(&self) -> &[u8; 32] {
+        &self.nonce
+    }
+
+    /// Answers the challenge with the proof that `data` is held, failing with `BadIdentifier` if
+    /// `data` is not the data challenged for.
+    pub fn respond(&self, data: &Data) -> Result<[u8; 32], Error> {
+        if data.identifier() != self.identifier {
+            return Err(Error::BadIdentifier);
+        }
+        Ok(prove(&self.nonce, &serialise(data)?))
+    }
+
+    /// Checks `proof` answers the challenge, given the auditor's own copy of the data.
+    pub fn verify(&self, proof: &[u8; 32], expected: &Data) -> Result<bool, Error> {
+        Ok(expected.identifier() == self.identifier &&
+           verify(&self.nonce, proof, &serialise(expected)?))
+    }
+}
+
+/// Returns a fresh random nonce from rust_sodium, or without the `sodium` feature from the OS.
+#[cfg(feature = "sodium")]
+fn random_nonce() -> [u8; 32] {
+    let mut nonce = [0u8; 32];
+    randombytes::randombytes_into(&mut nonce);
+    nonce
+}
+
+/// Returns a fresh random nonce from rust_sodium, or without the `sodium` feature from the OS.
+#[cfg(not(feature = "sodium"))]
+fn random_nonce() -> [u8; 32] {
+    let mut rng = match OsRng::new() {
+        Ok(rng) => rng,
+        Err(error) => panic!("Failed to open the OS random number generator: {}", error),
+    };
+    let mut nonce = [0u8; 32];
+    rng.fill_bytes(&mut nonce);
+    nonce
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{Data, ImmutableData};
+    use error::Error;
+
+    #[test]
+    fn prove_and_verify() {
+        let nonce = [1; 32];
+        let proof = prove(&nonce, b"content");
+        assert!(verify(&nonce, &proof, b"content"));
+        assert!(!verify(&nonce, &proof, b"other content"));
+        assert!(!verify(&[2; 32], &proof, b"content"));
+    }
+
+    #[test]
+    fn challenge() {
+        let data = Data::Immutable(ImmutableData::new(b"chunk".to_vec()));
+        let other = Data::Immutable(ImmutableData::new(b"other chunk".to_vec()));
+        let challenge = StorageChallenge::new(data.identifier());
+        let proof = unwrap!(challenge.respond(&data));
+        assert!(unwrap!(challenge.verify(&proof, &data)));
+        assert!(!unwrap!(challenge.verify(&proof, &other)));
+        match challenge.respond(&other) {
+            Err(Error::BadIdentifier) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // A fresh challenge needs a fresh proof.
+        let next = StorageChallenge::new(data.identifier());
+        assert!(next.nonce() != challenge.nonce());
+        assert!(!unwrap!(next.verify(&proof, &data)));
+    }
+}
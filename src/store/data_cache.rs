@@ -0,0 +1,612 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use data::{Data, DataIdentifier};
+#[cfg(feature = "metrics")]
+use metrics::{self, Counter};
+use std::collections::{BTreeMap, HashMap};
+use store::tombstone::Tombstone;
+
+/// Default for the longest a tombstone is kept after it arrives, in seconds: one day.
+pub const DEFAULT_MAX_TOMBSTONE_TTL: u64 = 24 * 60 * 60;
+
+/// Hit, miss and eviction counts of a `DataCache` or `ValidationCache`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheMetrics {
+    /// Number of lookups which found the item.
+    pub hits: u64,
+    /// Number of lookups which did not find the item.
+    pub misses: u64,
+    /// Number of items evicted to make room for others.
+    pub evictions: u64,
+}
+
+impl CacheMetrics {
+    /// Returns the fraction of lookups which found the item, or 0 if there have been none.
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+struct Entry {
+    data: Data,
+    last_used: u64,
+}
+
+struct Unanchored {
+    tombstone: Tombstone,
+    arrived: u64,
+}
+
+/// A bounded in-memory cache of `Data` keyed by `DataIdentifier`.
+///
+/// The cache holds at most `max_items` items whose `payload_size()` adds up to at most
+/// `max_bytes`. When either limit would be exceeded the least recently used items are evicted.
+/// A cached `StructuredData` is only replaced by the same or a newer version.
+///
+/// The cache also keeps `Tombstone`s of deleted data until they expire, and at most
+/// `max_tombstone_ttl` after they arrive. While it holds a tombstone the data it deletes is known
+/// to be absent and is not cached.
+///
+/// Only the data a tombstone deletes shows who may delete it, so a tombstone is only trusted once
+/// it has been checked against that data, either cached when the tombstone arrives or inserted
+/// later. Until then it is unanchored: it is neither reported nor keeps any data out, and is
+/// dropped if other data arrives. Anyone can make unanchored tombstones, so they count against
+/// `max_items` and the oldest are dropped first to make room, before any item is evicted.
+pub struct DataCache {
+    max_items: usize,
+    max_bytes: usize,
+    max_tombstone_ttl: u64,
+    used_bytes: usize,
+    clock: u64,
+    entries: HashMap<DataIdentifier, Entry>,
+    recency: BTreeMap<u64, DataIdentifier>,
+    metrics: CacheMetrics,
+    tombstones: HashMap<DataIdentifier, Tombstone>,
+    unanchored: HashMap<DataIdentifier, Unanchored>,
+    arrivals: BTreeMap<u64, DataIdentifier>,
+}
+
+impl DataCache {
+    /// Creates an empty cache holding up to `max_items` items and `max_bytes` of payload, which
+    /// keeps tombstones for at most `DEFAULT_MAX_TOMBSTONE_TTL`.
+    pub fn new(max_items: usize, max_bytes: usize) -> DataCache {
+        DataCache {
+            max_items: max_items,
+            max_bytes: max_bytes,
+            max_tombstone_ttl: DEFAULT_MAX_TOMBSTONE_TTL,
+            used_bytes: 0,
+            clock: 0,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            metrics: CacheMetrics::default(),
+            tombstones: HashMap::new(),
+            unanchored: HashMap::new(),
+            arrivals: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the longest a tombstone is kept after it arrives, in seconds. Tombstones already held
+    /// are unaffected.
+    pub fn set_max_tombstone_ttl(&mut self, max_tombstone_ttl: u64) {
+        self.max_tombstone_ttl = max_tombstone_ttl;
+    }
+
+    /// Returns the cached item and marks it as most recently used.
+    pub fn get(&mut self, identifier: &DataIdentifier) -> Option<&Data> {
+        self.clock += 1;
+        match self.entries.get_mut(identifier) {
+            Some(entry) => {
+                let _ = self.recency.remove(&entry.last_used);
+                let _ = self.recency.insert(self.clock, *identifier);
+                entry.last_used = self.clock;
+                self.metrics.hits += 1;
+                #[cfg(feature = "metrics")]
+                metrics::increment(Counter::CacheHit, 1);
+                Some(&entry.data)
+            }
+            None => {
+                self.metrics.misses += 1;
+                #[cfg(feature = "metrics")]
+                metrics::increment(Counter::CacheMiss, 1);
+                None
+            }
+        }
+    }
+
+    /// Returns the cached item without affecting its recency or the metrics.
+    pub fn peek(&self, identifier: &DataIdentifier) -> Option<&Data> {
+        self.entries.get(identifier).map(|entry| &entry.data)
+    }
+
+    /// Returns `true` if the item is cached.
+    pub fn contains(&self, identifier: &DataIdentifier) -> bool {
+        self.entries.contains_key(identifier)
+    }
+
+    /// Caches `data`, evicting least recently used items as needed.
+    ///
+    /// Returns `false` if the item was not cached, either because its payload alone exceeds
+    /// `max_bytes`, because a newer version of the same `StructuredData` is already cached or
+    /// because the item has been deleted. An unanchored tombstone of the item is anchored if it is
+    /// a valid deletion of it, and dropped otherwise. Unanchored tombstones of other items are
+    /// dropped, oldest first, before any item is evicted to make room.
+    pub fn insert(&mut self, data: Data) -> bool {
+        let identifier = data.identifier();
+        let size = data.payload_size();
+        if size > self.max_bytes || self.max_items == 0 {
+            return false;
+        }
+        if self.tombstones.contains_key(&identifier) {
+            return false;
+        }
+        if let (Some(tombstone), &Data::Structured(ref new)) =
+               (self.remove_unanchored(&identifier), &data) {
+            if tombstone.validate_against(new).is_ok() {
+                let _ = self.tombstones.insert(identifier, tombstone);
+                return false;
+            }
+        }
+        if let (Some(&Entry { data: Data::Structured(ref cached), .. }),
+                &Data::Structured(ref new)) = (self.entries.get(&identifier), &data) {
+            if cached.get_version() > new.get_version() {
+                return false;
+            }
+        }
+        let _ = self.remove(&identifier);
+        while self.entries.len() + self.unanchored.len() >= self.max_items {
+            if !self.drop_oldest_unanchored() {
+                self.evict();
+            }
+        }
+        while self.used_bytes + size > self.max_bytes {
+            self.evict();
+        }
+
+        self.clock += 1;
+        self.used_bytes += size;
+        let _ = self.recency.insert(self.clock, identifier);
+        let _ = self.entries.insert(identifier,
+                                    Entry {
+                                        data: data,
+                                        last_used: self.clock,
+                                    });
+        true
+    }
+
+    /// Removes and returns the cached item.
+    pub fn remove(&mut self, identifier: &DataIdentifier) -> Option<Data> {
+        self.entries.remove(identifier).map(|entry| {
+            let _ = self.recency.remove(&entry.last_used);
+            self.used_bytes -= entry.data.payload_size();
+            entry.data
+        })
+    }
+
+    /// Records that the data `tombstone` deletes is absent, removing it from the cache. `now`, in
+    /// seconds since the Unix epoch, is when it arrives: it is kept for at most
+    /// `max_tombstone_ttl` from then, whatever its own expiry.
+    ///
+    /// If the data is not cached the tombstone can't be checked, so is only held unanchored, in
+    /// place of the oldest unanchored tombstone if the cache is full.
+    ///
+    /// Returns `false` if the tombstone is not a valid deletion of the cached data, or the data is
+    /// already known to be deleted, or an unanchored tombstone of a later version of the same data
+    /// is already held, or it is unanchored and the cache is full of items.
+    pub fn insert_tombstone(&mut self, mut tombstone: Tombstone, now: u64) -> bool {
+        if tombstone.validate().is_err() {
+            return false;
+        }
+        tombstone.limit_expiry(now.saturating_add(self.max_tombstone_ttl));
+        let identifier = tombstone.identifier();
+        if self.tombstones.contains_key(&identifier) {
+            return false;
+        }
+        let anchored = match self.entries.get(&identifier) {
+            Some(&Entry { data: Data::Structured(ref cached), .. }) => {
+                if tombstone.validate_against(cached).is_err() {
+                    return false;
+                }
+                true
+            }
+            Some(_) => return false,
+            None => false,
+        };
+        if anchored {
+            let _ = self.remove(&identifier);
+            let _ = self.remove_unanchored(&identifier);
+            let _ = self.tombstones.insert(identifier, tombstone);
+            return true;
+        }
+        if let Some(held) = self.unanchored.get(&identifier) {
+            if held.tombstone.version() > tombstone.version() {
+                return false;
+            }
+        }
+        let _ = self.remove_unanchored(&identifier);
+        if self.entries.len() + self.unanchored.len() >= self.max_items &&
+           !self.drop_oldest_unanchored() {
+            return false;
+        }
+        self.clock += 1;
+        let _ = self.arrivals.insert(self.clock, identifier);
+        let _ = self.unanchored.insert(identifier,
+                                       Unanchored {
+                                           tombstone: tombstone,
+                                           arrived: self.clock,
+                                       });
+        true
+    }
+
+    /// Returns the unexpired anchored tombstone of the item at `now`, in seconds since the Unix
+    /// epoch.
+    pub fn tombstone(&self, identifier: &DataIdentifier, now: u64) -> Option<&Tombstone> {
+        self.tombstones.get(identifier).and_then(|tombstone| if tombstone.is_expired(now) {
+            None
+        } else {
+            Some(tombstone)
+        })
+    }
+
+    /// Returns `true` if the item is known to have been deleted at `now`, in seconds since the
+    /// Unix epoch. An expired tombstone is dropped.
+    pub fn is_known_absent(&mut self, identifier: &DataIdentifier, now: u64) -> bool {
+        let expired = match self.tombstones.get(identifier) {
+            Some(tombstone) => tombstone.is_expired(now),
+            None => return false,
+        };
+        if expired {
+            let _ = self.tombstones.remove(identifier);
+        }
+        !expired
+    }
+
+    /// Drops the tombstones which have expired at `now`, in seconds since the Unix epoch.
+    pub fn purge_expired_tombstones(&mut self, now: u64) {
+        self.tombstones.retain(|_, tombstone| !tombstone.is_expired(now));
+        let expired: Vec<_> = self.unanchored
+            .values()
+            .filter(|unanchored| unanchored.tombstone.is_expired(now))
+            .map(|unanchored| unanchored.tombstone.identifier())
+            .collect();
+        for identifier in expired {
+            let _ = self.remove_unanchored(&identifier);
+        }
+    }
+
+    /// Number of tombstones held, anchored or not, including expired ones not yet dropped.
+    pub fn tombstone_count(&self) -> usize {
+        self.tombstones.len() + self.unanchored.len()
+    }
+
+    /// Removes all items and tombstones. The metrics are kept.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+        self.tombstones.clear();
+        self.unanchored.clear();
+        self.arrivals.clear();
+        self.used_bytes = 0;
+    }
+
+    /// Number of cached items.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if nothing is cached.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Total `payload_size()` of the cached items.
+    pub fn used_bytes(&self) -> usize {
+        self.used_bytes
+    }
+
+    /// Hit, miss and eviction counts since the cache was created.
+    pub fn metrics(&self) -> CacheMetrics {
+        self.metrics
+    }
+
+    fn remove_unanchored(&mut self, identifier: &DataIdentifier) -> Option<Tombstone> {
+        self.unanchored.remove(identifier).map(|unanchored| {
+            let _ = self.arrivals.remove(&unanchored.arrived);
+            unanchored.tombstone
+        })
+    }
+
+    /// Drops the unanchored tombstone which arrived first. Returns `false` if none is held.
+    fn drop_oldest_unanchored(&mut self) -> bool {
+        let oldest = match self.arrivals.keys().next() {
+            Some(&arrived) => arrived,
+            None => return false,
+        };
+        if let Some(identifier) = self.arrivals.remove(&oldest) {
+            let _ = self.unanchored.remove(&identifier);
+        }
+        true
+    }
+
+    fn evict(&mut self) {
+        let oldest = match self.recency.keys().next() {
+            Some(&last_used) => last_used,
+            None => return,
+        };
+        if let Some(identifier) = self.recency.remove(&oldest) {
+            if let Some(entry) = self.entries.remove(&identifier) {
+                self.used_bytes -= entry.data.payload_size();
+                self.metrics.evictions += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crypto::sign::{PublicKey, SecretKey};
+    use data::{Data, ImmutableData, StructuredData};
+    use rand;
+    use store::tombstone::Tombstone;
+    use test_utils;
+
+    fn immutable(size: usize) -> Data {
+        Data::Immutable(ImmutableData::new((0..size).map(|_| rand::random()).collect()))
+    }
+
+    #[test]
+    fn lru_eviction_by_count() {
+        let mut cache = DataCache::new(2, 1000);
+        let (first, second, third) = (immutable(10), immutable(10), immutable(10));
+        assert!(cache.insert(first.clone()));
+        assert!(cache.insert(second.clone()));
+        // Touch the first item so the second becomes least recently used.
+        assert_eq!(cache.get(&first.identifier()), Some(&first));
+        assert!(cache.insert(third.clone()));
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.contains(&first.identifier()));
+        assert!(!cache.contains(&second.identifier()));
+        assert!(cache.contains(&third.identifier()));
+        assert!(cache.get(&second.identifier()).is_none());
+        assert_eq!(cache.metrics(),
+                   CacheMetrics {
+                       hits: 1,
+                       misses: 1,
+                       evictions: 1,
+                   });
+    }
+
+    #[test]
+    fn lru_eviction_by_bytes() {
+        let mut cache = DataCache::new(10, 100);
+        let items: Vec<_> = (0..4).map(|_| immutable(30)).collect();
+        for item in &items {
+            assert!(cache.insert(item.clone()));
+        }
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.used_bytes(), 90);
+        assert!(cache.peek(&items[0].identifier()).is_none());
+
+        // Too large to ever be cached.
+        assert!(!cache.insert(immutable(101)));
+        assert_eq!(cache.len(), 3);
+
+        // A large item evicts as many as needed.
+        assert!(cache.insert(immutable(100)));
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.used_bytes(), 100);
+        assert_eq!(cache.metrics().evictions, 4);
+
+        cache.clear();
+        assert!(cache.is_empty());
+        assert_eq!(cache.used_bytes(), 0);
+    }
+
+    #[test]
+    fn structured_data_versions() {
+        let keys = test_utils::gen_keypair();
+        let name = rand::random();
+        let version = |version| {
+            Data::Structured(unwrap!(StructuredData::new(0,
+                                                         name,
+                                                         version,
+                                                         vec![version as u8],
+                                                         vec![keys.0],
+                                                         vec![],
+                                                         Some(&keys.1),
+                                                         false)))
+        };
+        let mut cache = DataCache::new(10, 1000);
+        assert!(cache.insert(version(1)));
+        assert!(cache.insert(version(2)));
+        assert!(!cache.insert(version(1)));
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.used_bytes(), 1);
+        assert_eq!(cache.peek(&version(2).identifier()), Some(&version(2)));
+        assert_eq!(cache.remove(&version(2).identifier()), Some(version(2)));
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn tombstones() {
+        let keys = test_utils::gen_keypair();
+        let structured_data = unwrap!(StructuredData::new(0,
+                                                          rand::random(),
+                                                          1,
+                                                          vec![1],
+                                                          vec![keys.0],
+                                                          vec![],
+                                                          Some(&keys.1),
+                                                          false));
+        let identifier = structured_data.identifier();
+        let deletion = unwrap!(structured_data.delete(&keys.1));
+        let mut cache = DataCache::new(10, 1000);
+        assert!(cache.insert(Data::Structured(structured_data.clone())));
+        assert!(!cache.is_known_absent(&identifier, 0));
+
+        // A tombstone removes the cached item and keeps it out until it expires.
+        assert!(cache.insert_tombstone(unwrap!(Tombstone::new(deletion.clone(), 100)), 0));
+        assert!(!cache.contains(&identifier));
+        assert!(!cache.insert(Data::Structured(structured_data.clone())));
+        assert!(cache.is_known_absent(&identifier, 99));
+        assert_eq!(unwrap!(cache.tombstone(&identifier, 99)).deletion(), &deletion);
+        assert!(cache.tombstone(&identifier, 100).is_none());
+        assert_eq!(cache.tombstone_count(), 1);
+
+        cache.purge_expired_tombstones(99);
+        assert_eq!(cache.tombstone_count(), 1);
+        assert!(!cache.is_known_absent(&identifier, 100));
+        assert_eq!(cache.tombstone_count(), 0);
+        assert!(cache.insert(Data::Structured(structured_data)));
+
+        assert!(cache.insert_tombstone(unwrap!(Tombstone::new(deletion, 100)), 0));
+        cache.purge_expired_tombstones(100);
+        assert_eq!(cache.tombstone_count(), 0);
+    }
+
+    #[test]
+    fn forged_tombstones() {
+        let keys = test_utils::gen_keypair();
+        let forger = test_utils::gen_keypair();
+        let name = rand::random();
+        let version = |owner: &(PublicKey, SecretKey)| {
+            unwrap!(StructuredData::new(0,
+                                        name,
+                                        1,
+                                        vec![1],
+                                        vec![owner.0],
+                                        vec![],
+                                        Some(&owner.1),
+                                        false))
+        };
+        let structured_data = version(&keys);
+        let identifier = structured_data.identifier();
+        // Signed by the forger as the previous owner, so valid on its own.
+        let forged = unwrap!(Tombstone::new(unwrap!(version(&forger).delete(&forger.1)), 100));
+        let genuine = unwrap!(Tombstone::new(unwrap!(structured_data.delete(&keys.1)), 100));
+
+        // A forged tombstone of cached data is refused.
+        let mut cache = DataCache::new(10, 1000);
+        assert!(cache.insert(Data::Structured(structured_data.clone())));
+        assert!(!cache.insert_tombstone(forged.clone(), 0));
+        assert!(cache.contains(&identifier));
+        assert!(!cache.is_known_absent(&identifier, 0));
+        assert_eq!(cache.tombstone_count(), 0);
+
+        // Without the data it is held unanchored, but neither reported nor keeping the data out.
+        let mut cache = DataCache::new(10, 1000);
+        assert!(cache.insert_tombstone(forged, 0));
+        assert!(!cache.is_known_absent(&identifier, 0));
+        assert!(cache.tombstone(&identifier, 0).is_none());
+        assert!(cache.insert(Data::Structured(structured_data.clone())));
+        assert_eq!(cache.tombstone_count(), 0);
+
+        // A genuine unanchored tombstone is anchored by the data it deletes.
+        let mut cache = DataCache::new(10, 1000);
+        assert!(cache.insert_tombstone(genuine.clone(), 0));
+        assert!(!cache.is_known_absent(&identifier, 0));
+        assert!(!cache.insert(Data::Structured(structured_data)));
+        assert!(cache.is_known_absent(&identifier, 0));
+        assert_eq!(unwrap!(cache.tombstone(&identifier, 0)), &genuine);
+    }
+
+    #[test]
+    fn tombstone_ttl_is_capped() {
+        let keys = test_utils::gen_keypair();
+        let structured_data = unwrap!(StructuredData::new(0,
+                                                          rand::random(),
+                                                          1,
+                                                          vec![1],
+                                                          vec![keys.0],
+                                                          vec![],
+                                                          Some(&keys.1),
+                                                          false));
+        let identifier = structured_data.identifier();
+        let deletion = unwrap!(structured_data.delete(&keys.1));
+        let mut cache = DataCache::new(10, 1000);
+        cache.set_max_tombstone_ttl(50);
+        assert!(cache.insert(Data::Structured(structured_data)));
+
+        // A tombstone asking to be kept forever is kept for the cache's maximum.
+        assert!(cache.insert_tombstone(unwrap!(Tombstone::new(deletion, u64::max_value())), 1000));
+        assert_eq!(unwrap!(cache.tombstone(&identifier, 1000)).expires_at(), 1050);
+        assert!(cache.is_known_absent(&identifier, 1049));
+        assert!(!cache.is_known_absent(&identifier, 1050));
+    }
+
+    #[test]
+    fn unanchored_tombstone_flood() {
+        let max_items = 10;
+        let mut cache = DataCache::new(max_items, 1000);
+        let items: Vec<_> = (0..max_items / 2).map(|_| immutable(10)).collect();
+        for item in &items {
+            assert!(cache.insert(item.clone()));
+        }
+
+        // Tombstones of data the cache doesn't hold only fill the room the items leave.
+        let deleted = |version: u64| {
+            let keys = test_utils::gen_keypair();
+            let structured_data = unwrap!(StructuredData::new(0,
+                                                              rand::random(),
+                                                              version,
+                                                              vec![1],
+                                                              vec![keys.0],
+                                                              vec![],
+                                                              Some(&keys.1),
+                                                              false));
+            let deletion = unwrap!(structured_data.delete(&keys.1));
+            (structured_data, unwrap!(Tombstone::new(deletion, u64::max_value())))
+        };
+        let flood: Vec<_> = (0..3 * max_items as u64).map(&deleted).collect();
+        for &(_, ref tombstone) in &flood {
+            assert!(cache.insert_tombstone(tombstone.clone(), 0));
+            assert!(cache.len() + cache.tombstone_count() <= max_items);
+        }
+        assert_eq!(cache.len(), items.len());
+        assert!(items.iter().all(|item| cache.contains(&item.identifier())));
+        assert_eq!(cache.tombstone_count(), max_items - items.len());
+
+        // The oldest were dropped first, so only the latest are anchored by their data.
+        let (ref first, _) = flood[0];
+        let (ref last, _) = flood[flood.len() - 1];
+        assert!(!cache.insert(Data::Structured(last.clone())));
+        assert!(cache.is_known_absent(&last.identifier(), 0));
+        assert!(cache.insert(Data::Structured(first.clone())));
+        assert_eq!(cache.len(), items.len() + 1);
+        // One anchored tombstone and the unanchored ones left.
+        assert_eq!(cache.tombstone_count(), max_items - items.len());
+
+        // Items take the place of unanchored tombstones before evicting each other.
+        while cache.len() < max_items {
+            assert!(cache.insert(immutable(10)));
+        }
+        assert_eq!(cache.tombstone_count(), 1);
+        assert_eq!(cache.metrics().evictions, 0);
+
+        // And once the cache is full of items, an unanchored tombstone is refused.
+        assert!(!cache.insert_tombstone(deleted(0).1, 0));
+        assert_eq!(cache.len(), max_items);
+        assert_eq!(cache.tombstone_count(), 1);
+    }
+}
@@ -0,0 +1,414 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Asynchronous data stores
+//! The `DataStore` trait lets clients fetch, store and delete data without knowing where it is
+//! held: in memory, on local disk, behind a proxy to remote vaults or in a test double. Its
+//! methods return boxed futures (feature `async`), so slow backends need not block the caller.
+//!
+//! - `MemoryStore` : holds data in memory.
+//! - `DiskStore` : holds data as files in a directory (feature `fs`).
+//! - `PriorityStore` : combines stores, fetching from the first in priority order which has the
+//!                     data and storing to and deleting from all of them.
+//!
+//! Only `StructuredData` may be deleted, with a `Tombstone` proving its owners deleted it.
+
+#[cfg(feature = "audit")]
+use audit::{self, AuditOperation};
+#[cfg(feature = "fs")]
+use chunk_store::{ChunkStore, RestoreReport};
+use data::{Data, DataIdentifier};
+#[cfg(feature = "fs")]
+use data::envelope;
+use error::Error;
+use futures::{Future, future};
+#[cfg(feature = "metrics")]
+use metrics;
+use std::collections::HashMap;
+#[cfg(feature = "fs")]
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use store::tombstone::Tombstone;
+
+/// The future returned by the methods of a `DataStore`.
+pub type StoreFuture<T> = Box<Future<Item = T, Error = Error> + Send>;
+
+/// A store of data, addressed by `DataIdentifier`.
+pub trait DataStore: Send + Sync {
+    /// Fetches the data identified by `identifier`, failing with `NoFile` if it is not held.
+    fn get(&self, identifier: DataIdentifier) -> StoreFuture<Data>;
+
+    /// Stores `data`, replacing any data held with the same identifier.
+    ///
+    /// Fails as `Data::validate` does if `data` is invalid, e.g. immutable data whose name is not
+    /// the hash of its value.
+    fn put(&self, data: Data) -> StoreFuture<()>;
+
+    /// Deletes the data identified by `identifier`, as authorised by `proof`.
+    ///
+    /// Fails with `BadIdentifier` if `proof` is not for `identifier` or the data held is not
+    /// `StructuredData`, and fails as `Tombstone::validate_against` does if the proof is not a
+    /// valid successor of the data held. Deleting data which is not held succeeds.
+    ///
+    /// Which owners may delete data is only known from the data itself, so a store which does
+    /// not hold the data accepts any validly signed proof.
+    fn delete(&self, identifier: DataIdentifier, proof: Tombstone) -> StoreFuture<()>;
+}
+
+/// Checks `proof` authorises deleting `identifier`, held as `stored` if at all.
+fn check_deletion(identifier: &DataIdentifier,
+                  proof: &Tombstone,
+                  stored: Option<&Data>)
+                  -> Result<(), Error> {
+    if proof.identifier() != *identifier {
+        return Err(Error::BadIdentifier);
+    }
+    proof.validate()?;
+    match stored {
+        None => Ok(()),
+        Some(&Data::Structured(ref previous)) => proof.validate_against(previous),
+        Some(_) => Err(Error::BadIdentifier),
+    }
+}
+
+/// A `DataStore` holding data in memory.
+#[derive(Default)]
+pub struct MemoryStore {
+    data: Mutex<HashMap<DataIdentifier, Data>>,
+}
+
+impl MemoryStore {
+    /// Creates an empty store.
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+
+    /// Returns the number of items held.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Is the store empty.
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn lock(&self) -> MutexGuard<HashMap<DataIdentifier, Data>> {
+        // A panic while the lock was held cannot leave the map half-updated.
+        self.data.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl DataStore for MemoryStore {
+    fn get(&self, identifier: DataIdentifier) -> StoreFuture<Data> {
+        let result = self.lock().get(&identifier).cloned().ok_or(Error::NoFile);
+        #[cfg(feature = "metrics")]
+        metrics::record_get(&result);
+        Box::new(future::result(result))
+    }
+
+    fn put(&self, data: Data) -> StoreFuture<()> {
+        let result = data.validate();
+        #[cfg(feature = "audit")]
+        audit::record_data(AuditOperation::Put, &data, &result);
+        #[cfg(feature = "metrics")]
+        metrics::record_put(data.payload_size(), &result);
+        if result.is_ok() {
+            let _ = self.lock().insert(data.identifier(), data);
+        }
+        Box::new(future::result(result))
+    }
+
+    fn delete(&self, identifier: DataIdentifier, proof: Tombstone) -> StoreFuture<()> {
+        let mut data = self.lock();
+        let result = check_deletion(&identifier, &proof, data.get(&identifier));
+        if result.is_ok() {
+            let _ = data.remove(&identifier);
+        }
+        #[cfg(feature = "audit")]
+        audit::record(AuditOperation::Delete, identifier, Some(proof.version()), &result);
+        Box::new(future::result(result))
+    }
+}
+
+/// A `DataStore` holding data as files in a directory, using at most `max_space` bytes.
+///
+/// The data is written in a versioned `envelope`, so files written in an earlier format, or
+/// before there were envelopes, are still read.
+#[cfg(feature = "fs")]
+pub struct DiskStore {
+    chunks: Mutex<ChunkStore<DataIdentifier, Data>>,
+}
+
+#[cfg(feature = "fs")]
+impl DiskStore {
+    /// Creates an empty store in `root`, which is created if it doesn't exist.
+    pub fn new(root: PathBuf, max_space: u64) -> Result<DiskStore, Error> {
+        Ok(DiskStore { chunks: Mutex::new(ChunkStore::new(root, max_space)?) })
+    }
+
+    /// Reopens a store left in `root` by an earlier run, as `ChunkStore::open` does.
+    pub fn open(root: PathBuf, max_space: u64) -> Result<(DiskStore, RestoreReport), Error> {
+        let (chunks, report) = ChunkStore::open(root, max_space)?;
+        Ok((DiskStore { chunks: Mutex::new(chunks) }, report))
+    }
+
+    fn with_chunks<T, F>(&self, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut ChunkStore<DataIdentifier, Data>) -> Result<T, Error>
+    {
+        let mut chunks = self.chunks.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        f(&mut chunks)
+    }
+}
+
+#[cfg(feature = "fs")]
+fn read_chunk(chunks: &ChunkStore<DataIdentifier, Data>,
+              identifier: &DataIdentifier)
+              -> Result<Data, Error> {
+    envelope::decode(&chunks.get_bytes(identifier)?)
+}
+
+#[cfg(feature = "fs")]
+impl DataStore for DiskStore {
+    fn get(&self, identifier: DataIdentifier) -> StoreFuture<Data> {
+        let result = self.with_chunks(|chunks| read_chunk(chunks, &identifier));
+        #[cfg(feature = "metrics")]
+        metrics::record_get(&result);
+        Box::new(future::result(result))
+    }
+
+    fn put(&self, data: Data) -> StoreFuture<()> {
+        let result = self.with_chunks(|chunks| {
+            data.validate()?;
+            chunks.put_bytes(&data.identifier(), &envelope::encode(&data)?)
+        });
+        #[cfg(feature = "audit")]
+        audit::record_data(AuditOperation::Put, &data, &result);
+        #[cfg(feature = "metrics")]
+        metrics::record_put(data.payload_size(), &result);
+        Box::new(future::result(result))
+    }
+
+    fn delete(&self, identifier: DataIdentifier, proof: Tombstone) -> StoreFuture<()> {
+        let result = self.with_chunks(|chunks| {
+            let stored = if chunks.has(&identifier) {
+                Some(read_chunk(chunks, &identifier)?)
+            } else {
+                None
+            };
+            check_deletion(&identifier, &proof, stored.as_ref())?;
+            chunks.delete(&identifier)
+        });
+        #[cfg(feature = "audit")]
+        audit::record(AuditOperation::Delete, identifier, Some(proof.version()), &result);
+        Box::new(future::result(result))
+    }
+}
+
+/// A `DataStore` combining others in priority order, e.g. a local store before a remote one.
+///
+/// `get` tries each store in turn, from the highest priority, and returns the data from the
+/// first which has it; if none does, it fails with `NoFile`. `put` and `delete` go to every store
+/// and fail if any of them does.
+#[derive(Default)]
+pub struct PriorityStore {
+    stores: Vec<(u32, Arc<DataStore>)>,
+}
+
+impl PriorityStore {
+    /// Creates a store combining no stores.
+    pub fn new() -> PriorityStore {
+        PriorityStore::default()
+    }
+
+    /// Adds `store` with priority `priority`. Stores of higher priority are tried first; stores
+    /// of equal priority in the order they were added.
+    pub fn add(&mut self, priority: u32, store: Arc<DataStore>) {
+        let index = self.stores.iter().take_while(|&&(other, _)| other >= priority).count();
+        self.stores.insert(index, (priority, store));
+    }
+
+    /// Returns the number of stores combined.
+    pub fn len(&self) -> usize {
+        self.stores.len()
+    }
+
+    /// Does the store combine no stores.
+    pub fn is_empty(&self) -> bool {
+        self.stores.is_empty()
+    }
+}
+
+fn get_from(stores: Vec<Arc<DataStore>>, identifier: DataIdentifier) -> StoreFuture<Data> {
+    let mut remaining = stores.into_iter();
+    match remaining.next() {
+        None => Box::new(future::err(Error::NoFile)),
+        Some(store) => {
+            Box::new(store.get(identifier)
+                .or_else(move |_| get_from(remaining.collect(), identifier)))
+        }
+    }
+}
+
+impl DataStore for PriorityStore {
+    fn get(&self, identifier: DataIdentifier) -> StoreFuture<Data> {
+        get_from(self.stores.iter().map(|&(_, ref store)| store.clone()).collect(),
+                 identifier)
+    }
+
+    fn put(&self, data: Data) -> StoreFuture<()> {
+        let puts: Vec<_> = self.stores
+            .iter()
+            .map(|&(_, ref store)| store.put(data.clone()))
+            .collect();
+        Box::new(future::join_all(puts).map(|_| ()))
+    }
+
+    fn delete(&self, identifier: DataIdentifier, proof: Tombstone) -> StoreFuture<()> {
+        let deletes: Vec<_> = self.stores
+            .iter()
+            .map(|&(_, ref store)| store.delete(identifier, proof.clone()))
+            .collect();
+        Box::new(future::join_all(deletes).map(|_| ()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{Data, DataIdentifier, ImmutableData, StructuredData};
+    use error::Error;
+    use futures::Future;
+    use rand;
+    use std::sync::Arc;
+    use store::tombstone::Tombstone;
+    use test_utils;
+
+    fn structured_data(name: [u8; 32]) -> (StructuredData, Tombstone) {
+        let keys = test_utils::gen_keypair();
+        let structured_data = unwrap!(StructuredData::new(0,
+                                                          name,
+                                                          0,
+                                                          vec![1, 2, 3],
+                                                          vec![keys.0],
+                                                          vec![],
+                                                          Some(&keys.1),
+                                                          false));
+        let tombstone = unwrap!(Tombstone::new(unwrap!(structured_data.delete(&keys.1)), 100));
+        (structured_data, tombstone)
+    }
+
+    fn check_store<S: DataStore>(store: &S) {
+        let immutable = Data::Immutable(ImmutableData::new(vec![4, 5, 6]));
+        let (structured_data, tombstone) = structured_data(rand::random());
+        let structured = Data::Structured(structured_data);
+        unwrap!(store.put(immutable.clone()).wait());
+        unwrap!(store.put(structured.clone()).wait());
+        assert_eq!(unwrap!(store.get(immutable.identifier()).wait()), immutable);
+        assert_eq!(unwrap!(store.get(structured.identifier()).wait()), structured);
+
+        match store.delete(immutable.identifier(), tombstone.clone()).wait() {
+            Err(Error::BadIdentifier) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        unwrap!(store.delete(structured.identifier(), tombstone.clone()).wait());
+        match store.get(structured.identifier()).wait() {
+            Err(Error::NoFile) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        unwrap!(store.delete(structured.identifier(), tombstone).wait());
+
+        // A tombstone by other owners of data with the same identifier does not delete it.
+        let name = rand::random();
+        let (other, other_tombstone) = structured_data(name);
+        let other = Data::Structured(other);
+        unwrap!(store.put(other.clone()).wait());
+        let (_, forged) = structured_data(name);
+        assert!(store.delete(other.identifier(), forged).wait().is_err());
+        assert_eq!(unwrap!(store.get(other.identifier()).wait()), other);
+        unwrap!(store.delete(other.identifier(), other_tombstone).wait());
+
+        let too_large = Data::Immutable(ImmutableData::new(vec![0; ImmutableData::MAX_BYTES + 1]));
+        match store.put(too_large.clone()).wait() {
+            Err(Error::DataTooLarge) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert!(store.get(too_large.identifier()).wait().is_err());
+    }
+
+    #[test]
+    fn memory_store() {
+        let store = MemoryStore::new();
+        check_store(&store);
+        assert_eq!(store.len(), 1);
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn disk_store() {
+        use tempdir::TempDir;
+
+        let root = unwrap!(TempDir::new("test"));
+        check_store(&unwrap!(DiskStore::new(root.path().to_path_buf(), 1024 * 1024)));
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn disk_store_formats() {
+        use serialisation::serialise;
+        use tempdir::TempDir;
+
+        let root = unwrap!(TempDir::new("test"));
+        let path = root.path().to_path_buf();
+        let data = Data::Immutable(ImmutableData::new(vec![1, 2, 3]));
+        {
+            // A chunk written before there were envelopes, with immutable data as its value only.
+            let legacy = unwrap!(serialise(&(1u32, vec![1u8, 2, 3])));
+            let mut chunks = unwrap!(ChunkStore::<DataIdentifier, Data>::new(path.clone(), 1024));
+            unwrap!(chunks.put_bytes(&data.identifier(), &legacy));
+        }
+        let (store, report) = unwrap!(DiskStore::open(path, 1024 * 1024));
+        assert_eq!(report.restored, 1);
+        assert_eq!(unwrap!(store.get(data.identifier()).wait()), data);
+
+        let other = Data::Immutable(ImmutableData::new(vec![4, 5, 6]));
+        unwrap!(store.put(other.clone()).wait());
+        let bytes = unwrap!(store.with_chunks(|chunks| chunks.get_bytes(&other.identifier())));
+        assert_eq!(bytes, unwrap!(envelope::encode(&other)));
+        assert_eq!(unwrap!(store.get(other.identifier()).wait()), other);
+    }
+
+    #[test]
+    fn priority_store() {
+        let local = Arc::new(MemoryStore::new());
+        let remote = Arc::new(MemoryStore::new());
+        let mut store = PriorityStore::new();
+        store.add(1, remote.clone());
+        store.add(2, local.clone());
+        check_store(&store);
+        assert_eq!(local.len(), 1);
+        assert_eq!(remote.len(), 1);
+
+        let data = Data::Immutable(ImmutableData::new(vec![7]));
+        unwrap!(remote.put(data.clone()).wait());
+        assert_eq!(unwrap!(store.get(data.identifier()).wait()), data);
+        match store.get(DataIdentifier::Immutable(rand::random())).wait() {
+            Err(Error::NoFile) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,60 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Stores
+//!
+//! - `DataCache` : a bounded in-memory cache of `Data` keyed by `DataIdentifier`, evicting the
+//!                 least recently used items first.
+//! - `DataStore` : an asynchronous store of data with in-memory, on-disk and priority-ordered
+//!                 implementations (feature `async`).
+//! - `RefCountedStore` : a disk-based store of chunks which counts references to each chunk and
+//!                       only removes a chunk with its last reference.
+//! - `Scrubber` : walks the chunks of a `SharedChunkStore` at a set rate, checking each is intact
+//!                and quarantining those found corrupt.
+//! - `SharedChunkStore` : a disk-based store of chunks shared between threads, which reads or
+//!                        fetches a chunk once however many threads ask for it at the same time.
+//! - `Tombstone` : a signed record that data has been deleted, letting caches know it is absent.
+
+/// In-memory LRU cache of fetched data
+pub mod data_cache;
+/// Asynchronous stores with pluggable backends
+#[cfg(feature = "async")]
+pub mod data_store;
+/// Disk-based chunk store with reference counting
+#[cfg(feature = "fs")]
+pub mod ref_counted_store;
+/// Background integrity checking of stored chunks
+#[cfg(feature = "fs")]
+pub mod scrubber;
+/// Disk-based chunk store shared between threads, with coalesced reads and fetches
+#[cfg(feature = "fs")]
+pub mod shared_chunk_store;
+/// Owner-authorised records of deleted data
+pub mod tombstone;
+
+pub use store::data_cache::{CacheMetrics, DataCache};
+#[cfg(feature = "async")]
+pub use store::data_store::{DataStore, MemoryStore, PriorityStore, StoreFuture};
+#[cfg(all(feature = "async", feature = "fs"))]
+pub use store::data_store::DiskStore;
+#[cfg(feature = "fs")]
+pub use store::ref_counted_store::RefCountedStore;
+#[cfg(feature = "fs")]
+pub use store::scrubber::{ScrubAction, ScrubConfig, ScrubProgress, Scrubber, ScrubberHandle};
+#[cfg(feature = "fs")]
+pub use store::shared_chunk_store::SharedChunkStore;
+pub use store::tombstone::Tombstone;
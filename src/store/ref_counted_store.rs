@@ -0,0 +1,198 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use chunk_store::ChunkStore;
+use error::Error;
+use rustc_serialize::{Decodable, Encodable};
+use serialisation::{deserialise, serialise};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+const CHUNKS_DIR: &'static str = "chunks";
+const REF_COUNTS_FILE: &'static str = "ref_counts";
+
+/// A disk-based store of chunks which may be referenced many times, e.g. by several `DataMap`s
+/// sharing a chunk.
+///
+/// Each `put` of a key adds a reference and each `delete` removes one; a chunk is only removed
+/// from disk when its last reference is. The reference counts are kept on disk alongside the
+/// chunks, so reopening the store with `open` carries on counting where it left off.
+pub struct RefCountedStore<Key, Value> {
+    root: PathBuf,
+    chunks: ChunkStore<Key, Value>,
+    ref_counts: BTreeMap<Key, u64>,
+}
+
+impl<Key, Value> RefCountedStore<Key, Value>
+    where Key: Clone + Ord + Decodable + Encodable,
+          Value: Decodable + Encodable
+{
+    /// Creates a new, empty store in `root` with `max_space` allowed storage space for chunks.
+    pub fn new(root: PathBuf, max_space: u64) -> Result<RefCountedStore<Key, Value>, Error> {
+        let store = RefCountedStore {
+            chunks: ChunkStore::new(root.join(CHUNKS_DIR), max_space)?,
+            root: root,
+            ref_counts: BTreeMap::new(),
+        };
+        store.save_ref_counts()?;
+        Ok(store)
+    }
+
+    /// Opens a store previously created in `root`, with its chunks and reference counts.
+    pub fn open(root: PathBuf, max_space: u64) -> Result<RefCountedStore<Key, Value>, Error> {
+        let mut contents = Vec::new();
+        let _ = File::open(root.join(REF_COUNTS_FILE))?.read_to_end(&mut contents)?;
+        Ok(RefCountedStore {
+            chunks: ChunkStore::from_path(root.join(CHUNKS_DIR), max_space)?,
+            ref_counts: deserialise(&contents)?,
+            root: root,
+        })
+    }
+
+    /// Adds a reference to the chunk under `key`, storing `value` if it is the first one, and
+    /// returns the number of references.
+    pub fn put(&mut self, key: &Key, value: &Value) -> Result<u64, Error> {
+        let count = self.ref_count(key) + 1;
+        if count == 1 {
+            self.chunks.put(key, value)?;
+        }
+        let _ = self.ref_counts.insert(key.clone(), count);
+        if let Err(error) = self.save_ref_counts() {
+            if count == 1 {
+                let _ = self.chunks.delete(key);
+            }
+            self.release(key, count - 1);
+            return Err(error);
+        }
+        Ok(count)
+    }
+
+    /// Removes a reference to the chunk under `key`, removing the chunk itself if it was the last
+    /// one, and returns the number of references left.
+    ///
+    /// Fails with `NoFile` if there is no reference to remove.
+    pub fn delete(&mut self, key: &Key) -> Result<u64, Error> {
+        let count = match self.ref_count(key) {
+            0 => return Err(Error::NoFile),
+            count => count - 1,
+        };
+        if count == 0 {
+            self.chunks.delete(key)?;
+        }
+        self.release(key, count);
+        self.save_ref_counts()?;
+        Ok(count)
+    }
+
+    /// Returns the chunk stored under `key`.
+    pub fn get(&self, key: &Key) -> Result<Value, Error> {
+        self.chunks.get(key)
+    }
+
+    /// Returns whether a chunk is stored under `key`.
+    pub fn has(&self, key: &Key) -> bool {
+        self.ref_counts.contains_key(key)
+    }
+
+    /// Returns the number of references to the chunk under `key`.
+    pub fn ref_count(&self, key: &Key) -> u64 {
+        self.ref_counts.get(key).cloned().unwrap_or(0)
+    }
+
+    /// Returns the keys of the chunks stored.
+    pub fn keys(&self) -> Vec<Key> {
+        self.ref_counts.keys().cloned().collect()
+    }
+
+    /// Returns the amount of storage space used by chunks since the store was created or opened.
+    pub fn used_space(&self) -> u64 {
+        self.chunks.used_space()
+    }
+
+    /// Sets the count of `key` to `count`, forgetting it at zero.
+    fn release(&mut self, key: &Key, count: u64) {
+        let _ = if count == 0 {
+            self.ref_counts.remove(key)
+        } else {
+            self.ref_counts.insert(key.clone(), count)
+        };
+    }
+
+    /// Writes the reference counts to a temporary file and renames it over the old one, so a
+    /// crash leaves either the old or the new counts.
+    fn save_ref_counts(&self) -> Result<(), Error> {
+        let path = self.root.join(REF_COUNTS_FILE);
+        let temp_path = path.with_extension("tmp");
+        let mut file = File::create(&temp_path)?;
+        file.write_all(&serialise(&self.ref_counts)?)?;
+        file.sync_all()?;
+        fs::rename(temp_path, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use error::Error;
+    use tempdir::TempDir;
+
+    #[test]
+    fn chunks_removed_at_zero_references() {
+        let root = unwrap!(TempDir::new("test"));
+        let path = root.path().to_path_buf();
+        let mut store = unwrap!(RefCountedStore::<u64, Vec<u8>>::new(path, 1000));
+        assert_eq!(unwrap!(store.put(&1, &vec![1; 10])), 1);
+        assert_eq!(unwrap!(store.put(&1, &vec![1; 10])), 2);
+        assert_eq!(unwrap!(store.put(&2, &vec![2; 10])), 1);
+        assert_eq!(store.keys(), vec![1, 2]);
+
+        assert_eq!(unwrap!(store.delete(&1)), 1);
+        assert_eq!(unwrap!(store.get(&1)), vec![1; 10]);
+        assert_eq!(unwrap!(store.delete(&1)), 0);
+        assert!(!store.has(&1));
+        match store.get(&1) {
+            Err(Error::NoFile) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match store.delete(&1) {
+            Err(Error::NoFile) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert_eq!(unwrap!(store.get(&2)), vec![2; 10]);
+    }
+
+    #[test]
+    fn ref_counts_survive_reopening() {
+        let root = unwrap!(TempDir::new("test"));
+        let path = root.path().to_path_buf();
+        {
+            let mut store = unwrap!(RefCountedStore::<u64, Vec<u8>>::new(path.clone(), 1000));
+            let _ = unwrap!(store.put(&1, &vec![1; 10]));
+            let _ = unwrap!(store.put(&1, &vec![1; 10]));
+        }
+
+        let mut store = unwrap!(RefCountedStore::<u64, Vec<u8>>::open(path, 1000));
+        assert_eq!(store.ref_count(&1), 2);
+        assert_eq!(unwrap!(store.delete(&1)), 1);
+        assert_eq!(unwrap!(store.get(&1)), vec![1; 10]);
+        assert_eq!(unwrap!(store.delete(&1)), 0);
+        assert!(store.keys().is_empty());
+    }
+}
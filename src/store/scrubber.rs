@@ -0,0 +1,393 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use data::{Data, DataIdentifier};
+use error::Error;
+use rustc_serialize::{Decodable, Encodable};
+use serialisation::serialise;
+use sha3::hash;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use store::SharedChunkStore;
+
+/// How fast a `Scrubber` works through a store: `batch_size` chunks are checked at a time, with a
+/// pause of `interval` after each batch when running in the background.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScrubConfig {
+    /// Number of chunks checked in each batch.
+    pub batch_size: usize,
+    /// Pause between batches.
+    pub interval: Duration,
+}
+
+impl Default for ScrubConfig {
+    /// 16 chunks a second.
+    fn default() -> ScrubConfig {
+        ScrubConfig {
+            batch_size: 16,
+            interval: Duration::from_secs(1),
+        }
+    }
+}
+
+/// What to do with a chunk found corrupt, as decided by the callback given to the `Scrubber`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScrubAction {
+    /// Move the chunk into the store's `lost+found` directory, so it is no longer served.
+    Quarantine,
+    /// Leave the chunk where it is, e.g. to replace it with a good copy fetched from elsewhere.
+    Keep,
+}
+
+/// Progress of a `Scrubber` through the store.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ScrubProgress {
+    /// Number of complete passes over the store.
+    pub passes: u64,
+    /// Number of chunks in the store when the current pass began.
+    pub chunks_in_pass: u64,
+    /// Number of chunks checked so far in the current pass.
+    pub checked_in_pass: u64,
+    /// Number of chunks checked in all passes.
+    pub checked: u64,
+    /// Number of chunks found corrupt in all passes.
+    pub corrupt: u64,
+    /// Number of corrupt chunks quarantined.
+    pub quarantined: u64,
+}
+
+/// A key data is stored under, which must match the data.
+///
+/// The name of immutable data is worked out from its value when it is read, so bit rot in the
+/// value can only be told from the key it was stored under.
+pub trait ScrubKey {
+    /// Checks `data` matches this key, failing with `BadIdentifier` if not.
+    fn check(&self, data: &Data) -> Result<(), Error>;
+}
+
+impl ScrubKey for [u8; 32] {
+    /// The key must be the name of the data, the local name of its identifier or the hash of the
+    /// serialised data, as the stores of this crate use.
+    fn check(&self, data: &Data) -> Result<(), Error> {
+        if *self == *data.name() || *self == data.identifier().local_name() ||
+           *self == hash(&serialise(data)?) {
+            Ok(())
+        } else {
+            Err(Error::BadIdentifier)
+        }
+    }
+}
+
+impl ScrubKey for DataIdentifier {
+    fn check(&self, data: &Data) -> Result<(), Error> {
+        if *self == data.identifier() {
+            Ok(())
+        } else {
+            Err(Error::BadIdentifier)
+        }
+    }
+}
+
+/// Checks the integrity of data held in a store under `key`: that the data matches the key, that
+/// it is valid and that every signature on structured data is valid for one of its owners.
+/// Structured data not yet signed as its signature policy requires is not corrupt.
+pub fn check_integrity<Key: ScrubKey>(key: &Key, data: &Data) -> Result<(), Error> {
+    key.check(data)?;
+    data.validate()?;
+    if let Data::Structured(ref structured_data) = *data {
+        structured_data.verify_added_signatures()?;
+    }
+    Ok(())
+}
+
+/// Walks the chunks of a store, a batch at a time, checking each with `check_integrity` so that
+/// chunks corrupted on disk are found before they are served, e.g. by a long-running vault. A
+/// chunk which can't be read back or deserialised is corrupt too.
+///
+/// Each pass checks the chunks in the store when it began; chunks deleted since are skipped and
+/// chunks added since are left to the next pass. A scrubber can be driven by calling
+/// `scrub_batch`, or moved onto a thread of its own with `spawn`.
+pub struct Scrubber<Key> {
+    store: SharedChunkStore<Key, Data>,
+    config: ScrubConfig,
+    pending: Vec<Key>,
+    progress: ScrubProgress,
+}
+
+impl<Key> Scrubber<Key>
+    where Key: ScrubKey + Clone + Ord + Decodable + Encodable
+{
+    /// Creates a scrubber of `store`, working at the rate set by `config`.
+    pub fn new(store: SharedChunkStore<Key, Data>, config: ScrubConfig) -> Scrubber<Key> {
+        Scrubber {
+            store: store,
+            config: config,
+            pending: Vec::new(),
+            progress: ScrubProgress::default(),
+        }
+    }
+
+    /// Returns the progress so far.
+    pub fn progress(&self) -> ScrubProgress {
+        self.progress
+    }
+
+    /// Checks the next batch of chunks, starting a new pass if the last one is complete, and
+    /// returns how many were checked. The batch ends early when the pass completes.
+    ///
+    /// Each chunk found corrupt is passed to `on_corrupt` with the error found, and quarantined if
+    /// it says so. Fails if a chunk can't be quarantined.
+    pub fn scrub_batch<F>(&mut self, on_corrupt: &mut F) -> Result<usize, Error>
+        where F: FnMut(&Key, &Error) -> ScrubAction
+    {
+        if self.pending.is_empty() {
+            self.pending = self.store.keys();
+            // Chunks are taken from the back.
+            self.pending.sort_by(|lhs, rhs| rhs.cmp(lhs));
+            self.progress.chunks_in_pass = self.pending.len() as u64;
+            self.progress.checked_in_pass = 0;
+        }
+        let mut checked = 0;
+        while checked < self.config.batch_size {
+            let key = match self.pending.pop() {
+                Some(key) => key,
+                None => break,
+            };
+            let result = match self.store.get(&key) {
+                Ok(data) => check_integrity(&key, &data),
+                Err(Error::NoFile) => Ok(()),
+                Err(error) => Err(error),
+            };
+            checked += 1;
+            self.progress.checked += 1;
+            self.progress.checked_in_pass += 1;
+            if let Err(error) = result {
+                self.progress.corrupt += 1;
+                if on_corrupt(&key, &error) == ScrubAction::Quarantine {
+                    let _ = self.store.quarantine(&key)?;
+                    self.progress.quarantined += 1;
+                }
+            }
+        }
+        if self.pending.is_empty() {
+            self.progress.passes += 1;
+        }
+        Ok(checked)
+    }
+}
+
+impl<Key> Scrubber<Key>
+    where Key: ScrubKey + Clone + Ord + Decodable + Encodable + Send + Sync + 'static
+{
+    /// Moves the scrubber onto a thread of its own, which checks a batch of chunks, pauses for the
+    /// configured interval and repeats until the returned handle is stopped or dropped.
+    ///
+    /// Failures to quarantine a chunk are logged and the scrubber carries on.
+    pub fn spawn<F>(mut self, mut on_corrupt: F) -> ScrubberHandle
+        where F: FnMut(&Key, &Error) -> ScrubAction + Send + 'static
+    {
+        let (stop_sender, stop_receiver) = mpsc::channel();
+        let progress = Arc::new(Mutex::new(self.progress));
+        let shared_progress = progress.clone();
+        let thread = thread::spawn(move || loop {
+            if let Err(error) = self.scrub_batch(&mut on_corrupt) {
+                warn!("Failed to quarantine a corrupt chunk: {:?}", error);
+            }
+            *shared_progress.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) =
+                self.progress;
+            match stop_receiver.recv_timeout(self.config.interval) {
+                Err(RecvTimeoutError::Timeout) => (),
+                Ok(()) | Err(RecvTimeoutError::Disconnected) => break,
+            }
+        });
+        ScrubberHandle {
+            stop_sender: stop_sender,
+            progress: progress,
+            thread: Some(thread),
+        }
+    }
+}
+
+/// A handle to a `Scrubber` running on a thread of its own, which stops it when dropped.
+pub struct ScrubberHandle {
+    stop_sender: Sender<()>,
+    progress: Arc<Mutex<ScrubProgress>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ScrubberHandle {
+    /// Returns the progress of the scrubber as of its last batch.
+    pub fn progress(&self) -> ScrubProgress {
+        *self.progress.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Stops the scrubber, waiting for it to finish the batch it is checking, and returns its
+    /// final progress.
+    pub fn stop(mut self) -> ScrubProgress {
+        self.join();
+        self.progress()
+    }
+
+    fn join(&mut self) {
+        let _ = self.stop_sender.send(());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for ScrubberHandle {
+    fn drop(&mut self) {
+        self.join();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{Data, DataIdentifier, ImmutableData, StructuredData};
+    use error::Error;
+    use rustc_serialize::hex::ToHex;
+    use serialisation::serialise;
+    use std::fs::File;
+    use std::io::Write;
+    use std::path::Path;
+    use std::time::Duration;
+    use store::SharedChunkStore;
+    use tempdir::TempDir;
+    use test_utils;
+
+    /// Overwrites the file holding the chunk under `name` with `bytes`.
+    fn corrupt(root: &Path, name: &[u8; 32], bytes: &[u8]) {
+        let path = root.join(unwrap!(serialise(name)).to_hex());
+        unwrap!(unwrap!(File::create(path)).write_all(bytes));
+    }
+
+    fn store_with_chunks(root: &Path) -> (SharedChunkStore<[u8; 32], Data>, Vec<Data>) {
+        let store = unwrap!(SharedChunkStore::new(root.to_path_buf(), 100_000));
+        let keys = test_utils::gen_keypair();
+        let mut chunks: Vec<_> = (0..4u8)
+            .map(|index| Data::Immutable(ImmutableData::new(vec![index; 100])))
+            .collect();
+        chunks.push(Data::Structured(unwrap!(StructuredData::new(0,
+                                                                 [9; 32],
+                                                                 0,
+                                                                 vec![1, 2, 3],
+                                                                 vec![keys.0],
+                                                                 vec![],
+                                                                 Some(&keys.1),
+                                                                 false))));
+        for data in &chunks {
+            unwrap!(store.put(data.name(), data));
+        }
+        (store, chunks)
+    }
+
+    #[test]
+    fn finds_and_quarantines_corrupt_chunks() {
+        let root = unwrap!(TempDir::new("test"));
+        let (store, chunks) = store_with_chunks(root.path());
+
+        // Bit rot in the value of immutable data, and in the data of structured data, which the
+        // signature no longer covers.
+        let mut rotten = unwrap!(serialise(&chunks[1]));
+        let last = rotten.len() - 2;
+        rotten[last] ^= 1;
+        corrupt(root.path(), chunks[1].name(), &rotten);
+        let mut serialised = unwrap!(serialise(&chunks[4]));
+        let data_position = unwrap!(serialised.windows(3).position(|bytes| bytes == [1, 2, 3]));
+        serialised[data_position] = 7;
+        corrupt(root.path(), chunks[4].name(), &serialised);
+        // A chunk which can't be deserialised.
+        corrupt(root.path(), chunks[2].name(), &[1, 2, 3]);
+
+        let config = ScrubConfig {
+            batch_size: 2,
+            interval: Duration::from_millis(0),
+        };
+        let mut scrubber = Scrubber::new(store.clone(), config);
+        let mut found = Vec::new();
+        {
+            let mut on_corrupt = |name: &[u8; 32], _: &Error| {
+                found.push(*name);
+                if *name == *chunks[2].name() {
+                    ScrubAction::Keep
+                } else {
+                    ScrubAction::Quarantine
+                }
+            };
+            assert_eq!(unwrap!(scrubber.scrub_batch(&mut on_corrupt)), 2);
+            assert_eq!(scrubber.progress().passes, 0);
+            assert_eq!(unwrap!(scrubber.scrub_batch(&mut on_corrupt)), 2);
+            assert_eq!(unwrap!(scrubber.scrub_batch(&mut on_corrupt)), 1);
+        }
+        let progress = scrubber.progress();
+        assert_eq!(progress.passes, 1);
+        assert_eq!(progress.chunks_in_pass, 5);
+        assert_eq!(progress.checked_in_pass, 5);
+        assert_eq!(progress.corrupt, 3);
+        assert_eq!(progress.quarantined, 2);
+
+        found.sort();
+        let mut expected = vec![*chunks[1].name(), *chunks[2].name(), *chunks[4].name()];
+        expected.sort();
+        assert_eq!(found, expected);
+        assert!(!store.has(chunks[1].name()));
+        assert!(store.has(chunks[2].name()));
+        assert!(!store.has(chunks[4].name()));
+        assert_eq!(unwrap!(store.get(chunks[0].name())), chunks[0]);
+    }
+
+    #[test]
+    fn keys_checked() {
+        let data = Data::Immutable(ImmutableData::new(vec![1; 10]));
+        unwrap!(check_integrity(data.name(), &data));
+        unwrap!(check_integrity(&data.identifier(), &data));
+        unwrap!(check_integrity(&hash(&unwrap!(serialise(&data))), &data));
+        match check_integrity(&[0; 32], &data) {
+            Err(Error::BadIdentifier) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match check_integrity(&DataIdentifier::Immutable([0; 32]), &data) {
+            Err(Error::BadIdentifier) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn runs_in_background() {
+        let root = unwrap!(TempDir::new("test"));
+        let (store, chunks) = store_with_chunks(root.path());
+        corrupt(root.path(), chunks[3].name(), &[1, 2, 3]);
+
+        let config = ScrubConfig {
+            batch_size: 1,
+            interval: Duration::from_millis(1),
+        };
+        let handle = Scrubber::new(store.clone(), config).spawn(|_, _| ScrubAction::Quarantine);
+        while handle.progress().passes == 0 {
+            thread::sleep(Duration::from_millis(1));
+        }
+        let progress = handle.stop();
+        assert!(progress.checked >= 5);
+        assert_eq!(progress.quarantined, 1);
+        assert!(!store.has(chunks[3].name()));
+        assert_eq!(store.keys().len(), 4);
+    }
+}
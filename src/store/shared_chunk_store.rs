@@ -0,0 +1,253 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use chunk_store::{ChunkStore, RestoreReport};
+use error::Error;
+use rustc_serialize::{Decodable, Encodable};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// The value most recently read or fetched under a key, shared by the callers working on it.
+type Slot<Value> = Arc<Mutex<Option<Value>>>;
+
+struct Inner<Key, Value> {
+    chunks: RwLock<ChunkStore<Key, Value>>,
+    slots: Mutex<BTreeMap<Key, Slot<Value>>>,
+}
+
+/// A handle to a disk-based chunk store which can be cloned and shared between threads, e.g.
+/// the message handlers of a vault.
+///
+/// Reads of different keys run concurrently; writes hold the store exclusively only while the
+/// chunk is written. Calls for the same key are serialised by a lock of their own, and a caller
+/// which waited for another reading or fetching the same chunk is handed its value rather than
+/// reading or fetching it again.
+pub struct SharedChunkStore<Key, Value> {
+    inner: Arc<Inner<Key, Value>>,
+}
+
+impl<Key, Value> SharedChunkStore<Key, Value>
+    where Key: Clone + Ord + Decodable + Encodable,
+          Value: Clone + Decodable + Encodable
+{
+    /// Creates a new, empty store in `root` with `max_space` allowed storage space.
+    pub fn new(root: PathBuf, max_space: u64) -> Result<SharedChunkStore<Key, Value>, Error> {
+        Ok(SharedChunkStore::with_chunks(ChunkStore::new(root, max_space)?))
+    }
+
+    /// Reopens a store left in `root` by an earlier run, as `ChunkStore::open` does.
+    pub fn open(root: PathBuf,
+                max_space: u64)
+                -> Result<(SharedChunkStore<Key, Value>, RestoreReport), Error> {
+        let (chunks, report) = ChunkStore::open(root, max_space)?;
+        Ok((SharedChunkStore::with_chunks(chunks), report))
+    }
+
+    /// Stores `value` under `key`, overwriting any value already there.
+    pub fn put(&self, key: &Key, value: &Value) -> Result<(), Error> {
+        self.with_slot(key, |slot| {
+            *slot = None;
+            self.write().put(key, value)
+        })
+    }
+
+    /// Deletes the value stored under `key`. Deleting a key which is not stored succeeds.
+    pub fn delete(&self, key: &Key) -> Result<(), Error> {
+        self.with_slot(key, |slot| {
+            *slot = None;
+            self.write().delete(key)
+        })
+    }
+
+    /// Returns the value stored under `key`, failing with `NoFile` if there is none.
+    pub fn get(&self, key: &Key) -> Result<Value, Error> {
+        self.with_slot(key, |slot| self.read_into(key, slot))
+    }
+
+    /// Returns the value stored under `key`, or else fetches it with `fetch`, e.g. from another
+    /// vault, and stores it.
+    ///
+    /// Concurrent calls for the same key fetch it once: the others wait and are handed the value
+    /// fetched. Fails as `fetch` does, or as `put` does if the fetched value can't be stored.
+    pub fn get_or_fetch<F>(&self, key: &Key, fetch: F) -> Result<Value, Error>
+        where F: FnOnce(&Key) -> Result<Value, Error>
+    {
+        self.with_slot(key, |slot| {
+            if slot.is_some() || self.read().has(key) {
+                return self.read_into(key, slot);
+            }
+            let value = fetch(key)?;
+            self.write().put(key, &value)?;
+            *slot = Some(value.clone());
+            Ok(value)
+        })
+    }
+
+    /// Moves the value stored under `key` out of the store, into its `lost+found` directory, e.g.
+    /// once it has been found corrupt, returning its path there. Fails with `NoFile` if there is
+    /// no value under `key`.
+    pub fn quarantine(&self, key: &Key) -> Result<PathBuf, Error> {
+        self.with_slot(key, |slot| {
+            *slot = None;
+            self.write().quarantine_chunk(key)
+        })
+    }
+
+    /// Returns whether a value is stored under `key`.
+    pub fn has(&self, key: &Key) -> bool {
+        self.read().has(key)
+    }
+
+    /// Returns the keys of the values stored.
+    pub fn keys(&self) -> Vec<Key> {
+        self.read().keys()
+    }
+
+    /// Returns the maximum amount of storage space available.
+    pub fn max_space(&self) -> u64 {
+        self.read().max_space()
+    }
+
+    /// Returns the amount of storage space already used.
+    pub fn used_space(&self) -> u64 {
+        self.read().used_space()
+    }
+
+    fn with_chunks(chunks: ChunkStore<Key, Value>) -> SharedChunkStore<Key, Value> {
+        SharedChunkStore {
+            inner: Arc::new(Inner {
+                chunks: RwLock::new(chunks),
+                slots: Mutex::new(BTreeMap::new()),
+            }),
+        }
+    }
+
+    /// Returns the value in `slot`, or else reads it from disk into `slot`.
+    fn read_into(&self, key: &Key, slot: &mut Option<Value>) -> Result<Value, Error> {
+        if let Some(ref value) = *slot {
+            return Ok(value.clone());
+        }
+        let value = self.read().get(key)?;
+        *slot = Some(value.clone());
+        Ok(value)
+    }
+
+    /// Calls `f` holding the lock of `key`, with the value last read or fetched under it by a
+    /// concurrent call, if any.
+    fn with_slot<T, F>(&self, key: &Key, f: F) -> T
+        where F: FnOnce(&mut Option<Value>) -> T
+    {
+        let slot = self.slots().entry(key.clone()).or_insert_with(Slot::default).clone();
+        let result = {
+            let mut value = slot.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            f(&mut *value)
+        };
+        let mut slots = self.slots();
+        // Only the map and this call hold the slot, and no other call can take it while the map
+        // is locked, so nobody is waiting on it.
+        if Arc::strong_count(&slot) == 2 {
+            let _ = slots.remove(key);
+        }
+        result
+    }
+
+    fn slots(&self) -> MutexGuard<BTreeMap<Key, Slot<Value>>> {
+        // Each change to the map is a single insertion or removal, so a panic while the lock was
+        // held cannot leave it half-updated.
+        self.inner.slots.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn read(&self) -> RwLockReadGuard<ChunkStore<Key, Value>> {
+        self.inner.chunks.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<ChunkStore<Key, Value>> {
+        self.inner.chunks.write().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<Key, Value> Clone for SharedChunkStore<Key, Value> {
+    fn clone(&self) -> SharedChunkStore<Key, Value> {
+        SharedChunkStore { inner: self.inner.clone() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use error::Error;
+    use std::sync::{Arc, Barrier};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use tempdir::TempDir;
+
+    #[test]
+    fn put_get_and_delete() {
+        let root = unwrap!(TempDir::new("test"));
+        let store = unwrap!(SharedChunkStore::<u64, Vec<u8>>::new(root.path().to_path_buf(),
+                                                                  1000));
+        let clone = store.clone();
+        unwrap!(store.put(&1, &vec![1; 10]));
+        assert_eq!(unwrap!(clone.get(&1)), vec![1; 10]);
+        unwrap!(clone.put(&1, &vec![2; 10]));
+        assert_eq!(unwrap!(store.get(&1)), vec![2; 10]);
+        assert_eq!(store.keys(), vec![1]);
+
+        unwrap!(clone.delete(&1));
+        assert!(!store.has(&1));
+        match store.get(&1) {
+            Err(Error::NoFile) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert!(unwrap!(store.inner.slots.lock()).is_empty());
+    }
+
+    #[test]
+    fn concurrent_fetches_coalesced() {
+        let root = unwrap!(TempDir::new("test"));
+        let store = unwrap!(SharedChunkStore::<u64, Vec<u8>>::new(root.path().to_path_buf(),
+                                                                  1000));
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let barrier = Arc::new(Barrier::new(8));
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let store = store.clone();
+                let fetches = fetches.clone();
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    let _ = barrier.wait();
+                    unwrap!(store.get_or_fetch(&1, |_| {
+                        let _ = fetches.fetch_add(1, Ordering::SeqCst);
+                        Ok(vec![1; 10])
+                    }))
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(unwrap!(handle.join()), vec![1; 10]);
+        }
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+        assert_eq!(unwrap!(store.get(&1)), vec![1; 10]);
+
+        match store.get_or_fetch(&2, |_| Err(Error::NoFile)) {
+            Err(Error::NoFile) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert!(!store.has(&2));
+    }
+}
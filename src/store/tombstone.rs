@@ -0,0 +1,166 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use data::{DataIdentifier, StructuredData};
+#[cfg(feature = "serialisation")]
+use data::untrusted;
+use error::Error;
+#[cfg(feature = "serialisation")]
+use serialisation::serialise;
+
+/// A record that data has been deleted, so caches can answer that it is known to be absent
+/// instead of fetching it again.
+///
+/// It holds the signed deletion itself, i.e. the tombstone version of the `StructuredData` (see
+/// `StructuredData::delete`), so whoever holds the data can check its owners authorised the
+/// deletion with `validate_against`. Without the data, anyone can make a tombstone which
+/// `validate` accepts by naming themselves as the previous owners, so it proves nothing. It is
+/// kept until `expires_at`, in seconds since the Unix epoch.
+#[derive(Hash, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serialisation", derive(RustcEncodable, RustcDecodable))]
+pub struct Tombstone {
+    deletion: StructuredData,
+    expires_at: u64,
+}
+
+impl Tombstone {
+    /// Creates a tombstone from the signed `deletion`, kept until `expires_at`.
+    ///
+    /// Fails as `validate` does.
+    pub fn new(deletion: StructuredData, expires_at: u64) -> Result<Tombstone, Error> {
+        let tombstone = Tombstone {
+            deletion: deletion,
+            expires_at: expires_at,
+        };
+        tombstone.validate()?;
+        Ok(tombstone)
+    }
+
+    /// Identifier of the deleted data.
+    pub fn identifier(&self) -> DataIdentifier {
+        self.deletion.identifier()
+    }
+
+    /// Version of the deletion, one more than the last version of the data.
+    pub fn version(&self) -> u64 {
+        self.deletion.get_version()
+    }
+
+    /// The signed deletion.
+    pub fn deletion(&self) -> &StructuredData {
+        &self.deletion
+    }
+
+    /// Time until which the tombstone is kept, in seconds since the Unix epoch.
+    pub fn expires_at(&self) -> u64 {
+        self.expires_at
+    }
+
+    /// Brings `expires_at` forward to `latest` if it is later.
+    pub(crate) fn limit_expiry(&mut self, latest: u64) {
+        if self.expires_at > latest {
+            self.expires_at = latest;
+        }
+    }
+
+    /// Has the tombstone expired at `now`, in seconds since the Unix epoch.
+    pub fn is_expired(&self, now: u64) -> bool {
+        now >= self.expires_at
+    }
+
+    /// Checks the deletion is one, signed as its signature policy requires by the previous owners
+    /// it records. Whether those were the real owners of the data is only checked by
+    /// `validate_against`.
+    pub fn validate(&self) -> Result<(), Error> {
+        if !self.deletion.is_deleted() {
+            return Err(Error::Validation);
+        }
+        self.deletion.verify_own_signatures()
+    }
+
+    /// Checks the deletion is a valid successor of `previous`, which also confirms the owners it
+    /// records as deleting the data were the real ones.
+    pub fn validate_against(&self, previous: &StructuredData) -> Result<(), Error> {
+        previous.validate_self_against_successor(&self.deletion)
+    }
+
+    /// Serialises the tombstone for sending.
+    #[cfg(feature = "serialisation")]
+    pub fn serialise(&self) -> Result<Vec<u8>, Error> {
+        serialise(self).map_err(From::from)
+    }
+
+    /// Parses a received tombstone and checks it as `validate` does.
+    #[cfg(feature = "serialisation")]
+    pub fn deserialise(bytes: &[u8]) -> Result<Tombstone, Error> {
+        let tombstone: Tombstone = untrusted::deserialise(bytes)?;
+        tombstone.validate()?;
+        Ok(tombstone)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::StructuredData;
+    use error::Error;
+    use rand;
+    use serialisation::serialise;
+    use test_utils;
+
+    #[test]
+    fn owner_authorised_deletion() {
+        let keys = test_utils::gen_keypair();
+        let other_keys = test_utils::gen_keypair();
+        let structured_data = unwrap!(StructuredData::new(0,
+                                                          rand::random(),
+                                                          0,
+                                                          vec![1],
+                                                          vec![keys.0],
+                                                          vec![],
+                                                          Some(&keys.1),
+                                                          false));
+        let tombstone = unwrap!(Tombstone::new(unwrap!(structured_data.delete(&keys.1)), 100));
+        assert_eq!(tombstone.identifier(), structured_data.identifier());
+        assert_eq!(tombstone.version(), 1);
+        assert!(!tombstone.is_expired(99));
+        assert!(tombstone.is_expired(100));
+        unwrap!(tombstone.validate_against(&structured_data));
+        let serialised = unwrap!(tombstone.serialise());
+        assert_eq!(unwrap!(Tombstone::deserialise(&serialised)), tombstone);
+
+        // Only deletions can be tombstones.
+        match Tombstone::new(structured_data.clone(), 100) {
+            Err(Error::Validation) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // A deletion signed by someone else is refused.
+        match Tombstone::new(unwrap!(structured_data.delete(&other_keys.1)), 100) {
+            Err(Error::Validation) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        let forged = Tombstone {
+            deletion: unwrap!(structured_data.delete(&other_keys.1)),
+            expires_at: 100,
+        };
+        match Tombstone::deserialise(&unwrap!(serialise(&forged))) {
+            Err(Error::Validation) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
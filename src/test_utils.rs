@@ -0,0 +1,94 @@
+// Copyright 2016 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+//! # Test utilities
+//! Generators of keys and data for tests (feature `test_utils`). Given a seeded `Rng`, or a seed
+//! for keys, they produce the same fixtures on every run.
+
+use crypto::sign::{self, PublicKey, SecretKey, Seed};
+use data::{ImmutableData, StructuredData};
+use rand::{self, Rng};
+use sha3::hash;
+
+/// Returns the signing keypair derived from `seed`. The same seed always gives the same keys.
+pub fn seeded_keypair(seed: &[u8]) -> (PublicKey, SecretKey) {
+    sign::keypair_from_seed(&Seed(hash(seed)))
+}
+
+/// Returns a new signing keypair derived from a seed drawn from `rand`, so tests need neither
+/// rust_sodium's key generation nor its random number generator.
+pub fn gen_keypair() -> (PublicKey, SecretKey) {
+    sign::keypair_from_seed(&Seed(rand::random()))
+}
+
+/// Returns `ImmutableData` holding `size` bytes from `rng`.
+pub fn random_immutable_data<R: Rng>(rng: &mut R, size: usize) -> ImmutableData {
+    ImmutableData::new(rng.gen_iter().take(size).collect())
+}
+
+/// Returns version 0 of non-ledger `StructuredData` with a name, type tag and up to 1 KiB of data
+/// from `rng`, owned and signed by all of `owners`.
+///
+/// # Panics
+///
+/// Panics if `owners` is empty.
+pub fn random_structured_data<R: Rng>(rng: &mut R,
+                                      owners: &[(PublicKey, SecretKey)])
+                                      -> StructuredData {
+    assert!(!owners.is_empty(), "structured data needs at least one owner");
+    let data_len = rng.gen_range(0, 1025);
+    let data = rng.gen_iter().take(data_len).collect();
+    let mut structured_data =
+        match StructuredData::new(rng.gen(),
+                                  rng.gen(),
+                                  0,
+                                  data,
+                                  owners.iter().map(|&(ref public_key, _)| *public_key).collect(),
+                                  vec![],
+                                  None,
+                                  false) {
+            Ok(structured_data) => structured_data,
+            Err(error) => panic!("Failed to create structured data: {:?}", error),
+        };
+    for &(_, ref secret_key) in owners {
+        let _ = structured_data.add_signature(secret_key);
+    }
+    structured_data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, XorShiftRng};
+
+    #[test]
+    fn deterministic() {
+        assert_eq!(seeded_keypair(b"seed").0, seeded_keypair(b"seed").0);
+        assert!(seeded_keypair(b"seed").0 != seeded_keypair(b"other seed").0);
+        assert!(gen_keypair().0 != gen_keypair().0);
+
+        let owners = [seeded_keypair(b"owner 1"), seeded_keypair(b"owner 2")];
+        let fixtures = || {
+            let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+            (random_immutable_data(&mut rng, 100), random_structured_data(&mut rng, &owners))
+        };
+        let (immutable_data, structured_data) = fixtures();
+        assert_eq!(immutable_data.payload_size(), 100);
+        assert!(structured_data.verify_own_signatures().is_ok());
+        assert_eq!(fixtures(), (immutable_data, structured_data));
+    }
+}
@@ -0,0 +1,240 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use error::Error;
+use hex;
+use std::cmp::Ordering;
+use std::fmt::{self, Debug, Display, Formatter};
+
+/// Length in bytes of a name.
+pub const XOR_NAME_LEN: usize = 32;
+
+/// Length in bits of a name.
+pub const XOR_NAME_BITS: usize = XOR_NAME_LEN * 8;
+
+/// A 256-bit name in the network address space, ordered by XOR distance where a reference point
+/// is given.
+///
+/// Converts from and to the `[u8; 32]` names returned by `Data::name()` and
+/// `DataIdentifier::name()`.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, Default)]
+#[cfg_attr(feature = "serialisation", derive(RustcEncodable, RustcDecodable))]
+pub struct XorName(pub [u8; XOR_NAME_LEN]);
+
+impl XorName {
+    /// Parses a name from its hex representation.
+    pub fn from_hex(hex: &str) -> Result<XorName, Error> {
+        let bytes = hex::from_hex(hex).ok_or(Error::BadIdentifier)?;
+        if bytes.len() != XOR_NAME_LEN {
+            return Err(Error::BadIdentifier);
+        }
+        let mut name = [0u8; XOR_NAME_LEN];
+        name.copy_from_slice(&bytes);
+        Ok(XorName(name))
+    }
+
+    /// Hex representation of the name.
+    pub fn to_hex(&self) -> String {
+        hex::to_hex(&self.0)
+    }
+
+    /// The XOR distance between `self` and `other`.
+    pub fn distance(&self, other: &XorName) -> XorName {
+        let mut distance = [0u8; XOR_NAME_LEN];
+        for (i, byte) in distance.iter_mut().enumerate() {
+            *byte = self.0[i] ^ other.0[i];
+        }
+        XorName(distance)
+    }
+
+    /// Compares `lhs` and `rhs` by their XOR distance to `self`.
+    ///
+    /// Returns `Ordering::Less` if `lhs` is closer to `self` than `rhs`.
+    pub fn cmp_distance(&self, lhs: &XorName, rhs: &XorName) -> Ordering {
+        for i in 0..XOR_NAME_LEN {
+            if lhs.0[i] != rhs.0[i] {
+                return Ord::cmp(&(lhs.0[i] ^ self.0[i]), &(rhs.0[i] ^ self.0[i]));
+            }
+        }
+        Ordering::Equal
+    }
+
+    /// Returns `true` if `self` is strictly closer to `target` than `other` is.
+    pub fn closer_to(&self, target: &XorName, other: &XorName) -> bool {
+        target.cmp_distance(self, other) == Ordering::Less
+    }
+
+    /// The number of leading bits `self` and `other` have in common, i.e. the index of the
+    /// routing table bucket `other` falls into as seen from `self`.
+    ///
+    /// Equal names share all `XOR_NAME_BITS` bits.
+    pub fn bucket_index(&self, other: &XorName) -> usize {
+        for i in 0..XOR_NAME_LEN {
+            let diff = self.0[i] ^ other.0[i];
+            if diff != 0 {
+                return i * 8 + diff.leading_zeros() as usize;
+            }
+        }
+        XOR_NAME_BITS
+    }
+}
+
+/// Returns `true` if name `a` is strictly closer to `target` than name `b` is.
+pub fn is_closer(a: &[u8; XOR_NAME_LEN],
+                 b: &[u8; XOR_NAME_LEN],
+                 target: &[u8; XOR_NAME_LEN])
+                 -> bool {
+    XorName(*a).closer_to(&XorName(*target), &XorName(*b))
+}
+
+/// Returns the (at most) `k` names of `candidates` closest to `target`, closest first.
+///
+/// Duplicate candidates are returned only once.
+pub fn closest_nodes(target: &[u8; XOR_NAME_LEN],
+                     candidates: &[[u8; XOR_NAME_LEN]],
+                     k: usize)
+                     -> Vec<[u8; XOR_NAME_LEN]> {
+    let target = XorName(*target);
+    let mut closest = candidates.to_vec();
+    closest.sort_by(|lhs, rhs| target.cmp_distance(&XorName(*lhs), &XorName(*rhs)));
+    closest.dedup();
+    closest.truncate(k);
+    closest
+}
+
+impl From<[u8; XOR_NAME_LEN]> for XorName {
+    fn from(name: [u8; XOR_NAME_LEN]) -> XorName {
+        XorName(name)
+    }
+}
+
+impl<'a> From<&'a [u8; XOR_NAME_LEN]> for XorName {
+    fn from(name: &'a [u8; XOR_NAME_LEN]) -> XorName {
+        XorName(*name)
+    }
+}
+
+impl From<XorName> for [u8; XOR_NAME_LEN] {
+    fn from(name: XorName) -> [u8; XOR_NAME_LEN] {
+        name.0
+    }
+}
+
+impl AsRef<[u8]> for XorName {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Debug for XorName {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter,
+               "{:02x}{:02x}{:02x}..",
+               self.0[0],
+               self.0[1],
+               self.0[2])
+    }
+}
+
+impl Display for XorName {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter, "{}", self.to_hex())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand;
+    use std::cmp::Ordering;
+
+    #[test]
+    fn hex_round_trip() {
+        let name = XorName(rand::random());
+        assert_eq!(name.to_hex().len(), 2 * XOR_NAME_LEN);
+        assert_eq!(unwrap!(XorName::from_hex(&name.to_hex())), name);
+        assert_eq!(format!("{}", name), name.to_hex());
+        assert!(XorName::from_hex("0102").is_err());
+        assert!(XorName::from_hex("not hex").is_err());
+    }
+
+    #[test]
+    fn conversions() {
+        let raw: [u8; XOR_NAME_LEN] = rand::random();
+        let name = XorName::from(raw);
+        assert_eq!(XorName::from(&raw), name);
+        let back: [u8; XOR_NAME_LEN] = name.into();
+        assert_eq!(back, raw);
+    }
+
+    #[test]
+    fn distance() {
+        let origin = XorName([0; XOR_NAME_LEN]);
+        let mut near = [0; XOR_NAME_LEN];
+        near[XOR_NAME_LEN - 1] = 1;
+        let near = XorName(near);
+        let mut far = [0; XOR_NAME_LEN];
+        far[0] = 0x80;
+        let far = XorName(far);
+
+        assert_eq!(origin.cmp_distance(&near, &far), Ordering::Less);
+        assert_eq!(origin.cmp_distance(&far, &near), Ordering::Greater);
+        assert_eq!(origin.cmp_distance(&far, &far), Ordering::Equal);
+        assert!(near.closer_to(&origin, &far));
+        assert!(!far.closer_to(&origin, &near));
+        assert!(!near.closer_to(&origin, &near));
+        // Distance is relative to the reference point.
+        assert_eq!(far.cmp_distance(&near, &far), Ordering::Greater);
+        assert_eq!(near.distance(&far), far.distance(&near));
+        assert_eq!(near.distance(&near), origin);
+    }
+
+    #[test]
+    fn closest() {
+        let target = [0; XOR_NAME_LEN];
+        let name = |first, last| {
+            let mut name = [0; XOR_NAME_LEN];
+            name[0] = first;
+            name[XOR_NAME_LEN - 1] = last;
+            name
+        };
+        let candidates = [name(4, 0), name(0, 1), name(1, 0), name(0, 1), name(0, 200)];
+        assert_eq!(closest_nodes(&target, &candidates, 3),
+                   vec![name(0, 1), name(0, 200), name(1, 0)]);
+        assert_eq!(closest_nodes(&target, &candidates, 10).len(), 4);
+        assert!(closest_nodes(&target, &candidates, 0).is_empty());
+        assert!(closest_nodes(&target, &[], 3).is_empty());
+
+        assert!(is_closer(&name(0, 200), &name(1, 0), &target));
+        assert!(!is_closer(&name(1, 0), &name(0, 200), &target));
+        assert!(!is_closer(&name(1, 0), &name(1, 0), &target));
+        // Closeness is relative to the target.
+        assert!(is_closer(&name(1, 0), &name(0, 200), &name(1, 1)));
+    }
+
+    #[test]
+    fn bucket_index() {
+        let name = XorName(rand::random());
+        assert_eq!(name.bucket_index(&name), XOR_NAME_BITS);
+        for bit in 0..XOR_NAME_BITS {
+            let mut other = name;
+            other.0[bit / 8] ^= 0x80 >> (bit % 8);
+            assert_eq!(name.bucket_index(&other), bit);
+            assert_eq!(other.bucket_index(&name), bit);
+        }
+    }
+}